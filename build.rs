@@ -0,0 +1,29 @@
+use std::path::Path;
+
+// Ricompila quando cambia una migrazione, così sqlx::migrate! incorpora sempre lo schema aggiornato,
+// o quando cambia la cache delle query (anche quando ne viene creata la cartella di un backend).
+// Le macro query! usano la cache di .sqlx/<backend>/ quando DATABASE_URL non è impostato, così il
+// progetto compila anche senza un database. Per aggiornarla dopo aver cambiato una query:
+//   rm .sqlx/sqlite/*.json && touch src/main.rs
+//   DATABASE_URL=sqlite:percorso/dev.db cargo check --no-default-features --features sqlite
+// (con il database creato dalle migrazioni; lo stesso per postgres e mysql).
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=.sqlx");
+    println!("cargo:rerun-if-env-changed=SQLX_OFFLINE_DIR");
+
+    // Un SQLX_OFFLINE_DIR esplicito (es. da cargo sqlx prepare) ha la precedenza
+    if std::env::var_os("SQLX_OFFLINE_DIR").is_some() {
+        return;
+    }
+    let backend = ["sqlite", "postgres", "mysql"]
+        .into_iter()
+        .find(|backend| std::env::var_os(format!("CARGO_FEATURE_{}", backend.to_uppercase())).is_some());
+    if let Some(backend) = backend {
+        let cartella = Path::new(env!("CARGO_MANIFEST_DIR")).join(".sqlx").join(backend);
+        // sqlx scrive nella cartella durante una compilazione con il database: deve già esistere
+        if cartella.is_dir() {
+            println!("cargo:rustc-env=SQLX_OFFLINE_DIR={}", cartella.display());
+        }
+    }
+}
@@ -6,6 +6,7 @@ import LoginPage from './components/LoginPage';
 import NotFoundPage from './components/NotFoundPage';
 import HomePage from "./components/HomePage.tsx"; // Importa la pagina 404
 import RegistrationPage from './components/RegistrationPage';
+import CheckInPage from './components/CheckInPage';
 
 // Funzione helper per verificare l'autenticazione
 const isAuthenticated = (): boolean => {
@@ -67,6 +68,16 @@ function App(): JSX.Element {
                     element={isAuthenticated() ? <Navigate to="/home" /> : <RegistrationPage />}
                 />
 
+                {/* Pagina aperta dal codice QR affisso alla porta dell'aula - Protetta */}
+                <Route
+                    path="/checkin/:idAula"
+                    element={
+                        <ProtectedRoute>
+                            <CheckInPage />
+                        </ProtectedRoute>
+                    }
+                />
+
                 {/* 3. Route per la Radice ("/") */}
                 <Route
                     path="/"
@@ -16,6 +16,10 @@ interface AulaInfo {
     Numero: number;
     Tipo_Aula: string;
 }
+interface MateriaInfo {
+    idMateria: number;
+    nomeMateria: string;
+}
 
 interface BookingFormModalProps {
     isOpen: boolean;
@@ -67,6 +71,23 @@ const BookingFormModal: React.FC<BookingFormModalProps> = ({ isOpen, onClose, on
     const [error, setError] = useState<string>('');
     const [isLoading, setIsLoading] = useState<boolean>(false); // CORRETTO: Aggiunto setIsLoading
     const userId = localStorage.getItem('userId');
+    const [materie, setMaterie] = useState<MateriaInfo[]>([]);
+    const [idMateriaSelezionata, setIdMateriaSelezionata] = useState<string>(''); // Facoltativa
+
+    // Solo le materie insegnate dal professore: compaiono sulla griglia stampata dell'aula
+    useEffect(() => {
+        if (!isOpen || !userId) return;
+        axios.get<MateriaInfo[]>(`http://localhost:8000/api/materie?professore=${userId}`)
+            .then(response => {
+                const elenco = Array.isArray(response.data) ? response.data : [];
+                setMaterie(elenco);
+                setIdMateriaSelezionata(elenco.length === 1 ? elenco[0].idMateria.toString() : '');
+            })
+            .catch(err => {
+                console.error("Errore nel caricare le materie:", err);
+                setMaterie([]);
+            });
+    }, [isOpen, userId]);
 
     useEffect(() => {
         if (!isOpen || !tipoAula) {
@@ -180,6 +201,7 @@ const BookingFormModal: React.FC<BookingFormModalProps> = ({ isOpen, onClose, on
                 Id_Aula: parseInt(idAulaSelezionata, 10),
                 Data_Inizio: dataInizioISO,
                 Data_Fine: dataFineISO,
+                Id_Materia: idMateriaSelezionata ? parseInt(idMateriaSelezionata, 10) : null,
             };
             console.log("[handleSubmit] Invio prenotazione al backend:", prenotazioneData);
 
@@ -257,6 +279,17 @@ const BookingFormModal: React.FC<BookingFormModalProps> = ({ isOpen, onClose, on
                         </select>
                         {auleDisponibili.length === 0 && tipoAula && <small>Nessuna aula per {tipoAula}.</small>}
                     </div>
+                    {materie.length > 0 && (
+                        <div style={modalStyles.inputGroup}>
+                            <label htmlFor="materia" style={modalStyles.label}>Materia:</label>
+                            <select id="materia" value={idMateriaSelezionata} onChange={(e) => setIdMateriaSelezionata(e.target.value)} style={modalStyles.select} disabled={isLoading}>
+                                <option value="">Nessuna</option>
+                                {materie.map((materia: MateriaInfo) => (
+                                    <option key={materia.idMateria} value={materia.idMateria.toString()}>{materia.nomeMateria}</option>
+                                ))}
+                            </select>
+                        </div>
+                    )}
                     <div style={modalStyles.inputGroup}>
                         <label htmlFor="giornoPrenotazione" style={modalStyles.label}>Giorno:</label>
                         <input type="date" id="giornoPrenotazione" value={giornoPrenotazione} onChange={(e) => setGiornoPrenotazione(e.target.value)} required style={modalStyles.input} disabled={isLoading} />
@@ -26,6 +26,7 @@ interface CalendarEvent extends EventInput { // Estende EventInput per compatibi
     allDay?: boolean;
     backgroundColor?: string;
     borderColor?: string;
+    display?: string; // "background" per i blocchi di indisponibilità delle aule
     // Potresti aggiungere altri campi specifici dell'applicazione
     // extendedProps?: {
     //   professore?: string;
@@ -75,6 +76,27 @@ const CalendarView: React.FC = () => {
         fetchEvents();
     }, [fetchEvents]); // Chiama fetchEvents quando il componente monta (e se fetchEvents cambiasse)
 
+    // Aggiornamenti in tempo reale: quando un collega crea, modifica o annulla una prenotazione
+    // il calendario si ricarica. EventSource si riconnette da solo e invia Last-Event-ID,
+    // quindi gli eventi persi durante la disconnessione arrivano comunque.
+    useEffect(() => {
+        const token = localStorage.getItem('authToken');
+        if (!token) return;
+
+        const sorgente = new EventSource(`http://localhost:8000/api/prenotazioni/stream?token=${encodeURIComponent(token)}`);
+        const tipiEvento = [
+            'prenotazione.creata',
+            'prenotazione.modificata',
+            'prenotazione.annullata',
+            'prenotazione.approvata',
+            'ricarica', // Troppi eventi persi: il server chiede di ricaricare tutto
+        ];
+        const ricarica = () => { fetchEvents(); };
+        tipiEvento.forEach(tipo => sorgente.addEventListener(tipo, ricarica));
+
+        return () => sorgente.close();
+    }, [fetchEvents]);
+
     // Gestore per quando si clicca su una data o uno slot temporale
     // @ts-ignore
     const handleDateClick = useCallback((clickInfo: DateClickArg) => {
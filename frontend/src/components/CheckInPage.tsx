@@ -0,0 +1,72 @@
+// frontend/src/components/CheckInPage.tsx
+// Pagina aperta dal codice QR affisso alla porta dell'aula: registra il check-in
+// del professore nella sua prenotazione in corso.
+import React, { useEffect, useState, type JSX } from 'react';
+import { Link, useParams, useSearchParams } from 'react-router-dom';
+import axios, { AxiosError } from 'axios';
+
+interface RispostaCheckIn {
+    status: string;
+    message: string;
+}
+
+const styles: { [key: string]: React.CSSProperties } = {
+    container: {
+        display: 'flex',
+        flexDirection: 'column',
+        alignItems: 'center',
+        justifyContent: 'center',
+        minHeight: '80vh',
+        textAlign: 'center',
+        fontFamily: 'Arial, sans-serif',
+        padding: '0 20px',
+    },
+    title: {
+        fontSize: '2em',
+        color: '#555',
+    },
+    successo: {
+        fontSize: '1.3em',
+        color: '#2e7d32',
+    },
+    errore: {
+        fontSize: '1.3em',
+        color: '#c62828',
+    },
+    link: {
+        marginTop: '20px',
+        color: '#007bff',
+        textDecoration: 'none',
+    }
+};
+
+function CheckInPage(): JSX.Element {
+    const { idAula } = useParams<{ idAula: string }>();
+    const [searchParams] = useSearchParams();
+    const codice = searchParams.get('codice') ?? '';
+    const [esito, setEsito] = useState<{ ok: boolean; messaggio: string } | null>(null);
+
+    useEffect(() => {
+        const token = localStorage.getItem('authToken');
+        axios.post<RispostaCheckIn>(
+            `http://localhost:8000/api/checkin/aula/${idAula}?codice=${encodeURIComponent(codice)}`,
+            null,
+            { headers: { Authorization: `Bearer ${token}` } }
+        )
+            .then(response => setEsito({ ok: true, messaggio: response.data.message }))
+            .catch((err: AxiosError<RispostaCheckIn>) => {
+                setEsito({ ok: false, messaggio: err.response?.data?.message || 'Impossibile registrare il check-in.' });
+            });
+    }, [idAula, codice]);
+
+    return (
+        <div style={styles.container}>
+            <h1 style={styles.title}>Check-in</h1>
+            {esito === null && <p>Registrazione del check-in in corso...</p>}
+            {esito && <p style={esito.ok ? styles.successo : styles.errore}>{esito.messaggio}</p>}
+            <Link to="/home" style={styles.link}>Vai al calendario</Link>
+        </div>
+    );
+}
+
+export default CheckInPage;
@@ -0,0 +1,197 @@
+-- Professori, materie, plessi, aule e prenotazioni
+
+CREATE TABLE professore (
+    Id_Professore INT NOT NULL AUTO_INCREMENT,
+    Nome VARCHAR(100) NULL,
+    Cognome VARCHAR(100) NOT NULL,
+    Ruolo ENUM('docente', 'tecnico', 'admin') NOT NULL DEFAULT 'docente',
+    PRIMARY KEY (Id_Professore)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE credenziali (
+    Id_Professore_Cred INT NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    password_hash VARCHAR(255) NOT NULL,
+    PRIMARY KEY (Id_Professore_Cred),
+    UNIQUE KEY uq_credenziali_email (email),
+    CONSTRAINT fk_credenziali_professore FOREIGN KEY (Id_Professore_Cred)
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE materia (
+    Id_Materia INT NOT NULL AUTO_INCREMENT,
+    Nome VARCHAR(100) NOT NULL,
+    Descrizione TEXT NULL,
+    PRIMARY KEY (Id_Materia),
+    UNIQUE KEY uq_materia_nome (Nome)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE insegna (
+    Id_Professore INT NOT NULL,
+    Id_Materia INT NOT NULL,
+    PRIMARY KEY (Id_Professore, Id_Materia),
+    KEY idx_insegna_materia (Id_Materia),
+    CONSTRAINT fk_insegna_professore FOREIGN KEY (Id_Professore)
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    CONSTRAINT fk_insegna_materia FOREIGN KEY (Id_Materia)
+        REFERENCES materia (Id_Materia) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE plesso (
+    Id_Plesso INT NOT NULL AUTO_INCREMENT,
+    Nome VARCHAR(100) NOT NULL,
+    Indirizzo VARCHAR(255) NULL,
+    PRIMARY KEY (Id_Plesso)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Moduli orari di ogni plesso, in ora locale
+CREATE TABLE orario_plesso (
+    Id_Plesso INT NOT NULL,
+    Numero_Modulo INT NOT NULL,
+    Ora_Inizio TIME NOT NULL,
+    Ora_Fine TIME NOT NULL,
+    PRIMARY KEY (Id_Plesso, Numero_Modulo),
+    CONSTRAINT fk_orario_plesso FOREIGN KEY (Id_Plesso)
+        REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    CONSTRAINT ck_orario_intervallo CHECK (Ora_Inizio < Ora_Fine)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Giorni di calendario locali, estremi inclusi
+CREATE TABLE chiusura_plesso (
+    Id_Chiusura INT NOT NULL AUTO_INCREMENT,
+    Id_Plesso INT NOT NULL,
+    Data_Inizio DATE NOT NULL,
+    Data_Fine DATE NOT NULL,
+    Motivo VARCHAR(255) NOT NULL,
+    PRIMARY KEY (Id_Chiusura),
+    KEY idx_chiusura_plesso_date (Id_Plesso, Data_Inizio, Data_Fine),
+    CONSTRAINT fk_chiusura_plesso FOREIGN KEY (Id_Plesso)
+        REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    CONSTRAINT ck_chiusura_intervallo CHECK (Data_Inizio <= Data_Fine)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Minuti necessari per spostarsi da un plesso all'altro
+CREATE TABLE distanza_plessi (
+    Id_Plesso_Da INT NOT NULL,
+    Id_Plesso_A INT NOT NULL,
+    Minuti INT NOT NULL,
+    PRIMARY KEY (Id_Plesso_Da, Id_Plesso_A),
+    KEY idx_distanza_plesso_a (Id_Plesso_A),
+    CONSTRAINT fk_distanza_plesso_da FOREIGN KEY (Id_Plesso_Da)
+        REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    CONSTRAINT fk_distanza_plesso_a FOREIGN KEY (Id_Plesso_A)
+        REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    CONSTRAINT ck_distanza_minuti CHECK (Minuti >= 0)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE aula (
+    Id_Aula INT NOT NULL AUTO_INCREMENT,
+    Tipo_Aula VARCHAR(50) NOT NULL,
+    Numero INT NOT NULL,
+    Id_Plesso INT NOT NULL,
+    Politica_Prenotazione ENUM('esclusiva', 'condivisa_prenotazioni', 'condivisa_studenti') NOT NULL DEFAULT 'esclusiva',
+    Capacita INT NULL,
+    Richiede_Approvazione BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (Id_Aula),
+    UNIQUE KEY uq_aula_plesso (Id_Plesso, Tipo_Aula, Numero),
+    CONSTRAINT fk_aula_plesso FOREIGN KEY (Id_Plesso)
+        REFERENCES plesso (Id_Plesso),
+    CONSTRAINT ck_aula_capacita CHECK (Capacita IS NULL OR Capacita > 0)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Id_Aula NULL per le risorse mobili non installate in un'aula
+CREATE TABLE attrezzatura (
+    Id_Attrezzatura INT NOT NULL AUTO_INCREMENT,
+    Nome VARCHAR(100) NOT NULL,
+    Id_Aula INT NULL,
+    Quantita INT NOT NULL DEFAULT 1,
+    Prenotabile BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (Id_Attrezzatura),
+    KEY idx_attrezzatura_aula (Id_Aula),
+    CONSTRAINT fk_attrezzatura_aula FOREIGN KEY (Id_Aula)
+        REFERENCES aula (Id_Aula) ON DELETE SET NULL,
+    CONSTRAINT ck_attrezzatura_quantita CHECK (Quantita >= 0)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Date in UTC. Id_Aula NULL per le prenotazioni di sola attrezzatura;
+-- Approvata NULL se l'aula non richiede approvazione.
+CREATE TABLE prenotazione (
+    Id_Prenotazione INT NOT NULL AUTO_INCREMENT,
+    Id_Professore INT NOT NULL,
+    Id_Aula INT NULL,
+    Data_Inizio DATETIME NOT NULL,
+    Data_Fine DATETIME NOT NULL,
+    Numero_Studenti INT NULL,
+    Id_Materia INT NULL,
+    Stato ENUM('attiva', 'annullata', 'rilasciata') NOT NULL DEFAULT 'attiva',
+    Sequenza INT NOT NULL DEFAULT 0,
+    Data_Modifica DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    Approvata BOOLEAN NULL,
+    Check_In DATETIME NULL,
+    PRIMARY KEY (Id_Prenotazione),
+    KEY idx_prenotazione_aula_date (Id_Aula, Data_Inizio, Data_Fine),
+    KEY idx_prenotazione_professore_date (Id_Professore, Data_Inizio),
+    KEY idx_prenotazione_date (Data_Inizio, Data_Fine),
+    KEY idx_prenotazione_materia (Id_Materia),
+    CONSTRAINT fk_prenotazione_professore FOREIGN KEY (Id_Professore)
+        REFERENCES professore (Id_Professore),
+    CONSTRAINT fk_prenotazione_aula FOREIGN KEY (Id_Aula)
+        REFERENCES aula (Id_Aula),
+    CONSTRAINT fk_prenotazione_materia FOREIGN KEY (Id_Materia)
+        REFERENCES materia (Id_Materia) ON DELETE SET NULL,
+    CONSTRAINT ck_prenotazione_intervallo CHECK (Data_Inizio < Data_Fine),
+    CONSTRAINT ck_prenotazione_studenti CHECK (Numero_Studenti IS NULL OR Numero_Studenti > 0)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE prenotazione_attrezzatura (
+    Id_Prenotazione INT NOT NULL,
+    Id_Attrezzatura INT NOT NULL,
+    Quantita INT NOT NULL DEFAULT 1,
+    PRIMARY KEY (Id_Prenotazione, Id_Attrezzatura),
+    KEY idx_prenotazione_attrezzatura_attrezzatura (Id_Attrezzatura),
+    CONSTRAINT fk_pa_prenotazione FOREIGN KEY (Id_Prenotazione)
+        REFERENCES prenotazione (Id_Prenotazione) ON DELETE CASCADE,
+    CONSTRAINT fk_pa_attrezzatura FOREIGN KEY (Id_Attrezzatura)
+        REFERENCES attrezzatura (Id_Attrezzatura) ON DELETE CASCADE,
+    CONSTRAINT ck_pa_quantita CHECK (Quantita > 0)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Indisponibilità delle aule decise dagli amministratori, anche ricorrenti
+CREATE TABLE blocco_aula (
+    Id_Blocco INT NOT NULL AUTO_INCREMENT,
+    Id_Aula INT NOT NULL,
+    Data_Inizio DATETIME NOT NULL,
+    Data_Fine DATETIME NOT NULL,
+    Motivo VARCHAR(255) NOT NULL,
+    Ricorrenza ENUM('nessuna', 'giornaliera', 'settimanale') NOT NULL DEFAULT 'nessuna',
+    Ricorrenza_Fine DATETIME NULL,
+    PRIMARY KEY (Id_Blocco),
+    KEY idx_blocco_aula_date (Id_Aula, Data_Inizio, Data_Fine),
+    CONSTRAINT fk_blocco_aula FOREIGN KEY (Id_Aula)
+        REFERENCES aula (Id_Aula) ON DELETE CASCADE,
+    CONSTRAINT ck_blocco_intervallo CHECK (Data_Inizio < Data_Fine)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE segnalazione_guasto (
+    Id_Segnalazione INT NOT NULL AUTO_INCREMENT,
+    Id_Aula INT NULL,
+    Id_Attrezzatura INT NULL,
+    Id_Segnalatore INT NOT NULL,
+    Gravita ENUM('bassa', 'media', 'alta') NOT NULL,
+    Descrizione TEXT NOT NULL,
+    Stato ENUM('aperta', 'in_lavorazione', 'risolta') NOT NULL DEFAULT 'aperta',
+    Blocca_Aula BOOLEAN NOT NULL DEFAULT FALSE,
+    Note_Tecnico TEXT NULL,
+    Data_Creazione DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    Data_Aggiornamento DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (Id_Segnalazione),
+    KEY idx_segnalazione_aula_stato (Id_Aula, Stato),
+    KEY idx_segnalazione_attrezzatura (Id_Attrezzatura),
+    KEY idx_segnalazione_segnalatore (Id_Segnalatore),
+    CONSTRAINT fk_segnalazione_aula FOREIGN KEY (Id_Aula)
+        REFERENCES aula (Id_Aula) ON DELETE CASCADE,
+    CONSTRAINT fk_segnalazione_attrezzatura FOREIGN KEY (Id_Attrezzatura)
+        REFERENCES attrezzatura (Id_Attrezzatura) ON DELETE CASCADE,
+    CONSTRAINT fk_segnalazione_segnalatore FOREIGN KEY (Id_Segnalatore)
+        REFERENCES professore (Id_Professore)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,23 @@
+-- Feed iCalendar personali e risorse create dai client CalDAV
+
+CREATE TABLE feed_token (
+    Id_Professore INT NOT NULL,
+    Token VARCHAR(64) NOT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    PRIMARY KEY (Id_Professore),
+    UNIQUE KEY uq_feed_token (Token),
+    CONSTRAINT fk_feed_token_professore FOREIGN KEY (Id_Professore)
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Nome e UID scelti dal client per le prenotazioni create via CalDAV
+CREATE TABLE risorsa_caldav (
+    Id_Prenotazione INT NOT NULL,
+    Nome_Risorsa VARCHAR(255) NOT NULL,
+    Uid VARCHAR(255) NOT NULL,
+    PRIMARY KEY (Id_Prenotazione),
+    KEY idx_risorsa_caldav_uid (Uid),
+    KEY idx_risorsa_caldav_nome (Nome_Risorsa),
+    CONSTRAINT fk_risorsa_caldav_prenotazione FOREIGN KEY (Id_Prenotazione)
+        REFERENCES prenotazione (Id_Prenotazione) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
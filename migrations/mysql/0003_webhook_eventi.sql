@@ -0,0 +1,45 @@
+-- Webhook in uscita e registro degli eventi per gli aggiornamenti in tempo reale
+
+CREATE TABLE webhook (
+    Id_Webhook INT NOT NULL AUTO_INCREMENT,
+    Url VARCHAR(2048) NOT NULL,
+    Segreto VARCHAR(128) NOT NULL,
+    Eventi VARCHAR(255) NOT NULL, -- Tipi di evento separati da virgole
+    Formato ENUM('json', 'teams') NOT NULL DEFAULT 'json',
+    Attivo BOOLEAN NOT NULL DEFAULT TRUE,
+    Descrizione VARCHAR(255) NULL,
+    Data_Creazione DATETIME NOT NULL,
+    PRIMARY KEY (Id_Webhook)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+CREATE TABLE consegna_webhook (
+    Id_Consegna BIGINT NOT NULL AUTO_INCREMENT,
+    Id_Webhook INT NOT NULL,
+    Evento VARCHAR(64) NOT NULL,
+    Payload MEDIUMTEXT NOT NULL,
+    Stato ENUM('in_attesa', 'consegnata', 'fallita') NOT NULL DEFAULT 'in_attesa',
+    Tentativi INT NOT NULL DEFAULT 0,
+    Prossimo_Tentativo DATETIME NOT NULL,
+    Ultimo_Codice INT NULL,
+    Ultimo_Errore TEXT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    Data_Consegna DATETIME NULL,
+    PRIMARY KEY (Id_Consegna),
+    KEY idx_consegna_stato_tentativo (Stato, Prossimo_Tentativo),
+    KEY idx_consegna_webhook (Id_Webhook, Id_Consegna),
+    CONSTRAINT fk_consegna_webhook FOREIGN KEY (Id_Webhook)
+        REFERENCES webhook (Id_Webhook) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Nessuna chiave esterna: l'evento resta valido anche se la prenotazione viene eliminata
+CREATE TABLE evento_prenotazione (
+    Id_Evento BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    Tipo VARCHAR(64) NOT NULL,
+    Id_Prenotazione INT NOT NULL,
+    Id_Aula INT NULL,
+    Data_Inizio DATETIME NOT NULL,
+    Data_Fine DATETIME NOT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    PRIMARY KEY (Id_Evento),
+    KEY idx_evento_creazione (Data_Creazione)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,42 @@
+-- Coda delle email, preferenze di notifica e attività pianificate già eseguite
+
+CREATE TABLE email_in_uscita (
+    Id_Email BIGINT NOT NULL AUTO_INCREMENT,
+    Id_Professore INT NOT NULL,
+    Destinatario VARCHAR(255) NOT NULL,
+    Oggetto VARCHAR(255) NOT NULL,
+    Corpo MEDIUMTEXT NOT NULL,
+    Stato ENUM('in_attesa', 'inviata', 'fallita') NOT NULL DEFAULT 'in_attesa',
+    Tentativi INT NOT NULL DEFAULT 0,
+    Prossimo_Tentativo DATETIME NOT NULL,
+    Ultimo_Errore TEXT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    Data_Invio DATETIME NULL,
+    PRIMARY KEY (Id_Email),
+    KEY idx_email_stato_tentativo (Stato, Prossimo_Tentativo),
+    CONSTRAINT fk_email_professore FOREIGN KEY (Id_Professore)
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Senza una riga il professore riceve tutte le notifiche
+CREATE TABLE preferenza_notifica (
+    Id_Professore INT NOT NULL,
+    Creata BOOLEAN NOT NULL DEFAULT TRUE,
+    Modificata BOOLEAN NOT NULL DEFAULT TRUE,
+    Annullata BOOLEAN NOT NULL DEFAULT TRUE,
+    Approvata BOOLEAN NOT NULL DEFAULT TRUE,
+    Scavalcata BOOLEAN NOT NULL DEFAULT TRUE,
+    Promemoria BOOLEAN NOT NULL DEFAULT TRUE,
+    Riepilogo BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY (Id_Professore),
+    CONSTRAINT fk_preferenza_professore FOREIGN KEY (Id_Professore)
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Evita di ripetere promemoria e riepiloghi dopo un riavvio o con più istanze
+CREATE TABLE attivita_pianificata (
+    Chiave VARCHAR(191) NOT NULL,
+    Data_Esecuzione DATETIME NOT NULL,
+    PRIMARY KEY (Chiave),
+    KEY idx_attivita_esecuzione (Data_Esecuzione)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,37 @@
+-- Tablet dei chioschi, PIN per la prenotazione rapida e codici QR per il check-in
+
+CREATE TABLE dispositivo_chiosco (
+    Id_Dispositivo INT NOT NULL AUTO_INCREMENT,
+    Id_Aula INT NOT NULL,
+    Token VARCHAR(64) NOT NULL,
+    Descrizione VARCHAR(255) NULL,
+    Data_Creazione DATETIME NOT NULL,
+    Ultimo_Accesso DATETIME NULL,
+    PRIMARY KEY (Id_Dispositivo),
+    UNIQUE KEY uq_dispositivo_token (Token),
+    KEY idx_dispositivo_aula (Id_Aula),
+    CONSTRAINT fk_dispositivo_aula FOREIGN KEY (Id_Aula)
+        REFERENCES aula (Id_Aula) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- HMAC-SHA256 del PIN: univoco, perché il PIN da solo identifica il professore
+CREATE TABLE pin_chiosco (
+    Id_Professore INT NOT NULL,
+    Pin_Hash CHAR(64) NOT NULL,
+    Data_Modifica DATETIME NOT NULL,
+    PRIMARY KEY (Id_Professore),
+    UNIQUE KEY uq_pin_hash (Pin_Hash),
+    CONSTRAINT fk_pin_professore FOREIGN KEY (Id_Professore)
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
+
+-- Solo le prenotazioni successive a Data_Attivazione vengono rilasciate senza check-in
+CREATE TABLE checkin_aula (
+    Id_Aula INT NOT NULL,
+    Codice VARCHAR(64) NOT NULL,
+    Data_Attivazione DATETIME NOT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    PRIMARY KEY (Id_Aula),
+    CONSTRAINT fk_checkin_aula FOREIGN KEY (Id_Aula)
+        REFERENCES aula (Id_Aula) ON DELETE CASCADE
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE = utf8mb4_unicode_ci;
@@ -0,0 +1,4 @@
+-- Al tablet il professore si identifica con il proprio codice (Id_Professore) oltre al PIN,
+-- quindi i PIN non devono più essere unici
+
+ALTER TABLE pin_chiosco DROP INDEX uq_pin_hash;
@@ -0,0 +1,4 @@
+-- Aula da cui è stata spostata la prenotazione (NULL se l'aula non è cambiata): chi segue
+-- gli aggiornamenti dell'aula di partenza deve vedere la prenotazione andarsene
+
+ALTER TABLE evento_prenotazione ADD COLUMN Id_Aula_Precedente INT NULL AFTER Id_Aula;
@@ -0,0 +1,198 @@
+-- Professori, materie, plessi, aule e prenotazioni
+-- Stesso schema di migrations/mysql. I nomi delle colonne sono tra virgolette perché
+-- Postgres li porterebbe in minuscolo; le date sono TIMESTAMP in UTC come i DATETIME di MySQL
+-- e i CHECK sostituiscono gli ENUM.
+
+-- Serve per usare = sugli interi dentro un vincolo di esclusione GiST
+CREATE EXTENSION IF NOT EXISTS btree_gist;
+
+CREATE TABLE professore (
+    "Id_Professore" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Nome" VARCHAR(100) NULL,
+    "Cognome" VARCHAR(100) NOT NULL,
+    "Ruolo" VARCHAR(16) NOT NULL DEFAULT 'docente' CHECK ("Ruolo" IN ('docente', 'tecnico', 'admin'))
+);
+
+-- Le email si confrontano senza distinguere maiuscole e minuscole, come con la collation di MySQL
+CREATE TABLE credenziali (
+    "Id_Professore_Cred" INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore ("Id_Professore") ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    password_hash VARCHAR(255) NOT NULL
+);
+CREATE UNIQUE INDEX uq_credenziali_email ON credenziali (lower(email));
+
+CREATE TABLE materia (
+    "Id_Materia" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Nome" VARCHAR(100) NOT NULL UNIQUE,
+    "Descrizione" TEXT NULL
+);
+
+CREATE TABLE insegna (
+    "Id_Professore" INT4 NOT NULL REFERENCES professore ("Id_Professore") ON DELETE CASCADE,
+    "Id_Materia" INT4 NOT NULL REFERENCES materia ("Id_Materia") ON DELETE CASCADE,
+    PRIMARY KEY ("Id_Professore", "Id_Materia")
+);
+CREATE INDEX idx_insegna_materia ON insegna ("Id_Materia");
+
+CREATE TABLE plesso (
+    "Id_Plesso" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Nome" VARCHAR(100) NOT NULL,
+    "Indirizzo" VARCHAR(255) NULL
+);
+
+-- Moduli orari di ogni plesso, in ora locale
+CREATE TABLE orario_plesso (
+    "Id_Plesso" INT4 NOT NULL REFERENCES plesso ("Id_Plesso") ON DELETE CASCADE,
+    "Numero_Modulo" INT4 NOT NULL,
+    "Ora_Inizio" TIME NOT NULL,
+    "Ora_Fine" TIME NOT NULL,
+    PRIMARY KEY ("Id_Plesso", "Numero_Modulo"),
+    CHECK ("Ora_Inizio" < "Ora_Fine")
+);
+
+-- Giorni di calendario locali, estremi inclusi
+CREATE TABLE chiusura_plesso (
+    "Id_Chiusura" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Plesso" INT4 NOT NULL REFERENCES plesso ("Id_Plesso") ON DELETE CASCADE,
+    "Data_Inizio" DATE NOT NULL,
+    "Data_Fine" DATE NOT NULL,
+    "Motivo" VARCHAR(255) NOT NULL,
+    CHECK ("Data_Inizio" <= "Data_Fine")
+);
+CREATE INDEX idx_chiusura_plesso_date ON chiusura_plesso ("Id_Plesso", "Data_Inizio", "Data_Fine");
+
+-- Minuti necessari per spostarsi da un plesso all'altro
+CREATE TABLE distanza_plessi (
+    "Id_Plesso_Da" INT4 NOT NULL REFERENCES plesso ("Id_Plesso") ON DELETE CASCADE,
+    "Id_Plesso_A" INT4 NOT NULL REFERENCES plesso ("Id_Plesso") ON DELETE CASCADE,
+    "Minuti" INT4 NOT NULL CHECK ("Minuti" >= 0),
+    PRIMARY KEY ("Id_Plesso_Da", "Id_Plesso_A")
+);
+CREATE INDEX idx_distanza_plesso_a ON distanza_plessi ("Id_Plesso_A");
+
+CREATE TABLE aula (
+    "Id_Aula" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Tipo_Aula" VARCHAR(50) NOT NULL,
+    "Numero" INT4 NOT NULL,
+    "Id_Plesso" INT4 NOT NULL REFERENCES plesso ("Id_Plesso"),
+    "Politica_Prenotazione" VARCHAR(32) NOT NULL DEFAULT 'esclusiva'
+        CHECK ("Politica_Prenotazione" IN ('esclusiva', 'condivisa_prenotazioni', 'condivisa_studenti')),
+    "Capacita" INT4 NULL CHECK ("Capacita" IS NULL OR "Capacita" > 0),
+    "Richiede_Approvazione" BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE ("Id_Plesso", "Tipo_Aula", "Numero")
+);
+
+-- Id_Aula NULL per le risorse mobili non installate in un'aula
+CREATE TABLE attrezzatura (
+    "Id_Attrezzatura" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Nome" VARCHAR(100) NOT NULL,
+    "Id_Aula" INT4 NULL REFERENCES aula ("Id_Aula") ON DELETE SET NULL,
+    "Quantita" INT4 NOT NULL DEFAULT 1 CHECK ("Quantita" >= 0),
+    "Prenotabile" BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE INDEX idx_attrezzatura_aula ON attrezzatura ("Id_Aula");
+
+-- Date in UTC. Id_Aula NULL per le prenotazioni di sola attrezzatura;
+-- Approvata NULL se l'aula non richiede approvazione.
+-- Esclusiva copia la politica dell'aula (la tengono allineata i trigger qui sotto) e serve
+-- al vincolo di esclusione: due prenotazioni attive della stessa aula esclusiva non possono
+-- sovrapporsi nemmeno se il controllo dell'applicazione venisse aggirato.
+CREATE TABLE prenotazione (
+    "Id_Prenotazione" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Professore" INT4 NOT NULL REFERENCES professore ("Id_Professore"),
+    "Id_Aula" INT4 NULL REFERENCES aula ("Id_Aula"),
+    "Data_Inizio" TIMESTAMP NOT NULL,
+    "Data_Fine" TIMESTAMP NOT NULL,
+    "Numero_Studenti" INT4 NULL CHECK ("Numero_Studenti" IS NULL OR "Numero_Studenti" > 0),
+    "Id_Materia" INT4 NULL REFERENCES materia ("Id_Materia") ON DELETE SET NULL,
+    "Stato" VARCHAR(16) NOT NULL DEFAULT 'attiva' CHECK ("Stato" IN ('attiva', 'annullata', 'rilasciata')),
+    "Sequenza" INT4 NOT NULL DEFAULT 0,
+    "Data_Modifica" TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+    "Approvata" BOOLEAN NULL,
+    "Check_In" TIMESTAMP NULL,
+    "Esclusiva" BOOLEAN NOT NULL DEFAULT FALSE,
+    CHECK ("Data_Inizio" < "Data_Fine"),
+    CONSTRAINT ex_prenotazione_sovrapposta EXCLUDE USING gist (
+        "Id_Aula" WITH =,
+        tstzrange("Data_Inizio" AT TIME ZONE 'UTC', "Data_Fine" AT TIME ZONE 'UTC') WITH &&
+    ) WHERE ("Stato" = 'attiva' AND "Esclusiva")
+);
+CREATE INDEX idx_prenotazione_aula_date ON prenotazione ("Id_Aula", "Data_Inizio", "Data_Fine");
+CREATE INDEX idx_prenotazione_professore_date ON prenotazione ("Id_Professore", "Data_Inizio");
+CREATE INDEX idx_prenotazione_date ON prenotazione ("Data_Inizio", "Data_Fine");
+CREATE INDEX idx_prenotazione_materia ON prenotazione ("Id_Materia");
+
+CREATE FUNCTION prenotazione_esclusiva() RETURNS trigger AS $$
+BEGIN
+    NEW."Esclusiva" := COALESCE(
+        (SELECT "Politica_Prenotazione" = 'esclusiva' FROM aula WHERE "Id_Aula" = NEW."Id_Aula"),
+        FALSE
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tr_prenotazione_esclusiva
+    BEFORE INSERT OR UPDATE OF "Id_Aula" ON prenotazione
+    FOR EACH ROW EXECUTE FUNCTION prenotazione_esclusiva();
+
+-- Un'aula che diventa esclusiva vincola solo le prenotazioni non ancora terminate:
+-- se tra queste ce ne sono di attive sovrapposte l'UPDATE dell'aula fallisce
+CREATE FUNCTION aula_politica_aggiornata() RETURNS trigger AS $$
+BEGIN
+    IF NEW."Politica_Prenotazione" = 'esclusiva' THEN
+        UPDATE prenotazione SET "Esclusiva" = TRUE
+        WHERE "Id_Aula" = NEW."Id_Aula" AND "Data_Fine" > (now() AT TIME ZONE 'UTC');
+    ELSE
+        UPDATE prenotazione SET "Esclusiva" = FALSE
+        WHERE "Id_Aula" = NEW."Id_Aula" AND "Esclusiva";
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tr_aula_politica_aggiornata
+    AFTER UPDATE OF "Politica_Prenotazione" ON aula
+    FOR EACH ROW
+    WHEN (OLD."Politica_Prenotazione" IS DISTINCT FROM NEW."Politica_Prenotazione")
+    EXECUTE FUNCTION aula_politica_aggiornata();
+
+CREATE TABLE prenotazione_attrezzatura (
+    "Id_Prenotazione" INT4 NOT NULL REFERENCES prenotazione ("Id_Prenotazione") ON DELETE CASCADE,
+    "Id_Attrezzatura" INT4 NOT NULL REFERENCES attrezzatura ("Id_Attrezzatura") ON DELETE CASCADE,
+    "Quantita" INT4 NOT NULL DEFAULT 1 CHECK ("Quantita" > 0),
+    PRIMARY KEY ("Id_Prenotazione", "Id_Attrezzatura")
+);
+CREATE INDEX idx_prenotazione_attrezzatura_attrezzatura ON prenotazione_attrezzatura ("Id_Attrezzatura");
+
+-- Indisponibilità delle aule decise dagli amministratori, anche ricorrenti
+CREATE TABLE blocco_aula (
+    "Id_Blocco" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Aula" INT4 NOT NULL REFERENCES aula ("Id_Aula") ON DELETE CASCADE,
+    "Data_Inizio" TIMESTAMP NOT NULL,
+    "Data_Fine" TIMESTAMP NOT NULL,
+    "Motivo" VARCHAR(255) NOT NULL,
+    "Ricorrenza" VARCHAR(16) NOT NULL DEFAULT 'nessuna' CHECK ("Ricorrenza" IN ('nessuna', 'giornaliera', 'settimanale')),
+    "Ricorrenza_Fine" TIMESTAMP NULL,
+    CHECK ("Data_Inizio" < "Data_Fine")
+);
+CREATE INDEX idx_blocco_aula_date ON blocco_aula ("Id_Aula", "Data_Inizio", "Data_Fine");
+
+-- Data_Aggiornamento viene impostata esplicitamente da ogni UPDATE
+CREATE TABLE segnalazione_guasto (
+    "Id_Segnalazione" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Aula" INT4 NULL REFERENCES aula ("Id_Aula") ON DELETE CASCADE,
+    "Id_Attrezzatura" INT4 NULL REFERENCES attrezzatura ("Id_Attrezzatura") ON DELETE CASCADE,
+    "Id_Segnalatore" INT4 NOT NULL REFERENCES professore ("Id_Professore"),
+    "Gravita" VARCHAR(8) NOT NULL CHECK ("Gravita" IN ('bassa', 'media', 'alta')),
+    "Descrizione" TEXT NOT NULL,
+    "Stato" VARCHAR(16) NOT NULL DEFAULT 'aperta' CHECK ("Stato" IN ('aperta', 'in_lavorazione', 'risolta')),
+    "Blocca_Aula" BOOLEAN NOT NULL DEFAULT FALSE,
+    "Note_Tecnico" TEXT NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+    "Data_Aggiornamento" TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
+CREATE INDEX idx_segnalazione_aula_stato ON segnalazione_guasto ("Id_Aula", "Stato");
+CREATE INDEX idx_segnalazione_attrezzatura ON segnalazione_guasto ("Id_Attrezzatura");
+CREATE INDEX idx_segnalazione_segnalatore ON segnalazione_guasto ("Id_Segnalatore");
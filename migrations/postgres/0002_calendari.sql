@@ -0,0 +1,18 @@
+-- Feed iCalendar personali e risorse create dai client CalDAV
+
+CREATE TABLE feed_token (
+    "Id_Professore" INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore ("Id_Professore") ON DELETE CASCADE,
+    "Token" VARCHAR(64) NOT NULL UNIQUE,
+    "Data_Creazione" TIMESTAMP NOT NULL
+);
+
+-- Nome e UID scelti dal client per le prenotazioni create via CalDAV
+CREATE TABLE risorsa_caldav (
+    "Id_Prenotazione" INT4 NOT NULL PRIMARY KEY
+        REFERENCES prenotazione ("Id_Prenotazione") ON DELETE CASCADE,
+    "Nome_Risorsa" VARCHAR(255) NOT NULL,
+    "Uid" VARCHAR(255) NOT NULL
+);
+CREATE INDEX idx_risorsa_caldav_uid ON risorsa_caldav ("Uid");
+CREATE INDEX idx_risorsa_caldav_nome ON risorsa_caldav ("Nome_Risorsa");
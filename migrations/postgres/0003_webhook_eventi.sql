@@ -0,0 +1,40 @@
+-- Webhook in uscita e registro degli eventi per gli aggiornamenti in tempo reale
+
+CREATE TABLE webhook (
+    "Id_Webhook" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Url" VARCHAR(2048) NOT NULL,
+    "Segreto" VARCHAR(128) NOT NULL,
+    "Eventi" VARCHAR(255) NOT NULL, -- Tipi di evento separati da virgole
+    "Formato" VARCHAR(8) NOT NULL DEFAULT 'json' CHECK ("Formato" IN ('json', 'teams')),
+    "Attivo" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Descrizione" VARCHAR(255) NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL
+);
+
+CREATE TABLE consegna_webhook (
+    "Id_Consegna" INT8 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Webhook" INT4 NOT NULL REFERENCES webhook ("Id_Webhook") ON DELETE CASCADE,
+    "Evento" VARCHAR(64) NOT NULL,
+    "Payload" TEXT NOT NULL,
+    "Stato" VARCHAR(16) NOT NULL DEFAULT 'in_attesa' CHECK ("Stato" IN ('in_attesa', 'consegnata', 'fallita')),
+    "Tentativi" INT4 NOT NULL DEFAULT 0,
+    "Prossimo_Tentativo" TIMESTAMP NOT NULL,
+    "Ultimo_Codice" INT4 NULL,
+    "Ultimo_Errore" TEXT NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL,
+    "Data_Consegna" TIMESTAMP NULL
+);
+CREATE INDEX idx_consegna_stato_tentativo ON consegna_webhook ("Stato", "Prossimo_Tentativo");
+CREATE INDEX idx_consegna_webhook ON consegna_webhook ("Id_Webhook", "Id_Consegna");
+
+-- Nessuna chiave esterna: l'evento resta valido anche se la prenotazione viene eliminata
+CREATE TABLE evento_prenotazione (
+    "Id_Evento" INT8 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Tipo" VARCHAR(64) NOT NULL,
+    "Id_Prenotazione" INT4 NOT NULL,
+    "Id_Aula" INT4 NULL,
+    "Data_Inizio" TIMESTAMP NOT NULL,
+    "Data_Fine" TIMESTAMP NOT NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL
+);
+CREATE INDEX idx_evento_creazione ON evento_prenotazione ("Data_Creazione");
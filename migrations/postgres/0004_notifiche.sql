@@ -0,0 +1,36 @@
+-- Coda delle email, preferenze di notifica e attività pianificate già eseguite
+
+CREATE TABLE email_in_uscita (
+    "Id_Email" INT8 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Professore" INT4 NOT NULL REFERENCES professore ("Id_Professore") ON DELETE CASCADE,
+    "Destinatario" VARCHAR(255) NOT NULL,
+    "Oggetto" VARCHAR(255) NOT NULL,
+    "Corpo" TEXT NOT NULL,
+    "Stato" VARCHAR(16) NOT NULL DEFAULT 'in_attesa' CHECK ("Stato" IN ('in_attesa', 'inviata', 'fallita')),
+    "Tentativi" INT4 NOT NULL DEFAULT 0,
+    "Prossimo_Tentativo" TIMESTAMP NOT NULL,
+    "Ultimo_Errore" TEXT NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL,
+    "Data_Invio" TIMESTAMP NULL
+);
+CREATE INDEX idx_email_stato_tentativo ON email_in_uscita ("Stato", "Prossimo_Tentativo");
+
+-- Senza una riga il professore riceve tutte le notifiche
+CREATE TABLE preferenza_notifica (
+    "Id_Professore" INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore ("Id_Professore") ON DELETE CASCADE,
+    "Creata" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Modificata" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Annullata" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Approvata" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Scavalcata" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Promemoria" BOOLEAN NOT NULL DEFAULT TRUE,
+    "Riepilogo" BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+-- Evita di ripetere promemoria e riepiloghi dopo un riavvio o con più istanze
+CREATE TABLE attivita_pianificata (
+    "Chiave" VARCHAR(191) NOT NULL PRIMARY KEY,
+    "Data_Esecuzione" TIMESTAMP NOT NULL
+);
+CREATE INDEX idx_attivita_esecuzione ON attivita_pianificata ("Data_Esecuzione");
@@ -0,0 +1,28 @@
+-- Tablet dei chioschi, PIN per la prenotazione rapida e codici QR per il check-in
+
+CREATE TABLE dispositivo_chiosco (
+    "Id_Dispositivo" INT4 GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    "Id_Aula" INT4 NOT NULL REFERENCES aula ("Id_Aula") ON DELETE CASCADE,
+    "Token" VARCHAR(64) NOT NULL UNIQUE,
+    "Descrizione" VARCHAR(255) NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL,
+    "Ultimo_Accesso" TIMESTAMP NULL
+);
+CREATE INDEX idx_dispositivo_aula ON dispositivo_chiosco ("Id_Aula");
+
+-- HMAC-SHA256 del PIN: univoco, perché il PIN da solo identifica il professore
+CREATE TABLE pin_chiosco (
+    "Id_Professore" INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore ("Id_Professore") ON DELETE CASCADE,
+    "Pin_Hash" CHAR(64) NOT NULL UNIQUE,
+    "Data_Modifica" TIMESTAMP NOT NULL
+);
+
+-- Solo le prenotazioni successive a Data_Attivazione vengono rilasciate senza check-in
+CREATE TABLE checkin_aula (
+    "Id_Aula" INT4 NOT NULL PRIMARY KEY
+        REFERENCES aula ("Id_Aula") ON DELETE CASCADE,
+    "Codice" VARCHAR(64) NOT NULL,
+    "Data_Attivazione" TIMESTAMP NOT NULL,
+    "Data_Creazione" TIMESTAMP NOT NULL
+);
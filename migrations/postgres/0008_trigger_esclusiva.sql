@@ -0,0 +1,25 @@
+-- "Esclusiva" va ricalcolata a ogni UPDATE, non solo quando cambia l'aula: altrimenti un UPDATE
+-- che la imposta esplicitamente (o riattiva una prenotazione) la lascia disallineata dalla politica dell'aula.
+-- Come in aula_politica_aggiornata, una prenotazione già terminata non diventa esclusiva: le sovrapposizioni
+-- passate restano ammesse anche quando la si aggiorna (approvazione, rilascio...).
+
+CREATE OR REPLACE FUNCTION prenotazione_esclusiva() RETURNS trigger AS $$
+DECLARE
+    esclusiva BOOLEAN := COALESCE(
+        (SELECT "Politica_Prenotazione" = 'esclusiva' FROM aula WHERE "Id_Aula" = NEW."Id_Aula"),
+        FALSE
+    );
+BEGIN
+    IF TG_OP = 'UPDATE' AND esclusiva AND NEW."Data_Fine" <= (now() AT TIME ZONE 'UTC') THEN
+        NEW."Esclusiva" := OLD."Esclusiva";
+    ELSE
+        NEW."Esclusiva" := esclusiva;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER tr_prenotazione_esclusiva ON prenotazione;
+CREATE TRIGGER tr_prenotazione_esclusiva
+    BEFORE INSERT OR UPDATE ON prenotazione
+    FOR EACH ROW EXECUTE FUNCTION prenotazione_esclusiva();
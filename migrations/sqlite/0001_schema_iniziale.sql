@@ -0,0 +1,149 @@
+-- Professori, materie, plessi, aule e prenotazioni
+-- Stesso schema di migrations/mysql: INT4 fa leggere le colonne intere come i32 e i CHECK
+-- sostituiscono gli ENUM. Le date sono testo 'YYYY-MM-DD HH:MM:SS' in UTC.
+
+CREATE TABLE professore (
+    Id_Professore INTEGER PRIMARY KEY AUTOINCREMENT,
+    Nome TEXT NULL,
+    Cognome TEXT NOT NULL,
+    Ruolo TEXT NOT NULL DEFAULT 'docente' CHECK (Ruolo IN ('docente', 'tecnico', 'admin'))
+);
+
+CREATE TABLE credenziali (
+    Id_Professore_Cred INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    email TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE materia (
+    Id_Materia INTEGER PRIMARY KEY AUTOINCREMENT,
+    Nome TEXT NOT NULL UNIQUE,
+    Descrizione TEXT NULL
+);
+
+CREATE TABLE insegna (
+    Id_Professore INT4 NOT NULL REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    Id_Materia INT4 NOT NULL REFERENCES materia (Id_Materia) ON DELETE CASCADE,
+    PRIMARY KEY (Id_Professore, Id_Materia)
+);
+CREATE INDEX idx_insegna_materia ON insegna (Id_Materia);
+
+CREATE TABLE plesso (
+    Id_Plesso INTEGER PRIMARY KEY AUTOINCREMENT,
+    Nome TEXT NOT NULL,
+    Indirizzo TEXT NULL
+);
+
+-- Moduli orari di ogni plesso, in ora locale
+CREATE TABLE orario_plesso (
+    Id_Plesso INT4 NOT NULL REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    Numero_Modulo INT4 NOT NULL,
+    Ora_Inizio TIME NOT NULL,
+    Ora_Fine TIME NOT NULL,
+    PRIMARY KEY (Id_Plesso, Numero_Modulo),
+    CHECK (Ora_Inizio < Ora_Fine)
+);
+
+-- Giorni di calendario locali, estremi inclusi
+CREATE TABLE chiusura_plesso (
+    Id_Chiusura INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Plesso INT4 NOT NULL REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    Data_Inizio DATE NOT NULL,
+    Data_Fine DATE NOT NULL,
+    Motivo TEXT NOT NULL,
+    CHECK (Data_Inizio <= Data_Fine)
+);
+CREATE INDEX idx_chiusura_plesso_date ON chiusura_plesso (Id_Plesso, Data_Inizio, Data_Fine);
+
+-- Minuti necessari per spostarsi da un plesso all'altro
+CREATE TABLE distanza_plessi (
+    Id_Plesso_Da INT4 NOT NULL REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    Id_Plesso_A INT4 NOT NULL REFERENCES plesso (Id_Plesso) ON DELETE CASCADE,
+    Minuti INT4 NOT NULL CHECK (Minuti >= 0),
+    PRIMARY KEY (Id_Plesso_Da, Id_Plesso_A)
+);
+CREATE INDEX idx_distanza_plesso_a ON distanza_plessi (Id_Plesso_A);
+
+CREATE TABLE aula (
+    Id_Aula INTEGER PRIMARY KEY AUTOINCREMENT,
+    Tipo_Aula TEXT NOT NULL,
+    Numero INT4 NOT NULL,
+    Id_Plesso INT4 NOT NULL REFERENCES plesso (Id_Plesso),
+    Politica_Prenotazione TEXT NOT NULL DEFAULT 'esclusiva'
+        CHECK (Politica_Prenotazione IN ('esclusiva', 'condivisa_prenotazioni', 'condivisa_studenti')),
+    Capacita INT4 NULL CHECK (Capacita IS NULL OR Capacita > 0),
+    Richiede_Approvazione BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (Id_Plesso, Tipo_Aula, Numero)
+);
+
+-- Id_Aula NULL per le risorse mobili non installate in un'aula
+CREATE TABLE attrezzatura (
+    Id_Attrezzatura INTEGER PRIMARY KEY AUTOINCREMENT,
+    Nome TEXT NOT NULL,
+    Id_Aula INT4 NULL REFERENCES aula (Id_Aula) ON DELETE SET NULL,
+    Quantita INT4 NOT NULL DEFAULT 1 CHECK (Quantita >= 0),
+    Prenotabile BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE INDEX idx_attrezzatura_aula ON attrezzatura (Id_Aula);
+
+-- Date in UTC. Id_Aula NULL per le prenotazioni di sola attrezzatura;
+-- Approvata NULL se l'aula non richiede approvazione.
+CREATE TABLE prenotazione (
+    Id_Prenotazione INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Professore INT4 NOT NULL REFERENCES professore (Id_Professore),
+    Id_Aula INT4 NULL REFERENCES aula (Id_Aula),
+    Data_Inizio DATETIME NOT NULL,
+    Data_Fine DATETIME NOT NULL,
+    Numero_Studenti INT4 NULL CHECK (Numero_Studenti IS NULL OR Numero_Studenti > 0),
+    Id_Materia INT4 NULL REFERENCES materia (Id_Materia) ON DELETE SET NULL,
+    Stato TEXT NOT NULL DEFAULT 'attiva' CHECK (Stato IN ('attiva', 'annullata', 'rilasciata')),
+    Sequenza INT4 NOT NULL DEFAULT 0,
+    Data_Modifica DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    Approvata BOOLEAN NULL,
+    Check_In DATETIME NULL,
+    CHECK (Data_Inizio < Data_Fine)
+);
+CREATE INDEX idx_prenotazione_aula_date ON prenotazione (Id_Aula, Data_Inizio, Data_Fine);
+CREATE INDEX idx_prenotazione_professore_date ON prenotazione (Id_Professore, Data_Inizio);
+CREATE INDEX idx_prenotazione_date ON prenotazione (Data_Inizio, Data_Fine);
+CREATE INDEX idx_prenotazione_materia ON prenotazione (Id_Materia);
+
+CREATE TABLE prenotazione_attrezzatura (
+    Id_Prenotazione INT4 NOT NULL REFERENCES prenotazione (Id_Prenotazione) ON DELETE CASCADE,
+    Id_Attrezzatura INT4 NOT NULL REFERENCES attrezzatura (Id_Attrezzatura) ON DELETE CASCADE,
+    Quantita INT4 NOT NULL DEFAULT 1 CHECK (Quantita > 0),
+    PRIMARY KEY (Id_Prenotazione, Id_Attrezzatura)
+);
+CREATE INDEX idx_prenotazione_attrezzatura_attrezzatura ON prenotazione_attrezzatura (Id_Attrezzatura);
+
+-- Indisponibilità delle aule decise dagli amministratori, anche ricorrenti
+CREATE TABLE blocco_aula (
+    Id_Blocco INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Aula INT4 NOT NULL REFERENCES aula (Id_Aula) ON DELETE CASCADE,
+    Data_Inizio DATETIME NOT NULL,
+    Data_Fine DATETIME NOT NULL,
+    Motivo TEXT NOT NULL,
+    Ricorrenza TEXT NOT NULL DEFAULT 'nessuna' CHECK (Ricorrenza IN ('nessuna', 'giornaliera', 'settimanale')),
+    Ricorrenza_Fine DATETIME NULL,
+    CHECK (Data_Inizio < Data_Fine)
+);
+CREATE INDEX idx_blocco_aula_date ON blocco_aula (Id_Aula, Data_Inizio, Data_Fine);
+
+-- Data_Aggiornamento viene impostata esplicitamente da ogni UPDATE (SQLite non ha ON UPDATE)
+CREATE TABLE segnalazione_guasto (
+    Id_Segnalazione INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Aula INT4 NULL REFERENCES aula (Id_Aula) ON DELETE CASCADE,
+    Id_Attrezzatura INT4 NULL REFERENCES attrezzatura (Id_Attrezzatura) ON DELETE CASCADE,
+    Id_Segnalatore INT4 NOT NULL REFERENCES professore (Id_Professore),
+    Gravita TEXT NOT NULL CHECK (Gravita IN ('bassa', 'media', 'alta')),
+    Descrizione TEXT NOT NULL,
+    Stato TEXT NOT NULL DEFAULT 'aperta' CHECK (Stato IN ('aperta', 'in_lavorazione', 'risolta')),
+    Blocca_Aula BOOLEAN NOT NULL DEFAULT FALSE,
+    Note_Tecnico TEXT NULL,
+    Data_Creazione DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    Data_Aggiornamento DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_segnalazione_aula_stato ON segnalazione_guasto (Id_Aula, Stato);
+CREATE INDEX idx_segnalazione_attrezzatura ON segnalazione_guasto (Id_Attrezzatura);
+CREATE INDEX idx_segnalazione_segnalatore ON segnalazione_guasto (Id_Segnalatore);
@@ -0,0 +1,40 @@
+-- Webhook in uscita e registro degli eventi per gli aggiornamenti in tempo reale
+
+CREATE TABLE webhook (
+    Id_Webhook INTEGER PRIMARY KEY AUTOINCREMENT,
+    Url TEXT NOT NULL,
+    Segreto TEXT NOT NULL,
+    Eventi TEXT NOT NULL, -- Tipi di evento separati da virgole
+    Formato TEXT NOT NULL DEFAULT 'json' CHECK (Formato IN ('json', 'teams')),
+    Attivo BOOLEAN NOT NULL DEFAULT TRUE,
+    Descrizione TEXT NULL,
+    Data_Creazione DATETIME NOT NULL
+);
+
+CREATE TABLE consegna_webhook (
+    Id_Consegna INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Webhook INT4 NOT NULL REFERENCES webhook (Id_Webhook) ON DELETE CASCADE,
+    Evento TEXT NOT NULL,
+    Payload TEXT NOT NULL,
+    Stato TEXT NOT NULL DEFAULT 'in_attesa' CHECK (Stato IN ('in_attesa', 'consegnata', 'fallita')),
+    Tentativi INT4 NOT NULL DEFAULT 0,
+    Prossimo_Tentativo DATETIME NOT NULL,
+    Ultimo_Codice INT4 NULL,
+    Ultimo_Errore TEXT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    Data_Consegna DATETIME NULL
+);
+CREATE INDEX idx_consegna_stato_tentativo ON consegna_webhook (Stato, Prossimo_Tentativo);
+CREATE INDEX idx_consegna_webhook ON consegna_webhook (Id_Webhook, Id_Consegna);
+
+-- Nessuna chiave esterna: l'evento resta valido anche se la prenotazione viene eliminata
+CREATE TABLE evento_prenotazione (
+    Id_Evento INTEGER PRIMARY KEY AUTOINCREMENT,
+    Tipo TEXT NOT NULL,
+    Id_Prenotazione INT4 NOT NULL,
+    Id_Aula INT4 NULL,
+    Data_Inizio DATETIME NOT NULL,
+    Data_Fine DATETIME NOT NULL,
+    Data_Creazione DATETIME NOT NULL
+);
+CREATE INDEX idx_evento_creazione ON evento_prenotazione (Data_Creazione);
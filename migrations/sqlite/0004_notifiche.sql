@@ -0,0 +1,36 @@
+-- Coda delle email, preferenze di notifica e attività pianificate già eseguite
+
+CREATE TABLE email_in_uscita (
+    Id_Email INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Professore INT4 NOT NULL REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    Destinatario TEXT NOT NULL,
+    Oggetto TEXT NOT NULL,
+    Corpo TEXT NOT NULL,
+    Stato TEXT NOT NULL DEFAULT 'in_attesa' CHECK (Stato IN ('in_attesa', 'inviata', 'fallita')),
+    Tentativi INT4 NOT NULL DEFAULT 0,
+    Prossimo_Tentativo DATETIME NOT NULL,
+    Ultimo_Errore TEXT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    Data_Invio DATETIME NULL
+);
+CREATE INDEX idx_email_stato_tentativo ON email_in_uscita (Stato, Prossimo_Tentativo);
+
+-- Senza una riga il professore riceve tutte le notifiche
+CREATE TABLE preferenza_notifica (
+    Id_Professore INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    Creata BOOLEAN NOT NULL DEFAULT TRUE,
+    Modificata BOOLEAN NOT NULL DEFAULT TRUE,
+    Annullata BOOLEAN NOT NULL DEFAULT TRUE,
+    Approvata BOOLEAN NOT NULL DEFAULT TRUE,
+    Scavalcata BOOLEAN NOT NULL DEFAULT TRUE,
+    Promemoria BOOLEAN NOT NULL DEFAULT TRUE,
+    Riepilogo BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+-- Evita di ripetere promemoria e riepiloghi dopo un riavvio o con più istanze
+CREATE TABLE attivita_pianificata (
+    Chiave TEXT NOT NULL PRIMARY KEY,
+    Data_Esecuzione DATETIME NOT NULL
+);
+CREATE INDEX idx_attivita_esecuzione ON attivita_pianificata (Data_Esecuzione);
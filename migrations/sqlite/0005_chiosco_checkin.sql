@@ -0,0 +1,28 @@
+-- Tablet dei chioschi, PIN per la prenotazione rapida e codici QR per il check-in
+
+CREATE TABLE dispositivo_chiosco (
+    Id_Dispositivo INTEGER PRIMARY KEY AUTOINCREMENT,
+    Id_Aula INT4 NOT NULL REFERENCES aula (Id_Aula) ON DELETE CASCADE,
+    Token TEXT NOT NULL UNIQUE,
+    Descrizione TEXT NULL,
+    Data_Creazione DATETIME NOT NULL,
+    Ultimo_Accesso DATETIME NULL
+);
+CREATE INDEX idx_dispositivo_aula ON dispositivo_chiosco (Id_Aula);
+
+-- HMAC-SHA256 del PIN: univoco, perché il PIN da solo identifica il professore
+CREATE TABLE pin_chiosco (
+    Id_Professore INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    Pin_Hash TEXT NOT NULL UNIQUE,
+    Data_Modifica DATETIME NOT NULL
+);
+
+-- Solo le prenotazioni successive a Data_Attivazione vengono rilasciate senza check-in
+CREATE TABLE checkin_aula (
+    Id_Aula INT4 NOT NULL PRIMARY KEY
+        REFERENCES aula (Id_Aula) ON DELETE CASCADE,
+    Codice TEXT NOT NULL,
+    Data_Attivazione DATETIME NOT NULL,
+    Data_Creazione DATETIME NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Lingua preferita del professore per messaggi, email e documenti (NULL = quella del browser)
+
+ALTER TABLE professore ADD COLUMN Lingua TEXT NULL CHECK (Lingua IN ('it', 'en'));
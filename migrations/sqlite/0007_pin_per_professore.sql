@@ -0,0 +1,13 @@
+-- Al tablet il professore si identifica con il proprio codice (Id_Professore) oltre al PIN,
+-- quindi i PIN non devono più essere unici. SQLite non rimuove un vincolo UNIQUE: si ricrea la tabella.
+
+CREATE TABLE pin_chiosco_nuova (
+    Id_Professore INT4 NOT NULL PRIMARY KEY
+        REFERENCES professore (Id_Professore) ON DELETE CASCADE,
+    Pin_Hash TEXT NOT NULL,
+    Data_Modifica DATETIME NOT NULL
+);
+INSERT INTO pin_chiosco_nuova (Id_Professore, Pin_Hash, Data_Modifica)
+    SELECT Id_Professore, Pin_Hash, Data_Modifica FROM pin_chiosco;
+DROP TABLE pin_chiosco;
+ALTER TABLE pin_chiosco_nuova RENAME TO pin_chiosco;
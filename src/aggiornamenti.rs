@@ -0,0 +1,243 @@
+// src/aggiornamenti.rs
+// Aggiornamenti in tempo reale del calendario tramite Server-Sent Events.
+// Ogni evento pubblicato viene salvato in evento_prenotazione (l'id progressivo è anche l'id SSE)
+// e inoltrato ai client collegati. Alla riconnessione il browser invia Last-Event-ID e riceve
+// gli eventi persi nel frattempo; se sono troppi, o già eliminati, riceve un evento "ricarica"
+// e ricarica il calendario per intero.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::Serialize;
+use rocket::{Shutdown, State};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::auth_utils::{self, UtenteAutenticato};
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::metriche;
+use crate::models::TipoEvento;
+
+// Eventi in memoria per i client lenti prima che perdano il passo
+const CAPACITA_CANALE: usize = 256;
+const MASSIMO_ARRETRATI: i64 = 500;
+const GIORNI_CONSERVAZIONE: i64 = 2;
+const INTERVALLO_PULIZIA_SECONDI: u64 = 60 * 60;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AggiornamentoPrenotazione {
+    pub id: u64,
+    pub evento: TipoEvento,
+    pub id_prenotazione: i32,
+    pub id_aula: Option<i32>,
+    pub id_aula_precedente: Option<i32>, // Solo se la modifica ha cambiato aula
+    pub inizio: DateTime<Utc>,
+    pub fine: DateTime<Utc>,
+}
+
+impl AggiornamentoPrenotazione {
+    // Stessa semantica di sovrapposizione dei filtri start/end di GET /prenotazioni.
+    // Una prenotazione spostata interessa sia l'aula di arrivo sia quella di partenza.
+    fn corrisponde(&self, aula: Option<i32>, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> bool {
+        aula.is_none_or(|aula| self.id_aula == Some(aula) || self.id_aula_precedente == Some(aula))
+            && start.is_none_or(|start| self.fine > start)
+            && end.is_none_or(|end| self.inizio < end)
+    }
+}
+
+fn canale() -> &'static broadcast::Sender<AggiornamentoPrenotazione> {
+    static CANALE: OnceLock<broadcast::Sender<AggiornamentoPrenotazione>> = OnceLock::new();
+    CANALE.get_or_init(|| broadcast::channel(CAPACITA_CANALE).0)
+}
+
+// Salva l'evento con lo stato attuale della prenotazione e lo inoltra ai client collegati
+pub async fn registra(db_pool: &DbPool, tipo: TipoEvento, id_prenotazione: i32, id_aula_precedente: Option<i32>) -> Result<(), sqlx::Error> {
+    let (id_aula, inizio, fine) = match db::aggiornamenti::stato_prenotazione(db_pool, id_prenotazione).await? {
+        Some(prenotazione) => prenotazione,
+        None => return Ok(()),
+    };
+    let id_aula_precedente = id_aula_precedente.filter(|precedente| Some(*precedente) != id_aula);
+    let id = db::aggiornamenti::inserisci(db_pool, tipo.as_db(), id_prenotazione, id_aula, id_aula_precedente, inizio, fine).await?;
+
+    // Nessun client collegato non è un errore
+    let _ = canale().send(AggiornamentoPrenotazione {
+        id,
+        evento: tipo,
+        id_prenotazione,
+        id_aula,
+        id_aula_precedente,
+        inizio: inizio.and_utc(),
+        fine: fine.and_utc(),
+    });
+    Ok(())
+}
+
+// Eventi successivi a `ultimo`, oppure None se il client è rimasto indietro oltre la conservazione
+async fn arretrati(db_pool: &DbPool, ultimo: u64) -> Result<Option<Vec<AggiornamentoPrenotazione>>, sqlx::Error> {
+    let primo_conservato = db::aggiornamenti::primo_conservato(db_pool).await?;
+    if primo_conservato.is_some_and(|primo| primo > ultimo.saturating_add(1)) {
+        return Ok(None);
+    }
+
+    let righe = db::aggiornamenti::successivi(db_pool, ultimo, MASSIMO_ARRETRATI).await?;
+    if righe.len() as i64 >= MASSIMO_ARRETRATI {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        righe
+            .into_iter()
+            .map(|r| AggiornamentoPrenotazione {
+                id: r.Id_Evento,
+                evento: TipoEvento::from_db(&r.Tipo),
+                id_prenotazione: r.Id_Prenotazione,
+                id_aula: r.Id_Aula,
+                id_aula_precedente: r.Id_Aula_Precedente,
+                inizio: r.Data_Inizio.and_utc(),
+                fine: r.Data_Fine.and_utc(),
+            })
+            .collect(),
+    ))
+}
+
+// Gli eventi servono solo a riallineare i client che si riconnettono: si tengono per pochi giorni
+pub async fn avvia_pulizia(db_pool: DbPool) {
+    loop {
+        let limite = Utc::now() - Duration::days(GIORNI_CONSERVAZIONE);
+        let esito = db::aggiornamenti::elimina_precedenti(&db_pool, limite).await;
+        metriche::lavoro("pulizia_eventi", &esito);
+        if let Err(e) = esito {
+            tracing::error!(errore = %e, "Errore DB nella pulizia degli eventi delle prenotazioni");
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(INTERVALLO_PULIZIA_SECONDI)).await;
+    }
+}
+
+// Intestazione Last-Event-ID inviata da EventSource quando si riconnette
+pub struct UltimoEventoId(Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UltimoEventoId {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(UltimoEventoId(
+            req.headers().get_one("Last-Event-ID").and_then(|id| id.trim().parse().ok()),
+        ))
+    }
+}
+
+fn evento_sse(aggiornamento: &AggiornamentoPrenotazione) -> Event {
+    Event::json(aggiornamento)
+        .id(aggiornamento.id.to_string())
+        .event(aggiornamento.evento.as_db())
+}
+
+// EventSource non può impostare l'header Authorization: il JWT si può passare anche in `token`.
+// `ultimo` fa le veci di Last-Event-ID per i client che non lo inviano da soli.
+#[get("/prenotazioni/stream?<token>&<aula>&<start>&<end>&<ultimo>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn stream_prenotazioni(
+    db_pool: &State<DbPool>,
+    utente: Option<UtenteAutenticato>,
+    token: Option<&str>,
+    aula: Option<i32>,
+    start: Option<&str>,
+    end: Option<&str>,
+    ultimo: Option<u64>,
+    ultimo_evento: UltimoEventoId,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![Event + 'static], ErroreApi> {
+    if utente.is_none() {
+        let token = token.ok_or_else(|| ErroreApi::non_autenticato("token_mancante", "Token mancante."))?;
+        auth_utils::decodifica_token(token)?;
+    }
+    let start = crate::parse_data_opzionale(start, "start")?;
+    let end = crate::parse_data_opzionale(end, "end")?;
+
+    // Ci si iscrive prima di leggere gli arretrati, così nessun evento cade nel mezzo
+    let mut ricevitore = canale().subscribe();
+    let arretrati = match ultimo_evento.0.or(ultimo) {
+        Some(ultimo) => arretrati(db_pool.inner(), ultimo).await.map_err(|e| {
+            ErroreApi::interno("DB nel recuperare gli eventi delle prenotazioni", e)
+        })?,
+        None => Some(Vec::new()),
+    };
+
+    Ok(EventStream! {
+        let mut inviati = HashSet::new();
+        match arretrati {
+            Some(arretrati) => {
+                for aggiornamento in arretrati {
+                    inviati.insert(aggiornamento.id);
+                    if aggiornamento.corrisponde(aula, start, end) {
+                        yield evento_sse(&aggiornamento);
+                    }
+                }
+            }
+            None => yield Event::data("").event("ricarica"),
+        }
+
+        loop {
+            let aggiornamento = rocket::tokio::select! {
+                ricevuto = ricevitore.recv() => match ricevuto {
+                    Ok(aggiornamento) => aggiornamento,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => {
+                        yield Event::data("").event("ricarica");
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            if inviati.remove(&aggiornamento.id) || !aggiornamento.corrisponde(aula, start, end) {
+                continue;
+            }
+            yield evento_sse(&aggiornamento);
+        }
+    })
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::prenotazioni;
+    use crate::prova::{self, richiesta, utc};
+
+    #[tokio::test]
+    async fn una_prenotazione_spostata_arriva_anche_a_chi_segue_l_aula_di_partenza() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let partenza = prova::aula(&db_pool, "esclusiva", None).await;
+        let arrivo = prova::aula(&db_pool, "esclusiva", None).await;
+        let altra = prova::aula(&db_pool, "esclusiva", None).await;
+        let id_prenotazione = prenotazioni::crea(&db_pool, &richiesta(id_professore, partenza, "2030-01-07T08:00:00Z", "2030-01-07T09:00:00Z", None))
+            .await
+            .unwrap();
+
+        let precedente = prenotazioni::modifica(&db_pool, id_prenotazione, Some(arrivo), utc("2030-01-07T08:00:00Z"), utc("2030-01-07T09:00:00Z"), None)
+            .await
+            .unwrap();
+        assert_eq!(precedente, Some(partenza));
+        registra(&db_pool, TipoEvento::Modificata, id_prenotazione, precedente).await.unwrap();
+        // Spostata solo di orario: l'aula precedente non si salva
+        let precedente = prenotazioni::modifica(&db_pool, id_prenotazione, Some(arrivo), utc("2030-01-07T10:00:00Z"), utc("2030-01-07T11:00:00Z"), None)
+            .await
+            .unwrap();
+        registra(&db_pool, TipoEvento::Modificata, id_prenotazione, precedente).await.unwrap();
+
+        let eventi = arretrati(&db_pool, 0).await.unwrap().unwrap();
+        assert_eq!(eventi.len(), 2);
+        let (spostamento, cambio_orario) = (&eventi[0], &eventi[1]);
+        assert_eq!((spostamento.id_aula, spostamento.id_aula_precedente), (Some(arrivo), Some(partenza)));
+        assert!(spostamento.corrisponde(Some(partenza), None, None));
+        assert!(spostamento.corrisponde(Some(arrivo), None, None));
+        assert!(!spostamento.corrisponde(Some(altra), None, None));
+        assert_eq!(cambio_orario.id_aula_precedente, None);
+        assert!(!cambio_orario.corrisponde(Some(partenza), None, None));
+    }
+}
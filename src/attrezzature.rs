@@ -0,0 +1,104 @@
+// src/attrezzature.rs
+// Attrezzatura delle aule e risorse mobili prenotabili (carrelli portatili,
+// proiettori, kit di microscopi...). A differenza delle aule una risorsa ha più
+// unità: una richiesta è in conflitto solo se nello stesso momento verrebbero
+// impegnate più unità di quante ne esistono.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::db::{self, DbConnection, DbPool};
+use crate::errori::ErroreApi;
+use crate::models;
+
+fn utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(naive, Utc)
+}
+
+// Intervallo in cui una prenotazione impegna una risorsa e quante unità (o posti) occupa
+pub type Utilizzo = (DateTime<Utc>, DateTime<Utc>, i32);
+
+// Massimo numero di unità impegnate contemporaneamente dagli utilizzi dati
+pub fn picco_utilizzo(utilizzi: &[Utilizzo]) -> i32 {
+    let mut eventi: Vec<(DateTime<Utc>, i32)> = utilizzi
+        .iter()
+        .flat_map(|&(inizio, fine, quantita)| [(inizio, quantita), (fine, -quantita)])
+        .collect();
+    // A parità di istante le uscite vengono prima degli ingressi: 9:00-10:00 e 10:00-11:00 non si sovrappongono
+    eventi.sort_by_key(|&(istante, delta)| (istante, delta));
+
+    let mut corrente = 0;
+    let mut picco = 0;
+    for (_, delta) in eventi {
+        corrente += delta;
+        picco = picco.max(corrente);
+    }
+    picco
+}
+
+// Utilizzi della risorsa da parte di prenotazioni che si sovrappongono all'intervallo,
+// già tagliati sull'intervallo stesso
+pub async fn utilizzi_nel_periodo(
+    conn: &mut DbConnection,
+    id_attrezzatura: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Result<Vec<Utilizzo>, sqlx::Error> {
+    let utilizzi = db::attrezzature::utilizzi(conn, id_attrezzatura, inizio, fine).await?;
+    Ok(utilizzi
+        .into_iter()
+        .map(|(da, a, quantita)| (utc(da).max(inizio), utc(a).min(fine), quantita))
+        .collect())
+}
+
+// Con `plesso` si ottengono le attrezzature installate nelle sue aule più quelle
+// mobili non legate a un'aula, condivise tra le sedi
+#[get("/attrezzature?<plesso>")]
+pub async fn get_attrezzature(
+    db_pool: &State<DbPool>,
+    plesso: Option<i32>,
+) -> Result<Json<Vec<models::AttrezzaturaApi>>, ErroreApi> {
+    match db::attrezzature::elenco(db_pool.inner(), plesso).await {
+        Ok(attrezzature) => Ok(Json(attrezzature)),
+        Err(e) => {
+            Err(ErroreApi::interno("nel recuperare le attrezzature dal DB", e))
+        }
+    }
+}
+
+// Unità libere di ogni risorsa prenotabile per tutto l'intervallo richiesto
+#[get("/attrezzature/disponibili?<inizio>&<fine>&<plesso>")]
+pub async fn get_attrezzature_disponibili(
+    db_pool: &State<DbPool>,
+    inizio: &str,
+    fine: &str,
+    plesso: Option<i32>,
+) -> Result<Json<Vec<models::AttrezzaturaDisponibileApi>>, ErroreApi> {
+    let inizio = crate::parse_data(inizio, "inizio")?;
+    let fine = crate::parse_data(fine, "fine")?;
+    if fine <= inizio {
+        return Err(ErroreApi::campo("fine", "data_fine_non_successiva", "fine deve essere successiva a inizio."));
+    }
+
+    let errore_interno = |e: sqlx::Error| {
+        ErroreApi::interno("nel calcolare le attrezzature disponibili", e)
+    };
+
+    let mut conn = db_pool.acquire().await.map_err(errore_interno)?;
+    let risorse = db::attrezzature::prenotabili(&mut *conn, plesso).await.map_err(errore_interno)?;
+
+    let mut disponibili = Vec::with_capacity(risorse.len());
+    for (id_attrezzatura, nome, quantita) in risorse {
+        let utilizzi = utilizzi_nel_periodo(&mut conn, id_attrezzatura, inizio, fine)
+            .await
+            .map_err(errore_interno)?;
+        disponibili.push(models::AttrezzaturaDisponibileApi {
+            Id_Attrezzatura: id_attrezzatura,
+            Nome: nome,
+            Quantita: quantita,
+            Disponibili: (quantita - picco_utilizzo(&utilizzi)).max(0),
+        });
+    }
+    Ok(Json(disponibili))
+}
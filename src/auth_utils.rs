@@ -1,11 +1,21 @@
 // src/auth_utils.rs
 use argon2::{
     password_hash::{
-        rand_core::OsRng, // Per generare il sale
+        rand_core::{OsRng, RngCore}, // Per generare il sale e i token casuali
         PasswordHasher, SaltString
     },
     Argon2
 };
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::{Deserialize, Serialize};
+
+use crate::configurazione;
+use crate::errori::ErroreApi;
+use crate::models::Ruolo;
+use crate::registro;
+use crate::traduzioni::Lingua;
 
 // Funzione per hashare la password
 pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
@@ -14,4 +24,99 @@ pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Er
 
     // Hasha la password e restituisci la stringa dell'hash (che include algoritmo, sale, parametri e hash)
     Ok(argon2.hash_password(password.as_bytes(), &salt)?.to_string())
-}
\ No newline at end of file
+}
+
+// Segreto non indovinabile (feed dei calendari, firme dei webhook): 32 byte casuali in esadecimale
+pub fn token_casuale() -> String {
+    let mut byte = [0u8; 32];
+    OsRng.fill_bytes(&mut byte);
+    byte.iter().map(|b| format!("{:02x}", b)).collect()
+}
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Claims {
+    pub sub: String, // Subject (Id_Professore come stringa)
+    pub name: String,
+    pub exp: usize,  // Expiration timestamp (secondi da epoch)
+    #[serde(default)] // I token emessi prima dell'introduzione dei ruoli sono da docente
+    pub ruolo: Ruolo,
+    #[serde(default)] // None: lingua del browser
+    pub lingua: Option<Lingua>,
+}
+
+// Request guard: professore autenticato tramite header `Authorization: Bearer <jwt>`
+#[derive(Debug)]
+pub struct UtenteAutenticato {
+    pub id_professore: i32,
+    pub ruolo: Ruolo,
+    pub lingua: Option<Lingua>,
+}
+
+impl UtenteAutenticato {
+    pub fn is_tecnico(&self) -> bool {
+        matches!(self.ruolo, Ruolo::Tecnico | Ruolo::Admin)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.ruolo == Ruolo::Admin
+    }
+
+    // Per le operazioni riservate agli amministratori: 403 con il codice e il messaggio indicati
+    pub fn richiedi_admin(&self, codice: &'static str, messaggio: &str) -> Result<(), ErroreApi> {
+        if !self.is_admin() {
+            return Err(ErroreApi::vietato(codice, messaggio));
+        }
+        Ok(())
+    }
+}
+
+// JWT valido 7 giorni, emesso al login e quando cambiano i dati che contiene (es. la lingua)
+pub fn emetti_token(id_professore: i32, nome: &str, ruolo: Ruolo, lingua: Option<Lingua>) -> Result<String, ErroreApi> {
+    let jwt_secret = &configurazione::attuale().jwt_secret;
+    let claims = Claims {
+        sub: id_professore.to_string(),
+        name: nome.to_string(),
+        exp: (Utc::now() + Duration::days(7)).timestamp() as usize,
+        ruolo,
+        lingua,
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(jwt_secret.as_ref()))
+        .map_err(|e| ErroreApi::interno("nella generazione del token JWT", e))
+}
+
+// Verifica un JWT emesso al login. Usata anche dove il token non può viaggiare nell'header
+// (ad esempio EventSource nel browser, che lo passa nella query string).
+pub fn decodifica_token(token: &str) -> Result<UtenteAutenticato, ErroreApi> {
+    let jwt_secret = &configurazione::attuale().jwt_secret;
+
+    match decode::<Claims>(token, &DecodingKey::from_secret(jwt_secret.as_ref()), &Validation::default()) {
+        Ok(data) => match data.claims.sub.parse::<i32>() {
+            Ok(id_professore) => Ok(UtenteAutenticato {
+                id_professore,
+                ruolo: data.claims.ruolo,
+                lingua: data.claims.lingua,
+            }),
+            Err(_) => Err(ErroreApi::non_autenticato("token_non_valido", "Token non valido.")),
+        },
+        Err(_) => Err(ErroreApi::non_autenticato("token_non_valido", "Token non valido o scaduto.")),
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UtenteAutenticato {
+    type Error = ErroreApi;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match req.headers().get_one("Authorization").and_then(|h| h.strip_prefix("Bearer ")) {
+            Some(token) => token,
+            None => return Outcome::Error(ErroreApi::non_autenticato("token_mancante", "Token mancante.").in_guardia(req)),
+        };
+        match decodifica_token(token) {
+            Ok(utente) => {
+                registro::imposta_professore(req, utente.id_professore);
+                Outcome::Success(utente)
+            }
+            Err(errore) => Outcome::Error(errore.in_guardia(req)),
+        }
+    }
+}
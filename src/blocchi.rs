@@ -0,0 +1,245 @@
+// src/blocchi.rs
+// Blocchi di indisponibilità delle aule (manutenzione, lavori, chiusure).
+// Un blocco può ripetersi ogni giorno o ogni settimana: le occorrenze vengono
+// calcolate al volo nell'ora locale di Roma, così un blocco "ogni lunedì 8:30"
+// resta alle 8:30 anche dopo il cambio dell'ora legale.
+// Solo un amministratore crea ed elimina i blocchi: un blocco avvisa via email i professori scavalcati.
+
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Europe::Rome;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::db::{self, DbPool, Esecutore};
+use crate::errori::ErroreApi;
+use crate::models::{self, BloccoAulaDb, Ricorrenza};
+use crate::notifiche;
+use crate::traduzioni::Lingua;
+
+// Fin dove espandere una ricorrenza senza data di fine
+pub const ORIZZONTE_RICORRENZE_GIORNI: i64 = 366;
+
+#[derive(Debug, Clone)]
+pub struct OccorrenzaBlocco {
+    pub id_blocco: i32,
+    pub id_aula: i32,
+    pub motivo: String,
+    pub inizio: DateTime<Utc>,
+    pub fine: DateTime<Utc>,
+}
+
+fn utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(naive, Utc)
+}
+
+// Sposta un istante di N giorni mantenendo l'orario locale (Europe/Rome)
+fn sposta_giorni(istante: DateTime<Utc>, giorni: i64) -> DateTime<Utc> {
+    let locale = istante.with_timezone(&Rome).naive_local() + Duration::days(giorni);
+    Rome.from_local_datetime(&locale)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or(istante + Duration::days(giorni)) // Ora "saltata" dal cambio d'ora
+}
+
+// Tutte le occorrenze del blocco che si sovrappongono all'intervallo [da, a)
+pub fn occorrenze(blocco: &BloccoAulaDb, da: DateTime<Utc>, a: DateTime<Utc>) -> Vec<OccorrenzaBlocco> {
+    let inizio = utc(blocco.Data_Inizio);
+    let durata = utc(blocco.Data_Fine) - inizio;
+    let passo = match Ricorrenza::from_db(&blocco.Ricorrenza) {
+        Ricorrenza::Nessuna => 0,
+        Ricorrenza::Giornaliera => 1,
+        Ricorrenza::Settimanale => 7,
+    };
+    let occorrenza = |inizio_occ: DateTime<Utc>| OccorrenzaBlocco {
+        id_blocco: blocco.Id_Blocco,
+        id_aula: blocco.Id_Aula,
+        motivo: blocco.Motivo.clone(),
+        inizio: inizio_occ,
+        fine: inizio_occ + durata,
+    };
+
+    if passo == 0 {
+        return if inizio < a && inizio + durata > da { vec![occorrenza(inizio)] } else { vec![] };
+    }
+
+    let limite = match blocco.Ricorrenza_Fine {
+        Some(fine_ricorrenza) => utc(fine_ricorrenza).min(a),
+        None => a,
+    };
+    // Salta direttamente alle occorrenze vicine a `da` invece di partire dalla prima
+    let primo_indice = ((da - durata - inizio).num_days() / passo - 1).max(0);
+
+    let mut risultato = Vec::new();
+    let mut indice = primo_indice;
+    loop {
+        let inizio_occ = sposta_giorni(inizio, indice * passo);
+        if inizio_occ >= limite {
+            break;
+        }
+        if inizio_occ + durata > da {
+            risultato.push(occorrenza(inizio_occ));
+        }
+        indice += 1;
+    }
+    risultato
+}
+
+// Occorrenze dei blocchi (di un'aula o di tutte) che cadono nell'intervallo [da, a)
+pub async fn occorrenze_nel_periodo(
+    db: impl Esecutore<'_>,
+    id_aula: Option<i32>,
+    da: DateTime<Utc>,
+    a: DateTime<Utc>,
+) -> Result<Vec<OccorrenzaBlocco>, sqlx::Error> {
+    let blocchi = db::blocchi::nel_periodo(db, id_aula, da, a).await?;
+    Ok(blocchi.iter().flat_map(|b| occorrenze(b, da, a)).collect())
+}
+
+fn blocco_api(b: BloccoAulaDb) -> models::BloccoAulaApi {
+    models::BloccoAulaApi {
+        Id_Blocco: b.Id_Blocco,
+        Id_Aula: b.Id_Aula,
+        Data_Inizio: utc(b.Data_Inizio).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        Data_Fine: utc(b.Data_Fine).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        Motivo: b.Motivo,
+        Ricorrenza: Ricorrenza::from_db(&b.Ricorrenza),
+        Ricorrenza_Fine: b.Ricorrenza_Fine.map(|f| utc(f).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+    }
+}
+
+#[post("/aule/<id_aula>/blocchi", format = "json", data = "<payload>")]
+pub async fn crea_blocco(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_aula: i32,
+    payload: Json<models::NuovoBloccoPayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_blocchi", "Solo un amministratore può gestire i blocchi delle aule.")?;
+    let data_inizio = match DateTime::parse_from_rfc3339(&payload.data_inizio) {
+        Ok(dt) => dt.with_timezone(&Utc),
+        Err(_) => return Err(ErroreApi::campo("Data_Inizio", "formato_data_non_valido", "Formato Data_Inizio non valido.")),
+    };
+    let data_fine = match DateTime::parse_from_rfc3339(&payload.data_fine) {
+        Ok(dt) => dt.with_timezone(&Utc),
+        Err(_) => return Err(ErroreApi::campo("Data_Fine", "formato_data_non_valido", "Formato Data_Fine non valido.")),
+    };
+    if data_fine <= data_inizio {
+        return Err(ErroreApi::campo("Data_Fine", "data_fine_non_successiva", "Data_Fine deve essere successiva a Data_Inizio."));
+    }
+    if payload.motivo.trim().is_empty() {
+        return Err(ErroreApi::campo("Motivo", "motivo_obbligatorio", "Il motivo del blocco è obbligatorio."));
+    }
+
+    let ricorrenza = payload.ricorrenza.unwrap_or(Ricorrenza::Nessuna);
+    let ricorrenza_fine = match (&payload.ricorrenza_fine, ricorrenza) {
+        (Some(_), Ricorrenza::Nessuna) => {
+            return Err(ErroreApi::campo("Ricorrenza_Fine", "ricorrenza_mancante", "Ricorrenza_Fine richiede una ricorrenza giornaliera o settimanale."));
+        }
+        (Some(testo), _) => match DateTime::parse_from_rfc3339(testo) {
+            Ok(dt) if dt.with_timezone(&Utc) > data_inizio => Some(dt.with_timezone(&Utc)),
+            Ok(_) => return Err(ErroreApi::campo("Ricorrenza_Fine", "ricorrenza_fine_non_successiva", "Ricorrenza_Fine deve essere successiva a Data_Inizio.")),
+            Err(_) => return Err(ErroreApi::campo("Ricorrenza_Fine", "formato_data_non_valido", "Formato Ricorrenza_Fine non valido.")),
+        },
+        (None, _) => None,
+    };
+    if ricorrenza != Ricorrenza::Nessuna {
+        let passo = if ricorrenza == Ricorrenza::Giornaliera { 1 } else { 7 };
+        if data_fine - data_inizio > Duration::days(passo) {
+            return Err(ErroreApi::campo("Data_Fine", "blocco_piu_lungo_della_ricorrenza", "Un blocco ricorrente non può durare più del suo periodo di ripetizione."));
+        }
+    }
+
+    let motivo = payload.motivo.trim();
+    let id_blocco = match db::blocchi::inserisci(db_pool.inner(), id_aula, data_inizio, data_fine, motivo, ricorrenza.as_db(), ricorrenza_fine).await {
+        Ok(id_blocco) => id_blocco,
+        Err(e) => {
+            if let Some(db_err) = e.as_database_error() {
+                if db_err.is_foreign_key_violation() {
+                    return Err(ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata."));
+                }
+            }
+            return Err(ErroreApi::interno(format!("DB durante la creazione del blocco per l'aula {}", id_aula), e));
+        }
+    };
+
+    // Segnala le prenotazioni già presenti che cadono dentro il blocco
+    let orizzonte = ricorrenza_fine.unwrap_or_else(|| match ricorrenza {
+        Ricorrenza::Nessuna => data_fine,
+        _ => data_inizio + Duration::days(ORIZZONTE_RICORRENZE_GIORNI),
+    });
+    let occorrenze = match occorrenze_nel_periodo(db_pool.inner(), Some(id_aula), data_inizio, orizzonte).await {
+        Ok(occ) => occ.into_iter().filter(|o| o.id_blocco == id_blocco).collect::<Vec<_>>(),
+        Err(e) => {
+            tracing::error!(errore = %e, "Errore DB nel calcolare le occorrenze del blocco {}", id_blocco);
+            Vec::new()
+        }
+    };
+    let prenotazioni = db::prenotazioni::dell_aula_nel_periodo(db_pool.inner(), id_aula, data_inizio, orizzonte)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!(errore = %e, "Errore DB nel cercare le prenotazioni sovrapposte al blocco {}", id_blocco);
+            Vec::new()
+        });
+
+    let sovrapposte: Vec<models::PrenotazioneSovrappostaApi> = prenotazioni
+        .into_iter()
+        .filter(|p| {
+            let (inizio, fine) = (utc(p.Data_Inizio), utc(p.Data_Fine));
+            occorrenze.iter().any(|o| o.inizio < fine && o.fine > inizio)
+        })
+        .map(|p| models::PrenotazioneSovrappostaApi {
+            Id_Prenotazione: p.Id_Prenotazione,
+            Id_Professore: p.Id_Professore,
+            Data_Inizio: utc(p.Data_Inizio).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            Data_Fine: utc(p.Data_Fine).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        })
+        .collect();
+
+    // Avvisa i titolari delle prenotazioni scavalcate dal blocco
+    for prenotazione in &sovrapposte {
+        if let Err(e) = notifiche::accoda_scavalcamento(db_pool.inner(), prenotazione.Id_Prenotazione, motivo).await {
+            tracing::error!(errore = %e, "Errore nell'accodare l'avviso per la prenotazione {}", prenotazione.Id_Prenotazione);
+        }
+    }
+
+    Ok(status::Custom(Status::Created, Json(json!({
+        "status": "successo",
+        "message": lingua.traduci("messaggi.blocco_creato", "Blocco creato con successo!"),
+        "id_blocco": id_blocco,
+        "prenotazioni_sovrapposte": sovrapposte
+    }))))
+}
+
+#[get("/aule/<id_aula>/blocchi")]
+pub async fn get_blocchi_aula(
+    db_pool: &State<DbPool>,
+    id_aula: i32,
+) -> Result<Json<Vec<models::BloccoAulaApi>>, ErroreApi> {
+    match db::blocchi::dell_aula(db_pool.inner(), id_aula).await {
+        Ok(blocchi) => Ok(Json(blocchi.into_iter().map(blocco_api).collect())),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("nel recuperare i blocchi dell'aula {}", id_aula), e))
+        }
+    }
+}
+
+#[delete("/blocchi/<id_blocco>")]
+pub async fn elimina_blocco(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_blocco: i32,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_blocchi", "Solo un amministratore può gestire i blocchi delle aule.")?;
+    match db::blocchi::elimina(db_pool.inner(), id_blocco).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.blocco_eliminato", "Blocco eliminato.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("blocco_non_trovato", "Blocco non trovato.")),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("DB nell'eliminare il blocco {}", id_blocco), e))
+        }
+    }
+}
@@ -0,0 +1,804 @@
+// src/caldav.rs
+// Server CalDAV (RFC 4791): ogni aula è una collezione di calendario, da aggiungere a
+// Thunderbird o al Calendario di iOS. Le prenotazioni create o spostate dal client (PUT di
+// un VEVENT) passano dalle stesse verifiche di creare_prenotazione e modifica_prenotazione;
+// quelle rifiutate ricevono una risposta DAV:error con il motivo.
+//
+// Rocket 0.5 rifiuta i metodi WebDAV (PROPFIND, REPORT) prima di arrivare alle rotte,
+// quindi CalDAV ha un proprio listener hyper che condivide il pool del database.
+// I client di calendario non gestiscono i JWT: l'accesso usa HTTP Basic con le
+// stesse credenziali del login. I client ripetono le credenziali a ogni richiesta, quindi
+// quelle verificate restano valide per qualche minuto senza ricalcolare Argon2.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Request, Response, Server, StatusCode};
+use sha2::Sha256;
+
+use crate::configurazione;
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::ical::{self, Calendario, Evento};
+use crate::eventi;
+use crate::metriche;
+use crate::models::{Ruolo, TipoEvento};
+use crate::prenotazioni::{self, FiltroPrenotazioni, RichiestaPrenotazione};
+use crate::traduzioni::Lingua;
+
+const DIMENSIONE_MASSIMA_CORPO: usize = 1024 * 1024;
+// Le collezioni espongono le prenotazioni dell'ultimo anno e tutte quelle future
+const GIORNI_PASSATI: i64 = 365;
+// Un cambio di password o di ruolo vale per CalDAV al più dopo questo tempo
+const VALIDITA_CREDENZIALI_SECONDI: u64 = 5 * 60;
+
+const NS: &str = r#"xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav" xmlns:CS="http://calendarserver.org/ns/" xmlns:P="urn:prenotaula:caldav""#;
+
+#[derive(Clone, Copy)]
+struct UtenteDav {
+    id_professore: i32,
+    ruolo: Ruolo,
+}
+
+// Una prenotazione vista come risorsa .ics di una collezione
+struct RisorsaEvento {
+    id_prenotazione: i32,
+    id_professore: i32,
+    nome: String,
+    uid: String,
+    sequenza: i32,
+    numero_studenti: Option<i32>,
+    etag: String,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+    dati: String,
+}
+
+// Percorsi gestiti dal server
+enum Percorso {
+    Radice,
+    Principale(i32),
+    Calendari,
+    Aula(i32),
+    Evento(i32, String),
+    Sconosciuto,
+}
+
+fn leggi_percorso(percorso: &str) -> Percorso {
+    let segmenti: Vec<&str> = percorso.split('/').filter(|s| !s.is_empty()).collect();
+    match segmenti.as_slice() {
+        [] => Percorso::Radice,
+        [".well-known", "caldav"] => Percorso::Radice,
+        ["principals", id] => id.parse().map(Percorso::Principale).unwrap_or(Percorso::Sconosciuto),
+        ["calendari"] => Percorso::Calendari,
+        ["calendari", id] => id.parse().map(Percorso::Aula).unwrap_or(Percorso::Sconosciuto),
+        ["calendari", id, nome] if nome.ends_with(".ics") => id
+            .parse()
+            .map(|id| Percorso::Evento(id, nome.to_string()))
+            .unwrap_or(Percorso::Sconosciuto),
+        _ => Percorso::Sconosciuto,
+    }
+}
+
+fn href_aula(id_aula: i32) -> String {
+    format!("/calendari/{}/", id_aula)
+}
+
+fn nome_predefinito(id_prenotazione: i32) -> String {
+    format!("prenotazione-{}.ics", id_prenotazione)
+}
+
+fn etag(id_prenotazione: i32, sequenza: i32) -> String {
+    format!("\"{}-{}\"", id_prenotazione, sequenza)
+}
+
+fn escape_xml(testo: &str) -> String {
+    testo
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(testo: &str) -> String {
+    testo
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// ---------------------------------------------------------------------------
+// Lettura minima dei corpi XML: servono solo gli href e gli attributi del time-range
+// ---------------------------------------------------------------------------
+
+// Tag di apertura con il nome locale dato (qualunque prefisso di namespace):
+// restituisce gli attributi e la posizione subito dopo il tag
+fn tag_aperti<'a>(corpo: &'a str, nome: &str) -> Vec<(&'a str, usize, bool)> {
+    let mut risultato = Vec::new();
+    let mut posizione = 0;
+    while let Some(inizio) = corpo[posizione..].find('<') {
+        let inizio = posizione + inizio + 1;
+        let fine = match corpo[inizio..].find('>') {
+            Some(fine) => inizio + fine,
+            None => break,
+        };
+        let tag = &corpo[inizio..fine];
+        posizione = fine + 1;
+        if tag.starts_with(['/', '?', '!']) {
+            continue;
+        }
+        let chiuso = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (nome_completo, attributi) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let locale = nome_completo.rsplit(':').next().unwrap_or(nome_completo);
+        if locale == nome {
+            risultato.push((attributi, posizione, chiuso));
+        }
+    }
+    risultato
+}
+
+fn contiene_tag(corpo: &str, nome: &str) -> bool {
+    !tag_aperti(corpo, nome).is_empty()
+}
+
+fn valori_tag(corpo: &str, nome: &str) -> Vec<String> {
+    tag_aperti(corpo, nome)
+        .into_iter()
+        .filter(|(_, _, chiuso)| !chiuso)
+        .map(|(_, dopo, _)| {
+            let testo = &corpo[dopo..];
+            unescape_xml(testo[..testo.find('<').unwrap_or(testo.len())].trim())
+        })
+        .collect()
+}
+
+fn attributo(attributi: &str, nome: &str) -> Option<String> {
+    let inizio = attributi.find(&format!("{}=", nome))? + nome.len() + 1;
+    let resto = &attributi[inizio..];
+    let virgoletta = resto.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let resto = &resto[1..];
+    Some(resto[..resto.find(virgoletta)?].to_string())
+}
+
+fn data_xml(valore: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(valore.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+        .ok()
+        .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+}
+
+// Gli href possono arrivare come URL completi: conta solo il percorso
+fn percorso_href(href: &str) -> &str {
+    match href.split_once("://") {
+        Some((_, resto)) => resto.find('/').map(|i| &resto[i..]).unwrap_or("/"),
+        None => href,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Risposte
+// ---------------------------------------------------------------------------
+
+fn risposta(stato: StatusCode, tipo: &str, corpo: String) -> Response<Body> {
+    Response::builder()
+        .status(stato)
+        .header(header::CONTENT_TYPE, tipo)
+        .body(Body::from(corpo))
+        .unwrap_or_default()
+}
+
+fn vuota(stato: StatusCode) -> Response<Body> {
+    Response::builder().status(stato).body(Body::empty()).unwrap_or_default()
+}
+
+fn multistatus(risposte: Vec<String>) -> Response<Body> {
+    risposta(
+        StatusCode::MULTI_STATUS,
+        "application/xml; charset=utf-8",
+        format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus {}>{}</D:multistatus>", NS, risposte.concat()),
+    )
+}
+
+fn risposta_prop(href: &str, proprieta: &str) -> String {
+    format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        escape_xml(href),
+        proprieta
+    )
+}
+
+fn risposta_non_trovata(href: &str) -> String {
+    format!("<D:response><D:href>{}</D:href><D:status>HTTP/1.1 404 Not Found</D:status></D:response>", escape_xml(href))
+}
+
+// Errore DAV con l'elemento di precondizione violata e il motivo leggibile
+fn errore_dav(stato: StatusCode, precondizione: &str, messaggio: &str) -> Response<Body> {
+    risposta(
+        stato,
+        "application/xml; charset=utf-8",
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error {}>{}<P:motivo>{}</P:motivo></D:error>",
+            NS,
+            precondizione,
+            escape_xml(messaggio)
+        ),
+    )
+}
+
+fn errore_interno(contesto: &str, e: impl Display) -> Response<Body> {
+    tracing::error!(contesto, errore = %e, "Errore interno nel server CalDAV");
+    vuota(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn non_autorizzato() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header(header::WWW_AUTHENTICATE, "Basic realm=\"Prenotaula\", charset=\"UTF-8\"")
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+// ---------------------------------------------------------------------------
+// Accesso ai dati
+// ---------------------------------------------------------------------------
+
+// Credenziali già verificate, indicizzate per HMAC-SHA256 (con JWT_SECRET) di "email:password"
+type CredenzialiVerificate = HashMap<[u8; 32], (UtenteDav, Instant)>;
+
+fn credenziali_verificate() -> &'static Mutex<CredenzialiVerificate> {
+    static VERIFICATE: OnceLock<Mutex<CredenzialiVerificate>> = OnceLock::new();
+    VERIFICATE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn chiave_credenziali(credenziali: &str) -> [u8; 32] {
+    let segreto = &configurazione::attuale().jwt_secret;
+    let mut mac = Hmac::<Sha256>::new_from_slice(segreto.as_bytes()).expect("HMAC accetta chiavi di qualsiasi lunghezza");
+    mac.update(credenziali.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+async fn autentica(db_pool: &DbPool, req: &Request<Body>) -> Result<Option<UtenteDav>, sqlx::Error> {
+    let credenziali = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "))
+        .and_then(|v| BASE64.decode(v.trim()).ok())
+        .and_then(|v| String::from_utf8(v).ok());
+    let Some(credenziali) = credenziali else {
+        return Ok(None);
+    };
+    let Some((email, password)) = credenziali.split_once(':') else {
+        return Ok(None);
+    };
+
+    let chiave = chiave_credenziali(&credenziali);
+    {
+        let mut verificate = credenziali_verificate().lock().unwrap_or_else(|e| e.into_inner());
+        verificate.retain(|_, (_, verificate_il)| verificate_il.elapsed().as_secs() < VALIDITA_CREDENZIALI_SECONDI);
+        if let Some((utente, _)) = verificate.get(&chiave) {
+            return Ok(Some(*utente));
+        }
+    }
+
+    let record = db::professori::credenziali_con_ruolo(db_pool, email).await?;
+    let utente = record.and_then(|(id_professore, password_hash, ruolo)| {
+        let valida = PasswordHash::new(&password_hash)
+            .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            .unwrap_or(false);
+        valida.then(|| UtenteDav { id_professore, ruolo: Ruolo::from_db(&ruolo) })
+    });
+    // Solo le credenziali giuste: quelle sbagliate ripassano sempre da Argon2
+    if let Some(utente) = utente {
+        credenziali_verificate().lock().unwrap_or_else(|e| e.into_inner()).insert(chiave, (utente, Instant::now()));
+    }
+    Ok(utente)
+}
+
+// Nome visualizzato dell'aula, o None se non esiste
+async fn nome_aula(db_pool: &DbPool, id_aula: i32) -> Result<Option<String>, sqlx::Error> {
+    let aula = db::aule::con_plesso(db_pool, id_aula).await?;
+    Ok(aula.map(|a| format!("Aula {} {:02} ({})", a.Tipo_Aula, a.Numero, a.Nome_Plesso)))
+}
+
+// CTag della collezione: cambia a ogni prenotazione creata, annullata o modificata
+async fn ctag_aula(db_pool: &DbPool, id_aula: i32) -> Result<String, sqlx::Error> {
+    let (numero, ultima_modifica) = db::caldav::ctag_aula(db_pool, id_aula).await?;
+    Ok(format!(
+        "{}-{}",
+        numero,
+        ultima_modifica.map(|d| d.and_utc().timestamp()).unwrap_or(0)
+    ))
+}
+
+async fn eventi_aula(
+    db_pool: &DbPool,
+    id_aula: i32,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    lingua: Lingua,
+) -> Result<Vec<RisorsaEvento>, sqlx::Error> {
+    let minimo = Utc::now() - Duration::days(GIORNI_PASSATI);
+    let filtro = FiltroPrenotazioni {
+        id_aula: Some(id_aula),
+        start: Some(start.map_or(minimo, |s| s.max(minimo))),
+        end,
+        ..Default::default()
+    };
+    let elenco = prenotazioni::elenco(db_pool, &filtro).await?;
+
+    // Nome e UID scelti dal client per le prenotazioni create via CalDAV
+    let risorse = db::caldav::risorse_aula(db_pool, id_aula).await?;
+
+    Ok(elenco
+        .into_iter()
+        .map(|voce| {
+            let id = voce.db.Id_Prenotazione;
+            let (nome, uid) = risorse
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| (nome_predefinito(id), ical::uid_prenotazione(id)));
+            let mut calendario = Calendario::risorsa();
+            calendario.aggiungi(&Evento {
+                uid: uid.clone(),
+                sequenza: voce.db.Sequenza,
+                ultima_modifica: DateTime::from_naive_utc_and_offset(voce.db.Data_Modifica, Utc),
+                inizio: voce.inizio,
+                fine: voce.fine,
+                titolo: voce.titolo(lingua),
+                descrizione: Some(voce.descrizione(lingua)),
+                luogo: Some(voce.nome_risorsa),
+                annullato: false,
+            });
+            RisorsaEvento {
+                id_prenotazione: id,
+                id_professore: voce.db.Id_Professore,
+                nome,
+                uid,
+                sequenza: voce.db.Sequenza,
+                numero_studenti: voce.db.Numero_Studenti,
+                etag: etag(id, voce.db.Sequenza),
+                inizio: voce.inizio,
+                fine: voce.fine,
+                dati: calendario.chiudi(),
+            }
+        })
+        .collect())
+}
+
+async fn trova_evento(db_pool: &DbPool, id_aula: i32, nome: &str, lingua: Lingua) -> Result<Option<RisorsaEvento>, sqlx::Error> {
+    Ok(eventi_aula(db_pool, id_aula, None, None, lingua).await?.into_iter().find(|e| e.nome == nome))
+}
+
+// ---------------------------------------------------------------------------
+// Proprietà
+// ---------------------------------------------------------------------------
+
+fn proprieta_principale(utente: &UtenteDav) -> String {
+    format!(
+        "<D:resourcetype><D:collection/><D:principal/></D:resourcetype>\
+         <D:current-user-principal><D:href>/principals/{0}/</D:href></D:current-user-principal>\
+         <D:principal-URL><D:href>/principals/{0}/</D:href></D:principal-URL>\
+         <C:calendar-home-set><D:href>/calendari/</D:href></C:calendar-home-set>",
+        utente.id_professore
+    )
+}
+
+fn proprieta_collezione(nome: &str, ctag: &str) -> String {
+    format!(
+        "<D:resourcetype><D:collection/><C:calendar/></D:resourcetype>\
+         <D:displayname>{}</D:displayname>\
+         <C:supported-calendar-component-set><C:comp name=\"VEVENT\"/></C:supported-calendar-component-set>\
+         <D:supported-report-set>\
+           <D:supported-report><D:report><C:calendar-multiget/></D:report></D:supported-report>\
+           <D:supported-report><D:report><C:calendar-query/></D:report></D:supported-report>\
+         </D:supported-report-set>\
+         <D:current-user-privilege-set>\
+           <D:privilege><D:read/></D:privilege><D:privilege><D:write-content/></D:privilege>\
+           <D:privilege><D:bind/></D:privilege><D:privilege><D:unbind/></D:privilege>\
+         </D:current-user-privilege-set>\
+         <CS:getctag>{}</CS:getctag>",
+        escape_xml(nome),
+        escape_xml(ctag)
+    )
+}
+
+fn proprieta_evento(evento: &RisorsaEvento, con_dati: bool) -> String {
+    let mut proprieta = format!(
+        "<D:resourcetype/><D:getetag>{}</D:getetag><D:getcontenttype>text/calendar; charset=utf-8; component=vevent</D:getcontenttype>",
+        escape_xml(&evento.etag)
+    );
+    if con_dati {
+        proprieta.push_str(&format!("<C:calendar-data>{}</C:calendar-data>", escape_xml(&evento.dati)));
+    }
+    proprieta
+}
+
+// ---------------------------------------------------------------------------
+// Metodi
+// ---------------------------------------------------------------------------
+
+async fn propfind(db_pool: &DbPool, utente: &UtenteDav, percorso: &str, profondita_uno: bool, lingua: Lingua) -> Response<Body> {
+    match leggi_percorso(percorso) {
+        Percorso::Radice => multistatus(vec![risposta_prop(percorso, &proprieta_principale(utente))]),
+        // Ogni professore vede solo il proprio principal
+        Percorso::Principale(id_professore) if id_professore == utente.id_professore => {
+            multistatus(vec![risposta_prop(percorso, &proprieta_principale(utente))])
+        }
+        Percorso::Principale(_) => vuota(StatusCode::NOT_FOUND),
+        Percorso::Calendari => {
+            let mut risposte = vec![risposta_prop("/calendari/", "<D:resourcetype><D:collection/></D:resourcetype><D:displayname>Aule</D:displayname>")];
+            if profondita_uno {
+                let aule = match db::aule::id_aule(db_pool, None).await {
+                    Ok(aule) => aule,
+                    Err(e) => return errore_interno("nell'elencare le aule", e),
+                };
+                for id_aula in aule {
+                    let (nome, ctag) = match (nome_aula(db_pool, id_aula).await, ctag_aula(db_pool, id_aula).await) {
+                        (Ok(Some(nome)), Ok(ctag)) => (nome, ctag),
+                        (Ok(None), _) => continue,
+                        (Err(e), _) | (_, Err(e)) => return errore_interno("nel descrivere le aule", e),
+                    };
+                    risposte.push(risposta_prop(&href_aula(id_aula), &proprieta_collezione(&nome, &ctag)));
+                }
+            }
+            multistatus(risposte)
+        }
+        Percorso::Aula(id_aula) => {
+            let nome = match nome_aula(db_pool, id_aula).await {
+                Ok(Some(nome)) => nome,
+                Ok(None) => return vuota(StatusCode::NOT_FOUND),
+                Err(e) => return errore_interno("nel recuperare l'aula", e),
+            };
+            let ctag = match ctag_aula(db_pool, id_aula).await {
+                Ok(ctag) => ctag,
+                Err(e) => return errore_interno("nel calcolare il ctag", e),
+            };
+            let mut risposte = vec![risposta_prop(&href_aula(id_aula), &proprieta_collezione(&nome, &ctag))];
+            if profondita_uno {
+                match eventi_aula(db_pool, id_aula, None, None, lingua).await {
+                    Ok(eventi) => risposte.extend(eventi.iter().map(|e| {
+                        risposta_prop(&format!("{}{}", href_aula(id_aula), e.nome), &proprieta_evento(e, false))
+                    })),
+                    Err(e) => return errore_interno("nell'elencare le prenotazioni", e),
+                }
+            }
+            multistatus(risposte)
+        }
+        Percorso::Evento(id_aula, nome) => match trova_evento(db_pool, id_aula, &nome, lingua).await {
+            Ok(Some(evento)) => multistatus(vec![risposta_prop(percorso, &proprieta_evento(&evento, false))]),
+            Ok(None) => vuota(StatusCode::NOT_FOUND),
+            Err(e) => errore_interno("nel recuperare la prenotazione", e),
+        },
+        Percorso::Sconosciuto => vuota(StatusCode::NOT_FOUND),
+    }
+}
+
+async fn report(db_pool: &DbPool, percorso: &str, corpo: &str, lingua: Lingua) -> Response<Body> {
+    let id_aula = match leggi_percorso(percorso) {
+        Percorso::Aula(id_aula) => id_aula,
+        _ => return errore_dav(StatusCode::FORBIDDEN, "<D:supported-report/>", "I report sono supportati solo sulle collezioni delle aule."),
+    };
+
+    if contiene_tag(corpo, "calendar-multiget") {
+        let eventi = match eventi_aula(db_pool, id_aula, None, None, lingua).await {
+            Ok(eventi) => eventi,
+            Err(e) => return errore_interno("nel leggere le prenotazioni", e),
+        };
+        let risposte = valori_tag(corpo, "href")
+            .iter()
+            .map(|href| {
+                let percorso_evento = percorso_href(href);
+                match leggi_percorso(percorso_evento) {
+                    Percorso::Evento(id, nome) if id == id_aula => match eventi.iter().find(|e| e.nome == nome) {
+                        Some(evento) => risposta_prop(percorso_evento, &proprieta_evento(evento, true)),
+                        None => risposta_non_trovata(percorso_evento),
+                    },
+                    _ => risposta_non_trovata(percorso_evento),
+                }
+            })
+            .collect();
+        return multistatus(risposte);
+    }
+
+    if contiene_tag(corpo, "calendar-query") {
+        let intervallo = tag_aperti(corpo, "time-range").into_iter().next();
+        let start = intervallo.and_then(|(attributi, _, _)| attributo(attributi, "start")).and_then(|v| data_xml(&v));
+        let end = intervallo.and_then(|(attributi, _, _)| attributo(attributi, "end")).and_then(|v| data_xml(&v));
+        // Senza calendar-data richiesto il client vuole solo gli etag
+        let con_dati = contiene_tag(corpo, "calendar-data");
+        return match eventi_aula(db_pool, id_aula, start, end, lingua).await {
+            Ok(eventi) => multistatus(
+                eventi
+                    .iter()
+                    .filter(|e| start.is_none_or(|s| e.fine > s) && end.is_none_or(|f| e.inizio < f))
+                    .map(|e| risposta_prop(&format!("{}{}", href_aula(id_aula), e.nome), &proprieta_evento(e, con_dati)))
+                    .collect(),
+            ),
+            Err(e) => errore_interno("nel leggere le prenotazioni", e),
+        };
+    }
+
+    errore_dav(StatusCode::FORBIDDEN, "<D:supported-report/>", "Report non supportato.")
+}
+
+async fn get(db_pool: &DbPool, percorso: &str, con_corpo: bool, lingua: Lingua) -> Response<Body> {
+    let (id_aula, nome) = match leggi_percorso(percorso) {
+        Percorso::Evento(id_aula, nome) => (id_aula, nome),
+        Percorso::Sconosciuto => return vuota(StatusCode::NOT_FOUND),
+        _ => return risposta(StatusCode::OK, "text/plain; charset=utf-8", "Server CalDAV delle aule.".to_string()),
+    };
+    match trova_evento(db_pool, id_aula, &nome, lingua).await {
+        Ok(Some(evento)) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .header(header::ETAG, evento.etag)
+            .body(if con_corpo { Body::from(evento.dati) } else { Body::empty() })
+            .unwrap_or_default(),
+        Ok(None) => vuota(StatusCode::NOT_FOUND),
+        Err(e) => errore_interno("nel recuperare la prenotazione", e),
+    }
+}
+
+async fn put(db_pool: &DbPool, utente: &UtenteDav, percorso: &str, req_headers: &hyper::HeaderMap, corpo: &str, lingua: Lingua) -> Response<Body> {
+    let (id_aula, nome) = match leggi_percorso(percorso) {
+        Percorso::Evento(id_aula, nome) => (id_aula, nome),
+        _ => return vuota(StatusCode::METHOD_NOT_ALLOWED),
+    };
+    match nome_aula(db_pool, id_aula).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return vuota(StatusCode::CONFLICT), // Collezione inesistente (RFC 4918, 9.7.1)
+        Err(e) => return errore_interno("nel recuperare l'aula", e),
+    }
+
+    let esistente = match trova_evento(db_pool, id_aula, &nome, lingua).await {
+        Ok(esistente) => esistente,
+        Err(e) => return errore_interno("nel recuperare la prenotazione", e),
+    };
+    // If-Match protegge dalle modifiche perse: l'ETag cambia con Sequenza a ogni modifica
+    let if_match = req_headers.get(header::IF_MATCH).and_then(|v| v.to_str().ok());
+    match &esistente {
+        Some(_) if req_headers.get(header::IF_NONE_MATCH).is_some_and(|v| v == "*") => return vuota(StatusCode::PRECONDITION_FAILED),
+        Some(evento) if if_match.is_some_and(|v| v != "*" && v != evento.etag) => return vuota(StatusCode::PRECONDITION_FAILED),
+        Some(evento) if evento.id_professore != utente.id_professore && utente.ruolo != Ruolo::Admin => {
+            return errore_dav(StatusCode::FORBIDDEN, "<D:need-privileges/>", "Puoi modificare solo le tue prenotazioni.");
+        }
+        None if if_match.is_some() => return vuota(StatusCode::PRECONDITION_FAILED),
+        _ => {}
+    }
+
+    let eventi = match ical::leggi_calendario(corpo) {
+        Ok(eventi) => eventi,
+        Err(messaggio) => return errore_dav(StatusCode::FORBIDDEN, "<C:valid-calendar-data/>", &messaggio),
+    };
+    let evento = match eventi.as_slice() {
+        [Ok(evento)] => evento,
+        [Err((_, motivo))] => return errore_dav(StatusCode::FORBIDDEN, "<C:valid-calendar-data/>", motivo),
+        _ => return errore_dav(StatusCode::FORBIDDEN, "<C:valid-calendar-object-resource/>", "La risorsa deve contenere un solo VEVENT."),
+    };
+    if evento.regola.is_some() {
+        return errore_dav(
+            StatusCode::FORBIDDEN,
+            "<C:valid-calendar-object-resource/>",
+            "Gli eventi ricorrenti non sono supportati: usa l'importazione da file .ics.",
+        );
+    }
+    let uid = evento.uid.clone().unwrap_or_else(|| nome.trim_end_matches(".ics").to_string());
+    let inizio = evento.inizio.utc();
+
+    if let Some(esistente) = esistente {
+        // Una risorsa esistente non può cambiare UID
+        if esistente.uid != uid {
+            return errore_dav(
+                StatusCode::FORBIDDEN,
+                &format!("<C:no-uid-conflict><D:href>{}{}</D:href></C:no-uid-conflict>", href_aula(id_aula), escape_xml(&nome)),
+                "L'UID di una prenotazione non si può cambiare.",
+            );
+        }
+        return aggiorna(db_pool, &esistente, id_aula, inizio, inizio + evento.durata).await;
+    }
+
+    // Lo stesso UID non può comparire due volte nella collezione
+    match db::caldav::risorsa_con_uid(db_pool, id_aula, &uid).await {
+        Ok(Some(altra)) => {
+            return errore_dav(
+                StatusCode::FORBIDDEN,
+                &format!("<C:no-uid-conflict><D:href>{}{}</D:href></C:no-uid-conflict>", href_aula(id_aula), escape_xml(&altra)),
+                "Esiste già una prenotazione con lo stesso UID.",
+            )
+        }
+        Ok(None) => {}
+        Err(e) => return errore_interno("nel controllare l'UID", e),
+    }
+
+    let richiesta = RichiestaPrenotazione {
+        id_professore: utente.id_professore,
+        id_aula: Some(id_aula),
+        data_inizio: inizio,
+        data_fine: inizio + evento.durata,
+        attrezzature: Vec::new(),
+        numero_studenti: None,
+        id_materia: None,
+    };
+
+    let mut tx = match db_pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => return errore_interno("nell'iniziare la transazione", e),
+    };
+    let id_prenotazione = match prenotazioni::inserisci(&mut tx, &richiesta).await {
+        Ok(id) => id,
+        Err(errore) => return prenotazione_rifiutata(errore),
+    };
+    if let Err(e) = db::caldav::inserisci_risorsa(&mut *tx, id_prenotazione, &nome, &uid).await {
+        return errore_interno("nel salvare la risorsa CalDAV", e);
+    }
+    if let Err(e) = tx.commit().await {
+        return errore_interno("nel fare commit della prenotazione", e);
+    }
+    eventi::pubblica(db_pool, TipoEvento::Creata, id_prenotazione).await;
+
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header(header::ETAG, etag(id_prenotazione, 0))
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+// Risposta a una prenotazione rifiutata dalle verifiche di creazione o modifica
+fn prenotazione_rifiutata(errore: ErroreApi) -> Response<Body> {
+    match errore {
+        ErroreApi::Interno { contesto, errore } => errore_interno(&contesto, errore),
+        errore => {
+            // Un conflitto con altre prenotazioni, blocchi o chiusure è un 409; il resto viola una precondizione
+            let stato = if matches!(errore, ErroreApi::Conflitto(_)) {
+                metriche::conflitto(errore.codice());
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::FORBIDDEN
+            };
+            errore_dav(stato, "<P:prenotazione-rifiutata/>", &errore.messaggio())
+        }
+    }
+}
+
+// PUT su una risorsa esistente: sposta la prenotazione sul nuovo orario
+async fn aggiorna(
+    db_pool: &DbPool,
+    esistente: &RisorsaEvento,
+    id_aula: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Response<Body> {
+    let id_prenotazione = esistente.id_prenotazione;
+    let aula_precedente = match prenotazioni::modifica(db_pool, id_prenotazione, Some(id_aula), inizio, fine, esistente.numero_studenti).await {
+        Ok(aula_precedente) => aula_precedente,
+        Err(errore) => return prenotazione_rifiutata(errore),
+    };
+    eventi::pubblica_modifica(db_pool, id_prenotazione, aula_precedente).await;
+
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(header::ETAG, etag(id_prenotazione, esistente.sequenza + 1))
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+async fn delete(db_pool: &DbPool, utente: &UtenteDav, percorso: &str, lingua: Lingua) -> Response<Body> {
+    let (id_aula, nome) = match leggi_percorso(percorso) {
+        Percorso::Evento(id_aula, nome) => (id_aula, nome),
+        _ => return vuota(StatusCode::METHOD_NOT_ALLOWED),
+    };
+    let evento = match trova_evento(db_pool, id_aula, &nome, lingua).await {
+        Ok(Some(evento)) => evento,
+        Ok(None) => return vuota(StatusCode::NOT_FOUND),
+        Err(e) => return errore_interno("nel recuperare la prenotazione", e),
+    };
+    if evento.id_professore != utente.id_professore && utente.ruolo != Ruolo::Admin {
+        return errore_dav(StatusCode::FORBIDDEN, "<D:need-privileges/>", "Puoi annullare solo le tue prenotazioni.");
+    }
+    match prenotazioni::annulla(db_pool, evento.id_prenotazione).await {
+        Ok(annullata) => {
+            if annullata {
+                eventi::pubblica(db_pool, TipoEvento::Annullata, evento.id_prenotazione).await;
+            }
+            vuota(StatusCode::NO_CONTENT)
+        }
+        Err(e) => errore_interno("nell'annullare la prenotazione", e),
+    }
+}
+
+async fn leggi_corpo(body: &mut Body) -> Option<String> {
+    let mut byte = Vec::new();
+    while let Some(pezzo) = body.data().await {
+        byte.extend_from_slice(&pezzo.ok()?);
+        if byte.len() > DIMENSIONE_MASSIMA_CORPO {
+            return None;
+        }
+    }
+    String::from_utf8(byte).ok()
+}
+
+async fn gestisci(db_pool: DbPool, mut req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let metodo = req.method().as_str().to_string();
+    let percorso = req.uri().path().to_string();
+
+    if metodo == "OPTIONS" {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("DAV", "1, 3, calendar-access")
+            .header(header::ALLOW, "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT")
+            .body(Body::empty())
+            .unwrap_or_default());
+    }
+    if percorso.trim_end_matches('/') == "/.well-known/caldav" {
+        return Ok(Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header(header::LOCATION, "/")
+            .body(Body::empty())
+            .unwrap_or_default());
+    }
+
+    let utente = match autentica(&db_pool, &req).await {
+        Ok(Some(utente)) => utente,
+        Ok(None) => return Ok(non_autorizzato()),
+        Err(e) => return Ok(errore_interno("nell'autenticazione", e)),
+    };
+
+    let profondita_uno = req
+        .headers()
+        .get("Depth")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v != "0");
+    // Senza JWT la lingua dei testi degli eventi viene solo da Accept-Language
+    let lingua = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Lingua::da_accept_language)
+        .unwrap_or_default();
+    let intestazioni = req.headers().clone();
+    let corpo = match leggi_corpo(req.body_mut()).await {
+        Some(corpo) => corpo,
+        None => return Ok(vuota(StatusCode::PAYLOAD_TOO_LARGE)),
+    };
+
+    Ok(match metodo.as_str() {
+        "PROPFIND" => propfind(&db_pool, &utente, &percorso, profondita_uno, lingua).await,
+        "REPORT" => report(&db_pool, &percorso, &corpo, lingua).await,
+        "GET" => get(&db_pool, &percorso, true, lingua).await,
+        "HEAD" => get(&db_pool, &percorso, false, lingua).await,
+        "PUT" => put(&db_pool, &utente, &percorso, &intestazioni, &corpo, lingua).await,
+        "DELETE" => delete(&db_pool, &utente, &percorso, lingua).await,
+        _ => vuota(StatusCode::METHOD_NOT_ALLOWED),
+    })
+}
+
+// Avvia il listener CalDAV; in caso di errore il resto dell'applicazione continua a funzionare
+pub async fn avvia(db_pool: DbPool, indirizzo: SocketAddr) {
+    let servizio = make_service_fn(move |_| {
+        let db_pool = db_pool.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| gestisci(db_pool.clone(), req))) }
+    });
+    match Server::try_bind(&indirizzo) {
+        Ok(server) => {
+            tracing::info!("Server CalDAV in ascolto su {}", indirizzo);
+            if let Err(e) = server.serve(servizio).await {
+                tracing::error!(errore = %e, "Server CalDAV terminato con errore");
+            }
+        }
+        Err(e) => tracing::error!(errore = %e, "Impossibile avviare il server CalDAV su {}", indirizzo),
+    }
+}
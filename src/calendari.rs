@@ -0,0 +1,151 @@
+// src/calendari.rs
+// Feed iCalendar in sola lettura (per professore, per aula e per tipo di aula),
+// da aggiungere al calendario del telefono. I client di calendario non sanno inviare
+// il JWT, quindi l'accesso usa un token personale non indovinabile nella query string,
+// che l'utente può revocare o rigenerare in qualsiasi momento.
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::http::ContentType;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+
+use crate::auth_utils::{self, UtenteAutenticato};
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::ical::{self, Calendario, Evento};
+use crate::models::Ruolo;
+use crate::prenotazioni::{self, FiltroPrenotazioni};
+use crate::traduzioni::Lingua;
+
+// Finestra di prenotazioni esportata nei feed
+const GIORNI_PASSATI: i64 = 30;
+const GIORNI_FUTURI: i64 = 365;
+
+type RispostaFeed = Result<(ContentType, String), ErroreApi>;
+
+fn url_feed(token: &str, id_professore: i32) -> JsonValue {
+    json!({
+        "professore": format!("/api/calendario/professore/{}/feed.ics?token={}", id_professore, token),
+        "aula": format!("/api/calendario/aula/<Id_Aula>/feed.ics?token={}", token),
+        "tipo_aula": format!("/api/calendario/tipo/<Tipo_Aula>/feed.ics?token={}", token),
+    })
+}
+
+// Professore e ruolo a cui appartiene il token del feed
+async fn verifica_token(db_pool: &DbPool, token: &str) -> Result<(i32, Ruolo), ErroreApi> {
+    match db::calendari::verifica_token(db_pool, token).await {
+        Ok(Some((id_professore, ruolo))) => Ok((id_professore, Ruolo::from_db(&ruolo))),
+        Ok(None) => Err(ErroreApi::non_autenticato("token_calendario_non_valido", "Token del calendario non valido o revocato.")),
+        Err(e) => {
+            Err(ErroreApi::interno("DB nel verificare il token del calendario", e))
+        }
+    }
+}
+
+async fn genera_feed(db_pool: &DbPool, nome: &str, mut filtro: FiltroPrenotazioni, lingua: Lingua) -> RispostaFeed {
+    let adesso = Utc::now();
+    filtro.start = Some(adesso - Duration::days(GIORNI_PASSATI));
+    filtro.end = Some(adesso + Duration::days(GIORNI_FUTURI));
+    filtro.includi_annullate = true; // I client rimuovono l'evento quando lo vedono annullato
+
+    let elenco = match prenotazioni::elenco(db_pool, &filtro).await {
+        Ok(elenco) => elenco,
+        Err(e) => {
+            return Err(ErroreApi::interno(format!("nel recuperare le prenotazioni per il feed '{}'", nome), e));
+        }
+    };
+
+    let mut calendario = Calendario::new(nome);
+    for voce in elenco {
+        calendario.aggiungi(&Evento {
+            uid: ical::uid_prenotazione(voce.db.Id_Prenotazione),
+            sequenza: voce.db.Sequenza,
+            ultima_modifica: DateTime::from_naive_utc_and_offset(voce.db.Data_Modifica, Utc),
+            inizio: voce.inizio,
+            fine: voce.fine,
+            titolo: voce.titolo(lingua),
+            descrizione: Some(voce.descrizione(lingua)),
+            luogo: Some(voce.nome_risorsa),
+            annullato: voce.db.Stato != "attiva", // Annullata o rilasciata per mancato check-in
+        });
+    }
+    Ok((ContentType::Calendar, calendario.chiudi()))
+}
+
+#[get("/calendario/professore/<id_professore>/feed.ics?<token>")]
+pub async fn feed_professore(db_pool: &State<DbPool>, lingua: Lingua, id_professore: i32, token: &str) -> RispostaFeed {
+    let (proprietario, ruolo) = verifica_token(db_pool.inner(), token).await?;
+    if proprietario != id_professore && ruolo != Ruolo::Admin {
+        return Err(ErroreApi::vietato("calendario_non_consentito", "Puoi iscriverti solo al tuo calendario personale."));
+    }
+    let filtro = FiltroPrenotazioni { id_professore: Some(id_professore), ..Default::default() };
+    genera_feed(db_pool.inner(), "Le mie prenotazioni", filtro, lingua).await
+}
+
+#[get("/calendario/aula/<id_aula>/feed.ics?<token>")]
+pub async fn feed_aula(db_pool: &State<DbPool>, lingua: Lingua, id_aula: i32, token: &str) -> RispostaFeed {
+    verifica_token(db_pool.inner(), token).await?;
+    let nome = match db::aule::con_plesso(db_pool.inner(), id_aula).await {
+        Ok(Some(aula)) => prenotazioni::nome_aula(lingua, &aula.Tipo_Aula, aula.Numero),
+        Ok(None) => return Err(ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata.")),
+        Err(e) => {
+            return Err(ErroreApi::interno(format!("DB nel recuperare l'aula {}", id_aula), e));
+        }
+    };
+    let filtro = FiltroPrenotazioni { id_aula: Some(id_aula), ..Default::default() };
+    genera_feed(db_pool.inner(), &nome, filtro, lingua).await
+}
+
+#[get("/calendario/tipo/<tipo_aula>/feed.ics?<token>")]
+pub async fn feed_tipo_aula(db_pool: &State<DbPool>, lingua: Lingua, tipo_aula: &str, token: &str) -> RispostaFeed {
+    verifica_token(db_pool.inner(), token).await?;
+    let filtro = FiltroPrenotazioni { tipo_aula: Some(tipo_aula.to_string()), ..Default::default() };
+    genera_feed(db_pool.inner(), &format!("Aule {}", tipo_aula), filtro, lingua).await
+}
+
+// Token del feed dell'utente, creato al primo accesso
+#[get("/calendario/token")]
+pub async fn get_token_feed(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let esistente = match db::calendari::token(db_pool.inner(), utente.id_professore).await {
+        Ok(token) => token,
+        Err(e) => {
+            return Err(ErroreApi::interno("DB nel recuperare il token del calendario", e));
+        }
+    };
+    match esistente {
+        Some(token) => Ok(Json(json!({"status": "successo", "token": token, "feed": url_feed(&token, utente.id_professore)}))),
+        None => rigenera_token_feed(db_pool, utente).await,
+    }
+}
+
+// Sostituisce il token: i vecchi link ai feed smettono di funzionare
+#[post("/calendario/token")]
+pub async fn rigenera_token_feed(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let token = auth_utils::token_casuale();
+    match db::calendari::imposta_token(db_pool.inner(), utente.id_professore, &token).await {
+        Ok(_) => Ok(Json(json!({"status": "successo", "token": token, "feed": url_feed(&token, utente.id_professore)}))),
+        Err(e) => {
+            Err(ErroreApi::interno("DB nel generare il token del calendario", e))
+        }
+    }
+}
+
+#[delete("/calendario/token")]
+pub async fn revoca_token_feed(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    match db::calendari::revoca_token(db_pool.inner(), utente.id_professore).await {
+        Ok(_) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.token_calendario_revocato", "Token del calendario revocato.")}))),
+        Err(e) => {
+            Err(ErroreApi::interno("DB nel revocare il token del calendario", e))
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// src/checkin.rs
+// Check-in nelle aule tramite codice QR e rilascio automatico delle prenotazioni non usate.
+// Un amministratore genera il QR di un'aula e lo affigge alla porta: il QR apre la pagina
+// /checkin/<Id_Aula> del frontend con il codice segreto dell'aula, e il professore autenticato
+// conferma così di essere in aula. Solo le aule con un QR attivo partecipano: una prenotazione
+// senza check-in entro CHECKIN_TOLLERANZA_MINUTI (predefinito 15) dall'inizio viene rilasciata
+// (Stato = 'rilasciata') e il professore riceve un'email.
+// URL_PUBBLICO è l'indirizzo da cui è servito il frontend (predefinito http://localhost:8000).
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Europe::Rome;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rocket::http::ContentType;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+
+use crate::auth_utils::{self, UtenteAutenticato};
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::traduzioni::{valori, Lingua};
+use crate::{configurazione, eventi, metriche, models};
+
+const INTERVALLO_CONTROLLO_SECONDI: u64 = 60;
+// Il check-in è accettato già qualche minuto prima dell'inizio
+const ANTICIPO_CHECK_IN_MINUTI: i64 = 10;
+
+fn url_check_in(id_aula: i32, codice: &str) -> String {
+    format!("{}/checkin/{}?codice={}", configurazione::attuale().url_pubblico, id_aula, codice)
+}
+
+// Segna la prenotazione come usata (ad esempio se è stata creata dal tablet dell'aula)
+pub async fn registra_check_in(db_pool: &DbPool, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    db::checkin::registra(db_pool, id_prenotazione).await
+}
+
+// Rilascia le prenotazioni in corso senza check-in oltre la tolleranza.
+// Le prenotazioni create o spostate da poco hanno la tolleranza a partire dalla modifica,
+// quelle ancora da approvare non vengono toccate.
+async fn rilascia_assenti(db_pool: &DbPool, tolleranza: Duration) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now();
+    let limite = adesso - tolleranza;
+    let assenti = db::checkin::assenti(db_pool, limite, adesso).await?;
+
+    for id_prenotazione in assenti {
+        // Il check-in può arrivare proprio adesso: si rilascia solo se manca ancora
+        if db::checkin::rilascia(db_pool, id_prenotazione).await? {
+            eventi::pubblica_rilascio(db_pool, id_prenotazione).await;
+        }
+    }
+    Ok(())
+}
+
+pub async fn avvia_rilascio(db_pool: DbPool) {
+    let tolleranza = configurazione::attuale().tolleranza_check_in;
+    loop {
+        let esito = rilascia_assenti(&db_pool, tolleranza).await;
+        metriche::lavoro("rilascio_check_in", &esito);
+        if let Err(e) = esito {
+            tracing::error!(errore = %e, "Errore DB nel rilasciare le prenotazioni senza check-in");
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(INTERVALLO_CONTROLLO_SECONDI)).await;
+    }
+}
+
+// Conferma la presenza del professore nella sua prenotazione in corso nell'aula
+#[post("/checkin/aula/<id_aula>?<codice>")]
+pub async fn check_in(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_aula: i32,
+    codice: &str,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let valido = db::checkin::codice_valido(db_pool.inner(), id_aula, codice)
+        .await
+        .map_err(|e| ErroreApi::database("nel verificare il codice QR", e))?;
+    if !valido {
+        return Err(ErroreApi::vietato("codice_qr_non_valido", "Codice QR non valido o non più attivo."));
+    }
+
+    let adesso = Utc::now();
+    let (id_prenotazione, data_fine, check_in) = db::checkin::prenotazione_in_corso(
+        db_pool.inner(),
+        id_aula,
+        utente.id_professore,
+        adesso + Duration::minutes(ANTICIPO_CHECK_IN_MINUTI),
+        adesso,
+    )
+        .await
+        .map_err(|e| ErroreApi::database("nel cercare la prenotazione per il check-in", e))?
+        .ok_or_else(|| ErroreApi::non_trovato("nessuna_prenotazione_in_corso", "Non hai prenotazioni in corso in quest'aula."))?;
+
+    if check_in.is_some() {
+        let messaggio = lingua.traduci("messaggi.check_in_gia_registrato", "Check-in già registrato.");
+        return Ok(Json(json!({"status": "successo", "message": messaggio, "id_prenotazione": id_prenotazione})));
+    }
+    registra_check_in(db_pool.inner(), id_prenotazione)
+        .await
+        .map_err(|e| ErroreApi::database("nel registrare il check-in", e))?;
+    let fine = DateTime::<Utc>::from_naive_utc_and_offset(data_fine, Utc).with_timezone(&Rome);
+    let ora = fine.format("%H:%M").to_string();
+    let messaggio = lingua.traduci_con(
+        "messaggi.check_in_registrato",
+        &format!("Check-in registrato: l'aula è tua fino alle {}.", ora),
+        &valori(&[("ora", ora)]),
+    );
+    Ok(Json(json!({
+        "status": "successo",
+        "message": messaggio,
+        "id_prenotazione": id_prenotazione
+    })))
+}
+
+// Codice QR da stampare e affiggere alla porta. Al primo accesso attiva il check-in per l'aula.
+#[get("/aule/<id_aula>/qr.svg")]
+pub async fn qr_aula(db_pool: &State<DbPool>, utente: UtenteAutenticato, id_aula: i32) -> Result<(ContentType, String), ErroreApi> {
+    utente.richiedi_admin("solo_admin_check_in", "Solo un amministratore può gestire il check-in delle aule.")?;
+    if let Err(e) = db::checkin::attiva(db_pool.inner(), id_aula, &auth_utils::token_casuale()).await {
+        return Err(ErroreApi::database("nell'attivare il check-in dell'aula", e));
+    }
+    // L'attivazione non segnala l'aula inesistente: in quel caso qui non c'è nessuna riga
+    let codice = db::checkin::codice(db_pool.inner(), id_aula)
+        .await
+        .map_err(|e| ErroreApi::database("nel recuperare il codice QR dell'aula", e))?
+        .ok_or_else(|| ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata."))?;
+
+    let qr = QrCode::new(url_check_in(id_aula, &codice).as_bytes()).map_err(|e| {
+        ErroreApi::interno(format!("nel generare il codice QR dell'aula {}", id_aula), e)
+    })?;
+    let immagine = qr.render::<svg::Color>().min_dimensions(300, 300).build();
+    Ok((ContentType::SVG, immagine))
+}
+
+// Nuovo codice: i QR già stampati smettono di funzionare
+#[post("/aule/<id_aula>/qr")]
+pub async fn rigenera_qr_aula(db_pool: &State<DbPool>, lingua: Lingua, utente: UtenteAutenticato, id_aula: i32) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_check_in", "Solo un amministratore può gestire il check-in delle aule.")?;
+    let codice = auth_utils::token_casuale();
+    match db::checkin::rigenera(db_pool.inner(), id_aula, &codice).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.codice_qr_rigenerato", "Codice QR rigenerato: stampa e affiggi quello nuovo."), "url": url_check_in(id_aula, &codice)}))),
+        Ok(false) => Err(ErroreApi::non_trovato("check_in_non_attivo", "Il check-in non è attivo per questa aula.")),
+        Err(e) => Err(ErroreApi::database("nel rigenerare il codice QR dell'aula", e)),
+    }
+}
+
+// Disattiva il check-in: le prenotazioni dell'aula non verranno più rilasciate
+#[delete("/aule/<id_aula>/qr")]
+pub async fn disattiva_check_in_aula(db_pool: &State<DbPool>, lingua: Lingua, utente: UtenteAutenticato, id_aula: i32) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_check_in", "Solo un amministratore può gestire il check-in delle aule.")?;
+    match db::checkin::disattiva(db_pool.inner(), id_aula).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.check_in_disattivato", "Check-in disattivato per l'aula.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("check_in_non_attivo", "Il check-in non è attivo per questa aula.")),
+        Err(e) => Err(ErroreApi::database("nel disattivare il check-in dell'aula", e)),
+    }
+}
+
+// Mancati check-in per professore nel periodo, dai più frequenti
+#[get("/report/assenze?<start>&<end>")]
+pub async fn report_assenze(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+    start: Option<&str>,
+    end: Option<&str>,
+) -> Result<Json<Vec<models::AssenzeProfessoreApi>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_check_in", "Solo un amministratore può gestire il check-in delle aule.")?;
+    let start = crate::parse_data_opzionale(start, "start")?;
+    let end = crate::parse_data_opzionale(end, "end")?;
+    // Solo le prenotazioni per cui la tolleranza è già scaduta
+    let limite = Utc::now() - configurazione::attuale().tolleranza_check_in;
+
+    let righe = db::checkin::assenze(db_pool.inner(), limite, start, end)
+        .await
+        .map_err(|e| ErroreApi::database("nel calcolare il report dei mancati check-in", e))?;
+
+    Ok(Json(
+        righe
+            .into_iter()
+            .map(|r| models::AssenzeProfessoreApi {
+                Id_Professore: r.Id_Professore,
+                Nome: r.Nome,
+                Cognome: r.Cognome,
+                Prenotazioni: r.Prenotazioni,
+                Check_In: r.Check_In,
+                Rilasciate: r.Rilasciate,
+                Percentuale_Assenze: if r.Prenotazioni > 0 {
+                    (r.Rilasciate as f64 * 1000.0 / r.Prenotazioni as f64).round() / 10.0
+                } else {
+                    0.0
+                },
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::prenotazioni::{self, RichiestaPrenotazione};
+    use crate::prova::{self, stato};
+
+    // Prenotazione iniziata `iniziata_da` fa, che finisce tra mezz'ora e la cui ultima modifica risale a `modificata_da` fa
+    async fn in_corso(db_pool: &DbPool, id_aula: i32, iniziata_da: Duration, modificata_da: Duration) -> i32 {
+        let id_professore = prova::professore(db_pool, "Rossi").await;
+        let adesso = Utc::now();
+        let richiesta = RichiestaPrenotazione {
+            id_professore,
+            id_aula: Some(id_aula),
+            data_inizio: adesso - iniziata_da,
+            data_fine: adesso + Duration::minutes(30),
+            attrezzature: Vec::new(),
+            numero_studenti: None,
+            id_materia: None,
+        };
+        let id_prenotazione = prenotazioni::crea(db_pool, &richiesta).await.unwrap();
+        sqlx::query("UPDATE prenotazione SET Data_Modifica = ? WHERE Id_Prenotazione = ?")
+            .bind((adesso - modificata_da).naive_utc())
+            .bind(id_prenotazione)
+            .execute(db_pool)
+            .await
+            .unwrap();
+        id_prenotazione
+    }
+
+    // Aula con il QR attivo da un'ora
+    async fn aula_con_qr(db_pool: &DbPool) -> i32 {
+        let id_aula = prova::aula(db_pool, "esclusiva", None).await;
+        db::checkin::attiva(db_pool, id_aula, "codice").await.unwrap();
+        sqlx::query("UPDATE checkin_aula SET Data_Attivazione = ? WHERE Id_Aula = ?")
+            .bind((Utc::now() - Duration::hours(1)).naive_utc())
+            .bind(id_aula)
+            .execute(db_pool)
+            .await
+            .unwrap();
+        id_aula
+    }
+
+    #[tokio::test]
+    async fn rilascia_solo_le_prenotazioni_senza_check_in_oltre_la_tolleranza() {
+        let db_pool = prova::database().await;
+        let (mezz_ora, un_ora) = (Duration::minutes(30), Duration::hours(1));
+
+        let assente = in_corso(&db_pool, aula_con_qr(&db_pool).await, mezz_ora, un_ora).await;
+        let presente = in_corso(&db_pool, aula_con_qr(&db_pool).await, mezz_ora, un_ora).await;
+        registra_check_in(&db_pool, presente).await.unwrap();
+        let appena_iniziata = in_corso(&db_pool, aula_con_qr(&db_pool).await, Duration::minutes(5), un_ora).await;
+        let appena_spostata = in_corso(&db_pool, aula_con_qr(&db_pool).await, mezz_ora, Duration::minutes(5)).await;
+        let senza_qr = in_corso(&db_pool, prova::aula(&db_pool, "esclusiva", None).await, mezz_ora, un_ora).await;
+
+        rilascia_assenti(&db_pool, Duration::minutes(15)).await.unwrap();
+
+        assert_eq!(stato(&db_pool, assente).await, "rilasciata");
+        for id_prenotazione in [presente, appena_iniziata, appena_spostata, senza_qr] {
+            assert_eq!(stato(&db_pool, id_prenotazione).await, "attiva", "prenotazione {}", id_prenotazione);
+        }
+
+        // Il rilascio finisce tra gli aggiornamenti, e la prenotazione liberata non si rilascia due volte
+        let eventi: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM evento_prenotazione WHERE Id_Prenotazione = ?")
+            .bind(assente)
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!(eventi, 1);
+        assert!(!db::checkin::rilascia(&db_pool, assente).await.unwrap());
+    }
+}
@@ -0,0 +1,421 @@
+// src/chiosco.rs
+// Chiosco per i tablet montati fuori dalle aule: stato attuale dell'aula, prossime prenotazioni
+// della giornata e orario fino a cui resta libera, in JSON o come pagina HTML che si aggiorna da sola.
+// Il tablet non ha un utente: ogni dispositivo è registrato da un amministratore per una sola aula
+// e si autentica con il suo token nella query string.
+// Dal tablet si può prenotare al volo il modulo in corso inserendo il proprio codice (Id_Professore)
+// e il PIN personale. I PIN non sono unici, così impostarne uno non rivela nulla su quelli degli altri.
+// Sono salvati come HMAC-SHA256 con JWT_SECRET: cambiare JWT_SECRET invalida tutti i PIN.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use chrono_tz::Europe::Rome;
+use hmac::{Hmac, Mac};
+use minijinja::{context, Environment};
+use rocket::http::{ContentType, Status};
+use rocket::response::status;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+use sha2::Sha256;
+
+use crate::auth_utils::{self, UtenteAutenticato};
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::models::{self, TipoEvento};
+use crate::prenotazioni::{self, FiltroPrenotazioni, RichiestaPrenotazione};
+use crate::traduzioni::{valori, Lingua};
+use crate::{blocchi, checkin, configurazione, eventi, plessi};
+
+const LUNGHEZZA_PIN: usize = 6;
+// Dopo troppi PIN sbagliati il tablet smette di accettare prenotazioni per un po'
+const MASSIMO_TENTATIVI_PIN: u32 = 5;
+const BLOCCO_PIN_SECONDI: u64 = 10 * 60;
+const AGGIORNAMENTO_PAGINA_SECONDI: u64 = 30;
+
+// Id del dispositivo, se il token è registrato per questa aula
+async fn verifica_dispositivo(db_pool: &DbPool, token: &str, id_aula: i32) -> Result<i32, ErroreApi> {
+    let (id_dispositivo, aula_dispositivo) = db::chiosco::dispositivo(db_pool, token)
+        .await
+        .map_err(|e| ErroreApi::database("nel verificare il token del chiosco", e))?
+        .ok_or_else(|| ErroreApi::non_autenticato("dispositivo_non_registrato", "Dispositivo non registrato."))?;
+    if aula_dispositivo != id_aula {
+        return Err(ErroreApi::vietato("dispositivo_di_altra_aula", "Il dispositivo non è registrato per questa aula."));
+    }
+    if let Err(e) = db::chiosco::registra_accesso(db_pool, id_dispositivo).await {
+        tracing::error!(errore = %e, "Errore DB nell'aggiornare l'ultimo accesso del chiosco {}", id_dispositivo);
+    }
+    Ok(id_dispositivo)
+}
+
+fn hash_pin(pin: &str) -> String {
+    let segreto = &configurazione::attuale().jwt_secret;
+    let mut mac = Hmac::<Sha256>::new_from_slice(segreto.as_bytes()).expect("HMAC accetta chiavi di qualsiasi lunghezza");
+    mac.update(pin.as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn pin_valido(pin: &str) -> bool {
+    pin.len() == LUNGHEZZA_PIN && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+// PIN sbagliati per dispositivo: (tentativi, primo errore della serie)
+fn tentativi_pin() -> &'static Mutex<HashMap<i32, (u32, Instant)>> {
+    static TENTATIVI: OnceLock<Mutex<HashMap<i32, (u32, Instant)>>> = OnceLock::new();
+    TENTATIVI.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn dispositivo_bloccato(id_dispositivo: i32) -> bool {
+    let mut tentativi = tentativi_pin().lock().unwrap_or_else(|e| e.into_inner());
+    match tentativi.get(&id_dispositivo) {
+        Some((_, primo)) if primo.elapsed().as_secs() >= BLOCCO_PIN_SECONDI => {
+            tentativi.remove(&id_dispositivo);
+            false
+        }
+        Some((numero, _)) => *numero >= MASSIMO_TENTATIVI_PIN,
+        None => false,
+    }
+}
+
+fn registra_tentativo(id_dispositivo: i32, riuscito: bool) {
+    let mut tentativi = tentativi_pin().lock().unwrap_or_else(|e| e.into_inner());
+    if riuscito {
+        tentativi.remove(&id_dispositivo);
+    } else {
+        tentativi.entry(id_dispositivo).or_insert((0, Instant::now())).0 += 1;
+    }
+}
+
+fn ora_locale(data: DateTime<Utc>) -> String {
+    data.with_timezone(&Rome).format("%H:%M").to_string()
+}
+
+struct ModuloInCorso {
+    numero: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+}
+
+// Modulo dell'orario del plesso in corso adesso, se ce n'è uno
+async fn modulo_in_corso(db_pool: &DbPool, id_plesso: i32, adesso: DateTime<Utc>) -> Result<Option<ModuloInCorso>, sqlx::Error> {
+    let locale = adesso.with_timezone(&Rome);
+    let modulo = db::plessi::modulo_alle(db_pool, id_plesso, locale.time()).await?;
+    Ok(modulo.and_then(|m| {
+        Some(ModuloInCorso {
+            numero: m.Numero_Modulo,
+            inizio: plessi::in_utc(locale.date_naive(), m.Ora_Inizio)?,
+            fine: plessi::in_utc(locale.date_naive(), m.Ora_Fine)?,
+        })
+    }))
+}
+
+fn voce_chiosco(voce: &prenotazioni::VoceElenco) -> models::PrenotazioneChioscoApi {
+    models::PrenotazioneChioscoApi {
+        Professore: voce.nome_professore.clone(),
+        Materia: voce.db.Materia.clone(),
+        Ora_Inizio: ora_locale(voce.inizio),
+        Ora_Fine: ora_locale(voce.fine),
+        Da_Approvare: voce.db.Approvata == Some(false),
+    }
+}
+
+// Stato dell'aula in questo momento; None se l'aula non esiste
+async fn stato_aula(db_pool: &DbPool, id_aula: i32, lingua: Lingua) -> Result<Option<models::StatoChioscoApi>, sqlx::Error> {
+    let aula = match db::aule::con_plesso(db_pool, id_aula).await? {
+        Some(aula) => aula,
+        None => return Ok(None),
+    };
+
+    let adesso = Utc::now();
+    let oggi = adesso.with_timezone(&Rome).date_naive();
+    let (da, a) = match (plessi::in_utc(oggi, NaiveTime::MIN), plessi::in_utc(oggi + Duration::days(1), NaiveTime::MIN)) {
+        (Some(da), Some(a)) => (da, a),
+        _ => (adesso, adesso + Duration::days(1)),
+    };
+
+    let filtro = FiltroPrenotazioni { id_aula: Some(id_aula), start: Some(da), end: Some(a), ..Default::default() };
+    let prenotazioni = prenotazioni::elenco(db_pool, &filtro).await?;
+    let blocchi = blocchi::occorrenze_nel_periodo(db_pool, Some(id_aula), da, a).await?;
+    let chiusura = plessi::chiusura_nel_periodo(db_pool, id_aula, adesso, adesso + Duration::minutes(1)).await?;
+
+    let corrente = prenotazioni.iter().find(|p| p.inizio <= adesso && p.fine > adesso);
+    let indisponibile = match chiusura {
+        Some((_, motivo)) => Some(lingua.traduci_con(
+            "chiosco.plesso_chiuso",
+            &format!("Plesso chiuso: {}", motivo),
+            &valori(&[("motivo", motivo)]),
+        )),
+        None => blocchi.iter().find(|b| b.inizio <= adesso && b.fine > adesso).map(|b| b.motivo.clone()),
+    };
+    let libera = corrente.is_none() && indisponibile.is_none();
+    // Il prossimo impegno della giornata, prenotazione o blocco che sia
+    let libera_fino = prenotazioni
+        .iter()
+        .map(|p| p.inizio)
+        .chain(blocchi.iter().map(|b| b.inizio))
+        .filter(|inizio| *inizio > adesso)
+        .min()
+        .filter(|_| libera)
+        .map(ora_locale);
+
+    let modulo = modulo_in_corso(db_pool, aula.Id_Plesso, adesso).await?.map(|modulo| {
+        let occupato = indisponibile.is_some()
+            || prenotazioni.iter().any(|p| p.inizio < modulo.fine && p.fine > modulo.inizio)
+            || blocchi.iter().any(|b| b.inizio < modulo.fine && b.fine > modulo.inizio);
+        models::ModuloChioscoApi {
+            Numero_Modulo: modulo.numero,
+            Ora_Inizio: ora_locale(modulo.inizio),
+            Ora_Fine: ora_locale(modulo.fine),
+            Prenotabile: !occupato,
+        }
+    });
+
+    Ok(Some(models::StatoChioscoApi {
+        Aula: prenotazioni::nome_aula(lingua, &aula.Tipo_Aula, aula.Numero),
+        Plesso: aula.Nome_Plesso,
+        Ora_Attuale: ora_locale(adesso),
+        Corrente: corrente.map(voce_chiosco),
+        Indisponibile: indisponibile,
+        Prossime: prenotazioni.iter().filter(|p| p.inizio > adesso).map(voce_chiosco).collect(),
+        Libera: libera,
+        Libera_Fino: libera_fino,
+        Modulo_Corrente: modulo,
+    }))
+}
+
+async fn carica_stato(db_pool: &DbPool, token: &str, id_aula: i32, lingua: Lingua) -> Result<models::StatoChioscoApi, ErroreApi> {
+    verifica_dispositivo(db_pool, token, id_aula).await?;
+    stato_aula(db_pool, id_aula, lingua)
+        .await
+        .map_err(|e| ErroreApi::database("nel calcolare lo stato dell'aula per il chiosco", e))?
+        .ok_or_else(|| ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata."))
+}
+
+#[get("/chiosco/aula/<id_aula>?<token>")]
+pub async fn stato_chiosco(db_pool: &State<DbPool>, lingua: Lingua, id_aula: i32, token: &str) -> Result<Json<models::StatoChioscoApi>, ErroreApi> {
+    Ok(Json(carica_stato(db_pool.inner(), token, id_aula, lingua).await?))
+}
+
+fn modello_pagina() -> &'static Environment<'static> {
+    static MODELLO_PAGINA: OnceLock<Environment<'static>> = OnceLock::new();
+    MODELLO_PAGINA.get_or_init(|| {
+        let mut ambiente = Environment::new();
+        ambiente
+            .add_template("aula.html", include_str!("../templates/chiosco/aula.html"))
+            .expect("Modello del chiosco non valido");
+        ambiente
+    })
+}
+
+// Pagina da aprire a schermo intero sul tablet
+#[get("/chiosco/aula/<id_aula>/pagina?<token>")]
+pub async fn pagina_chiosco(db_pool: &State<DbPool>, lingua: Lingua, id_aula: i32, token: &str) -> Result<(ContentType, String), ErroreApi> {
+    let stato = carica_stato(db_pool.inner(), token, id_aula, lingua).await?;
+    let pagina = modello_pagina()
+        .get_template("aula.html")
+        .and_then(|modello| modello.render(context! { stato => stato, aggiornamento_secondi => AGGIORNAMENTO_PAGINA_SECONDI }))
+        .map_err(|e| {
+            ErroreApi::interno("nel comporre la pagina del chiosco", e)
+        })?;
+    Ok((ContentType::HTML, pagina))
+}
+
+// Prenota il modulo in corso a nome del professore con il codice e il PIN indicati
+#[post("/chiosco/aula/<id_aula>/prenota?<token>", format = "json", data = "<payload>")]
+pub async fn prenota_da_chiosco(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    id_aula: i32,
+    token: &str,
+    payload: Json<models::PrenotazioneChioscoPayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    let id_dispositivo = verifica_dispositivo(db_pool.inner(), token, id_aula).await?;
+    if dispositivo_bloccato(id_dispositivo) {
+        return Err(ErroreApi::troppe_richieste("troppi_pin_errati", "Troppi PIN errati: riprova tra qualche minuto."));
+    }
+    if !pin_valido(&payload.pin) {
+        return Err(ErroreApi::campo("pin", "formato_pin_non_valido", "Il PIN è composto da 6 cifre."));
+    }
+
+    let professore = db::chiosco::pin_professore(db_pool.inner(), payload.codice)
+        .await
+        .map_err(|e| ErroreApi::database("nel verificare il PIN del chiosco", e))?
+        .filter(|(pin_hash, _, _)| *pin_hash == hash_pin(&payload.pin));
+    registra_tentativo(id_dispositivo, professore.is_some());
+    // Stessa risposta per codice inesistente e PIN sbagliato
+    let (_, nome, cognome) = professore.ok_or_else(|| ErroreApi::non_autenticato("pin_errato", "Codice o PIN non validi."))?;
+    let id_professore = payload.codice;
+
+    let id_plesso = db::plessi::id_plesso_aula(db_pool.inner(), id_aula)
+        .await
+        .map_err(|e| ErroreApi::database("nel recuperare il plesso dell'aula", e))?
+        .ok_or_else(|| ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata."))?;
+    let modulo = modulo_in_corso(db_pool.inner(), id_plesso, Utc::now())
+        .await
+        .map_err(|e| ErroreApi::database("nel recuperare il modulo in corso", e))?
+        .ok_or_else(|| ErroreApi::conflitto("nessun_modulo_in_corso", "Nessun modulo di lezione in corso."))?;
+
+    let richiesta = RichiestaPrenotazione {
+        id_professore,
+        id_aula: Some(id_aula),
+        data_inizio: modulo.inizio,
+        data_fine: modulo.fine,
+        attrezzature: Vec::new(),
+        numero_studenti: None,
+        id_materia: None,
+    };
+    let id_prenotazione = prenotazioni::crea(db_pool.inner(), &richiesta).await?;
+    // Chi prenota dal tablet è già in aula
+    if let Err(e) = checkin::registra_check_in(db_pool.inner(), id_prenotazione).await {
+        tracing::error!(errore = %e, "Errore DB nel registrare il check-in della prenotazione {}", id_prenotazione);
+    }
+    eventi::pubblica(db_pool.inner(), TipoEvento::Creata, id_prenotazione).await;
+
+    let nome = match nome {
+        Some(nome) => format!("{} {}", nome, cognome),
+        None => cognome,
+    };
+    let (inizio, fine) = (ora_locale(modulo.inizio), ora_locale(modulo.fine));
+    let messaggio = lingua.traduci_con(
+        "messaggi.aula_prenotata_da_chiosco",
+        &format!("Aula prenotata per la {}ª ora ({}-{}) a nome di {}.", modulo.numero, inizio, fine, nome),
+        &valori(&[("numero", modulo.numero.to_string()), ("inizio", inizio), ("fine", fine), ("nome", nome)]),
+    );
+    Ok(status::Custom(Status::Created, Json(json!({
+        "status": "successo",
+        "message": messaggio,
+        "id_prenotazione": id_prenotazione
+    }))))
+}
+
+// Il PIN personale per prenotare dai tablet delle aule
+#[put("/chiosco/pin", format = "json", data = "<payload>")]
+pub async fn imposta_pin_chiosco(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    payload: Json<models::PinChioscoPayload>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    if !pin_valido(&payload.pin) {
+        return Err(ErroreApi::campo("Pin", "formato_pin_non_valido", "Il PIN deve essere composto da 6 cifre."));
+    }
+    let pin_hash = hash_pin(&payload.pin);
+
+    let mut tx = db_pool.begin().await.map_err(|e| ErroreApi::database("nell'iniziare la transazione", e))?;
+    db::chiosco::elimina_pin(&mut *tx, utente.id_professore)
+        .await
+        .map_err(|e| ErroreApi::database("nel sostituire il PIN del chiosco", e))?;
+    db::chiosco::inserisci_pin(&mut *tx, utente.id_professore, &pin_hash)
+        .await
+        .map_err(|e| ErroreApi::database("nel salvare il PIN del chiosco", e))?;
+    tx.commit().await.map_err(|e| ErroreApi::database("nel salvare il PIN del chiosco", e))?;
+    let codice = utente.id_professore.to_string();
+    let messaggio = lingua.traduci_con(
+        "messaggi.pin_impostato",
+        &format!("PIN impostato: al tablet usa il codice {} e il PIN.", codice),
+        &valori(&[("codice", codice)]),
+    );
+    Ok(Json(json!({"status": "successo", "message": messaggio, "codice_chiosco": utente.id_professore})))
+}
+
+#[delete("/chiosco/pin")]
+pub async fn elimina_pin_chiosco(db_pool: &State<DbPool>, lingua: Lingua, utente: UtenteAutenticato) -> Result<Json<JsonValue>, ErroreApi> {
+    db::chiosco::elimina_pin(db_pool.inner(), utente.id_professore)
+        .await
+        .map_err(|e| ErroreApi::database("nell'eliminare il PIN del chiosco", e))?;
+    Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.pin_eliminato", "PIN eliminato.")})))
+}
+
+#[get("/chiosco/dispositivi")]
+pub async fn get_dispositivi_chiosco(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+) -> Result<Json<Vec<models::DispositivoChioscoApi>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_chiosco", "Solo un amministratore può gestire i dispositivi del chiosco.")?;
+    db::chiosco::dispositivi(db_pool.inner())
+        .await
+        .map(Json)
+        .map_err(|e| ErroreApi::database("nel recuperare i dispositivi del chiosco", e))
+}
+
+// Il token viene mostrato solo qui, dentro l'indirizzo da aprire sul tablet
+#[post("/chiosco/dispositivi", format = "json", data = "<payload>")]
+pub async fn crea_dispositivo_chiosco(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    payload: Json<models::NuovoDispositivoChioscoPayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_chiosco", "Solo un amministratore può gestire i dispositivi del chiosco.")?;
+    let token = auth_utils::token_casuale();
+    match db::chiosco::inserisci_dispositivo(db_pool.inner(), payload.id_aula, &token, payload.descrizione.as_deref()).await {
+        Ok(id_dispositivo) => Ok(status::Custom(Status::Created, Json(json!({
+            "status": "successo",
+            "message": lingua.traduci("messaggi.dispositivo_registrato", "Dispositivo registrato. Conserva l'indirizzo: il token non verrà più mostrato."),
+            "id_dispositivo": id_dispositivo,
+            "token": token,
+            "pagina": format!("/api/chiosco/aula/{}/pagina?token={}", payload.id_aula, token)
+        })))),
+        Err(e) if e.as_database_error().is_some_and(|db_err| db_err.is_foreign_key_violation()) => {
+            Err(ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata."))
+        }
+        Err(e) => Err(ErroreApi::database("nel registrare il dispositivo del chiosco", e)),
+    }
+}
+
+#[delete("/chiosco/dispositivi/<id_dispositivo>")]
+pub async fn elimina_dispositivo_chiosco(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_dispositivo: i32,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_chiosco", "Solo un amministratore può gestire i dispositivi del chiosco.")?;
+    match db::chiosco::elimina_dispositivo(db_pool.inner(), id_dispositivo).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.dispositivo_eliminato", "Dispositivo eliminato.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("dispositivo_non_trovato", "Dispositivo non trovato.")),
+        Err(e) => Err(ErroreApi::database("nell'eliminare il dispositivo del chiosco", e)),
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::prova;
+
+    async fn prenota(db_pool: &DbPool, id_aula: i32, codice: i32, pin: &str) -> ErroreApi {
+        let payload = Json(models::PrenotazioneChioscoPayload { codice, pin: pin.to_string() });
+        prenota_da_chiosco(db_pool.into(), Lingua::default(), id_aula, "token-del-tablet", payload)
+            .await
+            .expect_err("La prenotazione dal chiosco doveva fallire")
+    }
+
+    #[tokio::test]
+    async fn dopo_troppi_pin_errati_il_chiosco_si_blocca() {
+        let db_pool = prova::database().await;
+        let id_aula = prova::aula(&db_pool, "esclusiva", None).await;
+        db::chiosco::inserisci_dispositivo(&db_pool, id_aula, "token-del-tablet", None).await.unwrap();
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        db::chiosco::inserisci_pin(&db_pool, id_professore, &hash_pin("123456")).await.unwrap();
+
+        // Codice inesistente e PIN sbagliato non si distinguono
+        let sconosciuto = prenota(&db_pool, id_aula, id_professore + 100, "123456").await;
+        let sbagliato = prenota(&db_pool, id_aula, id_professore, "654321").await;
+        for errore in [&sconosciuto, &sbagliato] {
+            assert_eq!((errore.stato(), errore.codice()), (Status::Unauthorized, "pin_errato"));
+        }
+        assert_eq!(sconosciuto.messaggio(), sbagliato.messaggio());
+
+        // Il PIN giusto azzera i tentativi: si arriva fino alla ricerca del modulo in corso
+        assert_eq!(prenota(&db_pool, id_aula, id_professore, "123456").await.codice(), "nessun_modulo_in_corso");
+
+        for _ in 0..MASSIMO_TENTATIVI_PIN {
+            assert_eq!(prenota(&db_pool, id_aula, id_professore, "654321").await.codice(), "pin_errato");
+        }
+        // Ora nemmeno il PIN giusto viene verificato
+        let bloccato = prenota(&db_pool, id_aula, id_professore, "123456").await;
+        assert_eq!((bloccato.stato(), bloccato.codice()), (Status::TooManyRequests, "troppi_pin_errati"));
+    }
+}
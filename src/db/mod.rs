@@ -0,0 +1,83 @@
+// src/db/mod.rs
+// Accesso al database. Ogni query vive qui, divisa per area (aule, prenotazioni, webhook...),
+// e i moduli delle API chiamano solo queste funzioni: il backend si sceglie a compilazione
+// con le feature di cargo, senza toccare il resto del codice.
+//
+//   cargo run                                            # MySQL (predefinito)
+//   cargo run --no-default-features --features postgres  # PostgreSQL, es. DATABASE_URL=postgres://utente@host/planner
+//   cargo run --no-default-features --features sqlite    # SQLite, es. DATABASE_URL=sqlite://dev.db
+//
+// Con SQLite va bene anche DATABASE_URL=sqlite::memory: per un database usa e getta.
+// Le migrazioni di ogni backend sono in migrations/<backend>/ e hanno le stesse versioni, tranne la
+// 0008 di PostgreSQL, che corregge il trigger del vincolo di esclusione che gli altri backend non hanno.
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use sqlx::migrate::{Migrate, MigrateError};
+
+#[cfg(any(
+    all(feature = "mysql", feature = "postgres"),
+    all(feature = "mysql", feature = "sqlite"),
+    all(feature = "postgres", feature = "sqlite"),
+))]
+compile_error!("Le feature \"mysql\", \"postgres\" e \"sqlite\" si escludono a vicenda: usa --no-default-features --features <backend>");
+
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+compile_error!("Scegli un backend del database con --features mysql, postgres oppure sqlite");
+
+#[cfg(feature = "mysql")]
+mod mysql;
+#[cfg(feature = "mysql")]
+pub use mysql::*;
+
+#[cfg(all(feature = "postgres", not(feature = "mysql")))]
+mod postgres;
+#[cfg(all(feature = "postgres", not(feature = "mysql")))]
+pub use postgres::*;
+
+#[cfg(all(feature = "sqlite", not(any(feature = "mysql", feature = "postgres"))))]
+mod sqlite;
+#[cfg(all(feature = "sqlite", not(any(feature = "mysql", feature = "postgres"))))]
+pub use sqlite::*;
+
+pub type DbPool = sqlx::Pool<Db>;
+
+const PRIMA_ATTESA_CONNESSIONE: Duration = Duration::from_millis(500);
+const MASSIMA_ATTESA_CONNESSIONE: Duration = Duration::from_secs(30);
+
+// All'avvio il database può non rispondere ancora (riavvio in corso, container partiti insieme):
+// si riprova con attese crescenti finché non è passato `attesa_massima`. Un URL non valido fallisce subito.
+pub async fn connetti_con_riprova(database_url: &str, attesa_massima: Duration) -> Result<DbPool, sqlx::Error> {
+    let inizio = Instant::now();
+    let mut attesa = PRIMA_ATTESA_CONNESSIONE;
+    let mut tentativo: u32 = 1;
+    loop {
+        match connetti(database_url).await {
+            Ok(pool) => return Ok(pool),
+            Err(e) if !matches!(e, sqlx::Error::Configuration(_)) && inizio.elapsed() + attesa <= attesa_massima => {
+                tracing::warn!(tentativo, errore = %e, attesa_ms = attesa.as_millis() as u64, "Database non raggiungibile: nuovo tentativo");
+                tokio::time::sleep(attesa).await;
+                attesa = (attesa * 2).min(MASSIMA_ATTESA_CONNESSIONE);
+                tentativo += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Versioni delle migrazioni incluse nel programma che il database non ha ancora applicato
+pub async fn migrazioni_mancanti(db_pool: &DbPool) -> Result<Vec<i64>, MigrateError> {
+    let mut conn = db_pool.acquire().await?;
+    let applicate: HashSet<i64> = conn.list_applied_migrations().await?.into_iter().map(|m| m.version).collect();
+    Ok(MIGRAZIONI
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration() && !applicate.contains(&m.version))
+        .map(|m| m.version)
+        .collect())
+}
+
+// Qualsiasi cosa su cui eseguire una query: il pool, una connessione o una transazione (&mut *tx)
+pub trait Esecutore<'c>: sqlx::Executor<'c, Database = Db> {}
+
+impl<'c, T: sqlx::Executor<'c, Database = Db>> Esecutore<'c> for T {}
@@ -0,0 +1,73 @@
+// src/db/mysql/blocchi.rs
+// Blocchi di indisponibilità delle aule; le occorrenze delle ricorrenze si calcolano in src/blocchi.rs.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::BloccoAulaDb;
+
+// Blocchi (di un'aula o di tutte) con almeno un'occorrenza possibile nell'intervallo [da, a)
+pub async fn nel_periodo(
+    db: impl Esecutore<'_>,
+    id_aula: Option<i32>,
+    da: DateTime<Utc>,
+    a: DateTime<Utc>,
+) -> Result<Vec<BloccoAulaDb>, sqlx::Error> {
+    sqlx::query_as!(
+        BloccoAulaDb,
+        r#"
+        SELECT Id_Blocco, Id_Aula, Data_Inizio, Data_Fine, Motivo, Ricorrenza, Ricorrenza_Fine
+        FROM blocco_aula
+        WHERE (? IS NULL OR Id_Aula = ?)
+          AND Data_Inizio < ?
+          AND (
+                (Ricorrenza = 'nessuna' AND Data_Fine > ?)
+             OR (Ricorrenza <> 'nessuna' AND (Ricorrenza_Fine IS NULL OR Ricorrenza_Fine > ?))
+          )
+        "#,
+        id_aula, id_aula, a, da, da
+    )
+        .fetch_all(db)
+        .await
+}
+
+pub async fn dell_aula(db: impl Esecutore<'_>, id_aula: i32) -> Result<Vec<BloccoAulaDb>, sqlx::Error> {
+    sqlx::query_as!(
+        BloccoAulaDb,
+        "SELECT Id_Blocco, Id_Aula, Data_Inizio, Data_Fine, Motivo, Ricorrenza, Ricorrenza_Fine FROM blocco_aula WHERE Id_Aula = ? ORDER BY Data_Inizio",
+        id_aula
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce l'id del nuovo blocco
+pub async fn inserisci(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    data_inizio: DateTime<Utc>,
+    data_fine: DateTime<Utc>,
+    motivo: &str,
+    ricorrenza: &str,
+    ricorrenza_fine: Option<DateTime<Utc>>,
+) -> Result<i32, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "INSERT INTO blocco_aula (Id_Aula, Data_Inizio, Data_Fine, Motivo, Ricorrenza, Ricorrenza_Fine) VALUES (?, ?, ?, ?, ?, ?)",
+        id_aula,
+        data_inizio,
+        data_fine,
+        motivo,
+        ricorrenza,
+        ricorrenza_fine
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_id() as i32)
+}
+
+pub async fn elimina(db: impl Esecutore<'_>, id_blocco: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("DELETE FROM blocco_aula WHERE Id_Blocco = ?", id_blocco)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
@@ -0,0 +1,48 @@
+// src/db/mysql/calendari.rs
+// Token personali dei feed iCalendar.
+
+use chrono::Utc;
+
+use crate::db::Esecutore;
+
+// Professore e ruolo a cui appartiene il token
+pub async fn verifica_token(db: impl Esecutore<'_>, token: &str) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT f.Id_Professore, pr.Ruolo
+        FROM feed_token f
+        JOIN professore pr ON f.Id_Professore = pr.Id_Professore
+        WHERE f.Token = ?
+        "#,
+        token
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Professore, r.Ruolo)))
+}
+
+pub async fn token(db: impl Esecutore<'_>, id_professore: i32) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT Token FROM feed_token WHERE Id_Professore = ?", id_professore)
+        .fetch_optional(db)
+        .await
+}
+
+// Crea il token del professore o sostituisce quello esistente
+pub async fn imposta_token(db: impl Esecutore<'_>, id_professore: i32, token: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO feed_token (Id_Professore, Token, Data_Creazione) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE Token = VALUES(Token), Data_Creazione = VALUES(Data_Creazione)",
+        id_professore,
+        token,
+        Utc::now()
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn revoca_token(db: impl Esecutore<'_>, id_professore: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM feed_token WHERE Id_Professore = ?", id_professore)
+        .execute(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+// src/db/mysql/guasti.rs
+// Segnalazioni di guasto su aule e attrezzature.
+
+use chrono::Utc;
+
+use crate::db::Esecutore;
+use crate::models::SegnalazioneGuastoDb;
+
+// Guasto aperto, grave e bloccante sull'aula o su un'attrezzatura installata nell'aula
+pub async fn bloccante(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT s.Id_Segnalazione, s.Descrizione
+        FROM segnalazione_guasto s
+        LEFT JOIN attrezzatura t ON s.Id_Attrezzatura = t.Id_Attrezzatura
+        WHERE s.Blocca_Aula = TRUE
+          AND s.Gravita = 'alta'
+          AND s.Stato <> 'risolta'
+          AND COALESCE(s.Id_Aula, t.Id_Aula) = ?
+        ORDER BY s.Data_Creazione
+        LIMIT 1
+        "#,
+        id_aula
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Segnalazione, r.Descrizione)))
+}
+
+pub async fn aule_inagibili(db: impl Esecutore<'_>) -> Result<Vec<i32>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT DISTINCT COALESCE(s.Id_Aula, t.Id_Aula) AS `Id_Aula?: i32`
+        FROM segnalazione_guasto s
+        LEFT JOIN attrezzatura t ON s.Id_Attrezzatura = t.Id_Attrezzatura
+        WHERE s.Blocca_Aula = TRUE
+          AND s.Gravita = 'alta'
+          AND s.Stato <> 'risolta'
+        "#
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().filter_map(|r| r.Id_Aula).collect())
+}
+
+// Restituisce l'id della nuova segnalazione
+pub async fn inserisci(
+    db: impl Esecutore<'_>,
+    id_aula: Option<i32>,
+    id_attrezzatura: Option<i32>,
+    id_segnalatore: i32,
+    gravita: &str,
+    descrizione: &str,
+    blocca_aula: bool,
+) -> Result<i32, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "INSERT INTO segnalazione_guasto (Id_Aula, Id_Attrezzatura, Id_Segnalatore, Gravita, Descrizione, Stato, Blocca_Aula) VALUES (?, ?, ?, ?, ?, 'aperta', ?)",
+        id_aula,
+        id_attrezzatura,
+        id_segnalatore,
+        gravita,
+        descrizione,
+        blocca_aula
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_id() as i32)
+}
+
+// Le più gravi per prime, poi le più recenti
+pub async fn elenco(
+    db: impl Esecutore<'_>,
+    stato: Option<&str>,
+    id_aula: Option<i32>,
+    plesso: Option<i32>,
+) -> Result<Vec<SegnalazioneGuastoDb>, sqlx::Error> {
+    sqlx::query_as!(
+        SegnalazioneGuastoDb,
+        r#"
+        SELECT s.Id_Segnalazione, s.Id_Aula, s.Id_Attrezzatura, s.Id_Segnalatore, s.Gravita, s.Descrizione,
+               s.Stato, s.Blocca_Aula AS `Blocca_Aula: bool`, s.Note_Tecnico, s.Data_Creazione, s.Data_Aggiornamento
+        FROM segnalazione_guasto s
+        LEFT JOIN attrezzatura t ON s.Id_Attrezzatura = t.Id_Attrezzatura
+        LEFT JOIN aula a ON a.Id_Aula = COALESCE(s.Id_Aula, t.Id_Aula)
+        WHERE (? IS NULL OR s.Stato = ?)
+          AND (? IS NULL OR a.Id_Aula = ?)
+          AND (? IS NULL OR a.Id_Plesso = ?)
+        ORDER BY FIELD(s.Gravita, 'alta', 'media', 'bassa'), s.Data_Creazione DESC
+        "#,
+        stato, stato,
+        id_aula, id_aula,
+        plesso, plesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Aula, attrezzatura, gravità e blocco dell'aula della segnalazione
+pub async fn trova(db: impl Esecutore<'_>, id_segnalazione: i32) -> Result<Option<(Option<i32>, Option<i32>, String, bool)>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT Id_Aula, Id_Attrezzatura, Gravita, Blocca_Aula AS `Blocca_Aula: bool` FROM segnalazione_guasto WHERE Id_Segnalazione = ?",
+        id_segnalazione
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Aula, r.Id_Attrezzatura, r.Gravita, r.Blocca_Aula)))
+}
+
+// Le note restano invariate se `note_tecnico` è None
+pub async fn aggiorna(
+    db: impl Esecutore<'_>,
+    id_segnalazione: i32,
+    stato: &str,
+    note_tecnico: Option<&str>,
+    blocca_aula: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE segnalazione_guasto SET Stato = ?, Note_Tecnico = COALESCE(?, Note_Tecnico), Blocca_Aula = ?, Data_Aggiornamento = ? WHERE Id_Segnalazione = ?",
+        stato,
+        note_tecnico,
+        blocca_aula,
+        Utc::now(),
+        id_segnalazione
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
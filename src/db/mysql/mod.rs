@@ -0,0 +1,39 @@
+// src/db/mysql/mod.rs
+// Backend MySQL, usato in produzione.
+
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::mysql::MySqlPoolOptions;
+
+pub mod aggiornamenti;
+pub mod attrezzature;
+pub mod aule;
+pub mod blocchi;
+pub mod caldav;
+pub mod calendari;
+pub mod checkin;
+pub mod chiosco;
+pub mod guasti;
+pub mod notifiche;
+pub mod plessi;
+pub mod prenotazioni;
+pub mod professori;
+pub mod promemoria;
+pub mod webhook;
+
+pub type Db = sqlx::MySql;
+pub type DbConnection = sqlx::MySqlConnection;
+
+pub async fn connetti(database_url: &str) -> Result<super::DbPool, sqlx::Error> {
+    MySqlPoolOptions::new().connect(database_url).await
+}
+
+pub static MIGRAZIONI: Migrator = sqlx::migrate!("./migrations/mysql");
+
+pub async fn migra(db_pool: &super::DbPool) -> Result<(), MigrateError> {
+    MIGRAZIONI.run(db_pool).await
+}
+
+// MySQL non ha vincoli di esclusione: le sovrapposizioni le controlla solo l'applicazione
+pub fn violazione_sovrapposizione(_e: &sqlx::Error) -> bool {
+    false
+}
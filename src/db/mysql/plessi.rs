@@ -0,0 +1,226 @@
+// src/db/mysql/plessi.rs
+// Plessi, orario delle lezioni, giorni di chiusura e distanze tra le sedi.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::{ChiusuraConPlessoDb, ChiusuraPlessoApi, ModuloOrarioApi, ModuloPlessoDb, PlessoApi, PrenotazioneVicinaDb};
+
+pub async fn elenco(db: impl Esecutore<'_>) -> Result<Vec<PlessoApi>, sqlx::Error> {
+    sqlx::query_as!(PlessoApi, "SELECT Id_Plesso, Nome, Indirizzo FROM plesso ORDER BY Nome")
+        .fetch_all(db)
+        .await
+}
+
+pub async fn id_plesso_aula(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<i32>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT Id_Plesso FROM aula WHERE Id_Aula = ?", id_aula)
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn orario(db: impl Esecutore<'_>, id_plesso: i32) -> Result<Vec<ModuloOrarioApi>, sqlx::Error> {
+    sqlx::query_as!(
+        ModuloOrarioApi,
+        "SELECT Numero_Modulo, Ora_Inizio, Ora_Fine FROM orario_plesso WHERE Id_Plesso = ? ORDER BY Numero_Modulo",
+        id_plesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Moduli orari di tutti i plessi
+pub async fn orari(db: impl Esecutore<'_>) -> Result<Vec<ModuloPlessoDb>, sqlx::Error> {
+    sqlx::query_as!(
+        ModuloPlessoDb,
+        "SELECT Id_Plesso, Numero_Modulo, Ora_Inizio, Ora_Fine FROM orario_plesso ORDER BY Id_Plesso, Numero_Modulo"
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Modulo del plesso in corso all'ora locale indicata
+pub async fn modulo_alle(db: impl Esecutore<'_>, id_plesso: i32, ora: NaiveTime) -> Result<Option<ModuloOrarioApi>, sqlx::Error> {
+    sqlx::query_as!(
+        ModuloOrarioApi,
+        "SELECT Numero_Modulo, Ora_Inizio, Ora_Fine FROM orario_plesso WHERE Id_Plesso = ? AND Ora_Inizio <= ? AND Ora_Fine > ? LIMIT 1",
+        id_plesso,
+        ora,
+        ora
+    )
+        .fetch_optional(db)
+        .await
+}
+
+// Restituisce l'id del nuovo plesso
+pub async fn inserisci_plesso(db: impl Esecutore<'_>, nome: &str, indirizzo: Option<&str>) -> Result<i32, sqlx::Error> {
+    let risultato = sqlx::query!("INSERT INTO plesso (Nome, Indirizzo) VALUES (?, ?)", nome, indirizzo)
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_id() as i32)
+}
+
+pub async fn aggiorna_plesso(db: impl Esecutore<'_>, id_plesso: i32, nome: &str, indirizzo: Option<&str>) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("UPDATE plesso SET Nome = ?, Indirizzo = ? WHERE Id_Plesso = ?", nome, indirizzo, id_plesso)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn elimina_orario(db: impl Esecutore<'_>, id_plesso: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM orario_plesso WHERE Id_Plesso = ?", id_plesso)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn inserisci_modulo(
+    db: impl Esecutore<'_>,
+    id_plesso: i32,
+    numero_modulo: i32,
+    ora_inizio: NaiveTime,
+    ora_fine: NaiveTime,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO orario_plesso (Id_Plesso, Numero_Modulo, Ora_Inizio, Ora_Fine) VALUES (?, ?, ?, ?)",
+        id_plesso,
+        numero_modulo,
+        ora_inizio,
+        ora_fine
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Chiusura del plesso dell'aula che tocca i giorni locali [primo, ultimo]
+pub async fn chiusura_aula(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    primo: NaiveDate,
+    ultimo: NaiveDate,
+) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT c.Id_Chiusura, c.Motivo
+        FROM chiusura_plesso c
+        JOIN aula a ON a.Id_Plesso = c.Id_Plesso
+        WHERE a.Id_Aula = ? AND c.Data_Inizio <= ? AND c.Data_Fine >= ?
+        LIMIT 1
+        "#,
+        id_aula,
+        ultimo,
+        primo
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Chiusura, r.Motivo)))
+}
+
+pub async fn chiusi(db: impl Esecutore<'_>, primo: NaiveDate, ultimo: NaiveDate) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT DISTINCT Id_Plesso FROM chiusura_plesso WHERE Data_Inizio <= ? AND Data_Fine >= ?",
+        ultimo,
+        primo
+    )
+        .fetch_all(db)
+        .await
+}
+
+pub async fn chiusure(db: impl Esecutore<'_>, id_plesso: i32) -> Result<Vec<ChiusuraPlessoApi>, sqlx::Error> {
+    sqlx::query_as!(
+        ChiusuraPlessoApi,
+        "SELECT Id_Chiusura, Id_Plesso, Data_Inizio, Data_Fine, Motivo FROM chiusura_plesso WHERE Id_Plesso = ? ORDER BY Data_Inizio",
+        id_plesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Chiusure (di un plesso o di tutti) che toccano i giorni [primo, ultimo]
+pub async fn chiusure_nel_periodo(
+    db: impl Esecutore<'_>,
+    plesso: Option<i32>,
+    primo: NaiveDate,
+    ultimo: NaiveDate,
+) -> Result<Vec<ChiusuraConPlessoDb>, sqlx::Error> {
+    sqlx::query_as!(
+        ChiusuraConPlessoDb,
+        r#"
+        SELECT c.Id_Chiusura, c.Id_Plesso, pl.Nome AS Nome_Plesso, c.Data_Inizio, c.Data_Fine, c.Motivo
+        FROM chiusura_plesso c
+        JOIN plesso pl ON c.Id_Plesso = pl.Id_Plesso
+        WHERE (? IS NULL OR c.Id_Plesso = ?) AND c.Data_Inizio <= ? AND c.Data_Fine >= ?
+        "#,
+        plesso, plesso,
+        ultimo,
+        primo
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce l'id della nuova chiusura
+pub async fn inserisci_chiusura(
+    db: impl Esecutore<'_>,
+    id_plesso: i32,
+    data_inizio: NaiveDate,
+    data_fine: NaiveDate,
+    motivo: &str,
+) -> Result<i32, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "INSERT INTO chiusura_plesso (Id_Plesso, Data_Inizio, Data_Fine, Motivo) VALUES (?, ?, ?, ?)",
+        id_plesso,
+        data_inizio,
+        data_fine,
+        motivo
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_id() as i32)
+}
+
+pub async fn elimina_chiusura(db: impl Esecutore<'_>, id_chiusura: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("DELETE FROM chiusura_plesso WHERE Id_Chiusura = ?", id_chiusura)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Prenotazioni attive del professore in plessi diversi da quello dell'aula, nell'intervallo,
+// con i minuti per spostarsi (o il valore predefinito se la distanza non è indicata)
+pub async fn prenotazioni_in_altri_plessi(
+    db: impl Esecutore<'_>,
+    id_professore: i32,
+    id_aula: i32,
+    da: DateTime<Utc>,
+    a: DateTime<Utc>,
+    minuti_predefiniti: i32,
+) -> Result<Vec<PrenotazioneVicinaDb>, sqlx::Error> {
+    sqlx::query_as!(
+        PrenotazioneVicinaDb,
+        r#"
+        SELECT p.Data_Inizio, p.Data_Fine, a.Tipo_Aula, a.Numero, pl.Nome AS Nome_Plesso,
+               COALESCE((
+                   SELECT MIN(d.Minuti) FROM distanza_plessi d
+                   WHERE (d.Id_Plesso_Da = a.Id_Plesso AND d.Id_Plesso_A = nuova.Id_Plesso)
+                      OR (d.Id_Plesso_Da = nuova.Id_Plesso AND d.Id_Plesso_A = a.Id_Plesso)
+               ), ?) AS `Minuti!: i32`
+        FROM prenotazione p
+        JOIN aula a ON p.Id_Aula = a.Id_Aula
+        JOIN plesso pl ON a.Id_Plesso = pl.Id_Plesso
+        JOIN aula nuova ON nuova.Id_Aula = ?
+        WHERE p.Id_Professore = ?
+          AND p.Stato = 'attiva'
+          AND a.Id_Plesso <> nuova.Id_Plesso
+          AND p.Data_Fine > ? AND p.Data_Inizio < ?
+        ORDER BY p.Data_Inizio
+        "#,
+        minuti_predefiniti,
+        id_aula,
+        id_professore,
+        da,
+        a
+    )
+        .fetch_all(db)
+        .await
+}
@@ -0,0 +1,31 @@
+// src/db/mysql/promemoria.rs
+// Registro delle attività pianificate già eseguite, condiviso fra più istanze.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Esecutore;
+
+// false se un'altra istanza ha già eseguito l'attività con questa chiave
+pub async fn segna_eseguita(db: impl Esecutore<'_>, chiave: &str) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "INSERT IGNORE INTO attivita_pianificata (Chiave, Data_Esecuzione) VALUES (?, ?)",
+        chiave,
+        Utc::now()
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn iscritti_riepilogo(db: impl Esecutore<'_>) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT Id_Professore FROM preferenza_notifica WHERE Riepilogo = TRUE")
+        .fetch_all(db)
+        .await
+}
+
+pub async fn elimina_precedenti(db: impl Esecutore<'_>, limite: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM attivita_pianificata WHERE Data_Esecuzione < ?", limite)
+        .execute(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+// src/db/mysql/webhook.rs
+// Iscrizioni ai webhook e coda delle consegne.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::{ConsegnaInScadenzaDb, ConsegnaWebhookApi, WebhookDb};
+
+// Id ed eventi (separati da virgole) delle iscrizioni attive
+pub async fn iscrizioni_attive(db: impl Esecutore<'_>) -> Result<Vec<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!("SELECT Id_Webhook, Eventi FROM webhook WHERE Attivo = TRUE")
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|w| (w.Id_Webhook, w.Eventi)).collect())
+}
+
+pub async fn accoda_consegna(db: impl Esecutore<'_>, id_webhook: i32, evento: &str, payload: &str) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now();
+    sqlx::query!(
+        r#"
+        INSERT INTO consegna_webhook (Id_Webhook, Evento, Payload, Stato, Tentativi, Prossimo_Tentativo, Data_Creazione)
+        VALUES (?, ?, ?, 'in_attesa', 0, ?, ?)
+        "#,
+        id_webhook,
+        evento,
+        payload,
+        adesso,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Consegne in attesa di webhook attivi il cui tentativo è dovuto entro `adesso`
+pub async fn consegne_in_scadenza(db: impl Esecutore<'_>, adesso: DateTime<Utc>, limite: i64) -> Result<Vec<ConsegnaInScadenzaDb>, sqlx::Error> {
+    sqlx::query_as!(
+        ConsegnaInScadenzaDb,
+        r#"
+        SELECT c.Id_Consegna, c.Evento, c.Payload, c.Tentativi, w.Url, w.Segreto, w.Formato
+        FROM consegna_webhook c
+        JOIN webhook w ON c.Id_Webhook = w.Id_Webhook
+        WHERE c.Stato = 'in_attesa' AND c.Prossimo_Tentativo <= ? AND w.Attivo = TRUE
+        ORDER BY c.Prossimo_Tentativo
+        LIMIT ?
+        "#,
+        adesso,
+        limite
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Presa in carico fino a `scadenza`: false se un altro processo l'ha già presa
+pub async fn prendi_in_carico(
+    db: impl Esecutore<'_>,
+    id_consegna: i64,
+    adesso: DateTime<Utc>,
+    scadenza: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "UPDATE consegna_webhook SET Prossimo_Tentativo = ? WHERE Id_Consegna = ? AND Stato = 'in_attesa' AND Prossimo_Tentativo <= ?",
+        scadenza,
+        id_consegna,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn segna_consegnata(db: impl Esecutore<'_>, id_consegna: i64, tentativi: i32, codice: Option<i32>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE consegna_webhook SET Stato = 'consegnata', Tentativi = ?, Ultimo_Codice = ?, Ultimo_Errore = NULL, Data_Consegna = ? WHERE Id_Consegna = ?",
+        tentativi,
+        codice,
+        Utc::now(),
+        id_consegna
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn ripianifica(
+    db: impl Esecutore<'_>,
+    id_consegna: i64,
+    stato: &str,
+    tentativi: i32,
+    codice: Option<i32>,
+    errore: &str,
+    prossimo_tentativo: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE consegna_webhook SET Stato = ?, Tentativi = ?, Ultimo_Codice = ?, Ultimo_Errore = ?, Prossimo_Tentativo = ? WHERE Id_Consegna = ?",
+        stato,
+        tentativi,
+        codice,
+        errore,
+        prossimo_tentativo,
+        id_consegna
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn elenco(db: impl Esecutore<'_>) -> Result<Vec<WebhookDb>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookDb,
+        "SELECT Id_Webhook, Url, Eventi, Formato, Attivo AS `Attivo: bool`, Descrizione, Data_Creazione FROM webhook ORDER BY Id_Webhook"
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce l'id del nuovo webhook, creato attivo
+pub async fn inserisci(
+    db: impl Esecutore<'_>,
+    url: &str,
+    segreto: &str,
+    eventi: &str,
+    formato: &str,
+    descrizione: Option<&str>,
+) -> Result<i32, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "INSERT INTO webhook (Url, Segreto, Eventi, Formato, Attivo, Descrizione, Data_Creazione) VALUES (?, ?, ?, ?, TRUE, ?, ?)",
+        url,
+        segreto,
+        eventi,
+        formato,
+        descrizione,
+        Utc::now()
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_id() as i32)
+}
+
+// I campi None restano invariati
+pub async fn modifica(db: impl Esecutore<'_>, id_webhook: i32, attivo: Option<bool>, eventi: Option<&str>) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "UPDATE webhook SET Attivo = COALESCE(?, Attivo), Eventi = COALESCE(?, Eventi) WHERE Id_Webhook = ?",
+        attivo,
+        eventi,
+        id_webhook
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn elimina(db: impl Esecutore<'_>, id_webhook: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("DELETE FROM webhook WHERE Id_Webhook = ?", id_webhook)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Registro delle consegne, dalla più recente
+pub async fn consegne(db: impl Esecutore<'_>, id_webhook: i32, stato: Option<&str>, limite: i64) -> Result<Vec<ConsegnaWebhookApi>, sqlx::Error> {
+    sqlx::query_as!(
+        ConsegnaWebhookApi,
+        r#"
+        SELECT Id_Consegna, Id_Webhook, Evento, Stato, Tentativi, Prossimo_Tentativo, Ultimo_Codice,
+               Ultimo_Errore, Data_Creazione, Data_Consegna, Payload
+        FROM consegna_webhook
+        WHERE Id_Webhook = ? AND (? IS NULL OR Stato = ?)
+        ORDER BY Id_Consegna DESC
+        LIMIT ?
+        "#,
+        id_webhook,
+        stato, stato,
+        limite
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Rimette in coda la consegna per un invio immediato
+pub async fn riprova(db: impl Esecutore<'_>, id_consegna: i64) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "UPDATE consegna_webhook SET Stato = 'in_attesa', Tentativi = 0, Prossimo_Tentativo = ? WHERE Id_Consegna = ?",
+        Utc::now(),
+        id_consegna
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
@@ -0,0 +1,97 @@
+// src/db/postgres/aggiornamenti.rs
+// Eventi delle prenotazioni conservati per i client SSE che si riconnettono.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::EventoPrenotazioneDb;
+
+// Aula, inizio e fine attuali della prenotazione
+pub async fn stato_prenotazione(
+    db: impl Esecutore<'_>,
+    id_prenotazione: i32,
+) -> Result<Option<(Option<i32>, NaiveDateTime, NaiveDateTime)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT "Id_Aula", "Data_Inizio", "Data_Fine" FROM prenotazione WHERE "Id_Prenotazione" = $1"#,
+        id_prenotazione
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Aula, r.Data_Inizio, r.Data_Fine)))
+}
+
+// Restituisce l'id dell'evento, che è anche l'id SSE
+pub async fn inserisci(
+    db: impl Esecutore<'_>,
+    tipo: &str,
+    id_prenotazione: i32,
+    id_aula: Option<i32>,
+    id_aula_precedente: Option<i32>,
+    inizio: NaiveDateTime,
+    fine: NaiveDateTime,
+) -> Result<u64, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO evento_prenotazione ("Tipo", "Id_Prenotazione", "Id_Aula", "Id_Aula_Precedente", "Data_Inizio", "Data_Fine", "Data_Creazione")
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING "Id_Evento"
+        "#,
+        tipo,
+        id_prenotazione,
+        id_aula,
+        id_aula_precedente,
+        inizio,
+        fine,
+        adesso
+    )
+        .fetch_one(db)
+        .await?;
+    Ok(id as u64)
+}
+
+pub async fn primo_conservato(db: impl Esecutore<'_>) -> Result<Option<u64>, sqlx::Error> {
+    let primo: Option<i64> = sqlx::query_scalar!(r#"SELECT MIN("Id_Evento") FROM evento_prenotazione"#)
+        .fetch_one(db)
+        .await?;
+    Ok(primo.map(|id| id as u64))
+}
+
+// Al più `limite` eventi successivi a `ultimo`, in ordine
+pub async fn successivi(db: impl Esecutore<'_>, ultimo: u64, limite: i64) -> Result<Vec<EventoPrenotazioneDb>, sqlx::Error> {
+    // Postgres non ha interi senza segno: l'id è un BIGINT
+    let ultimo = ultimo as i64;
+    let eventi = sqlx::query!(
+        r#"
+        SELECT "Id_Evento", "Tipo", "Id_Prenotazione", "Id_Aula", "Id_Aula_Precedente", "Data_Inizio", "Data_Fine"
+        FROM evento_prenotazione
+        WHERE "Id_Evento" > $1
+        ORDER BY "Id_Evento"
+        LIMIT $2
+        "#,
+        ultimo,
+        limite
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(eventi
+        .into_iter()
+        .map(|e| EventoPrenotazioneDb {
+            Id_Evento: e.Id_Evento as u64,
+            Tipo: e.Tipo,
+            Id_Prenotazione: e.Id_Prenotazione,
+            Id_Aula: e.Id_Aula,
+            Id_Aula_Precedente: e.Id_Aula_Precedente,
+            Data_Inizio: e.Data_Inizio,
+            Data_Fine: e.Data_Fine,
+        })
+        .collect())
+}
+
+pub async fn elimina_precedenti(db: impl Esecutore<'_>, limite: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    let limite = limite.naive_utc();
+    sqlx::query!(r#"DELETE FROM evento_prenotazione WHERE "Data_Creazione" < $1"#, limite)
+        .execute(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,116 @@
+// src/db/postgres/aule.rs
+// Aule, con la loro politica di prenotazione e il plesso di appartenenza.
+
+use crate::db::{DbConnection, Esecutore};
+use crate::models::{AulaApi, AulaPlessoDb};
+
+pub async fn elenco(db: impl Esecutore<'_>, tipo: Option<&str>, plesso: Option<i32>) -> Result<Vec<AulaApi>, sqlx::Error> {
+    sqlx::query_as!(
+        AulaApi,
+        r#"
+        SELECT "Id_Aula", "Tipo_Aula", "Numero", "Politica_Prenotazione", "Capacita", "Id_Plesso",
+               "Richiede_Approvazione" AS "Richiede_Approvazione: bool"
+        FROM aula
+        WHERE ($1::TEXT IS NULL OR "Tipo_Aula" = $1) AND ($2::INT4 IS NULL OR "Id_Plesso" = $2)
+        ORDER BY "Tipo_Aula", "Numero"
+        "#,
+        tipo,
+        plesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Id di tutte le aule, o solo di quelle del plesso
+pub async fn id_aule(db: impl Esecutore<'_>, plesso: Option<i32>) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT "Id_Aula" FROM aula WHERE $1::INT4 IS NULL OR "Id_Plesso" = $1 ORDER BY "Id_Aula""#,
+        plesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce false se l'aula non esiste
+pub async fn imposta_politica(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    politica: &str,
+    capacita: Option<i32>,
+    richiede_approvazione: Option<bool>,
+) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(
+        r#"UPDATE aula SET "Politica_Prenotazione" = $1, "Capacita" = $2, "Richiede_Approvazione" = COALESCE($3, "Richiede_Approvazione") WHERE "Id_Aula" = $4"#,
+        politica,
+        capacita,
+        richiede_approvazione,
+        id_aula
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Blocca la riga dell'aula fino al commit: due richieste concorrenti sulla stessa aula vengono serializzate.
+// Restituisce politica, capacità e se l'aula richiede approvazione.
+pub async fn blocca(conn: &mut DbConnection, id_aula: i32) -> Result<Option<(String, Option<i32>, bool)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT "Politica_Prenotazione", "Capacita", "Richiede_Approvazione" AS "Richiede_Approvazione: bool" FROM aula WHERE "Id_Aula" = $1 FOR UPDATE"#,
+        id_aula
+    )
+        .fetch_optional(conn)
+        .await?;
+    Ok(record.map(|r| (r.Politica_Prenotazione, r.Capacita, r.Richiede_Approvazione)))
+}
+
+// Come blocca, ma senza bloccare la riga (per le anteprime)
+pub async fn politica(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<(String, Option<i32>, bool)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT "Politica_Prenotazione", "Capacita", "Richiede_Approvazione" AS "Richiede_Approvazione: bool" FROM aula WHERE "Id_Aula" = $1"#,
+        id_aula
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Politica_Prenotazione, r.Capacita, r.Richiede_Approvazione)))
+}
+
+pub async fn con_plesso(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<AulaPlessoDb>, sqlx::Error> {
+    sqlx::query_as!(
+        AulaPlessoDb,
+        r#"
+        SELECT a."Id_Aula", a."Tipo_Aula", a."Numero", a."Id_Plesso", pl."Nome" AS "Nome_Plesso"
+        FROM aula a
+        JOIN plesso pl ON a."Id_Plesso" = pl."Id_Plesso"
+        WHERE a."Id_Aula" = $1
+        "#,
+        id_aula
+    )
+        .fetch_optional(db)
+        .await
+}
+
+// Aule filtrate per id, plesso e tipo, ordinate per plesso e nome
+pub async fn elenco_con_plesso(
+    db: impl Esecutore<'_>,
+    id_aula: Option<i32>,
+    plesso: Option<i32>,
+    tipo: Option<&str>,
+) -> Result<Vec<AulaPlessoDb>, sqlx::Error> {
+    sqlx::query_as!(
+        AulaPlessoDb,
+        r#"
+        SELECT a."Id_Aula", a."Tipo_Aula", a."Numero", a."Id_Plesso", pl."Nome" AS "Nome_Plesso"
+        FROM aula a
+        JOIN plesso pl ON a."Id_Plesso" = pl."Id_Plesso"
+        WHERE ($1::INT4 IS NULL OR a."Id_Aula" = $1)
+          AND ($2::INT4 IS NULL OR a."Id_Plesso" = $2)
+          AND ($3::TEXT IS NULL OR a."Tipo_Aula" = $3)
+        ORDER BY pl."Nome", a."Tipo_Aula", a."Numero"
+        "#,
+        id_aula,
+        plesso,
+        tipo
+    )
+        .fetch_all(db)
+        .await
+}
@@ -0,0 +1,79 @@
+// src/db/postgres/blocchi.rs
+// Blocchi di indisponibilità delle aule; le occorrenze delle ricorrenze si calcolano in src/blocchi.rs.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::BloccoAulaDb;
+
+// Blocchi (di un'aula o di tutte) con almeno un'occorrenza possibile nell'intervallo [da, a)
+pub async fn nel_periodo(
+    db: impl Esecutore<'_>,
+    id_aula: Option<i32>,
+    da: DateTime<Utc>,
+    a: DateTime<Utc>,
+) -> Result<Vec<BloccoAulaDb>, sqlx::Error> {
+    let (da, a) = (da.naive_utc(), a.naive_utc());
+    sqlx::query_as!(
+        BloccoAulaDb,
+        r#"
+        SELECT "Id_Blocco", "Id_Aula", "Data_Inizio", "Data_Fine", "Motivo", "Ricorrenza", "Ricorrenza_Fine"
+        FROM blocco_aula
+        WHERE ($1::INT4 IS NULL OR "Id_Aula" = $1)
+          AND "Data_Inizio" < $2
+          AND (
+                ("Ricorrenza" = 'nessuna' AND "Data_Fine" > $3)
+             OR ("Ricorrenza" <> 'nessuna' AND ("Ricorrenza_Fine" IS NULL OR "Ricorrenza_Fine" > $4))
+          )
+        "#,
+        id_aula,
+        a,
+        da,
+        da
+    )
+        .fetch_all(db)
+        .await
+}
+
+pub async fn dell_aula(db: impl Esecutore<'_>, id_aula: i32) -> Result<Vec<BloccoAulaDb>, sqlx::Error> {
+    sqlx::query_as!(
+        BloccoAulaDb,
+        r#"SELECT "Id_Blocco", "Id_Aula", "Data_Inizio", "Data_Fine", "Motivo", "Ricorrenza", "Ricorrenza_Fine" FROM blocco_aula WHERE "Id_Aula" = $1 ORDER BY "Data_Inizio""#,
+        id_aula
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce l'id del nuovo blocco
+pub async fn inserisci(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    data_inizio: DateTime<Utc>,
+    data_fine: DateTime<Utc>,
+    motivo: &str,
+    ricorrenza: &str,
+    ricorrenza_fine: Option<DateTime<Utc>>,
+) -> Result<i32, sqlx::Error> {
+    let data_inizio = data_inizio.naive_utc();
+    let data_fine = data_fine.naive_utc();
+    let ricorrenza_fine = ricorrenza_fine.map(|d| d.naive_utc());
+    sqlx::query_scalar!(
+        r#"INSERT INTO blocco_aula ("Id_Aula", "Data_Inizio", "Data_Fine", "Motivo", "Ricorrenza", "Ricorrenza_Fine") VALUES ($1, $2, $3, $4, $5, $6) RETURNING "Id_Blocco""#,
+        id_aula,
+        data_inizio,
+        data_fine,
+        motivo,
+        ricorrenza,
+        ricorrenza_fine
+    )
+        .fetch_one(db)
+        .await
+}
+
+pub async fn elimina(db: impl Esecutore<'_>, id_blocco: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(r#"DELETE FROM blocco_aula WHERE "Id_Blocco" = $1"#, id_blocco)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
@@ -0,0 +1,165 @@
+// src/db/postgres/checkin.rs
+// Check-in con QR nelle aule e rilascio delle prenotazioni senza check-in.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::AssenzeProfessoreDb;
+
+pub async fn registra(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"UPDATE prenotazione SET "Check_In" = $1 WHERE "Id_Prenotazione" = $2 AND "Check_In" IS NULL"#,
+        adesso,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Prenotazioni in corso, senza check-in, iniziate (e modificate) prima di `limite`
+pub async fn assenti(db: impl Esecutore<'_>, limite: DateTime<Utc>, adesso: DateTime<Utc>) -> Result<Vec<i32>, sqlx::Error> {
+    let (limite, adesso) = (limite.naive_utc(), adesso.naive_utc());
+    sqlx::query_scalar!(
+        r#"
+        SELECT p."Id_Prenotazione"
+        FROM prenotazione p
+        JOIN checkin_aula c ON c."Id_Aula" = p."Id_Aula"
+        WHERE p."Stato" = 'attiva'
+          AND p."Check_In" IS NULL
+          AND (p."Approvata" IS NULL OR p."Approvata" = TRUE)
+          AND p."Data_Inizio" >= c."Data_Attivazione"
+          AND p."Data_Inizio" <= $1
+          AND p."Data_Modifica" <= $2
+          AND p."Data_Fine" > $3
+        "#,
+        limite,
+        limite,
+        adesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// false se nel frattempo è arrivato il check-in o la prenotazione non è più attiva
+pub async fn rilascia(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        r#"UPDATE prenotazione SET "Stato" = 'rilasciata', "Sequenza" = "Sequenza" + 1, "Data_Modifica" = $1 WHERE "Id_Prenotazione" = $2 AND "Stato" = 'attiva' AND "Check_In" IS NULL"#,
+        adesso,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn codice_valido(db: impl Esecutore<'_>, id_aula: i32, codice: &str) -> Result<bool, sqlx::Error> {
+    let trovati = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "conteggio!" FROM checkin_aula WHERE "Id_Aula" = $1 AND "Codice" = $2"#, id_aula, codice)
+        .fetch_one(db)
+        .await?;
+    Ok(trovati > 0)
+}
+
+// Id, fine ed eventuale check-in della prenotazione del professore nell'aula iniziata entro `entro`
+pub async fn prenotazione_in_corso(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    id_professore: i32,
+    entro: DateTime<Utc>,
+    adesso: DateTime<Utc>,
+) -> Result<Option<(i32, NaiveDateTime, Option<NaiveDateTime>)>, sqlx::Error> {
+    let entro = entro.naive_utc();
+    let adesso = adesso.naive_utc();
+    let record = sqlx::query!(
+        r#"
+        SELECT "Id_Prenotazione", "Data_Fine", "Check_In"
+        FROM prenotazione
+        WHERE "Id_Aula" = $1 AND "Id_Professore" = $2 AND "Stato" = 'attiva' AND "Data_Inizio" <= $3 AND "Data_Fine" > $4
+        ORDER BY "Data_Inizio"
+        LIMIT 1
+        "#,
+        id_aula,
+        id_professore,
+        entro,
+        adesso
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Prenotazione, r.Data_Fine, r.Check_In)))
+}
+
+// Non fa nulla se il check-in è già attivo, né se l'aula non esiste
+pub async fn attiva(db: impl Esecutore<'_>, id_aula: i32, codice: &str) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"
+        INSERT INTO checkin_aula ("Id_Aula", "Codice", "Data_Attivazione", "Data_Creazione")
+        SELECT $1, $2, $3, $3 WHERE EXISTS (SELECT 1 FROM aula WHERE "Id_Aula" = $1)
+        ON CONFLICT DO NOTHING
+        "#,
+        id_aula,
+        codice,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn codice(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(r#"SELECT "Codice" FROM checkin_aula WHERE "Id_Aula" = $1"#, id_aula)
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn rigenera(db: impl Esecutore<'_>, id_aula: i32, codice: &str) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(r#"UPDATE checkin_aula SET "Codice" = $1, "Data_Creazione" = $2 WHERE "Id_Aula" = $3"#, codice, adesso, id_aula)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn disattiva(db: impl Esecutore<'_>, id_aula: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(r#"DELETE FROM checkin_aula WHERE "Id_Aula" = $1"#, id_aula)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Per professore: prenotazioni soggette a check-in iniziate prima di `limite`, check-in fatti e rilasci
+pub async fn assenze(
+    db: impl Esecutore<'_>,
+    limite: DateTime<Utc>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<Vec<AssenzeProfessoreDb>, sqlx::Error> {
+    let limite = limite.naive_utc();
+    let (start, end) = (start.map(|d| d.naive_utc()), end.map(|d| d.naive_utc()));
+    sqlx::query_as!(
+        AssenzeProfessoreDb,
+        r#"
+        SELECT pr."Id_Professore", pr."Nome", pr."Cognome",
+               COUNT(*) AS "Prenotazioni!: i64",
+               SUM(CASE WHEN p."Check_In" IS NOT NULL THEN 1 ELSE 0 END) AS "Check_In!: i64",
+               SUM(CASE WHEN p."Stato" = 'rilasciata' THEN 1 ELSE 0 END) AS "Rilasciate!: i64"
+        FROM prenotazione p
+        JOIN checkin_aula c ON c."Id_Aula" = p."Id_Aula"
+        JOIN professore pr ON pr."Id_Professore" = p."Id_Professore"
+        WHERE p."Stato" IN ('attiva', 'rilasciata')
+          AND p."Data_Inizio" >= c."Data_Attivazione"
+          AND p."Data_Inizio" <= $1
+          AND ($2::TIMESTAMP IS NULL OR p."Data_Inizio" >= $2)
+          AND ($3::TIMESTAMP IS NULL OR p."Data_Inizio" < $3)
+        GROUP BY pr."Id_Professore", pr."Nome", pr."Cognome"
+        ORDER BY SUM(CASE WHEN p."Stato" = 'rilasciata' THEN 1 ELSE 0 END) DESC, pr."Cognome"
+        "#,
+        limite,
+        start,
+        end
+    )
+        .fetch_all(db)
+        .await
+}
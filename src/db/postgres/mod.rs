@@ -0,0 +1,42 @@
+// src/db/postgres/mod.rs
+// Backend PostgreSQL. Le colonne hanno gli stessi nomi di MySQL ma vanno scritte tra
+// virgolette; le date sono TIMESTAMP in UTC, quindi si passano come NaiveDateTime.
+// Oltre al controllo dell'applicazione, un vincolo di esclusione impedisce che due
+// prenotazioni attive della stessa aula esclusiva si sovrappongano.
+
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::postgres::PgPoolOptions;
+
+pub mod aggiornamenti;
+pub mod attrezzature;
+pub mod aule;
+pub mod blocchi;
+pub mod caldav;
+pub mod calendari;
+pub mod checkin;
+pub mod chiosco;
+pub mod guasti;
+pub mod notifiche;
+pub mod plessi;
+pub mod prenotazioni;
+pub mod professori;
+pub mod promemoria;
+pub mod webhook;
+
+pub type Db = sqlx::Postgres;
+pub type DbConnection = sqlx::PgConnection;
+
+pub async fn connetti(database_url: &str) -> Result<super::DbPool, sqlx::Error> {
+    PgPoolOptions::new().connect(database_url).await
+}
+
+pub static MIGRAZIONI: Migrator = sqlx::migrate!("./migrations/postgres");
+
+pub async fn migra(db_pool: &super::DbPool) -> Result<(), MigrateError> {
+    MIGRAZIONI.run(db_pool).await
+}
+
+// L'errore viene dal vincolo ex_prenotazione_sovrapposta (SQLSTATE 23P01, exclusion_violation)
+pub fn violazione_sovrapposizione(e: &sqlx::Error) -> bool {
+    e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23P01")
+}
@@ -0,0 +1,195 @@
+// src/db/postgres/webhook.rs
+// Iscrizioni ai webhook e coda delle consegne.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::{ConsegnaInScadenzaDb, ConsegnaWebhookApi, WebhookDb};
+
+// Id ed eventi (separati da virgole) delle iscrizioni attive
+pub async fn iscrizioni_attive(db: impl Esecutore<'_>) -> Result<Vec<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!(r#"SELECT "Id_Webhook", "Eventi" FROM webhook WHERE "Attivo" = TRUE"#)
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|w| (w.Id_Webhook, w.Eventi)).collect())
+}
+
+pub async fn accoda_consegna(db: impl Esecutore<'_>, id_webhook: i32, evento: &str, payload: &str) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"
+        INSERT INTO consegna_webhook ("Id_Webhook", "Evento", "Payload", "Stato", "Tentativi", "Prossimo_Tentativo", "Data_Creazione")
+        VALUES ($1, $2, $3, 'in_attesa', 0, $4, $5)
+        "#,
+        id_webhook,
+        evento,
+        payload,
+        adesso,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Consegne in attesa di webhook attivi il cui tentativo è dovuto entro `adesso`
+pub async fn consegne_in_scadenza(db: impl Esecutore<'_>, adesso: DateTime<Utc>, limite: i64) -> Result<Vec<ConsegnaInScadenzaDb>, sqlx::Error> {
+    let adesso = adesso.naive_utc();
+    sqlx::query_as!(
+        ConsegnaInScadenzaDb,
+        r#"
+        SELECT c."Id_Consegna" AS "Id_Consegna!", c."Evento", c."Payload", c."Tentativi", w."Url", w."Segreto", w."Formato"
+        FROM consegna_webhook c
+        JOIN webhook w ON c."Id_Webhook" = w."Id_Webhook"
+        WHERE c."Stato" = 'in_attesa' AND c."Prossimo_Tentativo" <= $1 AND w."Attivo" = TRUE
+        ORDER BY c."Prossimo_Tentativo"
+        LIMIT $2
+        "#,
+        adesso,
+        limite
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Presa in carico fino a `scadenza`: false se un altro processo l'ha già presa
+pub async fn prendi_in_carico(
+    db: impl Esecutore<'_>,
+    id_consegna: i64,
+    adesso: DateTime<Utc>,
+    scadenza: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let (adesso, scadenza) = (adesso.naive_utc(), scadenza.naive_utc());
+    let risultato = sqlx::query!(
+        r#"UPDATE consegna_webhook SET "Prossimo_Tentativo" = $1 WHERE "Id_Consegna" = $2 AND "Stato" = 'in_attesa' AND "Prossimo_Tentativo" <= $3"#,
+        scadenza,
+        id_consegna,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn segna_consegnata(db: impl Esecutore<'_>, id_consegna: i64, tentativi: i32, codice: Option<i32>) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"UPDATE consegna_webhook SET "Stato" = 'consegnata', "Tentativi" = $1, "Ultimo_Codice" = $2, "Ultimo_Errore" = NULL, "Data_Consegna" = $3 WHERE "Id_Consegna" = $4"#,
+        tentativi,
+        codice,
+        adesso,
+        id_consegna
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn ripianifica(
+    db: impl Esecutore<'_>,
+    id_consegna: i64,
+    stato: &str,
+    tentativi: i32,
+    codice: Option<i32>,
+    errore: &str,
+    prossimo_tentativo: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let prossimo_tentativo = prossimo_tentativo.naive_utc();
+    sqlx::query!(
+        r#"UPDATE consegna_webhook SET "Stato" = $1, "Tentativi" = $2, "Ultimo_Codice" = $3, "Ultimo_Errore" = $4, "Prossimo_Tentativo" = $5 WHERE "Id_Consegna" = $6"#,
+        stato,
+        tentativi,
+        codice,
+        errore,
+        prossimo_tentativo,
+        id_consegna
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn elenco(db: impl Esecutore<'_>) -> Result<Vec<WebhookDb>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookDb,
+        r#"SELECT "Id_Webhook", "Url", "Eventi", "Formato", "Attivo" AS "Attivo: bool", "Descrizione", "Data_Creazione" FROM webhook ORDER BY "Id_Webhook""#
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce l'id del nuovo webhook, creato attivo
+pub async fn inserisci(
+    db: impl Esecutore<'_>,
+    url: &str,
+    segreto: &str,
+    eventi: &str,
+    formato: &str,
+    descrizione: Option<&str>,
+) -> Result<i32, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query_scalar!(
+        r#"INSERT INTO webhook ("Url", "Segreto", "Eventi", "Formato", "Attivo", "Descrizione", "Data_Creazione") VALUES ($1, $2, $3, $4, TRUE, $5, $6) RETURNING "Id_Webhook""#,
+        url,
+        segreto,
+        eventi,
+        formato,
+        descrizione,
+        adesso
+    )
+        .fetch_one(db)
+        .await
+}
+
+// I campi None restano invariati
+pub async fn modifica(db: impl Esecutore<'_>, id_webhook: i32, attivo: Option<bool>, eventi: Option<&str>) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(
+        r#"UPDATE webhook SET "Attivo" = COALESCE($1, "Attivo"), "Eventi" = COALESCE($2, "Eventi") WHERE "Id_Webhook" = $3"#,
+        attivo,
+        eventi,
+        id_webhook
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn elimina(db: impl Esecutore<'_>, id_webhook: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!(r#"DELETE FROM webhook WHERE "Id_Webhook" = $1"#, id_webhook)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Registro delle consegne, dalla più recente
+pub async fn consegne(db: impl Esecutore<'_>, id_webhook: i32, stato: Option<&str>, limite: i64) -> Result<Vec<ConsegnaWebhookApi>, sqlx::Error> {
+    sqlx::query_as!(
+        ConsegnaWebhookApi,
+        r#"
+        SELECT "Id_Consegna" AS "Id_Consegna!", "Id_Webhook", "Evento", "Stato", "Tentativi", "Prossimo_Tentativo", "Ultimo_Codice",
+               "Ultimo_Errore", "Data_Creazione", "Data_Consegna", "Payload"
+        FROM consegna_webhook
+        WHERE "Id_Webhook" = $1 AND ($2::TEXT IS NULL OR "Stato" = $2)
+        ORDER BY "Id_Consegna" DESC
+        LIMIT $3
+        "#,
+        id_webhook,
+        stato,
+        limite
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Rimette in coda la consegna per un invio immediato
+pub async fn riprova(db: impl Esecutore<'_>, id_consegna: i64) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        r#"UPDATE consegna_webhook SET "Stato" = 'in_attesa', "Tentativi" = 0, "Prossimo_Tentativo" = $1 WHERE "Id_Consegna" = $2"#,
+        adesso,
+        id_consegna
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
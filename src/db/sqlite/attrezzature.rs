@@ -0,0 +1,97 @@
+// src/db/sqlite/attrezzature.rs
+// Attrezzatura delle aule e risorse mobili prenotabili.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::db::{DbConnection, Esecutore};
+use crate::models::AttrezzaturaApi;
+
+// Con `plesso` le attrezzature installate nelle sue aule più quelle mobili
+pub async fn elenco(db: impl Esecutore<'_>, plesso: Option<i32>) -> Result<Vec<AttrezzaturaApi>, sqlx::Error> {
+    sqlx::query_as!(
+        AttrezzaturaApi,
+        r#"
+        SELECT t.Id_Attrezzatura AS "Id_Attrezzatura!: i32", t.Nome, t.Id_Aula, t.Quantita, t.Prenotabile AS "Prenotabile: bool"
+        FROM attrezzatura t
+        LEFT JOIN aula a ON t.Id_Aula = a.Id_Aula
+        WHERE (? IS NULL OR t.Id_Aula IS NULL OR a.Id_Plesso = ?)
+        ORDER BY t.Nome
+        "#,
+        plesso, plesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Id, nome e quantità delle risorse prenotabili
+pub async fn prenotabili(db: impl Esecutore<'_>, plesso: Option<i32>) -> Result<Vec<(i32, String, i32)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT t.Id_Attrezzatura AS "Id_Attrezzatura!: i32", t.Nome, t.Quantita
+        FROM attrezzatura t
+        LEFT JOIN aula a ON t.Id_Aula = a.Id_Aula
+        WHERE t.Prenotabile = TRUE AND (? IS NULL OR t.Id_Aula IS NULL OR a.Id_Plesso = ?)
+        ORDER BY t.Nome
+        "#,
+        plesso, plesso
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|r| (r.Id_Attrezzatura, r.Nome, r.Quantita)).collect())
+}
+
+// Inizio, fine e quantità delle prenotazioni attive della risorsa che toccano l'intervallo
+pub async fn utilizzi(
+    db: impl Esecutore<'_>,
+    id_attrezzatura: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Result<Vec<(NaiveDateTime, NaiveDateTime, i32)>, sqlx::Error> {
+    let fine = fine.naive_utc();
+    let inizio = inizio.naive_utc();
+    let record = sqlx::query!(
+        r#"
+        SELECT p.Data_Inizio, p.Data_Fine, pa.Quantita
+        FROM prenotazione_attrezzatura pa
+        JOIN prenotazione p ON pa.Id_Prenotazione = p.Id_Prenotazione
+        WHERE pa.Id_Attrezzatura = ? AND p.Stato = 'attiva' AND p.Data_Inizio < ? AND p.Data_Fine > ?
+        "#,
+        id_attrezzatura,
+        fine,
+        inizio
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|r| (r.Data_Inizio, r.Data_Fine, r.Quantita)).collect())
+}
+
+// Blocca la riga della risorsa fino al commit. Restituisce nome, quantità e se è prenotabile.
+pub async fn blocca(conn: &mut DbConnection, id_attrezzatura: i32) -> Result<Option<(String, i32, bool)>, sqlx::Error> {
+    super::blocca_scrittura(conn, "attrezzatura", "Id_Attrezzatura", id_attrezzatura).await?;
+    let record = sqlx::query!(
+        r#"SELECT Nome, Quantita, Prenotabile AS "Prenotabile: bool" FROM attrezzatura WHERE Id_Attrezzatura = ?"#,
+        id_attrezzatura
+    )
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(record.map(|r| (r.Nome, r.Quantita, r.Prenotabile)))
+}
+
+// Come blocca, ma senza bloccare la riga (per le anteprime)
+pub async fn prenotabilita(db: impl Esecutore<'_>, id_attrezzatura: i32) -> Result<Option<(String, i32, bool)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT Nome, Quantita, Prenotabile AS "Prenotabile: bool" FROM attrezzatura WHERE Id_Attrezzatura = ?"#,
+        id_attrezzatura
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Nome, r.Quantita, r.Prenotabile)))
+}
+
+// Aula in cui la risorsa è installata (None anche se la risorsa non esiste)
+pub async fn aula(db: impl Esecutore<'_>, id_attrezzatura: i32) -> Result<Option<i32>, sqlx::Error> {
+    Ok(sqlx::query_scalar!("SELECT Id_Aula FROM attrezzatura WHERE Id_Attrezzatura = ?", id_attrezzatura)
+        .fetch_optional(db)
+        .await?
+        .flatten())
+}
@@ -0,0 +1,63 @@
+// src/db/sqlite/caldav.rs
+// Risorse create dai client CalDAV e CTag delle collezioni delle aule.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+
+use crate::db::Esecutore;
+
+// Numero di prenotazioni dell'aula e data dell'ultima modifica
+pub async fn ctag_aula(db: impl Esecutore<'_>, id_aula: i32) -> Result<(i64, Option<NaiveDateTime>), sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT COUNT(*) AS "Numero!: i64", MAX(Data_Modifica) AS "Ultima_Modifica: NaiveDateTime" FROM prenotazione WHERE Id_Aula = ?"#,
+        id_aula
+    )
+        .fetch_one(db)
+        .await?;
+    Ok((record.Numero, record.Ultima_Modifica))
+}
+
+// Nome e UID scelti dal client per le prenotazioni dell'aula, per id della prenotazione
+pub async fn risorse_aula(db: impl Esecutore<'_>, id_aula: i32) -> Result<HashMap<i32, (String, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT rc.Id_Prenotazione, rc.Nome_Risorsa, rc.Uid
+        FROM risorsa_caldav rc
+        JOIN prenotazione p ON rc.Id_Prenotazione = p.Id_Prenotazione
+        WHERE p.Id_Aula = ?
+        "#,
+        id_aula
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|r| (r.Id_Prenotazione, (r.Nome_Risorsa, r.Uid))).collect())
+}
+
+// Nome della risorsa di una prenotazione attiva dell'aula con questo UID
+pub async fn risorsa_con_uid(db: impl Esecutore<'_>, id_aula: i32, uid: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT rc.Nome_Risorsa
+        FROM risorsa_caldav rc
+        JOIN prenotazione p ON rc.Id_Prenotazione = p.Id_Prenotazione
+        WHERE p.Id_Aula = ? AND p.Stato = 'attiva' AND rc.Uid = ?
+        "#,
+        id_aula,
+        uid
+    )
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn inserisci_risorsa(db: impl Esecutore<'_>, id_prenotazione: i32, nome: &str, uid: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO risorsa_caldav (Id_Prenotazione, Nome_Risorsa, Uid) VALUES (?, ?, ?)",
+        id_prenotazione,
+        nome,
+        uid
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
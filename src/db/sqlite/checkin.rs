@@ -0,0 +1,163 @@
+// src/db/sqlite/checkin.rs
+// Check-in con QR nelle aule e rilascio delle prenotazioni senza check-in.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::AssenzeProfessoreDb;
+
+pub async fn registra(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        "UPDATE prenotazione SET Check_In = ? WHERE Id_Prenotazione = ? AND Check_In IS NULL",
+        adesso,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Prenotazioni in corso, senza check-in, iniziate (e modificate) prima di `limite`
+pub async fn assenti(db: impl Esecutore<'_>, limite: DateTime<Utc>, adesso: DateTime<Utc>) -> Result<Vec<i32>, sqlx::Error> {
+    let (limite, adesso) = (limite.naive_utc(), adesso.naive_utc());
+    sqlx::query_scalar!(
+        r#"
+        SELECT p.Id_Prenotazione AS "Id_Prenotazione!: i32"
+        FROM prenotazione p
+        JOIN checkin_aula c ON c.Id_Aula = p.Id_Aula
+        WHERE p.Stato = 'attiva'
+          AND p.Check_In IS NULL
+          AND (p.Approvata IS NULL OR p.Approvata = TRUE)
+          AND p.Data_Inizio >= c.Data_Attivazione
+          AND p.Data_Inizio <= ?
+          AND p.Data_Modifica <= ?
+          AND p.Data_Fine > ?
+        "#,
+        limite,
+        limite,
+        adesso
+    )
+        .fetch_all(db)
+        .await
+}
+
+// false se nel frattempo è arrivato il check-in o la prenotazione non è più attiva
+pub async fn rilascia(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        "UPDATE prenotazione SET Stato = 'rilasciata', Sequenza = Sequenza + 1, Data_Modifica = ? WHERE Id_Prenotazione = ? AND Stato = 'attiva' AND Check_In IS NULL",
+        adesso,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn codice_valido(db: impl Esecutore<'_>, id_aula: i32, codice: &str) -> Result<bool, sqlx::Error> {
+    let trovati = sqlx::query_scalar!("SELECT COUNT(*) FROM checkin_aula WHERE Id_Aula = ? AND Codice = ?", id_aula, codice)
+        .fetch_one(db)
+        .await?;
+    Ok(trovati > 0)
+}
+
+// Id, fine ed eventuale check-in della prenotazione del professore nell'aula iniziata entro `entro`
+pub async fn prenotazione_in_corso(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    id_professore: i32,
+    entro: DateTime<Utc>,
+    adesso: DateTime<Utc>,
+) -> Result<Option<(i32, NaiveDateTime, Option<NaiveDateTime>)>, sqlx::Error> {
+    let entro = entro.naive_utc();
+    let adesso = adesso.naive_utc();
+    let record = sqlx::query!(
+        r#"
+        SELECT Id_Prenotazione AS "Id_Prenotazione!: i32", Data_Fine, Check_In
+        FROM prenotazione
+        WHERE Id_Aula = ? AND Id_Professore = ? AND Stato = 'attiva' AND Data_Inizio <= ? AND Data_Fine > ?
+        ORDER BY Data_Inizio
+        LIMIT 1
+        "#,
+        id_aula,
+        id_professore,
+        entro,
+        adesso
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Prenotazione, r.Data_Fine, r.Check_In)))
+}
+
+// Non fa nulla se il check-in è già attivo, né se l'aula non esiste
+pub async fn attiva(db: impl Esecutore<'_>, id_aula: i32, codice: &str) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        "INSERT OR IGNORE INTO checkin_aula (Id_Aula, Codice, Data_Attivazione, Data_Creazione) SELECT ?, ?, ?, ? WHERE EXISTS (SELECT 1 FROM aula WHERE Id_Aula = ?)",
+        id_aula,
+        codice,
+        adesso,
+        adesso,
+        id_aula
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn codice(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!("SELECT Codice FROM checkin_aula WHERE Id_Aula = ?", id_aula)
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn rigenera(db: impl Esecutore<'_>, id_aula: i32, codice: &str) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!("UPDATE checkin_aula SET Codice = ?, Data_Creazione = ? WHERE Id_Aula = ?", codice, adesso, id_aula)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn disattiva(db: impl Esecutore<'_>, id_aula: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("DELETE FROM checkin_aula WHERE Id_Aula = ?", id_aula)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Per professore: prenotazioni soggette a check-in iniziate prima di `limite`, check-in fatti e rilasci
+pub async fn assenze(
+    db: impl Esecutore<'_>,
+    limite: DateTime<Utc>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<Vec<AssenzeProfessoreDb>, sqlx::Error> {
+    let limite = limite.naive_utc();
+    let (start, end) = (start.map(|d| d.naive_utc()), end.map(|d| d.naive_utc()));
+    sqlx::query_as!(
+        AssenzeProfessoreDb,
+        r#"
+        SELECT pr.Id_Professore AS "Id_Professore!: i32", pr.Nome, pr.Cognome,
+               COUNT(*) AS "Prenotazioni!: i64",
+               SUM(CASE WHEN p.Check_In IS NOT NULL THEN 1 ELSE 0 END) AS "Check_In!: i64",
+               SUM(CASE WHEN p.Stato = 'rilasciata' THEN 1 ELSE 0 END) AS "Rilasciate!: i64"
+        FROM prenotazione p
+        JOIN checkin_aula c ON c.Id_Aula = p.Id_Aula
+        JOIN professore pr ON pr.Id_Professore = p.Id_Professore
+        WHERE p.Stato IN ('attiva', 'rilasciata')
+          AND p.Data_Inizio >= c.Data_Attivazione
+          AND p.Data_Inizio <= ?
+          AND (? IS NULL OR p.Data_Inizio >= ?)
+          AND (? IS NULL OR p.Data_Inizio < ?)
+        GROUP BY pr.Id_Professore, pr.Nome, pr.Cognome
+        ORDER BY SUM(CASE WHEN p.Stato = 'rilasciata' THEN 1 ELSE 0 END) DESC, pr.Cognome
+        "#,
+        limite,
+        start, start,
+        end, end
+    )
+        .fetch_all(db)
+        .await
+}
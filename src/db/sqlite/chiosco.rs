@@ -0,0 +1,85 @@
+// src/db/sqlite/chiosco.rs
+// Tablet dei chioschi e PIN per la prenotazione rapida.
+
+use chrono::Utc;
+
+use crate::db::Esecutore;
+use crate::models::DispositivoChioscoApi;
+
+// Id del dispositivo e aula per cui è registrato
+pub async fn dispositivo(db: impl Esecutore<'_>, token: &str) -> Result<Option<(i32, i32)>, sqlx::Error> {
+    let record = sqlx::query!(r#"SELECT Id_Dispositivo AS "Id_Dispositivo!: i32", Id_Aula FROM dispositivo_chiosco WHERE Token = ?"#, token)
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Dispositivo, r.Id_Aula)))
+}
+
+pub async fn registra_accesso(db: impl Esecutore<'_>, id_dispositivo: i32) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!("UPDATE dispositivo_chiosco SET Ultimo_Accesso = ? WHERE Id_Dispositivo = ?", adesso, id_dispositivo)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Hash del PIN, nome e cognome del professore, se ha impostato un PIN
+pub async fn pin_professore(db: impl Esecutore<'_>, id_professore: i32) -> Result<Option<(String, Option<String>, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT pc.Pin_Hash, pr.Nome, pr.Cognome FROM pin_chiosco pc JOIN professore pr ON pc.Id_Professore = pr.Id_Professore WHERE pc.Id_Professore = ?"#,
+        id_professore
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Pin_Hash, r.Nome, r.Cognome)))
+}
+
+pub async fn elimina_pin(db: impl Esecutore<'_>, id_professore: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM pin_chiosco WHERE Id_Professore = ?", id_professore)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn inserisci_pin(db: impl Esecutore<'_>, id_professore: i32, pin_hash: &str) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        "INSERT INTO pin_chiosco (Id_Professore, Pin_Hash, Data_Modifica) VALUES (?, ?, ?)",
+        id_professore,
+        pin_hash,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn dispositivi(db: impl Esecutore<'_>) -> Result<Vec<DispositivoChioscoApi>, sqlx::Error> {
+    sqlx::query_as!(
+        DispositivoChioscoApi,
+        r#"SELECT Id_Dispositivo AS "Id_Dispositivo!: i32", Id_Aula, Descrizione, Data_Creazione, Ultimo_Accesso FROM dispositivo_chiosco ORDER BY Id_Aula, Id_Dispositivo"#
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Restituisce l'id del nuovo dispositivo
+pub async fn inserisci_dispositivo(db: impl Esecutore<'_>, id_aula: i32, token: &str, descrizione: Option<&str>) -> Result<i32, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        "INSERT INTO dispositivo_chiosco (Id_Aula, Token, Descrizione, Data_Creazione) VALUES (?, ?, ?, ?)",
+        id_aula,
+        token,
+        descrizione,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_rowid() as i32)
+}
+
+pub async fn elimina_dispositivo(db: impl Esecutore<'_>, id_dispositivo: i32) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("DELETE FROM dispositivo_chiosco WHERE Id_Dispositivo = ?", id_dispositivo)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
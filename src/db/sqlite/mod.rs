@@ -0,0 +1,69 @@
+// src/db/sqlite/mod.rs
+// Backend SQLite, per lo sviluppo locale e i test senza un server MySQL.
+// Le date si salvano come NaiveDateTime UTC (stesso formato testuale ovunque, così i
+// confronti tra stringhe restano corretti) e SQLite non ha SELECT ... FOR UPDATE:
+// dove MySQL blocca una riga qui si prende subito il lock di scrittura del database.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+pub mod aggiornamenti;
+pub mod attrezzature;
+pub mod aule;
+pub mod blocchi;
+pub mod caldav;
+pub mod calendari;
+pub mod checkin;
+pub mod chiosco;
+pub mod guasti;
+pub mod notifiche;
+pub mod plessi;
+pub mod prenotazioni;
+pub mod professori;
+pub mod promemoria;
+pub mod webhook;
+
+pub type Db = sqlx::Sqlite;
+pub type DbConnection = sqlx::SqliteConnection;
+
+pub async fn connetti(database_url: &str) -> Result<super::DbPool, sqlx::Error> {
+    let in_memoria = database_url.contains(":memory:") || database_url.contains("mode=memory");
+    let mut opzioni = SqliteConnectOptions::from_str(database_url)?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .busy_timeout(Duration::from_secs(5));
+    if !in_memoria {
+        opzioni = opzioni.journal_mode(SqliteJournalMode::Wal);
+    }
+
+    // Un database in memoria sparisce con l'ultima connessione: il pool ne tiene sempre una aperta
+    let mut pool = SqlitePoolOptions::new();
+    if in_memoria {
+        pool = pool.min_connections(1).idle_timeout(None).max_lifetime(None);
+    }
+    pool.connect_with(opzioni).await
+}
+
+pub static MIGRAZIONI: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+pub async fn migra(db_pool: &super::DbPool) -> Result<(), MigrateError> {
+    MIGRAZIONI.run(db_pool).await
+}
+
+// Equivalente di SELECT ... FOR UPDATE: un UPDATE che non cambia nulla fa passare la
+// transazione in scrittura, così le transazioni concorrenti aspettano il commit
+async fn blocca_scrittura(conn: &mut DbConnection, tabella: &str, colonna: &str, id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("UPDATE {tabella} SET {colonna} = {colonna} WHERE {colonna} = ?"))
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Come con MySQL, le sovrapposizioni le controlla solo l'applicazione
+pub fn violazione_sovrapposizione(_e: &sqlx::Error) -> bool {
+    false
+}
@@ -0,0 +1,173 @@
+// src/db/sqlite/notifiche.rs
+// Coda delle email in uscita e preferenze di notifica dei professori.
+
+use chrono::{DateTime, Utc};
+
+use crate::db::Esecutore;
+use crate::models::{DestinatarioDb, EmailInUscitaDb, PreferenzeNotificheApi};
+
+// Email, nome e preferenze salvate del professore (None se non ha credenziali)
+pub async fn destinatario(db: impl Esecutore<'_>, id_professore: i32) -> Result<Option<DestinatarioDb>, sqlx::Error> {
+    sqlx::query_as!(
+        DestinatarioDb,
+        r#"
+        SELECT c.email, pr.Nome, pr.Lingua,
+            pn.Creata AS "Creata?: bool",
+            pn.Modificata AS "Modificata?: bool",
+            pn.Annullata AS "Annullata?: bool",
+            pn.Approvata AS "Approvata?: bool",
+            pn.Scavalcata AS "Scavalcata?: bool",
+            pn.Promemoria AS "Promemoria?: bool",
+            pn.Riepilogo AS "Riepilogo?: bool"
+        FROM credenziali c
+        JOIN professore pr ON pr.Id_Professore = c.Id_Professore_Cred
+        LEFT JOIN preferenza_notifica pn ON pn.Id_Professore = c.Id_Professore_Cred
+        WHERE c.Id_Professore_Cred = ?
+        "#,
+        id_professore
+    )
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn accoda_email(db: impl Esecutore<'_>, id_professore: i32, destinatario: &str, oggetto: &str, corpo: &str) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_in_uscita (Id_Professore, Destinatario, Oggetto, Corpo, Stato, Tentativi, Prossimo_Tentativo, Data_Creazione)
+        VALUES (?, ?, ?, ?, 'in_attesa', 0, ?, ?)
+        "#,
+        id_professore,
+        destinatario,
+        oggetto,
+        corpo,
+        adesso,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn email_in_scadenza(db: impl Esecutore<'_>, adesso: DateTime<Utc>, limite: i64) -> Result<Vec<EmailInUscitaDb>, sqlx::Error> {
+    let adesso = adesso.naive_utc();
+    sqlx::query_as!(
+        EmailInUscitaDb,
+        r#"
+        SELECT Id_Email AS "Id_Email!", Destinatario, Oggetto, Corpo, Tentativi
+        FROM email_in_uscita
+        WHERE Stato = 'in_attesa' AND Prossimo_Tentativo <= ?
+        ORDER BY Prossimo_Tentativo
+        LIMIT ?
+        "#,
+        adesso,
+        limite
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Presa in carico fino a `scadenza`: false se un altro processo l'ha già presa
+pub async fn prendi_in_carico(
+    db: impl Esecutore<'_>,
+    id_email: i64,
+    adesso: DateTime<Utc>,
+    scadenza: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let scadenza = scadenza.naive_utc();
+    let adesso = adesso.naive_utc();
+    let risultato = sqlx::query!(
+        "UPDATE email_in_uscita SET Prossimo_Tentativo = ? WHERE Id_Email = ? AND Stato = 'in_attesa' AND Prossimo_Tentativo <= ?",
+        scadenza,
+        id_email,
+        adesso
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn segna_inviata(db: impl Esecutore<'_>, id_email: i64, tentativi: i32) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        "UPDATE email_in_uscita SET Stato = 'inviata', Tentativi = ?, Ultimo_Errore = NULL, Data_Invio = ? WHERE Id_Email = ?",
+        tentativi,
+        adesso,
+        id_email
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn ripianifica(
+    db: impl Esecutore<'_>,
+    id_email: i64,
+    stato: &str,
+    tentativi: i32,
+    errore: &str,
+    prossimo_tentativo: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let prossimo_tentativo = prossimo_tentativo.naive_utc();
+    sqlx::query!(
+        "UPDATE email_in_uscita SET Stato = ?, Tentativi = ?, Ultimo_Errore = ?, Prossimo_Tentativo = ? WHERE Id_Email = ?",
+        stato,
+        tentativi,
+        errore,
+        prossimo_tentativo,
+        id_email
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// None se il professore non ha mai salvato le sue preferenze
+pub async fn preferenze(db: impl Esecutore<'_>, id_professore: i32) -> Result<Option<PreferenzeNotificheApi>, sqlx::Error> {
+    sqlx::query_as!(
+        PreferenzeNotificheApi,
+        r#"
+        SELECT
+            Creata AS "Creata: bool",
+            Modificata AS "Modificata: bool",
+            Annullata AS "Annullata: bool",
+            Approvata AS "Approvata: bool",
+            Scavalcata AS "Scavalcata: bool",
+            Promemoria AS "Promemoria: bool",
+            Riepilogo AS "Riepilogo: bool"
+        FROM preferenza_notifica
+        WHERE Id_Professore = ?
+        "#,
+        id_professore
+    )
+        .fetch_optional(db)
+        .await
+}
+
+pub async fn salva_preferenze(db: impl Esecutore<'_>, id_professore: i32, preferenze: &PreferenzeNotificheApi) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO preferenza_notifica (Id_Professore, Creata, Modificata, Annullata, Approvata, Scavalcata, Promemoria, Riepilogo)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(Id_Professore) DO UPDATE SET
+            Creata = excluded.Creata,
+            Modificata = excluded.Modificata,
+            Annullata = excluded.Annullata,
+            Approvata = excluded.Approvata,
+            Scavalcata = excluded.Scavalcata,
+            Promemoria = excluded.Promemoria,
+            Riepilogo = excluded.Riepilogo
+        "#,
+        id_professore,
+        preferenze.Creata,
+        preferenze.Modificata,
+        preferenze.Annullata,
+        preferenze.Approvata,
+        preferenze.Scavalcata,
+        preferenze.Promemoria,
+        preferenze.Riepilogo
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
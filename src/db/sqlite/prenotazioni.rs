@@ -0,0 +1,313 @@
+// src/db/sqlite/prenotazioni.rs
+// Prenotazioni di aule e attrezzatura. I controlli di disponibilità sono in src/prenotazioni.rs.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rocket::futures::stream::BoxStream;
+use rocket::futures::StreamExt;
+use rocket::response::stream::stream;
+
+use crate::db::{DbConnection, DbPool, Esecutore};
+use crate::models::{AttrezzaturaRichiesta, PrenotazioneDb, PrenotazioneIntervalloDb, RigaEsportazioneDb};
+use crate::prenotazioni::{FiltroPrenotazioni, RichiestaPrenotazione};
+
+// Inizio, fine e numero di studenti delle prenotazioni attive dell'aula che toccano l'intervallo
+pub async fn utilizzi_aula(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Result<Vec<(NaiveDateTime, NaiveDateTime, Option<i32>)>, sqlx::Error> {
+    let (inizio, fine) = (inizio.naive_utc(), fine.naive_utc());
+    let record = sqlx::query!(
+        "SELECT Data_Inizio, Data_Fine, Numero_Studenti FROM prenotazione WHERE Id_Aula = ? AND Stato = 'attiva' AND Data_Inizio < ? AND Data_Fine > ?",
+        id_aula,
+        fine,
+        inizio
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|r| (r.Data_Inizio, r.Data_Fine, r.Numero_Studenti)).collect())
+}
+
+// Restituisce l'id della nuova prenotazione; `approvata` è Some(false) se l'aula richiede approvazione
+pub async fn inserisci(db: impl Esecutore<'_>, richiesta: &RichiestaPrenotazione, approvata: Option<bool>) -> Result<i32, sqlx::Error> {
+    let (data_inizio, data_fine) = (richiesta.data_inizio.naive_utc(), richiesta.data_fine.naive_utc());
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        "INSERT INTO prenotazione (Id_Professore, Id_Aula, Data_Inizio, Data_Fine, Numero_Studenti, Id_Materia, Stato, Sequenza, Data_Modifica, Approvata) VALUES (?, ?, ?, ?, ?, ?, 'attiva', 0, ?, ?)",
+        richiesta.id_professore,
+        richiesta.id_aula,
+        data_inizio,
+        data_fine,
+        richiesta.numero_studenti,
+        richiesta.id_materia,
+        adesso,
+        approvata
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_rowid() as i32)
+}
+
+pub async fn aggiungi_attrezzatura(db: impl Esecutore<'_>, id_prenotazione: i32, voce: &AttrezzaturaRichiesta) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO prenotazione_attrezzatura (Id_Prenotazione, Id_Attrezzatura, Quantita) VALUES (?, ?, ?)",
+        id_prenotazione,
+        voce.Id_Attrezzatura,
+        voce.Quantita
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Professore e stato della prenotazione
+pub async fn titolare_e_stato(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT Id_Professore, Stato FROM prenotazione WHERE Id_Prenotazione = ?",
+        id_prenotazione
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Professore, r.Stato)))
+}
+
+// Come titolare_e_stato, bloccando la riga fino al commit
+pub async fn blocca(conn: &mut DbConnection, id_prenotazione: i32) -> Result<Option<(i32, String)>, sqlx::Error> {
+    super::blocca_scrittura(conn, "prenotazione", "Id_Prenotazione", id_prenotazione).await?;
+    let record = sqlx::query!(
+        "SELECT Id_Professore, Stato FROM prenotazione WHERE Id_Prenotazione = ?",
+        id_prenotazione
+    )
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(record.map(|r| (r.Id_Professore, r.Stato)))
+}
+
+pub async fn attrezzature(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<Vec<AttrezzaturaRichiesta>, sqlx::Error> {
+    sqlx::query_as!(
+        AttrezzaturaRichiesta,
+        "SELECT Id_Attrezzatura, Quantita FROM prenotazione_attrezzatura WHERE Id_Prenotazione = ?",
+        id_prenotazione
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Marca la prenotazione annullata senza cambiarne la sequenza, per escluderla dai controlli
+// di disponibilità dentro una transazione di modifica
+pub async fn sospendi(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE prenotazione SET Stato = 'annullata' WHERE Id_Prenotazione = ?", id_prenotazione)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Nuovo orario (ed eventualmente nuova aula) di una prenotazione, che torna attiva
+pub async fn sposta(
+    db: impl Esecutore<'_>,
+    id_prenotazione: i32,
+    id_aula: Option<i32>,
+    data_inizio: DateTime<Utc>,
+    data_fine: DateTime<Utc>,
+    numero_studenti: Option<i32>,
+    approvata: Option<bool>,
+) -> Result<(), sqlx::Error> {
+    let (data_inizio, data_fine) = (data_inizio.naive_utc(), data_fine.naive_utc());
+    let adesso = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"
+        UPDATE prenotazione
+        SET Id_Aula = ?, Data_Inizio = ?, Data_Fine = ?, Numero_Studenti = ?, Stato = 'attiva',
+            Sequenza = Sequenza + 1, Data_Modifica = ?, Approvata = ?
+        WHERE Id_Prenotazione = ?
+        "#,
+        id_aula,
+        data_inizio,
+        data_fine,
+        numero_studenti,
+        adesso,
+        approvata,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+// Restituisce false se la prenotazione non era in attesa di approvazione
+pub async fn approva(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        "UPDATE prenotazione SET Approvata = TRUE, Sequenza = Sequenza + 1, Data_Modifica = ? WHERE Id_Prenotazione = ? AND Stato = 'attiva' AND Approvata = FALSE",
+        adesso,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Restituisce false se la prenotazione non era attiva
+pub async fn annulla(db: impl Esecutore<'_>, id_prenotazione: i32) -> Result<bool, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    let risultato = sqlx::query!(
+        "UPDATE prenotazione SET Stato = 'annullata', Sequenza = Sequenza + 1, Data_Modifica = ? WHERE Id_Prenotazione = ? AND Stato = 'attiva'",
+        adesso,
+        id_prenotazione
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+pub async fn elenco(db: impl Esecutore<'_>, filtro: &FiltroPrenotazioni) -> Result<Vec<PrenotazioneDb>, sqlx::Error> {
+    let (start, end) = (filtro.start.map(|d| d.naive_utc()), filtro.end.map(|d| d.naive_utc()));
+    sqlx::query_as!(
+        PrenotazioneDb, // La struct che mappa il risultato della query
+        r#"
+        SELECT
+            p.Id_Prenotazione AS "Id_Prenotazione!: i32",
+            p.Data_Inizio,      -- Verrà letto come NaiveDateTime
+            p.Data_Fine,        -- Verrà letto come NaiveDateTime
+            p.Id_Aula,
+            a.Tipo_Aula AS "Tipo_Aula?",
+            a.Numero AS "Numero_Aula?",
+            a.Politica_Prenotazione AS "Politica_Prenotazione?",
+            a.Capacita,
+            p.Numero_Studenti,
+            pl.Nome AS "Nome_Plesso?",
+            p.Id_Professore,
+            p.Stato,
+            p.Sequenza,
+            p.Data_Modifica,
+            p.Approvata AS "Approvata?: bool",
+            pr.Nome AS Nome_Professore,
+            pr.Cognome AS Cognome_Professore,
+            m.Nome AS "Materia?"
+        FROM
+            prenotazione p
+        LEFT JOIN
+            aula a ON p.Id_Aula = a.Id_Aula
+        LEFT JOIN
+            materia m ON p.Id_Materia = m.Id_Materia
+        LEFT JOIN
+            plesso pl ON a.Id_Plesso = pl.Id_Plesso
+        JOIN
+            professore pr ON p.Id_Professore = pr.Id_Professore
+        WHERE (? IS NULL OR p.Data_Fine > ?)
+          AND (? IS NULL OR p.Data_Inizio < ?)
+          AND (? IS NULL OR a.Id_Plesso = ?)
+          AND (? IS NULL OR p.Id_Professore = ?)
+          AND (? IS NULL OR p.Id_Aula = ?)
+          AND (? IS NULL OR a.Tipo_Aula = ?)
+          AND (? IS NULL OR p.Id_Prenotazione = ?)
+          AND (? OR p.Stato = 'attiva')
+        ORDER BY p.Data_Inizio ASC
+        "#,
+        start, start,
+        end, end,
+        filtro.plesso, filtro.plesso,
+        filtro.id_professore, filtro.id_professore,
+        filtro.id_aula, filtro.id_aula,
+        filtro.tipo_aula, filtro.tipo_aula,
+        filtro.id_prenotazione, filtro.id_prenotazione,
+        filtro.includi_annullate
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Id della prenotazione, nome e quantità dell'attrezzatura prenotata nell'intervallo del filtro
+pub async fn attrezzature_prenotate(db: impl Esecutore<'_>, filtro: &FiltroPrenotazioni) -> Result<Vec<(i32, String, i32)>, sqlx::Error> {
+    let (start, end) = (filtro.start.map(|d| d.naive_utc()), filtro.end.map(|d| d.naive_utc()));
+    let record = sqlx::query!(
+        r#"
+        SELECT pa.Id_Prenotazione, t.Nome, pa.Quantita
+        FROM prenotazione_attrezzatura pa
+        JOIN attrezzatura t ON pa.Id_Attrezzatura = t.Id_Attrezzatura
+        JOIN prenotazione p ON pa.Id_Prenotazione = p.Id_Prenotazione
+        WHERE (? IS NULL OR p.Data_Fine > ?)
+          AND (? IS NULL OR p.Data_Inizio < ?)
+        ORDER BY t.Nome
+        "#,
+        start, start,
+        end, end
+    )
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|r| (r.Id_Prenotazione, r.Nome, r.Quantita)).collect())
+}
+
+// Le prenotazioni attive come flusso di righe, nello stesso ordine e con gli stessi filtri di elenco
+// (i limiti del filtro vivono dentro il flusso, perché la query li tiene in prestito)
+pub fn righe_esportazione<'e>(db_pool: &'e DbPool, filtro: &'e FiltroPrenotazioni) -> BoxStream<'e, Result<RigaEsportazioneDb, sqlx::Error>> {
+    Box::pin(stream! {
+        let (start, end) = (filtro.start.map(|d| d.naive_utc()), filtro.end.map(|d| d.naive_utc()));
+        let mut righe = sqlx::query_as!(
+            RigaEsportazioneDb,
+            r#"
+            SELECT
+                p.Data_Inizio,
+                p.Data_Fine,
+                a.Tipo_Aula AS "Tipo_Aula?",
+                a.Numero AS "Numero_Aula?",
+                a.Id_Plesso AS "Id_Plesso?",
+                pl.Nome AS "Nome_Plesso?",
+                pr.Nome AS Nome_Professore,
+                pr.Cognome AS Cognome_Professore,
+                m.Nome AS "Materia?",
+                p.Approvata AS "Approvata?: bool"
+            FROM prenotazione p
+            LEFT JOIN aula a ON p.Id_Aula = a.Id_Aula
+            LEFT JOIN plesso pl ON a.Id_Plesso = pl.Id_Plesso
+            LEFT JOIN materia m ON p.Id_Materia = m.Id_Materia
+            JOIN professore pr ON p.Id_Professore = pr.Id_Professore
+            WHERE (? IS NULL OR p.Data_Fine > ?)
+              AND (? IS NULL OR p.Data_Inizio < ?)
+              AND (? IS NULL OR a.Id_Plesso = ?)
+              AND p.Stato = 'attiva'
+            ORDER BY p.Data_Inizio ASC
+            "#,
+            start, start,
+            end, end,
+            filtro.plesso, filtro.plesso
+        )
+            .fetch(db_pool);
+        while let Some(riga) = righe.next().await {
+            yield riga;
+        }
+    })
+}
+
+// Prenotazioni attive dell'aula che toccano l'intervallo [da, a)
+pub async fn dell_aula_nel_periodo(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    da: DateTime<Utc>,
+    a: DateTime<Utc>,
+) -> Result<Vec<PrenotazioneIntervalloDb>, sqlx::Error> {
+    let (da, a) = (da.naive_utc(), a.naive_utc());
+    sqlx::query_as!(
+        PrenotazioneIntervalloDb,
+        r#"SELECT Id_Prenotazione AS "Id_Prenotazione!: i32", Id_Professore, Data_Inizio, Data_Fine FROM prenotazione WHERE Id_Aula = ? AND Stato = 'attiva' AND Data_Inizio < ? AND Data_Fine > ? ORDER BY Data_Inizio"#,
+        id_aula,
+        a,
+        da
+    )
+        .fetch_all(db)
+        .await
+}
+
+// Prenotazioni attive dell'aula non ancora terminate
+pub async fn future_dell_aula(db: impl Esecutore<'_>, id_aula: i32) -> Result<Vec<PrenotazioneIntervalloDb>, sqlx::Error> {
+    let adesso = Utc::now().naive_utc();
+    sqlx::query_as!(
+        PrenotazioneIntervalloDb,
+        r#"SELECT Id_Prenotazione AS "Id_Prenotazione!: i32", Id_Professore, Data_Inizio, Data_Fine FROM prenotazione WHERE Id_Aula = ? AND Stato = 'attiva' AND Data_Fine > ? ORDER BY Data_Inizio"#,
+        id_aula,
+        adesso
+    )
+        .fetch_all(db)
+        .await
+}
@@ -0,0 +1,123 @@
+// src/db/sqlite/professori.rs
+// Professori, credenziali e materie insegnate.
+
+use crate::db::Esecutore;
+use crate::models::{MateriaApi, ProfessoreDb};
+
+// Id e hash della password dell'utente con questa email
+pub async fn credenziali(db: impl Esecutore<'_>, email: &str) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT Id_Professore_Cred, password_hash FROM credenziali WHERE email = ?",
+        email
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Professore_Cred, r.password_hash)))
+}
+
+// Come credenziali, con il ruolo (per l'autenticazione Basic del server CalDAV)
+pub async fn credenziali_con_ruolo(db: impl Esecutore<'_>, email: &str) -> Result<Option<(i32, String, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT c.Id_Professore_Cred, c.password_hash, p.Ruolo
+        FROM credenziali c
+        JOIN professore p ON c.Id_Professore_Cred = p.Id_Professore
+        WHERE c.email = ?
+        "#,
+        email
+    )
+        .fetch_optional(db)
+        .await?;
+    Ok(record.map(|r| (r.Id_Professore_Cred, r.password_hash, r.Ruolo)))
+}
+
+pub async fn professore(db: impl Esecutore<'_>, id_professore: i32) -> Result<ProfessoreDb, sqlx::Error> {
+    sqlx::query_as!(
+        ProfessoreDb,
+        "SELECT Nome, Cognome, Ruolo, Lingua FROM professore WHERE Id_Professore = ?",
+        id_professore
+    )
+        .fetch_one(db)
+        .await
+}
+
+// `None` torna alla lingua del browser. Restituisce false se il professore non esiste.
+pub async fn imposta_lingua(db: impl Esecutore<'_>, id_professore: i32, lingua: Option<&str>) -> Result<bool, sqlx::Error> {
+    let risultato = sqlx::query!("UPDATE professore SET Lingua = ? WHERE Id_Professore = ?", lingua, id_professore)
+        .execute(db)
+        .await?;
+    Ok(risultato.rows_affected() == 1)
+}
+
+// Email registrate, per riconoscere l'organizzatore degli eventi importati
+pub async fn email_professori(db: impl Esecutore<'_>) -> Result<Vec<(i32, String)>, sqlx::Error> {
+    let record = sqlx::query!("SELECT Id_Professore_Cred, email FROM credenziali")
+        .fetch_all(db)
+        .await?;
+    Ok(record.into_iter().map(|r| (r.Id_Professore_Cred, r.email)).collect())
+}
+
+pub async fn email_registrata(db: impl Esecutore<'_>, email: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM credenziali WHERE email = ?)")
+        .bind(email)
+        .fetch_one(db)
+        .await
+}
+
+// Restituisce l'id del nuovo professore
+pub async fn inserisci(db: impl Esecutore<'_>, nome: &str, cognome: &str) -> Result<i32, sqlx::Error> {
+    let risultato = sqlx::query!(
+        "INSERT INTO professore (Nome, Cognome) VALUES (?, ?)",
+        nome, cognome
+    )
+        .execute(db)
+        .await?;
+    Ok(risultato.last_insert_rowid() as i32)
+}
+
+pub async fn inserisci_credenziali(db: impl Esecutore<'_>, id_professore: i32, email: &str, password_hash: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO credenziali (Id_Professore_Cred, email, password_hash) VALUES (?, ?, ?)",
+        id_professore, email, password_hash
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn aggiungi_materia(db: impl Esecutore<'_>, id_professore: i32, id_materia: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO insegna (Id_Professore, Id_Materia) VALUES (?, ?)",
+        id_professore, id_materia
+    )
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn insegna(db: impl Esecutore<'_>, id_professore: i32, id_materia: i32) -> Result<bool, sqlx::Error> {
+    let conteggio = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM insegna WHERE Id_Professore = ? AND Id_Materia = ?",
+        id_professore,
+        id_materia
+    )
+        .fetch_one(db)
+        .await?;
+    Ok(conteggio > 0)
+}
+
+// Con `professore` solo le materie che insegna
+pub async fn materie(db: impl Esecutore<'_>, professore: Option<i32>) -> Result<Vec<MateriaApi>, sqlx::Error> {
+    sqlx::query_as!(
+        MateriaApi,
+        r#"
+        SELECT Id_Materia AS "Id_Materia!: i32", Nome, Descrizione FROM materia
+        WHERE ? IS NULL OR Id_Materia IN (SELECT Id_Materia FROM insegna WHERE Id_Professore = ?)
+        ORDER BY Nome ASC
+        "#,
+        professore,
+        professore
+    )
+        .fetch_all(db)
+        .await
+}
@@ -0,0 +1,323 @@
+// src/errori.rs
+// Errore unico delle API. Ogni variante ha il suo stato HTTP e ogni errore un codice stabile
+// (es. "aula_non_trovata") su cui il frontend può decidere senza interpretare il messaggio.
+// Il corpo resta quello di sempre, {"status": "fallito"|"errore", "message": ...}, con in più
+// "code", gli eventuali dati dell'errore (es. "posti_residui") e, per i 422, l'elenco dei campi.
+// I messaggi sono tradotti nella lingua della richiesta usando il codice come chiave del catalogo.
+
+use std::fmt::Display;
+
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::serde_json::Map;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::serde::Serialize;
+
+use crate::{metriche, registro};
+use crate::traduzioni::{valori, Lingua};
+
+#[derive(Debug)]
+pub enum ErroreApi {
+    RichiestaNonValida(Dettaglio), // 400: richiesta malformata o incompleta
+    NonAutenticato(Dettaglio),     // 401
+    Vietato(Dettaglio),            // 403
+    NonTrovato(Dettaglio),         // 404
+    Conflitto(Dettaglio),          // 409: in conflitto con lo stato attuale (sovrapposizioni, stati...)
+    TroppoGrande(Dettaglio),       // 413
+    CampiNonValidi(Vec<ErroreCampo>), // 422: campi ben formati ma con valori non accettabili
+    TroppeRichieste(Dettaglio),    // 429
+    Interno { contesto: String, errore: String }, // 500: il dettaglio finisce solo nel log
+}
+
+#[derive(Debug)]
+pub struct Dettaglio {
+    pub codice: &'static str,
+    pub messaggio: String,
+    pub dati: Map<String, JsonValue>,
+}
+
+#[derive(Debug)]
+pub struct ErroreCampo {
+    pub campo: String, // Nome del campo come arriva nel JSON o nella query (es. "Data_Fine")
+    pub codice: &'static str,
+    pub messaggio: String,
+}
+
+impl ErroreCampo {
+    pub fn new(campo: impl Into<String>, codice: &'static str, messaggio: impl Into<String>) -> ErroreCampo {
+        ErroreCampo { campo: campo.into(), codice, messaggio: messaggio.into() }
+    }
+
+    // Nei testi tradotti {campo} è il nome del campo
+    pub fn messaggio_in(&self, lingua: Lingua) -> String {
+        lingua.traduci_con(&format!("errori.{}", self.codice), &self.messaggio, &valori(&[("campo", self.campo.clone())]))
+    }
+}
+
+fn dettaglio(codice: &'static str, messaggio: impl Into<String>) -> Dettaglio {
+    Dettaglio { codice, messaggio: messaggio.into(), dati: Map::new() }
+}
+
+impl ErroreApi {
+    pub fn richiesta_non_valida(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::RichiestaNonValida(dettaglio(codice, messaggio))
+    }
+
+    pub fn non_autenticato(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::NonAutenticato(dettaglio(codice, messaggio))
+    }
+
+    pub fn vietato(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::Vietato(dettaglio(codice, messaggio))
+    }
+
+    pub fn non_trovato(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::NonTrovato(dettaglio(codice, messaggio))
+    }
+
+    pub fn conflitto(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::Conflitto(dettaglio(codice, messaggio))
+    }
+
+    pub fn troppo_grande(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::TroppoGrande(dettaglio(codice, messaggio))
+    }
+
+    pub fn troppe_richieste(codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::TroppeRichieste(dettaglio(codice, messaggio))
+    }
+
+    // Un solo campo non valido, il caso più comune
+    pub fn campo(campo: impl Into<String>, codice: &'static str, messaggio: impl Into<String>) -> ErroreApi {
+        ErroreApi::CampiNonValidi(vec![ErroreCampo::new(campo, codice, messaggio)])
+    }
+
+    // L'errore originale va nel log insieme all'id della richiesta; al client arriva solo un errore interno generico
+    pub fn interno(contesto: impl Into<String>, e: impl Display) -> ErroreApi {
+        ErroreApi::Interno { contesto: contesto.into(), errore: e.to_string() }
+    }
+
+    // Errore interno del database, es. database("nel leggere l'aula", e)
+    pub fn database(contesto: &str, e: sqlx::Error) -> ErroreApi {
+        ErroreApi::interno(format!("DB {}", contesto), e)
+    }
+
+    // Aggiunge un dato al corpo della risposta (es. l'id del blocco che impedisce la prenotazione)
+    pub fn con(mut self, chiave: &str, valore: impl Serialize) -> ErroreApi {
+        if let Some(dettaglio) = self.dettaglio_mut() {
+            dettaglio.dati.insert(chiave.to_string(), json!(valore));
+        }
+        self
+    }
+
+    fn dettaglio_mut(&mut self) -> Option<&mut Dettaglio> {
+        match self {
+            ErroreApi::RichiestaNonValida(d)
+            | ErroreApi::NonAutenticato(d)
+            | ErroreApi::Vietato(d)
+            | ErroreApi::NonTrovato(d)
+            | ErroreApi::Conflitto(d)
+            | ErroreApi::TroppoGrande(d)
+            | ErroreApi::TroppeRichieste(d) => Some(d),
+            ErroreApi::CampiNonValidi(_) | ErroreApi::Interno { .. } => None,
+        }
+    }
+
+    pub fn stato(&self) -> Status {
+        match self {
+            ErroreApi::RichiestaNonValida(_) => Status::BadRequest,
+            ErroreApi::NonAutenticato(_) => Status::Unauthorized,
+            ErroreApi::Vietato(_) => Status::Forbidden,
+            ErroreApi::NonTrovato(_) => Status::NotFound,
+            ErroreApi::Conflitto(_) => Status::Conflict,
+            ErroreApi::TroppoGrande(_) => Status::PayloadTooLarge,
+            ErroreApi::CampiNonValidi(_) => Status::UnprocessableEntity,
+            ErroreApi::TroppeRichieste(_) => Status::TooManyRequests,
+            ErroreApi::Interno { .. } => Status::InternalServerError,
+        }
+    }
+
+    pub fn codice(&self) -> &'static str {
+        match self {
+            ErroreApi::CampiNonValidi(_) => "campi_non_validi",
+            ErroreApi::Interno { .. } => "errore_interno",
+            ErroreApi::RichiestaNonValida(d)
+            | ErroreApi::NonAutenticato(d)
+            | ErroreApi::Vietato(d)
+            | ErroreApi::NonTrovato(d)
+            | ErroreApi::Conflitto(d)
+            | ErroreApi::TroppoGrande(d)
+            | ErroreApi::TroppeRichieste(d) => d.codice,
+        }
+    }
+
+    pub fn messaggio(&self) -> String {
+        self.messaggio_in(Lingua::Italiano)
+    }
+
+    pub fn messaggio_in(&self, lingua: Lingua) -> String {
+        match self {
+            ErroreApi::CampiNonValidi(campi) => campi.iter().map(|c| c.messaggio_in(lingua)).collect::<Vec<_>>().join(" "),
+            ErroreApi::Interno { .. } => lingua.traduci("errori.errore_interno", "Errore interno del server."),
+            ErroreApi::RichiestaNonValida(d)
+            | ErroreApi::NonAutenticato(d)
+            | ErroreApi::Vietato(d)
+            | ErroreApi::NonTrovato(d)
+            | ErroreApi::Conflitto(d)
+            | ErroreApi::TroppoGrande(d)
+            | ErroreApi::TroppeRichieste(d) => {
+                lingua.traduci_con(&format!("errori.{}", d.codice), &d.messaggio, &d.dati)
+            }
+        }
+    }
+
+    // Log e metriche dell'errore che sta per arrivare al client
+    fn registra(&self, req: &Request<'_>) {
+        match self {
+            ErroreApi::Interno { contesto, errore } => tracing::error!(
+                id_richiesta = %registro::id_richiesta(req),
+                id_professore = registro::professore(req),
+                contesto = %contesto,
+                errore = %errore,
+                "Errore interno"
+            ),
+            ErroreApi::Conflitto(d) => metriche::conflitto(d.codice),
+            _ => {}
+        }
+    }
+
+    pub fn corpo_in(&self, lingua: Lingua) -> JsonValue {
+        let esito = if matches!(self, ErroreApi::Interno { .. }) { "errore" } else { "fallito" };
+        let mut corpo = json!({"status": esito, "code": self.codice(), "message": self.messaggio_in(lingua)});
+        match self {
+            ErroreApi::CampiNonValidi(campi) => {
+                corpo["campi"] = campi
+                    .iter()
+                    .map(|c| json!({"campo": c.campo, "code": c.codice, "message": c.messaggio_in(lingua)}))
+                    .collect();
+            }
+            ErroreApi::Interno { .. } => {}
+            ErroreApi::RichiestaNonValida(d)
+            | ErroreApi::NonAutenticato(d)
+            | ErroreApi::Vietato(d)
+            | ErroreApi::NonTrovato(d)
+            | ErroreApi::Conflitto(d)
+            | ErroreApi::TroppoGrande(d)
+            | ErroreApi::TroppeRichieste(d) => {
+                for (chiave, valore) in &d.dati {
+                    corpo[chiave] = valore.clone();
+                }
+            }
+        }
+        corpo
+    }
+
+    // Per le request guard: Rocket passa la richiesta al catcher, che risponde con questo corpo
+    pub fn in_guardia(self, req: &Request<'_>) -> (Status, ErroreApi) {
+        self.registra(req);
+        let corpo = self.corpo_in(Lingua::della_richiesta(req));
+        req.local_cache(|| ErroreGuardia(Some((self.stato(), corpo))));
+        (self.stato(), self)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ErroreApi {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        self.registra(req);
+        (self.stato(), Json(self.corpo_in(Lingua::della_richiesta(req)))).respond_to(req)
+    }
+}
+
+// Corpo lasciato da una request guard che fallisce, così il catcher lo restituisce com'è
+struct ErroreGuardia(Option<(Status, JsonValue)>);
+
+// Gli errori prodotti da Rocket prima di arrivare a un handler (JSON malformato, token mancante...)
+// hanno lo stesso formato di quelli delle API
+#[catch(default)]
+fn errore_predefinito(stato: Status, req: &Request<'_>) -> (Status, Json<JsonValue>) {
+    // Una guardia opzionale (Option<UtenteAutenticato>) può aver fallito senza causare questo errore
+    if let Some((stato_guardia, corpo)) = &req.local_cache(|| ErroreGuardia(None)).0 {
+        if *stato_guardia == stato {
+            return (stato, Json(corpo.clone()));
+        }
+    }
+    let errore = match stato.code {
+        400 => ErroreApi::richiesta_non_valida("richiesta_non_valida", "Richiesta non valida."),
+        401 => ErroreApi::non_autenticato("non_autenticato", "Autenticazione richiesta."),
+        403 => ErroreApi::vietato("vietato", "Operazione non consentita."),
+        404 => ErroreApi::non_trovato("risorsa_non_trovata", "Risorsa non trovata."),
+        413 => ErroreApi::troppo_grande("richiesta_troppo_grande", "La richiesta è troppo grande."),
+        // Rocket risponde 422 quando il corpo JSON non corrisponde al tipo atteso
+        422 => ErroreApi::campo("corpo", "corpo_non_valido", "Il corpo della richiesta non ha il formato atteso."),
+        _ if stato.code >= 500 => ErroreApi::interno("risposta di Rocket", stato),
+        _ => ErroreApi::richiesta_non_valida("richiesta_non_valida", stato.reason_lossy()),
+    };
+    errore.registra(req);
+    (stato, Json(errore.corpo_in(Lingua::della_richiesta(req))))
+}
+
+pub fn catchers() -> Vec<rocket::Catcher> {
+    catchers![errore_predefinito]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ogni_variante_ha_il_suo_stato_http() {
+        let errori = [
+            (ErroreApi::richiesta_non_valida("richiesta_non_valida", "x"), 400),
+            (ErroreApi::non_autenticato("non_autenticato", "x"), 401),
+            (ErroreApi::vietato("vietato", "x"), 403),
+            (ErroreApi::non_trovato("aula_non_trovata", "x"), 404),
+            (ErroreApi::conflitto("aula_occupata", "x"), 409),
+            (ErroreApi::troppo_grande("richiesta_troppo_grande", "x"), 413),
+            (ErroreApi::campo("Data_Fine", "campo_obbligatorio", "x"), 422),
+            (ErroreApi::troppe_richieste("troppi_pin_errati", "x"), 429),
+            (ErroreApi::interno("nel leggere l'aula", "connessione persa"), 500),
+        ];
+        for (errore, stato) in errori {
+            assert_eq!(errore.stato().code, stato, "{:?}", errore);
+            assert_eq!(errore.corpo_in(Lingua::Italiano)["code"], errore.codice());
+        }
+    }
+
+    #[test]
+    fn il_corpo_porta_il_codice_e_i_dati_dell_errore() {
+        let errore = ErroreApi::conflitto("aula_al_completo", "L'aula ha già raggiunto il massimo di 3 prenotazioni contemporanee.")
+            .con("capienza", 3);
+        let corpo = errore.corpo_in(Lingua::Inglese);
+        assert_eq!(corpo["status"], "fallito");
+        assert_eq!(corpo["code"], "aula_al_completo");
+        assert_eq!(corpo["capienza"], 3);
+        assert_eq!(corpo["message"], "The room has already reached the maximum of 3 simultaneous bookings.");
+        assert!(corpo.get("campi").is_none());
+    }
+
+    #[test]
+    fn un_422_elenca_i_campi_non_validi() {
+        let errore = ErroreApi::CampiNonValidi(vec![
+            ErroreCampo::new("Data_Fine", "campo_obbligatorio", "Il campo Data_Fine è obbligatorio."),
+            ErroreCampo::new("Numero_Studenti", "numero_studenti_non_valido", "Numero di studenti non valido."),
+        ]);
+        let corpo = errore.corpo_in(Lingua::Inglese);
+        assert_eq!(errore.stato(), Status::UnprocessableEntity);
+        assert_eq!(corpo["code"], "campi_non_validi");
+        // Un codice senza traduzione mantiene il messaggio originale
+        assert_eq!(
+            corpo["campi"],
+            json!([
+                {"campo": "Data_Fine", "code": "campo_obbligatorio", "message": "The field Data_Fine is required."},
+                {"campo": "Numero_Studenti", "code": "numero_studenti_non_valido", "message": "Numero di studenti non valido."},
+            ])
+        );
+    }
+
+    #[test]
+    fn un_errore_interno_non_mostra_il_dettaglio() {
+        let corpo = ErroreApi::interno("DB nel leggere l'aula", "password authentication failed").corpo_in(Lingua::Italiano);
+        assert_eq!(corpo, json!({"status": "errore", "code": "errore_interno", "message": "Errore interno del server."}));
+    }
+}
@@ -0,0 +1,296 @@
+// src/esportazione.rs
+// Esportazione delle prenotazioni in CSV e in XLSX, con gli stessi filtri di GET /prenotazioni.
+// Le righe arrivano dal database una alla volta: il CSV viene inviato man mano che si legge,
+// l'XLSX viene scritto in modalità a memoria costante su un file temporaneo e poi inviato da lì.
+// Intestazioni e stati sono nella lingua della richiesta.
+// I file contengono i nomi dei professori: solo un amministratore può scaricarli.
+
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom};
+
+use chrono::{NaiveDateTime, NaiveTime};
+use chrono_tz::Europe::Rome;
+use rocket::futures::StreamExt;
+use rocket::http::{ContentType, Header};
+use rocket::response::stream::{ByteStream, ReaderStream};
+use rocket::State;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::models::RigaEsportazioneDb;
+use crate::prenotazioni::FiltroPrenotazioni;
+use crate::traduzioni::Lingua;
+
+// Chiave nel catalogo delle traduzioni e testo italiano
+const INTESTAZIONI: [(&str, &str); 9] = [
+    ("aula", "Aula"),
+    ("tipo_aula", "Tipo aula"),
+    ("plesso", "Plesso"),
+    ("professore", "Professore"),
+    ("materia", "Materia"),
+    ("inizio", "Inizio"),
+    ("fine", "Fine"),
+    ("moduli", "Moduli"),
+    ("stato", "Stato"),
+];
+
+fn intestazioni(lingua: Lingua) -> [String; 9] {
+    INTESTAZIONI.map(|(chiave, italiano)| lingua.traduci(&format!("esportazione.intestazioni.{}", chiave), italiano))
+}
+
+#[derive(Responder)]
+pub struct FileEsportato<T> {
+    contenuto: T,
+    tipo: ContentType,
+    disposizione: Header<'static>,
+}
+
+fn file_esportato<T>(contenuto: T, tipo: ContentType, estensione: &str) -> FileEsportato<T> {
+    let nome = format!("prenotazioni_{}.{}", chrono::Utc::now().with_timezone(&Rome).format("%Y%m%d_%H%M"), estensione);
+    FileEsportato {
+        contenuto,
+        tipo,
+        disposizione: Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", nome)),
+    }
+}
+
+struct RigaEsportazione {
+    aula: String,
+    tipo_aula: String,
+    plesso: String,
+    professore: String,
+    materia: String,
+    inizio: NaiveDateTime, // Ora locale
+    fine: NaiveDateTime,   // Ora locale
+    moduli: String,
+    stato: String,
+}
+
+impl RigaEsportazione {
+    fn campi(&self) -> [String; 9] {
+        [
+            self.aula.clone(),
+            self.tipo_aula.clone(),
+            self.plesso.clone(),
+            self.professore.clone(),
+            self.materia.clone(),
+            self.inizio.format("%d/%m/%Y %H:%M").to_string(),
+            self.fine.format("%d/%m/%Y %H:%M").to_string(),
+            self.moduli.clone(),
+            self.stato.clone(),
+        ]
+    }
+}
+
+// Moduli dell'orario del plesso, in ora locale
+type ModuliPlessi = HashMap<i32, Vec<(i32, NaiveTime, NaiveTime)>>;
+
+async fn moduli_plessi(db_pool: &DbPool) -> Result<ModuliPlessi, sqlx::Error> {
+    let mut moduli: ModuliPlessi = HashMap::new();
+    for modulo in db::plessi::orari(db_pool).await? {
+        moduli.entry(modulo.Id_Plesso).or_default().push((modulo.Numero_Modulo, modulo.Ora_Inizio, modulo.Ora_Fine));
+    }
+    Ok(moduli)
+}
+
+// Moduli toccati dalla prenotazione: "3", "3-4"; vuoto se il plesso non ha un orario
+// o se la prenotazione occupa più giorni
+fn numeri_moduli(moduli: Option<&Vec<(i32, NaiveTime, NaiveTime)>>, inizio: NaiveDateTime, fine: NaiveDateTime) -> String {
+    if inizio.date() != fine.date() {
+        return String::new();
+    }
+    let numeri: Vec<i32> = moduli
+        .map(|moduli| {
+            moduli
+                .iter()
+                .filter(|(_, ora_inizio, ora_fine)| *ora_inizio < fine.time() && *ora_fine > inizio.time())
+                .map(|(numero, _, _)| *numero)
+                .collect()
+        })
+        .unwrap_or_default();
+    match (numeri.first(), numeri.last()) {
+        (Some(primo), Some(ultimo)) if primo == ultimo => primo.to_string(),
+        (Some(primo), Some(ultimo)) => format!("{}-{}", primo, ultimo),
+        _ => String::new(),
+    }
+}
+
+fn in_ora_locale(data: NaiveDateTime) -> NaiveDateTime {
+    data.and_utc().with_timezone(&Rome).naive_local()
+}
+
+fn converti(riga: RigaEsportazioneDb, moduli: &ModuliPlessi, lingua: Lingua) -> RigaEsportazione {
+    let inizio = in_ora_locale(riga.Data_Inizio);
+    let fine = in_ora_locale(riga.Data_Fine);
+    RigaEsportazione {
+        aula: match (&riga.Tipo_Aula, riga.Numero_Aula) {
+            (Some(tipo), Some(numero)) => format!("{} {:02}", tipo, numero),
+            _ => String::new(), // Prenotazione di sola attrezzatura
+        },
+        tipo_aula: riga.Tipo_Aula.unwrap_or_default(),
+        plesso: riga.Nome_Plesso.unwrap_or_default(),
+        professore: match riga.Nome_Professore {
+            Some(nome) => format!("{} {}", riga.Cognome_Professore, nome),
+            None => riga.Cognome_Professore,
+        },
+        materia: riga.Materia.unwrap_or_default(),
+        moduli: numeri_moduli(riga.Id_Plesso.and_then(|id| moduli.get(&id)), inizio, fine),
+        inizio,
+        fine,
+        stato: if riga.Approvata == Some(false) {
+            lingua.traduci("esportazione.stati.da_approvare", "da approvare")
+        } else {
+            lingua.traduci("esportazione.stati.confermata", "confermata")
+        },
+    }
+}
+
+fn filtro(start: Option<&str>, end: Option<&str>, plesso: Option<i32>) -> Result<FiltroPrenotazioni, ErroreApi> {
+    Ok(FiltroPrenotazioni {
+        start: crate::parse_data_opzionale(start, "start")?,
+        end: crate::parse_data_opzionale(end, "end")?,
+        plesso,
+        ..Default::default()
+    })
+}
+
+// Excel esegue come formula una cella che inizia con = + - @ (o tabulazione e a capo, usati per
+// aggirare il controllo): l'apostrofo iniziale la fa restare testo. Nell'XLSX le celle sono già testo.
+fn cella_sicura(campo: &str) -> String {
+    if campo.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", campo)
+    } else {
+        campo.to_string()
+    }
+}
+
+fn riga_csv(campi: &[String]) -> Vec<u8> {
+    let mut scrittore = csv::WriterBuilder::new().delimiter(b';').from_writer(Vec::new());
+    // Scrivere in un Vec non può fallire
+    let _ = scrittore.write_record(campi.iter().map(|campo| cella_sicura(campo)));
+    scrittore.into_inner().unwrap_or_default()
+}
+
+// Separatore ";" e BOM UTF-8: Excel in italiano apre il file senza passare dall'importazione guidata
+#[get("/prenotazioni/esporta.csv?<start>&<end>&<plesso>")]
+pub async fn esporta_csv(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    start: Option<&str>,
+    end: Option<&str>,
+    plesso: Option<i32>,
+) -> Result<FileEsportato<ByteStream![Vec<u8>]>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_esportazione", "Solo un amministratore può esportare le prenotazioni.")?;
+    let filtro = filtro(start, end, plesso)?;
+    let db_pool = db_pool.inner().clone();
+    let flusso = ByteStream! {
+        let mut intestazione = "\u{feff}".as_bytes().to_vec();
+        intestazione.extend(riga_csv(&intestazioni(lingua)));
+        yield intestazione;
+
+        let moduli = match moduli_plessi(&db_pool).await {
+            Ok(moduli) => moduli,
+            Err(e) => {
+                tracing::error!(errore = %e, "Errore DB durante l'esportazione CSV delle prenotazioni");
+                return;
+            }
+        };
+        let mut righe = db::prenotazioni::righe_esportazione(&db_pool, &filtro);
+        while let Some(riga) = righe.next().await {
+            match riga {
+                Ok(riga) => yield riga_csv(&converti(riga, &moduli, lingua).campi()),
+                Err(e) => {
+                    // Le intestazioni sono già partite: si può solo interrompere il file
+                    tracing::error!(errore = %e, "Errore DB durante l'esportazione CSV delle prenotazioni");
+                    break;
+                }
+            }
+        }
+    };
+    Ok(file_esportato(flusso, ContentType::CSV, "csv"))
+}
+
+async fn scrivi_xlsx(db_pool: DbPool, filtro: FiltroPrenotazioni, lingua: Lingua) -> Result<std::fs::File, String> {
+    let mut cartella = Workbook::new();
+    let grassetto = Format::new().set_bold();
+    let formato_data = Format::new().set_num_format("dd/mm/yyyy hh:mm");
+    let foglio = cartella.add_worksheet_with_constant_memory();
+    let errore_xlsx = |e: XlsxError| e.to_string();
+
+    foglio.set_name(lingua.traduci("esportazione.foglio", "Prenotazioni")).map_err(errore_xlsx)?;
+    for (colonna, intestazione) in intestazioni(lingua).iter().enumerate() {
+        foglio.write_string_with_format(0, colonna as u16, intestazione, &grassetto).map_err(errore_xlsx)?;
+    }
+    foglio.set_freeze_panes(1, 0).map_err(errore_xlsx)?;
+
+    let errore_db = |e: sqlx::Error| format!("errore DB: {}", e);
+    let moduli = moduli_plessi(&db_pool).await.map_err(errore_db)?;
+    let mut righe = db::prenotazioni::righe_esportazione(&db_pool, &filtro);
+    let mut numero: u32 = 1;
+    while let Some(riga) = righe.next().await {
+        let riga = converti(riga.map_err(errore_db)?, &moduli, lingua);
+        foglio.write_string(numero, 0, &riga.aula).map_err(errore_xlsx)?;
+        foglio.write_string(numero, 1, &riga.tipo_aula).map_err(errore_xlsx)?;
+        foglio.write_string(numero, 2, &riga.plesso).map_err(errore_xlsx)?;
+        foglio.write_string(numero, 3, &riga.professore).map_err(errore_xlsx)?;
+        foglio.write_string(numero, 4, &riga.materia).map_err(errore_xlsx)?;
+        foglio.write_datetime_with_format(numero, 5, riga.inizio, &formato_data).map_err(errore_xlsx)?;
+        foglio.write_datetime_with_format(numero, 6, riga.fine, &formato_data).map_err(errore_xlsx)?;
+        foglio.write_string(numero, 7, &riga.moduli).map_err(errore_xlsx)?;
+        foglio.write_string(numero, 8, &riga.stato).map_err(errore_xlsx)?;
+        numero += 1;
+    }
+
+    // Compressione e scrittura del file su un thread a parte: con molte righe richiede tempo
+    tokio::task::spawn_blocking(move || {
+        let mut file = tempfile::tempfile().map_err(|e| e.to_string())?;
+        cartella.save_to_writer(&mut file).map_err(errore_xlsx)?;
+        file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+        Ok(file)
+    })
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Il file temporaneo non ha nome sul disco e sparisce quando la risposta è stata inviata
+#[get("/prenotazioni/esporta.xlsx?<start>&<end>&<plesso>")]
+pub async fn esporta_xlsx(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    start: Option<&str>,
+    end: Option<&str>,
+    plesso: Option<i32>,
+) -> Result<FileEsportato<ReaderStream![tokio::fs::File]>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_esportazione", "Solo un amministratore può esportare le prenotazioni.")?;
+    let filtro = filtro(start, end, plesso)?;
+    let file = scrivi_xlsx(db_pool.inner().clone(), filtro, lingua).await.map_err(|e| {
+        ErroreApi::interno("durante l'esportazione XLSX delle prenotazioni", e)
+    })?;
+    Ok(file_esportato(ReaderStream::one(tokio::fs::File::from_std(file)), ContentType::new("application", "vnd.openxmlformats-officedocument.spreadsheetml.sheet"), "xlsx"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn le_formule_restano_testo() {
+        let campi: Vec<String> = ["=HYPERLINK(\"http://x\")", "+1", "-2", "@SUM(A1)", "Rossi", "Aula 3"]
+            .iter()
+            .map(|campo| campo.to_string())
+            .collect();
+        let riga = String::from_utf8(riga_csv(&campi)).unwrap();
+        assert_eq!(riga, "\"'=HYPERLINK(\"\"http://x\"\")\";'+1;'-2;'@SUM(A1);Rossi;Aula 3\n");
+    }
+
+    #[test]
+    fn i_campi_normali_non_cambiano() {
+        assert_eq!(cella_sicura("D'Angelo"), "D'Angelo");
+        assert_eq!(cella_sicura("01/02/2026 08:00"), "01/02/2026 08:00");
+        assert_eq!(cella_sicura(""), "");
+    }
+}
@@ -0,0 +1,50 @@
+// src/eventi.rs
+// Punto unico da cui partono le notifiche sul ciclo di vita delle prenotazioni.
+// Chi crea, modifica, annulla o approva una prenotazione chiama `pubblica` dopo il commit;
+// ogni canale di notifica (aggiornamenti in tempo reale, webhook, email) decide se e come inoltrare l'evento.
+
+use crate::aggiornamenti;
+use crate::db::DbPool;
+use crate::metriche;
+use crate::models::TipoEvento;
+use crate::notifiche;
+use crate::webhook;
+
+// Gli errori vengono solo registrati: la prenotazione è già salvata e non va fatta fallire
+pub async fn pubblica(db_pool: &DbPool, tipo: TipoEvento, id_prenotazione: i32) {
+    inoltra(db_pool, tipo, id_prenotazione, None).await;
+}
+
+// Prenotazione modificata: gli aggiornamenti in tempo reale raggiungono anche chi segue l'aula di partenza
+pub async fn pubblica_modifica(db_pool: &DbPool, id_prenotazione: i32, id_aula_precedente: Option<i32>) {
+    inoltra(db_pool, TipoEvento::Modificata, id_prenotazione, id_aula_precedente).await;
+}
+
+async fn inoltra(db_pool: &DbPool, tipo: TipoEvento, id_prenotazione: i32, id_aula_precedente: Option<i32>) {
+    metriche::prenotazione(tipo);
+    if let Err(e) = aggiornamenti::registra(db_pool, tipo, id_prenotazione, id_aula_precedente).await {
+        tracing::error!(evento = tipo.as_db(), id_prenotazione, errore = %e, "Errore nel registrare l'evento della prenotazione");
+    }
+    if let Err(e) = webhook::accoda(db_pool, tipo, id_prenotazione).await {
+        tracing::error!(evento = tipo.as_db(), id_prenotazione, errore = %e, "Errore nell'accodare i webhook della prenotazione");
+    }
+    if let Err(e) = notifiche::accoda(db_pool, tipo, id_prenotazione).await {
+        tracing::error!(evento = tipo.as_db(), id_prenotazione, errore = %e, "Errore nell'accodare l'email della prenotazione");
+    }
+}
+
+// Prenotazione rilasciata per mancato check-in: per calendari e webhook è un annullamento,
+// il professore riceve un'email dedicata
+pub async fn pubblica_rilascio(db_pool: &DbPool, id_prenotazione: i32) {
+    let tipo = TipoEvento::Annullata;
+    metriche::prenotazione_rilasciata();
+    if let Err(e) = aggiornamenti::registra(db_pool, tipo, id_prenotazione, None).await {
+        tracing::error!(evento = tipo.as_db(), id_prenotazione, errore = %e, "Errore nel registrare l'evento della prenotazione");
+    }
+    if let Err(e) = webhook::accoda(db_pool, tipo, id_prenotazione).await {
+        tracing::error!(evento = tipo.as_db(), id_prenotazione, errore = %e, "Errore nell'accodare i webhook della prenotazione");
+    }
+    if let Err(e) = notifiche::accoda_rilascio(db_pool, id_prenotazione).await {
+        tracing::error!(id_prenotazione, errore = %e, "Errore nell'accodare l'email di rilascio della prenotazione");
+    }
+}
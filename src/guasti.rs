@@ -0,0 +1,201 @@
+// src/guasti.rs
+// Segnalazioni di guasto su aule e attrezzature.
+// Un docente apre la segnalazione, il tecnico ne aggiorna lo stato. Una segnalazione
+// di gravità alta ancora aperta può rendere l'aula non prenotabile (Blocca_Aula):
+// lo decide il tecnico o un amministratore, alla creazione o aggiornando la segnalazione.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::db::{self, DbPool, Esecutore};
+use crate::errori::ErroreApi;
+use crate::models::{self, Gravita, SegnalazioneGuastoDb, StatoGuasto};
+use crate::traduzioni::Lingua;
+
+fn utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(naive, Utc)
+}
+
+fn segnalazione_api(s: SegnalazioneGuastoDb) -> models::SegnalazioneGuastoApi {
+    models::SegnalazioneGuastoApi {
+        Id_Segnalazione: s.Id_Segnalazione,
+        Id_Aula: s.Id_Aula,
+        Id_Attrezzatura: s.Id_Attrezzatura,
+        Id_Segnalatore: s.Id_Segnalatore,
+        Gravita: Gravita::from_db(&s.Gravita),
+        Descrizione: s.Descrizione,
+        Stato: StatoGuasto::from_db(&s.Stato),
+        Blocca_Aula: s.Blocca_Aula,
+        Note_Tecnico: s.Note_Tecnico,
+        Data_Creazione: utc(s.Data_Creazione).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        Data_Aggiornamento: utc(s.Data_Aggiornamento).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    }
+}
+
+// Guasto aperto, grave e bloccante che rende l'aula non prenotabile.
+// Un guasto su un'attrezzatura blocca l'aula in cui l'attrezzatura è installata.
+pub async fn guasto_bloccante(db: impl Esecutore<'_>, id_aula: i32) -> Result<Option<(i32, String)>, sqlx::Error> {
+    db::guasti::bloccante(db, id_aula).await
+}
+
+// Tutte le aule rese non prenotabili da un guasto
+pub async fn aule_inagibili(db_pool: &DbPool) -> Result<Vec<i32>, sqlx::Error> {
+    db::guasti::aule_inagibili(db_pool).await
+}
+
+// Prenotazioni future dell'aula, da segnalare a chi apre o aggiorna un guasto
+async fn prenotazioni_interessate(db_pool: &DbPool, id_aula: i32) -> Result<Vec<models::PrenotazioneSovrappostaApi>, sqlx::Error> {
+    let prenotazioni = db::prenotazioni::future_dell_aula(db_pool, id_aula).await?;
+
+    Ok(prenotazioni
+        .into_iter()
+        .map(|p| models::PrenotazioneSovrappostaApi {
+            Id_Prenotazione: p.Id_Prenotazione,
+            Id_Professore: p.Id_Professore,
+            Data_Inizio: utc(p.Data_Inizio).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            Data_Fine: utc(p.Data_Fine).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        })
+        .collect())
+}
+
+// Aula a cui si riferisce la segnalazione (diretta o tramite l'attrezzatura)
+async fn aula_della_segnalazione(db_pool: &DbPool, id_aula: Option<i32>, id_attrezzatura: Option<i32>) -> Result<Option<i32>, sqlx::Error> {
+    if id_aula.is_some() {
+        return Ok(id_aula);
+    }
+    match id_attrezzatura {
+        Some(id) => db::attrezzature::aula(db_pool, id).await,
+        None => Ok(None),
+    }
+}
+
+#[post("/guasti", format = "json", data = "<payload>")]
+pub async fn crea_segnalazione(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    payload: Json<models::NuovaSegnalazionePayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    if payload.id_aula.is_none() && payload.id_attrezzatura.is_none() {
+        return Err(ErroreApi::richiesta_non_valida("risorsa_mancante", "Indica l'aula o l'attrezzatura guasta."));
+    }
+    if payload.descrizione.trim().is_empty() {
+        return Err(ErroreApi::campo("Descrizione", "descrizione_obbligatoria", "La descrizione del guasto è obbligatoria."));
+    }
+    // Un docente non può rendere l'aula non prenotabile: la richiesta resta al tecnico
+    let blocca_aula = payload.blocca_aula && payload.gravita == Gravita::Alta && utente.is_tecnico();
+
+    let id_segnalazione = match db::guasti::inserisci(
+        db_pool.inner(),
+        payload.id_aula,
+        payload.id_attrezzatura,
+        utente.id_professore,
+        payload.gravita.as_db(),
+        payload.descrizione.trim(),
+        blocca_aula,
+    )
+        .await
+    {
+        Ok(id_segnalazione) => id_segnalazione,
+        Err(e) => {
+            if let Some(db_err) = e.as_database_error() {
+                if db_err.is_foreign_key_violation() {
+                    return Err(ErroreApi::non_trovato("risorsa_non_trovata", "Aula o attrezzatura non trovata."));
+                }
+            }
+            return Err(ErroreApi::interno("DB durante la creazione della segnalazione", e));
+        }
+    };
+
+    // Solo un guasto che blocca l'aula tocca le prenotazioni
+    let prenotazioni = if blocca_aula {
+        match aula_della_segnalazione(db_pool.inner(), payload.id_aula, payload.id_attrezzatura).await {
+            Ok(Some(id_aula)) => prenotazioni_interessate(db_pool.inner(), id_aula).await,
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+            .unwrap_or_else(|e| {
+                tracing::error!(errore = %e, "Errore DB nel cercare le prenotazioni interessate dal guasto {}", id_segnalazione);
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+
+    Ok(status::Custom(Status::Created, Json(json!({
+        "status": "successo",
+        "message": lingua.traduci("messaggi.segnalazione_registrata", "Segnalazione registrata con successo!"),
+        "id_segnalazione": id_segnalazione,
+        "aula_bloccata": blocca_aula,
+        "prenotazioni_interessate": prenotazioni
+    }))))
+}
+
+#[get("/guasti?<stato>&<id_aula>&<plesso>")]
+pub async fn get_segnalazioni(
+    db_pool: &State<DbPool>,
+    _utente: UtenteAutenticato,
+    stato: Option<&str>,
+    id_aula: Option<i32>,
+    plesso: Option<i32>,
+) -> Result<Json<Vec<models::SegnalazioneGuastoApi>>, ErroreApi> {
+    match db::guasti::elenco(db_pool.inner(), stato, id_aula, plesso).await {
+        Ok(segnalazioni) => Ok(Json(segnalazioni.into_iter().map(segnalazione_api).collect())),
+        Err(e) => {
+            Err(ErroreApi::interno("nel recuperare le segnalazioni dal DB", e))
+        }
+    }
+}
+
+#[patch("/guasti/<id_segnalazione>", format = "json", data = "<payload>")]
+pub async fn aggiorna_segnalazione(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_segnalazione: i32,
+    payload: Json<models::AggiornamentoSegnalazionePayload>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    if !utente.is_tecnico() {
+        return Err(ErroreApi::vietato("solo_tecnico_segnalazioni", "Solo il tecnico può aggiornare lo stato di una segnalazione."));
+    }
+
+    let (id_aula, id_attrezzatura, gravita, bloccava) = match db::guasti::trova(db_pool.inner(), id_segnalazione).await {
+        Ok(Some(segnalazione)) => segnalazione,
+        Ok(None) => return Err(ErroreApi::non_trovato("segnalazione_non_trovata", "Segnalazione non trovata.")),
+        Err(e) => {
+            return Err(ErroreApi::interno(format!("DB nel recuperare la segnalazione {}", id_segnalazione), e));
+        }
+    };
+
+    let blocca_aula = payload.blocca_aula.unwrap_or(bloccava) && Gravita::from_db(&gravita) == Gravita::Alta;
+
+    if let Err(e) = db::guasti::aggiorna(db_pool.inner(), id_segnalazione, payload.stato.as_db(), payload.note_tecnico.as_deref(), blocca_aula).await {
+        return Err(ErroreApi::interno(format!("DB nell'aggiornare la segnalazione {}", id_segnalazione), e));
+    }
+
+    // Se l'aula resta bloccata, ricorda quali prenotazioni ne sono colpite
+    let prenotazioni = if blocca_aula && payload.stato != StatoGuasto::Risolta {
+        match aula_della_segnalazione(db_pool.inner(), id_aula, id_attrezzatura).await {
+            Ok(Some(id_aula)) => prenotazioni_interessate(db_pool.inner(), id_aula).await,
+            Ok(None) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+            .unwrap_or_else(|e| {
+                tracing::error!(errore = %e, "Errore DB nel cercare le prenotazioni interessate dal guasto {}", id_segnalazione);
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+
+    Ok(Json(json!({
+        "status": "successo",
+        "message": lingua.traduci("messaggi.segnalazione_aggiornata", "Segnalazione aggiornata."),
+        "aula_bloccata": blocca_aula && payload.stato != StatoGuasto::Risolta,
+        "prenotazioni_interessate": prenotazioni
+    })))
+}
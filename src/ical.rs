@@ -0,0 +1,837 @@
+// src/ical.rs
+// Lettura e scrittura di calendari iCalendar (RFC 5545): feed .ics delle prenotazioni
+// e importazione di piani esterni. Gli orari scritti sono nel fuso Europe/Rome, con la
+// relativa definizione VTIMEZONE, così i client mostrano l'ora giusta anche a cavallo
+// del cambio dell'ora legale.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Europe::Rome;
+use chrono_tz::Tz;
+
+pub const PRODID: &str = "-//Prenotaula//Prenotazione aule//IT";
+pub const TZID: &str = "Europe/Rome";
+
+// Regole dell'ora legale europea in vigore dal 1996
+const VTIMEZONE_ROMA: [&str; 19] = [
+    "BEGIN:VTIMEZONE",
+    "TZID:Europe/Rome",
+    "X-LIC-LOCATION:Europe/Rome",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:+0100",
+    "TZOFFSETTO:+0200",
+    "TZNAME:CEST",
+    "DTSTART:19700329T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:+0200",
+    "TZOFFSETTO:+0100",
+    "TZNAME:CET",
+    "DTSTART:19701025T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+    "",
+];
+
+// Escape dei caratteri speciali nei valori di tipo TEXT
+pub fn escape_testo(testo: &str) -> String {
+    let mut risultato = String::with_capacity(testo.len());
+    for c in testo.chars() {
+        match c {
+            '\\' => risultato.push_str("\\\\"),
+            ';' => risultato.push_str("\\;"),
+            ',' => risultato.push_str("\\,"),
+            '\n' => risultato.push_str("\\n"),
+            '\r' => {}
+            _ => risultato.push(c),
+        }
+    }
+    risultato
+}
+
+// Spezza le righe più lunghe di 75 ottetti senza tagliare un carattere UTF-8
+fn piega_riga(riga: &str) -> String {
+    let mut risultato = String::with_capacity(riga.len() + 8);
+    let mut lunghezza = 0;
+    for c in riga.chars() {
+        if lunghezza + c.len_utf8() > 75 {
+            risultato.push_str("\r\n ");
+            lunghezza = 1;
+        }
+        risultato.push(c);
+        lunghezza += c.len_utf8();
+    }
+    risultato
+}
+
+// Data e ora locali di Roma, da usare con il parametro TZID
+pub fn data_locale(istante: DateTime<Utc>) -> String {
+    istante.with_timezone(&Rome).format("%Y%m%dT%H%M%S").to_string()
+}
+
+pub fn data_utc(istante: DateTime<Utc>) -> String {
+    istante.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// UID stabile di una prenotazione: non cambia se la prenotazione viene modificata o annullata
+pub fn uid_prenotazione(id_prenotazione: i32) -> String {
+    format!("prenotazione-{}@prenotaula", id_prenotazione)
+}
+
+#[derive(Debug)]
+pub struct Evento {
+    pub uid: String,
+    pub sequenza: i32,
+    pub ultima_modifica: DateTime<Utc>,
+    pub inizio: DateTime<Utc>,
+    pub fine: DateTime<Utc>,
+    pub titolo: String,
+    pub luogo: Option<String>,
+    pub descrizione: Option<String>,
+    pub annullato: bool,
+}
+
+pub struct Calendario {
+    contenuto: String,
+}
+
+impl Calendario {
+    pub fn new(nome: &str) -> Calendario {
+        let mut calendario = Calendario { contenuto: String::new() };
+        calendario.riga("BEGIN:VCALENDAR");
+        calendario.riga("VERSION:2.0");
+        calendario.riga(&format!("PRODID:{}", PRODID));
+        calendario.riga("CALSCALE:GREGORIAN");
+        calendario.riga("METHOD:PUBLISH");
+        calendario.riga(&format!("X-WR-CALNAME:{}", escape_testo(nome)));
+        calendario.riga(&format!("X-WR-TIMEZONE:{}", TZID));
+        calendario.contenuto.push_str(&VTIMEZONE_ROMA.join("\r\n"));
+        calendario
+    }
+
+    // Singola risorsa CalDAV: RFC 4791 non ammette METHOD negli oggetti salvati
+    pub fn risorsa() -> Calendario {
+        let mut calendario = Calendario { contenuto: String::new() };
+        calendario.riga("BEGIN:VCALENDAR");
+        calendario.riga("VERSION:2.0");
+        calendario.riga(&format!("PRODID:{}", PRODID));
+        calendario.riga("CALSCALE:GREGORIAN");
+        calendario.contenuto.push_str(&VTIMEZONE_ROMA.join("\r\n"));
+        calendario
+    }
+
+    fn riga(&mut self, riga: &str) {
+        self.contenuto.push_str(&piega_riga(riga));
+        self.contenuto.push_str("\r\n");
+    }
+
+    pub fn aggiungi(&mut self, evento: &Evento) {
+        self.riga("BEGIN:VEVENT");
+        self.riga(&format!("UID:{}", evento.uid));
+        self.riga(&format!("SEQUENCE:{}", evento.sequenza));
+        self.riga(&format!("DTSTAMP:{}", data_utc(evento.ultima_modifica)));
+        self.riga(&format!("LAST-MODIFIED:{}", data_utc(evento.ultima_modifica)));
+        self.riga(&format!("DTSTART;TZID={}:{}", TZID, data_locale(evento.inizio)));
+        self.riga(&format!("DTEND;TZID={}:{}", TZID, data_locale(evento.fine)));
+        self.riga(&format!("SUMMARY:{}", escape_testo(&evento.titolo)));
+        if let Some(luogo) = &evento.luogo {
+            self.riga(&format!("LOCATION:{}", escape_testo(luogo)));
+        }
+        if let Some(descrizione) = &evento.descrizione {
+            self.riga(&format!("DESCRIPTION:{}", escape_testo(descrizione)));
+        }
+        self.riga(if evento.annullato { "STATUS:CANCELLED" } else { "STATUS:CONFIRMED" });
+        self.riga("TRANSP:OPAQUE");
+        self.riga("END:VEVENT");
+    }
+
+    pub fn chiudi(mut self) -> String {
+        self.riga("END:VCALENDAR");
+        self.contenuto
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Lettura
+// ---------------------------------------------------------------------------
+
+// Una riga di contenuto già "spiegata": NOME;PARAM=valore:VALORE
+#[derive(Debug)]
+struct Proprieta {
+    nome: String,
+    parametri: Vec<(String, String)>,
+    valore: String,
+}
+
+impl Proprieta {
+    fn parametro(&self, nome: &str) -> Option<&str> {
+        self.parametri.iter().find(|(n, _)| n == nome).map(|(_, v)| v.as_str())
+    }
+}
+
+fn unescape_testo(testo: &str) -> String {
+    let mut risultato = String::with_capacity(testo.len());
+    let mut caratteri = testo.chars();
+    while let Some(c) = caratteri.next() {
+        if c != '\\' {
+            risultato.push(c);
+            continue;
+        }
+        match caratteri.next() {
+            Some('n') | Some('N') => risultato.push('\n'),
+            Some(altro) => risultato.push(altro),
+            None => {}
+        }
+    }
+    risultato
+}
+
+// Separa nome, parametri e valore ignorando i ';' e i ':' tra virgolette
+fn leggi_proprieta(riga: &str) -> Option<Proprieta> {
+    let mut parti: Vec<String> = Vec::new();
+    let mut corrente = String::new();
+    let mut tra_virgolette = false;
+    let mut valore = None;
+    for (i, c) in riga.char_indices() {
+        match c {
+            '"' => tra_virgolette = !tra_virgolette,
+            ';' if !tra_virgolette => parti.push(std::mem::take(&mut corrente)),
+            ':' if !tra_virgolette => {
+                valore = Some(riga[i + 1..].to_string());
+                break;
+            }
+            _ => corrente.push(c),
+        }
+    }
+    parti.push(corrente);
+
+    let mut parti = parti.into_iter();
+    let nome = parti.next()?.trim().to_ascii_uppercase();
+    let parametri = parti
+        .filter_map(|p| {
+            let (n, v) = p.split_once('=')?;
+            Some((n.trim().to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        })
+        .collect();
+    Some(Proprieta { nome, parametri, valore: valore? })
+}
+
+// Data letta da DTSTART, DTEND, EXDATE...: ora locale nel suo fuso
+#[derive(Debug, Clone, Copy)]
+pub struct DataIcal {
+    pub locale: NaiveDateTime,
+    pub fuso: Tz,
+    pub solo_data: bool,
+}
+
+impl DataIcal {
+    pub fn utc(&self) -> DateTime<Utc> {
+        da_locale(self.fuso, self.locale)
+    }
+}
+
+// Un orario che cade nel salto dell'ora legale viene spostato avanti di un'ora
+fn da_locale(fuso: Tz, locale: NaiveDateTime) -> DateTime<Utc> {
+    fuso.from_local_datetime(&locale)
+        .earliest()
+        .or_else(|| fuso.from_local_datetime(&(locale + Duration::hours(1))).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| DateTime::from_naive_utc_and_offset(locale, Utc))
+}
+
+// Senza TZID (ora "fluttuante") o con un TZID sconosciuto, ad esempio i nomi Windows
+// esportati da Outlook, si assume l'ora di Roma
+fn leggi_data(valore: &str, tzid: Option<&str>) -> Result<DataIcal, String> {
+    let valore = valore.trim();
+    if let Ok(data) = NaiveDate::parse_from_str(valore, "%Y%m%d") {
+        let fuso = tzid.and_then(|t| t.parse::<Tz>().ok()).unwrap_or(Rome);
+        return Ok(DataIcal { locale: data.and_hms_opt(0, 0, 0).unwrap_or_default(), fuso, solo_data: true });
+    }
+    if let Some(senza_z) = valore.strip_suffix('Z') {
+        let locale = NaiveDateTime::parse_from_str(senza_z, "%Y%m%dT%H%M%S").map_err(|_| format!("Data non valida: {}", valore))?;
+        return Ok(DataIcal { locale, fuso: Tz::UTC, solo_data: false });
+    }
+    let locale = NaiveDateTime::parse_from_str(valore, "%Y%m%dT%H%M%S").map_err(|_| format!("Data non valida: {}", valore))?;
+    let fuso = tzid.and_then(|t| t.parse::<Tz>().ok()).unwrap_or(Rome);
+    Ok(DataIcal { locale, fuso, solo_data: false })
+}
+
+// Durate del tipo P1D, PT1H30M, P1W
+fn leggi_durata(valore: &str) -> Result<Duration, String> {
+    let errore = || format!("Durata non valida: {}", valore);
+    let (negativa, resto) = match valore.trim().strip_prefix('-') {
+        Some(resto) => (true, resto),
+        None => (false, valore.trim().trim_start_matches('+')),
+    };
+    let resto = resto.strip_prefix('P').ok_or_else(errore)?;
+
+    let mut durata = Duration::zero();
+    let mut numero = String::new();
+    for c in resto.chars() {
+        match c {
+            'T' => {}
+            '0'..='9' => numero.push(c),
+            _ => {
+                let n: i64 = numero.parse().map_err(|_| errore())?;
+                numero.clear();
+                durata += match c {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return Err(errore()),
+                };
+            }
+        }
+    }
+    Ok(if negativa { -durata } else { durata })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequenza {
+    Giornaliera,
+    Settimanale,
+    Mensile,
+}
+
+// Sottoinsieme di RRULE usato dai calendari scolastici: FREQ giornaliera,
+// settimanale (con BYDAY) o mensile, con INTERVAL, COUNT e UNTIL
+#[derive(Debug, Clone)]
+pub struct RegolaRicorrenza {
+    pub frequenza: Frequenza,
+    pub intervallo: u32,
+    pub conteggio: Option<u32>,
+    pub fino_a: Option<DateTime<Utc>>,
+    pub giorni: Vec<Weekday>,
+}
+
+fn leggi_regola(valore: &str, tzid: Option<&str>) -> Result<RegolaRicorrenza, String> {
+    let mut frequenza = None;
+    let mut regola = RegolaRicorrenza { frequenza: Frequenza::Giornaliera, intervallo: 1, conteggio: None, fino_a: None, giorni: Vec::new() };
+    for parte in valore.split(';').filter(|p| !p.is_empty()) {
+        let (chiave, v) = parte.split_once('=').ok_or_else(|| format!("RRULE non valida: {}", valore))?;
+        match chiave.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequenza = Some(match v.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequenza::Giornaliera,
+                    "WEEKLY" => Frequenza::Settimanale,
+                    "MONTHLY" => Frequenza::Mensile,
+                    altra => return Err(format!("Frequenza di ricorrenza non supportata: {}", altra)),
+                })
+            }
+            "INTERVAL" => regola.intervallo = v.parse().ok().filter(|&i| i > 0).ok_or_else(|| format!("INTERVAL non valido: {}", v))?,
+            "COUNT" => regola.conteggio = Some(v.parse().map_err(|_| format!("COUNT non valido: {}", v))?),
+            "UNTIL" => {
+                let fino = leggi_data(v, tzid)?;
+                // Una UNTIL di sola data include l'intera giornata
+                regola.fino_a = Some(if fino.solo_data { da_locale(fino.fuso, fino.locale + Duration::days(1)) - Duration::seconds(1) } else { fino.utc() });
+            }
+            "BYDAY" => {
+                for giorno in v.split(',') {
+                    regola.giorni.push(match giorno.to_ascii_uppercase().as_str() {
+                        "MO" => Weekday::Mon,
+                        "TU" => Weekday::Tue,
+                        "WE" => Weekday::Wed,
+                        "TH" => Weekday::Thu,
+                        "FR" => Weekday::Fri,
+                        "SA" => Weekday::Sat,
+                        "SU" => Weekday::Sun,
+                        altro => return Err(format!("BYDAY non supportato: {}", altro)),
+                    });
+                }
+            }
+            "WKST" => {}
+            altra => return Err(format!("Regola di ricorrenza non supportata: {}", altra)),
+        }
+    }
+    regola.frequenza = frequenza.ok_or_else(|| "RRULE senza FREQ.".to_string())?;
+    if regola.frequenza != Frequenza::Settimanale && !regola.giorni.is_empty() {
+        return Err("BYDAY è supportato solo con FREQ=WEEKLY.".to_string());
+    }
+    Ok(regola)
+}
+
+fn aggiungi_mesi(data: NaiveDateTime, mesi: u32) -> Option<NaiveDateTime> {
+    let totale = data.month0() + mesi;
+    let anno = data.year() + (totale / 12) as i32;
+    NaiveDate::from_ymd_opt(anno, totale % 12 + 1, data.day()).map(|d| d.and_time(data.time()))
+}
+
+#[derive(Debug)]
+pub struct EventoImportato {
+    pub uid: Option<String>,
+    pub titolo: String,
+    pub luogo: Option<String>,
+    pub organizzatore: Option<String>, // Email, senza "mailto:"
+    pub inizio: DataIcal,
+    pub durata: Duration,
+    pub regola: Option<RegolaRicorrenza>,
+    pub esclusioni: Vec<DateTime<Utc>>,
+    pub annullato: bool,
+}
+
+impl EventoImportato {
+    // Inizio e fine di ogni occorrenza fino all'orizzonte, al massimo `massimo`.
+    // La regola viene applicata sull'ora locale, così un evento settimanale delle 8:00
+    // resta alle 8:00 anche dopo il cambio dell'ora legale.
+    pub fn occorrenze(&self, orizzonte: DateTime<Utc>, massimo: usize) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let regola = match &self.regola {
+            Some(regola) => regola,
+            None => {
+                let inizio = self.inizio.utc();
+                return vec![(inizio, inizio + self.durata)];
+            }
+        };
+
+        let partenza = self.inizio.locale;
+        let mut giorni = regola.giorni.clone();
+        if giorni.is_empty() {
+            giorni.push(partenza.weekday());
+        }
+        giorni.sort_by_key(|g| g.num_days_from_monday());
+        giorni.dedup();
+        let lunedi = partenza - Duration::days(partenza.weekday().num_days_from_monday() as i64);
+
+        let mut risultato = Vec::new();
+        let mut generate = 0u32;
+        // Il limite sui periodi evita cicli infiniti con regole senza fine e orizzonti lontani
+        for periodo in 0..10_000u32 {
+            let salto = periodo * regola.intervallo;
+            let candidati: Vec<NaiveDateTime> = match regola.frequenza {
+                Frequenza::Giornaliera => vec![partenza + Duration::days(salto as i64)],
+                Frequenza::Settimanale => giorni
+                    .iter()
+                    .map(|g| lunedi + Duration::weeks(salto as i64) + Duration::days(g.num_days_from_monday() as i64))
+                    .filter(|c| *c >= partenza)
+                    .collect(),
+                // I mesi senza quel giorno (es. il 31) vengono saltati, come da RFC 5545
+                Frequenza::Mensile => aggiungi_mesi(partenza, salto).into_iter().collect(),
+            };
+
+            for candidato in candidati {
+                let inizio = da_locale(self.inizio.fuso, candidato);
+                if regola.fino_a.is_some_and(|fino| inizio > fino) || inizio > orizzonte {
+                    return risultato;
+                }
+                if regola.conteggio.is_some_and(|c| generate >= c) {
+                    return risultato;
+                }
+                generate += 1;
+                if self.esclusioni.contains(&inizio) {
+                    continue;
+                }
+                risultato.push((inizio, inizio + self.durata));
+                if risultato.len() >= massimo {
+                    return risultato;
+                }
+            }
+        }
+        risultato
+    }
+}
+
+// Esito della lettura di un VEVENT: l'evento o il motivo per cui è stato scartato
+pub type EventoLetto = Result<EventoImportato, (Option<String>, String)>;
+
+fn leggi_evento(proprieta: &[Proprieta]) -> EventoLetto {
+    let trova = |nome: &str| proprieta.iter().find(|p| p.nome == nome);
+    let uid = trova("UID").map(|p| p.valore.trim().to_string());
+    let scarta = |motivo: String| (uid.clone(), motivo);
+
+    let dtstart = trova("DTSTART").ok_or_else(|| scarta("Evento senza DTSTART.".to_string()))?;
+    let inizio = leggi_data(&dtstart.valore, dtstart.parametro("TZID")).map_err(scarta)?;
+    if inizio.solo_data {
+        return Err(scarta("Gli eventi di un'intera giornata non vengono importati.".to_string()));
+    }
+    let durata = match (trova("DTEND"), trova("DURATION")) {
+        (Some(dtend), _) => leggi_data(&dtend.valore, dtend.parametro("TZID")).map_err(scarta)?.utc() - inizio.utc(),
+        (None, Some(durata)) => leggi_durata(&durata.valore).map_err(scarta)?,
+        (None, None) => return Err(scarta("Evento senza DTEND né DURATION.".to_string())),
+    };
+    let regola = match trova("RRULE") {
+        Some(rrule) => Some(leggi_regola(&rrule.valore, dtstart.parametro("TZID")).map_err(scarta)?),
+        None => None,
+    };
+
+    let mut esclusioni = Vec::new();
+    for exdate in proprieta.iter().filter(|p| p.nome == "EXDATE") {
+        for valore in exdate.valore.split(',') {
+            esclusioni.push(leggi_data(valore, exdate.parametro("TZID")).map_err(scarta)?.utc());
+        }
+    }
+
+    Ok(EventoImportato {
+        uid: uid.clone(),
+        titolo: trova("SUMMARY").map(|p| unescape_testo(&p.valore)).unwrap_or_default(),
+        luogo: trova("LOCATION").map(|p| unescape_testo(&p.valore)).filter(|l| !l.trim().is_empty()),
+        organizzatore: trova("ORGANIZER").map(|p| {
+            let valore = p.valore.trim();
+            match valore.get(..7) {
+                Some(prefisso) if prefisso.eq_ignore_ascii_case("mailto:") => valore[7..].to_string(),
+                _ => valore.to_string(),
+            }
+        }),
+        inizio,
+        durata,
+        regola,
+        esclusioni,
+        annullato: trova("STATUS").is_some_and(|p| p.valore.trim().eq_ignore_ascii_case("CANCELLED")),
+    })
+}
+
+// Legge tutti i VEVENT di un file .ics. Le occorrenze modificate singolarmente
+// (RECURRENCE-ID) diventano eventi a sé ed escludono l'occorrenza originale della serie.
+pub fn leggi_calendario(testo: &str) -> Result<Vec<EventoLetto>, String> {
+    // Le righe che iniziano con uno spazio o un tab continuano la precedente
+    let mut righe: Vec<String> = Vec::new();
+    for riga in testo.split('\n').map(|r| r.trim_end_matches('\r')) {
+        match (riga.strip_prefix(' ').or_else(|| riga.strip_prefix('\t')), righe.last_mut()) {
+            (Some(continuazione), Some(ultima)) => ultima.push_str(continuazione),
+            _ if riga.is_empty() => {}
+            _ => righe.push(riga.to_string()),
+        }
+    }
+    if !righe.first().is_some_and(|r| r.eq_ignore_ascii_case("BEGIN:VCALENDAR")) {
+        return Err("Il file non è un calendario iCalendar valido.".to_string());
+    }
+
+    let mut eventi = Vec::new();
+    let mut modifiche: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+    let mut corrente: Option<Vec<Proprieta>> = None;
+    let mut annidati = 0; // VALARM e simili dentro un VEVENT
+    for riga in &righe {
+        let proprieta = match leggi_proprieta(riga) {
+            Some(p) => p,
+            None => continue,
+        };
+        let componente = proprieta.valore.trim().to_ascii_uppercase();
+        match (proprieta.nome.as_str(), corrente.as_mut()) {
+            ("BEGIN", None) if componente == "VEVENT" => corrente = Some(Vec::new()),
+            ("BEGIN", Some(_)) => annidati += 1,
+            ("END", Some(_)) if annidati > 0 => annidati -= 1,
+            ("END", Some(_)) if componente == "VEVENT" => {
+                let proprieta_evento = corrente.take().unwrap_or_default();
+                if let Some(recurrence_id) = proprieta_evento.iter().find(|p| p.nome == "RECURRENCE-ID") {
+                    let uid = proprieta_evento.iter().find(|p| p.nome == "UID").map(|p| p.valore.trim().to_string());
+                    if let (Some(uid), Ok(data)) = (uid, leggi_data(&recurrence_id.valore, recurrence_id.parametro("TZID"))) {
+                        modifiche.entry(uid).or_default().push(data.utc());
+                    }
+                }
+                eventi.push(leggi_evento(&proprieta_evento));
+            }
+            (_, Some(evento)) if annidati == 0 => evento.push(proprieta),
+            _ => {}
+        }
+    }
+
+    for evento in eventi.iter_mut().flatten() {
+        if evento.regola.is_none() {
+            continue;
+        }
+        if let Some(date) = evento.uid.as_ref().and_then(|uid| modifiche.get(uid)) {
+            evento.esclusioni.extend(date.iter().copied());
+        }
+    }
+    Ok(eventi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendario(righe_evento: &[&str]) -> String {
+        let mut righe = vec!["BEGIN:VCALENDAR", "VERSION:2.0", "BEGIN:VEVENT", "UID:prova@test"];
+        righe.extend_from_slice(righe_evento);
+        righe.extend_from_slice(&["END:VEVENT", "END:VCALENDAR"]);
+        righe.join("\r\n")
+    }
+
+    fn evento(righe_evento: &[&str]) -> EventoImportato {
+        let mut eventi = leggi_calendario(&calendario(righe_evento)).unwrap();
+        assert_eq!(eventi.len(), 1);
+        eventi.remove(0).unwrap()
+    }
+
+    fn motivo_scarto(righe_evento: &[&str]) -> String {
+        match leggi_calendario(&calendario(righe_evento)).unwrap().remove(0) {
+            Ok(evento) => panic!("evento accettato: {:?}", evento),
+            Err((_, motivo)) => motivo,
+        }
+    }
+
+    fn utc(testo: &str) -> DateTime<Utc> {
+        testo.parse().unwrap()
+    }
+
+    fn inizi(evento: &EventoImportato) -> Vec<DateTime<Utc>> {
+        evento.occorrenze(utc("2030-01-01T00:00:00Z"), usize::MAX).into_iter().map(|(inizio, _)| inizio).collect()
+    }
+
+    #[test]
+    fn giornaliera_con_count() {
+        let evento = evento(&[
+            "DTSTART;TZID=Europe/Rome:20260105T080000",
+            "DTEND;TZID=Europe/Rome:20260105T090000",
+            "RRULE:FREQ=DAILY;COUNT=3",
+        ]);
+        let occorrenze = evento.occorrenze(utc("2030-01-01T00:00:00Z"), usize::MAX);
+        assert_eq!(occorrenze, vec![
+            (utc("2026-01-05T07:00:00Z"), utc("2026-01-05T08:00:00Z")),
+            (utc("2026-01-06T07:00:00Z"), utc("2026-01-06T08:00:00Z")),
+            (utc("2026-01-07T07:00:00Z"), utc("2026-01-07T08:00:00Z")),
+        ]);
+    }
+
+    #[test]
+    fn settimanale_resta_alla_stessa_ora_dopo_l_ora_legale() {
+        // L'ora legale 2026 inizia domenica 29 marzo
+        let evento = evento(&[
+            "DTSTART;TZID=Europe/Rome:20260323T080000",
+            "DURATION:PT1H",
+            "RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4",
+        ]);
+        assert_eq!(inizi(&evento), vec![
+            utc("2026-03-23T07:00:00Z"),
+            utc("2026-03-25T07:00:00Z"),
+            utc("2026-03-30T06:00:00Z"),
+            utc("2026-04-01T06:00:00Z"),
+        ]);
+    }
+
+    #[test]
+    fn settimanale_con_intervallo_salta_le_settimane() {
+        let evento = evento(&[
+            "DTSTART;TZID=Europe/Rome:20260112T100000",
+            "DURATION:PT2H",
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=3",
+        ]);
+        assert_eq!(inizi(&evento), vec![
+            utc("2026-01-12T09:00:00Z"),
+            utc("2026-01-26T09:00:00Z"),
+            utc("2026-02-09T09:00:00Z"),
+        ]);
+    }
+
+    #[test]
+    fn mensile_salta_i_mesi_senza_quel_giorno() {
+        let evento = evento(&[
+            "DTSTART;TZID=Europe/Rome:20260131T100000",
+            "DURATION:PT1H",
+            "RRULE:FREQ=MONTHLY;COUNT=3",
+        ]);
+        assert_eq!(inizi(&evento), vec![
+            utc("2026-01-31T09:00:00Z"),
+            utc("2026-03-31T08:00:00Z"),
+            utc("2026-05-31T08:00:00Z"),
+        ]);
+    }
+
+    #[test]
+    fn until_in_utc_e_in_ora_locale() {
+        let con_until = |until: &str| {
+            let rrule = format!("RRULE:FREQ=DAILY;UNTIL={}", until);
+            inizi(&evento(&["DTSTART;TZID=Europe/Rome:20260105T080000", "DURATION:PT1H", &rrule])).len()
+        };
+        // UNTIL è inclusivo
+        assert_eq!(con_until("20260107T070000Z"), 3);
+        assert_eq!(con_until("20260107T065959Z"), 2);
+        // Senza Z vale il fuso di DTSTART
+        assert_eq!(con_until("20260107T080000"), 3);
+        assert_eq!(con_until("20260107T075959"), 2);
+        // Una sola data include l'intera giornata
+        assert_eq!(con_until("20260107"), 3);
+    }
+
+    #[test]
+    fn until_in_utc_dopo_la_fine_dell_ora_legale() {
+        // L'ora legale 2026 finisce domenica 25 ottobre: le 8:00 passano da 06:00Z a 07:00Z
+        let evento = evento(&[
+            "DTSTART;TZID=Europe/Rome:20261023T080000",
+            "DURATION:PT1H",
+            "RRULE:FREQ=DAILY;UNTIL=20261026T070000Z",
+        ]);
+        assert_eq!(inizi(&evento), vec![
+            utc("2026-10-23T06:00:00Z"),
+            utc("2026-10-24T06:00:00Z"),
+            utc("2026-10-25T07:00:00Z"),
+            utc("2026-10-26T07:00:00Z"),
+        ]);
+    }
+
+    #[test]
+    fn ora_nel_salto_dell_ora_legale_va_avanti_di_un_ora() {
+        let evento = evento(&["DTSTART;TZID=Europe/Rome:20260329T023000", "DURATION:PT1H"]);
+        assert_eq!(inizi(&evento), vec![utc("2026-03-29T01:30:00Z")]);
+    }
+
+    #[test]
+    fn regola_senza_fine_si_ferma_a_diecimila_periodi() {
+        let evento = evento(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:FREQ=DAILY"]);
+        assert_eq!(evento.occorrenze(utc("2100-01-01T00:00:00Z"), usize::MAX).len(), 10_000);
+        assert_eq!(evento.occorrenze(utc("2100-01-01T00:00:00Z"), 5).len(), 5);
+        // L'orizzonte viene prima del limite
+        assert_eq!(evento.occorrenze(utc("2026-01-09T12:00:00Z"), usize::MAX).len(), 5);
+    }
+
+    #[test]
+    fn exdate_e_recurrence_id_escludono_le_occorrenze() {
+        let testo = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:serie@test",
+            "DTSTART;TZID=Europe/Rome:20260105T080000",
+            "DURATION:PT1H",
+            "RRULE:FREQ=DAILY;COUNT=4",
+            "EXDATE;TZID=Europe/Rome:20260106T080000",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:serie@test",
+            "RECURRENCE-ID;TZID=Europe/Rome:20260107T080000",
+            "DTSTART;TZID=Europe/Rome:20260107T100000",
+            "DURATION:PT1H",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+            .join("\r\n");
+        let eventi = leggi_calendario(&testo).unwrap();
+        assert_eq!(eventi.len(), 2);
+        let serie = eventi[0].as_ref().unwrap();
+        assert_eq!(inizi(serie), vec![utc("2026-01-05T07:00:00Z"), utc("2026-01-08T07:00:00Z")]);
+        let spostata = eventi[1].as_ref().unwrap();
+        assert_eq!(inizi(spostata), vec![utc("2026-01-07T09:00:00Z")]);
+    }
+
+    #[test]
+    fn legge_le_proprieta_del_vevent() {
+        let evento = evento(&[
+            "DTSTART;TZID=\"Europe/Rome\":20260105T080000",
+            "DTEND;TZID=Europe/Rome:20260105T093000",
+            "SUMMARY:Laboratorio\\, gruppo A\\;",
+            "  turno 1",
+            "LOCATION:Aula Lab 01 [Sede centrale]",
+            "ORGANIZER;CN=\"Rossi: Mario\":MAILTO:mario.rossi@scuola.it",
+            "STATUS:CANCELLED",
+            "BEGIN:VALARM",
+            "DESCRIPTION:Promemoria",
+            "TRIGGER:-PT15M",
+            "END:VALARM",
+            "DESCRIPTION:Esperimento\\nsecondo turno",
+        ]);
+        assert_eq!(evento.uid.as_deref(), Some("prova@test"));
+        assert_eq!(evento.titolo, "Laboratorio, gruppo A; turno 1");
+        assert_eq!(evento.luogo.as_deref(), Some("Aula Lab 01 [Sede centrale]"));
+        assert_eq!(evento.organizzatore.as_deref(), Some("mario.rossi@scuola.it"));
+        assert_eq!(evento.inizio.utc(), utc("2026-01-05T07:00:00Z"));
+        assert_eq!(evento.durata, Duration::minutes(90));
+        assert!(evento.annullato);
+        assert!(evento.regola.is_none());
+    }
+
+    #[test]
+    fn tzid_sconosciuto_e_ora_fluttuante_valgono_come_roma() {
+        let windows = evento(&["DTSTART;TZID=W. Europe Standard Time:20260105T080000", "DURATION:PT1H"]);
+        let fluttuante = evento(&["DTSTART:20260105T080000", "DURATION:PT1H"]);
+        let londra = evento(&["DTSTART;TZID=Europe/London:20260105T080000", "DURATION:PT1H"]);
+        assert_eq!(windows.inizio.utc(), utc("2026-01-05T07:00:00Z"));
+        assert_eq!(fluttuante.inizio.utc(), utc("2026-01-05T07:00:00Z"));
+        assert_eq!(londra.inizio.utc(), utc("2026-01-05T08:00:00Z"));
+    }
+
+    #[test]
+    fn scarta_gli_eventi_malformati() {
+        assert!(leggi_calendario("BEGIN:VEVENT\r\nEND:VEVENT").is_err());
+        assert!(leggi_calendario("").is_err());
+
+        assert!(motivo_scarto(&["DTEND:20260105T090000Z"]).contains("DTSTART"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z"]).contains("DTEND"));
+        assert!(motivo_scarto(&["DTSTART;VALUE=DATE:20260105", "DTEND;VALUE=DATE:20260106"]).contains("intera giornata"));
+        assert!(motivo_scarto(&["DTSTART:2026-01-05 08:00", "DURATION:PT1H"]).contains("Data non valida"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:1H"]).contains("Durata non valida"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:FREQ=YEARLY"]).contains("non supportata"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:COUNT=3"]).contains("FREQ"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:FREQ=DAILY;BYDAY=MO"]).contains("BYDAY"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:FREQ=WEEKLY;BYDAY=1MO"]).contains("BYDAY"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:FREQ=DAILY;INTERVAL=0"]).contains("INTERVAL"));
+        assert!(motivo_scarto(&["DTSTART:20260105T080000Z", "DURATION:PT1H", "RRULE:FREQ=DAILY;COUNT=tre"]).contains("COUNT"));
+    }
+
+    #[test]
+    fn un_evento_malformato_non_ferma_gli_altri() {
+        let testo = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:rotto@test",
+            "SUMMARY:Senza inizio",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:buono@test",
+            "DTSTART:20260105T080000Z",
+            "DURATION:PT1H",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+            .join("\n");
+        let eventi = leggi_calendario(&testo).unwrap();
+        assert!(matches!(&eventi[0], Err((Some(uid), _)) if uid == "rotto@test"));
+        assert_eq!(eventi[1].as_ref().unwrap().uid.as_deref(), Some("buono@test"));
+    }
+
+    #[test]
+    fn il_feed_usa_il_fuso_di_roma_con_la_sua_definizione() {
+        let mut calendario = Calendario::new("Aula Lab 01");
+        for (uid, inizio) in [("estate", "2026-07-01T06:00:00Z"), ("inverno", "2026-12-01T07:00:00Z")] {
+            calendario.aggiungi(&Evento {
+                uid: uid.to_string(),
+                sequenza: 2,
+                ultima_modifica: utc("2026-06-01T10:00:00Z"),
+                inizio: utc(inizio),
+                fine: utc(inizio) + Duration::hours(1),
+                titolo: "Laboratorio di fisica, gruppo A".to_string(),
+                luogo: Some("Aula Lab 01".to_string()),
+                descrizione: None,
+                annullato: false,
+            });
+        }
+        let testo = calendario.chiudi();
+
+        assert!(testo.contains(&VTIMEZONE_ROMA.join("\r\n")));
+        assert_eq!(testo.matches("BEGIN:VTIMEZONE").count(), 1);
+        assert!(testo.contains("DTSTART;TZID=Europe/Rome:20260701T080000\r\n"));
+        assert!(testo.contains("DTSTART;TZID=Europe/Rome:20261201T080000\r\n"));
+        assert!(testo.contains("DTSTAMP:20260601T100000Z\r\n"));
+        assert!(testo.contains("SUMMARY:Laboratorio di fisica\\, gruppo A\r\n"));
+        assert!(testo.ends_with("END:VCALENDAR\r\n"));
+
+        // Rileggendo il feed si ritrovano gli stessi istanti
+        let eventi = leggi_calendario(&testo).unwrap();
+        let letti: Vec<DateTime<Utc>> = eventi.iter().map(|e| e.as_ref().unwrap().inizio.utc()).collect();
+        assert_eq!(letti, vec![utc("2026-07-01T06:00:00Z"), utc("2026-12-01T07:00:00Z")]);
+    }
+
+    #[test]
+    fn le_righe_lunghe_vengono_piegate_senza_spezzare_i_caratteri() {
+        let titolo = "Attività di laboratorio è più lunga di settantacinque ottetti: àèìòù ".repeat(3);
+        let mut calendario = Calendario::risorsa();
+        calendario.aggiungi(&Evento {
+            uid: "lungo".to_string(),
+            sequenza: 0,
+            ultima_modifica: utc("2026-06-01T10:00:00Z"),
+            inizio: utc("2026-07-01T06:00:00Z"),
+            fine: utc("2026-07-01T07:00:00Z"),
+            titolo: titolo.clone(),
+            luogo: None,
+            descrizione: None,
+            annullato: false,
+        });
+        let testo = calendario.chiudi();
+        assert!(!testo.contains("METHOD:"));
+        assert!(testo.split("\r\n").all(|riga| riga.len() <= 75));
+
+        let eventi = leggi_calendario(&testo).unwrap();
+        assert_eq!(eventi[0].as_ref().unwrap().titolo, titolo);
+    }
+}
@@ -0,0 +1,382 @@
+// src/importazione.rs
+// Importazione di prenotazioni da un file .ics (piani di laboratorio tenuti in un calendario esterno).
+// Ogni occorrenza passa dalle stesse verifiche di creare_prenotazione. Per impostazione predefinita
+// l'importazione è un'anteprima che non scrive nulla: le occorrenze accettate restano in memoria,
+// così il resoconto tiene conto anche dei conflitti tra gli eventi dello stesso file.
+// Con `conferma=true` le occorrenze vengono create in un'unica transazione.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::data::{Data, ToByteUnit};
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Transaction};
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::db::{self, Db, DbPool};
+use crate::errori::ErroreApi;
+use crate::eventi;
+use crate::metriche;
+use crate::ical::{self, EventoImportato};
+use crate::models::TipoEvento;
+use crate::prenotazioni::{self, RichiestaPrenotazione};
+use crate::traduzioni::{valori, Lingua};
+
+const DIMENSIONE_MASSIMA_MIB: usize = 2;
+const MASSIMO_OCCORRENZE: usize = 1000;
+const ORIZZONTE_GIORNI: i64 = 366;
+
+// Normalizza un nome d'aula per il confronto: "Aula Lab-01" e "lab 1" diventano "lab 1"
+fn normalizza(testo: &str) -> String {
+    let parole: Vec<String> = testo
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse::<u64>().map(|n| n.to_string()).unwrap_or_else(|_| p.to_string()))
+        .collect();
+    match parole.split_first() {
+        Some((prima, resto)) if prima == "aula" && !resto.is_empty() => resto.join(" "),
+        _ => parole.join(" "),
+    }
+}
+
+// Indice delle aule per nome ("Lab 01") e per nome con plesso ("Lab 01 [Sede centrale]").
+// Un nome presente in più plessi è ambiguo (None) e va indicato con il plesso.
+async fn indice_aule(db_pool: &DbPool) -> Result<HashMap<String, Option<i32>>, sqlx::Error> {
+    let aule = db::aule::elenco_con_plesso(db_pool, None, None, None).await?;
+    Ok(indicizza(aule.into_iter().map(|aula| {
+        (aula.Id_Aula, format!("{} {}", aula.Tipo_Aula, aula.Numero), aula.Nome_Plesso)
+    })))
+}
+
+fn indicizza(aule: impl IntoIterator<Item = (i32, String, String)>) -> HashMap<String, Option<i32>> {
+    let mut indice: HashMap<String, Option<i32>> = HashMap::new();
+    for (id_aula, nome, plesso) in aule {
+        indice.insert(normalizza(&format!("{} {}", nome, plesso)), Some(id_aula));
+        indice
+            .entry(normalizza(&nome))
+            .and_modify(|id| *id = None)
+            .or_insert(Some(id_aula));
+    }
+    indice
+}
+
+fn trova_aula(indice: &HashMap<String, Option<i32>>, luogo: &str, lingua: Lingua) -> Result<i32, String> {
+    // Il LOCATION dei nostri feed aggiunge le attrezzature: "Aula Lab 01 [Sede] (+ Proiettore x1)"
+    let luogo = luogo.split(" (+").next().unwrap_or(luogo);
+    match indice.get(&normalizza(luogo)) {
+        Some(Some(id_aula)) => Ok(*id_aula),
+        Some(None) => Err(lingua.traduci_con(
+            "importazione.aula_ambigua",
+            &format!("L'aula \"{}\" esiste in più plessi: indica anche il plesso.", luogo),
+            &valori(&[("luogo", luogo.to_string())]),
+        )),
+        None => Err(lingua.traduci_con(
+            "importazione.aula_sconosciuta",
+            &format!("Aula \"{}\" non riconosciuta.", luogo),
+            &valori(&[("luogo", luogo.to_string())]),
+        )),
+    }
+}
+
+async fn indice_professori(db_pool: &DbPool) -> Result<HashMap<String, i32>, sqlx::Error> {
+    let credenziali = db::professori::email_professori(db_pool).await?;
+    Ok(credenziali.into_iter().map(|(id_professore, email)| (email.to_lowercase(), id_professore)).collect())
+}
+
+// Dove finiscono le occorrenze accettate
+enum Destinazione {
+    // Tutte nella stessa transazione: le aule verificate restano bloccate fino al commit
+    Conferma(Transaction<'static, Db>),
+    // Nessuna scrittura: le occorrenze accettate restano nell'anteprima
+    Anteprima(PoolConnection<Db>, prenotazioni::Anteprima),
+}
+
+fn voce_resoconto(
+    evento: Option<&EventoImportato>,
+    uid: Option<&str>,
+    occorrenza: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    esito: &str,
+    motivo: Option<String>,
+) -> JsonValue {
+    json!({
+        "uid": uid,
+        "titolo": evento.map(|e| e.titolo.as_str()),
+        "luogo": evento.and_then(|e| e.luogo.as_deref()),
+        "inizio": occorrenza.map(|(inizio, _)| inizio.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        "fine": occorrenza.map(|(_, fine)| fine.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        "esito": esito,
+        "motivo": motivo,
+    })
+}
+
+// Il corpo della richiesta è il contenuto del file .ics.
+// `id_aula` e `id_professore` si applicano a tutti gli eventi, al posto di LOCATION e ORGANIZER.
+#[post("/prenotazioni/importa?<conferma>&<id_aula>&<id_professore>", data = "<file>")]
+pub async fn importa_ics(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+    lingua: Lingua,
+    conferma: Option<bool>,
+    id_aula: Option<i32>,
+    id_professore: Option<i32>,
+    file: Data<'_>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let conferma = conferma.unwrap_or(false);
+    if id_professore.is_some_and(|id| id != utente.id_professore) && !utente.is_admin() {
+        return Err(ErroreApi::vietato("importazione_non_consentita", "Puoi importare solo prenotazioni a tuo nome."));
+    }
+
+    let testo = match file.open(DIMENSIONE_MASSIMA_MIB.mebibytes()).into_string().await {
+        Ok(testo) if testo.is_complete() => testo.into_inner(),
+        Ok(_) => {
+            return Err(ErroreApi::troppo_grande("file_troppo_grande", format!("Il file supera i {} MiB.", DIMENSIONE_MASSIMA_MIB))
+                .con("dimensione_massima_mib", DIMENSIONE_MASSIMA_MIB))
+        }
+        Err(_) => return Err(ErroreApi::richiesta_non_valida("file_non_utf8", "Il file deve essere un testo UTF-8.")),
+    };
+    let eventi = ical::leggi_calendario(&testo)
+        .map_err(|messaggio| ErroreApi::campo("file", "calendario_non_valido", messaggio))?;
+
+    let errore_interno = |e: sqlx::Error| {
+        ErroreApi::interno("DB durante l'importazione del calendario", e)
+    };
+    let aule = indice_aule(db_pool.inner()).await.map_err(errore_interno)?;
+    let professori = indice_professori(db_pool.inner()).await.map_err(errore_interno)?;
+
+    let adesso = Utc::now();
+    let orizzonte = adesso + Duration::days(ORIZZONTE_GIORNI);
+    let mut resoconto: Vec<JsonValue> = Vec::new();
+    let mut conteggi: HashMap<&str, usize> = HashMap::new();
+    let mut occorrenze_totali = 0;
+    let mut troncato = false;
+    let mut create: Vec<i32> = Vec::new();
+
+    let mut destinazione = if conferma {
+        Destinazione::Conferma(db_pool.begin().await.map_err(errore_interno)?)
+    } else {
+        Destinazione::Anteprima(db_pool.acquire().await.map_err(errore_interno)?, prenotazioni::Anteprima::default())
+    };
+    for letto in eventi {
+        let evento = match letto {
+            Ok(evento) => evento,
+            Err((uid, motivo)) => {
+                *conteggi.entry("scartata").or_default() += 1;
+                resoconto.push(voce_resoconto(None, uid.as_deref(), None, "scartata", Some(motivo)));
+                continue;
+            }
+        };
+        let uid = evento.uid.as_deref();
+        let scarta = |motivo: String| voce_resoconto(Some(&evento), uid, None, "scartata", Some(motivo));
+
+        if evento.annullato {
+            *conteggi.entry("scartata").or_default() += 1;
+            resoconto.push(scarta(lingua.traduci("importazione.evento_annullato", "Evento annullato.")));
+            continue;
+        }
+
+        let aula = match (id_aula, evento.luogo.as_deref()) {
+            (Some(id_aula), _) => Ok(id_aula),
+            (None, Some(luogo)) => trova_aula(&aule, luogo, lingua),
+            (None, None) => Err(lingua.traduci("importazione.location_mancante", "Evento senza LOCATION: indica l'aula con id_aula.")),
+        };
+        // Chi non è amministratore importa sempre a proprio nome
+        let professore = match (id_professore, evento.organizzatore.as_deref()) {
+            (Some(id), _) => Ok(id),
+            _ if !utente.is_admin() => Ok(utente.id_professore),
+            (None, Some(email)) => professori
+                .get(&email.to_lowercase())
+                .copied()
+                .ok_or_else(|| {
+                    lingua.traduci_con(
+                        "importazione.professore_sconosciuto",
+                        &format!("Nessun professore registrato con l'email {}.", email),
+                        &valori(&[("email", email.to_string())]),
+                    )
+                }),
+            (None, None) => Err(lingua.traduci("importazione.organizer_mancante", "Evento senza ORGANIZER: indica il professore con id_professore.")),
+        };
+        let (id_aula_evento, id_professore_evento) = match (aula, professore) {
+            (Ok(a), Ok(p)) => (a, p),
+            (Err(motivo), _) | (_, Err(motivo)) => {
+                *conteggi.entry("scartata").or_default() += 1;
+                resoconto.push(scarta(motivo));
+                continue;
+            }
+        };
+
+        for (inizio, fine) in evento.occorrenze(orizzonte, MASSIMO_OCCORRENZE) {
+            if occorrenze_totali >= MASSIMO_OCCORRENZE {
+                troncato = true;
+                break;
+            }
+            occorrenze_totali += 1;
+            if fine <= adesso {
+                *conteggi.entry("scartata").or_default() += 1;
+                let motivo = lingua.traduci("importazione.occorrenza_passata", "Occorrenza già passata.");
+                resoconto.push(voce_resoconto(Some(&evento), uid, Some((inizio, fine)), "scartata", Some(motivo)));
+                continue;
+            }
+
+            let richiesta = RichiestaPrenotazione {
+                id_professore: id_professore_evento,
+                id_aula: Some(id_aula_evento),
+                data_inizio: inizio,
+                data_fine: fine,
+                attrezzature: Vec::new(),
+                numero_studenti: None,
+                id_materia: None,
+            };
+            let risultato = match &mut destinazione {
+                Destinazione::Conferma(tx) => {
+                    // Un savepoint per occorrenza: un errore annulla solo quella
+                    let mut savepoint = tx.begin().await.map_err(errore_interno)?;
+                    let risultato = prenotazioni::inserisci(&mut savepoint, &richiesta).await;
+                    match risultato {
+                        Ok(_) => savepoint.commit().await.map_err(errore_interno)?,
+                        Err(_) => savepoint.rollback().await.map_err(errore_interno)?,
+                    }
+                    risultato.map(Some)
+                }
+                Destinazione::Anteprima(conn, anteprima) => prenotazioni::prova(conn, &richiesta, anteprima).await.map(|_| None),
+            };
+            match risultato {
+                Ok(Some(id_prenotazione)) => {
+                    *conteggi.entry("creata").or_default() += 1;
+                    let mut voce = voce_resoconto(Some(&evento), uid, Some((inizio, fine)), "creata", None);
+                    voce["id_prenotazione"] = json!(id_prenotazione);
+                    create.push(id_prenotazione);
+                    resoconto.push(voce);
+                }
+                Ok(None) => {
+                    *conteggi.entry("da_creare").or_default() += 1;
+                    resoconto.push(voce_resoconto(Some(&evento), uid, Some((inizio, fine)), "da_creare", None));
+                }
+                Err(errore) => {
+                    let esito = match &errore {
+                        ErroreApi::Conflitto(d) => {
+                            // L'anteprima non rifiuta niente
+                            if conferma {
+                                metriche::conflitto(d.codice);
+                            }
+                            "conflitto"
+                        }
+                        ErroreApi::Interno { contesto, errore } => {
+                            tracing::error!(id_professore = utente.id_professore, uid, contesto = %contesto, errore = %errore, "Errore interno nell'importare un'occorrenza");
+                            "errore"
+                        }
+                        _ => "scartata",
+                    };
+                    *conteggi.entry(esito).or_default() += 1;
+                    let motivo = Some(errore.messaggio_in(lingua));
+                    resoconto.push(voce_resoconto(Some(&evento), uid, Some((inizio, fine)), esito, motivo));
+                }
+            }
+        }
+    }
+
+    if let Destinazione::Conferma(tx) = destinazione {
+        tx.commit().await.map_err(errore_interno)?;
+        for id_prenotazione in create {
+            eventi::pubblica(db_pool.inner(), TipoEvento::Creata, id_prenotazione).await;
+        }
+    }
+
+    Ok(Json(json!({
+        "status": "successo",
+        "message": if conferma {
+            lingua.traduci("importazione.completata", "Importazione completata.")
+        } else {
+            lingua.traduci("importazione.anteprima", "Anteprima dell'importazione: nessuna prenotazione è stata salvata.")
+        },
+        "prova": !conferma,
+        "troncato": troncato,
+        "riepilogo": conteggi,
+        "eventi": resoconto
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indice() -> HashMap<String, Option<i32>> {
+        indicizza([
+            (1, "Lab 01".to_string(), "Sede centrale".to_string()),
+            (2, "Aula 12".to_string(), "Sede centrale".to_string()),
+            (3, "Aula 12".to_string(), "Succursale".to_string()),
+            (4, "Palestra A".to_string(), "Succursale".to_string()),
+        ])
+    }
+
+    #[test]
+    fn normalizza_ignora_maiuscole_punteggiatura_e_zeri() {
+        assert_eq!(normalizza("Aula Lab-01"), "lab 1");
+        assert_eq!(normalizza("lab 1"), "lab 1");
+        assert_eq!(normalizza("  LAB_001 "), "lab 1");
+        assert_eq!(normalizza("Aula 12 [Sede centrale]"), "12 sede centrale");
+        assert_eq!(normalizza("Laboratorio d'informatica"), "laboratorio d informatica");
+        assert_eq!(normalizza("Aula"), "aula");
+        assert_eq!(normalizza(""), "");
+    }
+
+    #[test]
+    fn trova_l_aula_con_o_senza_plesso() {
+        let indice = indice();
+        assert_eq!(trova_aula(&indice, "Aula Lab 01", Lingua::Italiano), Ok(1));
+        assert_eq!(trova_aula(&indice, "lab-1", Lingua::Italiano), Ok(1));
+        assert_eq!(trova_aula(&indice, "Lab 01 [Sede centrale]", Lingua::Italiano), Ok(1));
+        assert_eq!(trova_aula(&indice, "Aula 12 [Succursale]", Lingua::Italiano), Ok(3));
+        assert_eq!(trova_aula(&indice, "Palestra A", Lingua::Italiano), Ok(4));
+    }
+
+    #[test]
+    fn ignora_le_attrezzature_del_nostro_feed() {
+        let indice = indice();
+        assert_eq!(trova_aula(&indice, "Aula Lab 01 [Sede centrale] (+ Proiettore x1)", Lingua::Italiano), Ok(1));
+    }
+
+    #[test]
+    fn un_nome_in_piu_plessi_e_ambiguo() {
+        let indice = indice();
+        assert_eq!(
+            trova_aula(&indice, "Aula 12", Lingua::Italiano),
+            Err("L'aula \"Aula 12\" esiste in più plessi: indica anche il plesso.".to_string())
+        );
+        assert_eq!(
+            trova_aula(&indice, "Aula 12", Lingua::Inglese),
+            Err("The room \"Aula 12\" exists in more than one building: specify the building too.".to_string())
+        );
+    }
+
+    #[test]
+    fn un_aula_sconosciuta_viene_segnalata() {
+        let indice = indice();
+        assert_eq!(trova_aula(&indice, "Aula 99", Lingua::Italiano), Err("Aula \"Aula 99\" non riconosciuta.".to_string()));
+        assert_eq!(trova_aula(&indice, "Lab 01 [Altrove]", Lingua::Inglese), Err("Room \"Lab 01 [Altrove]\" not recognised.".to_string()));
+    }
+
+    #[test]
+    fn il_resoconto_riporta_l_occorrenza() {
+        let evento = ical::leggi_calendario(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:a@b\r\nSUMMARY:Fisica\r\nLOCATION:Lab 01\r\nDTSTART:20260105T080000Z\r\nDURATION:PT1H\r\nEND:VEVENT\r\nEND:VCALENDAR",
+        )
+        .unwrap()
+        .remove(0)
+        .unwrap();
+        let inizio: DateTime<Utc> = "2026-01-05T08:00:00Z".parse().unwrap();
+        let occorrenza = Some((inizio, inizio + Duration::hours(1)));
+        let voce = voce_resoconto(Some(&evento), evento.uid.as_deref(), occorrenza, "scartata", Some("Aula occupata".to_string()));
+        assert_eq!(voce, json!({
+            "uid": "a@b",
+            "titolo": "Fisica",
+            "luogo": "Lab 01",
+            "inizio": "2026-01-05T08:00:00Z",
+            "fine": "2026-01-05T09:00:00Z",
+            "esito": "scartata",
+            "motivo": "Aula occupata",
+        }));
+    }
+}
@@ -1,29 +1,53 @@
 mod models;
+mod db;
+mod errori;
 mod auth_utils;
+mod blocchi;
+mod guasti;
+mod attrezzature;
+mod prenotazioni;
+mod plessi;
+mod ical;
+mod calendari;
+mod importazione;
+mod caldav;
+mod eventi;
+mod webhook;
+mod aggiornamenti;
+mod notifiche;
+mod promemoria;
+mod stampa;
+mod esportazione;
+mod chiosco;
+mod checkin;
+mod traduzioni;
+mod registro;
+mod metriche;
+mod salute;
+mod configurazione;
+#[cfg(all(test, feature = "sqlite"))]
+mod prova;
 
 #[macro_use]
 extern crate rocket;
 use rocket::fs::{FileServer, NamedFile};
-use std::path::{Path, PathBuf};
-use argon2::{
-    password_hash::{
-        rand_core::OsRng,
-        PasswordHasher, SaltString
-    },
-    Argon2
-};
+use rocket::{Build, Rocket};
+use std::process::ExitCode;
+use std::path::PathBuf;
+use argon2::Argon2;
 use chrono_tz::Europe::Rome;
 use rocket::State;
 use rocket::serde::json::{Json, Value as JsonValue, json}; // json! macro per risposte d'errore
 use rocket::serde::{Deserialize, Serialize}; // Rocket riesporta Serialize/Deserialize da Serde
-use sqlx::mysql::MySqlPool; // O MySqlPoolOptions, se configuri il pool manualmente
+use db::DbPool;
 use argon2::{password_hash::{
     PasswordHash, PasswordVerifier // SaltString e PasswordHasher sarebbero per generare l'hash
 },};
-use jsonwebtoken::{encode, Header, EncodingKey}; // decode e DecodingKey sarebbero per verificare i token
-use chrono::{Utc, Duration, DateTime, TimeZone};
+use chrono::{Utc, Duration, DateTime};
 use rocket::http::Status;
 use rocket::response::status;
+use errori::{ErroreApi, ErroreCampo};
+use traduzioni::{valori, Lingua};
 
 // Per gestire le date e le scadenze dei token
 #[derive(serde::Serialize)]
@@ -47,13 +71,14 @@ struct LoginCredentials<'r> {
 }
 #[post("/prenotazioni", format = "json", data = "<payload>")]
 async fn creare_prenotazione(
-    db_pool: &State<MySqlPool>,
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
     payload: Json<models::NuovaPrenotazionePayload>,
     // Qui dovresti avere anche un Request Guard per l'autenticazione
     // per assicurarti che solo un professore loggato possa prenotare,
     // e che payload.id_professore corrisponda all'ID nel token.
     // auth_prof: AuthenticatedProfessor, // Esempio di request guard
-) -> Result<Json<JsonValue>, status::Custom<Json<JsonValue>>> { // status::Custom per errori HTTP specifici
+) -> Result<Json<JsonValue>, ErroreApi> {
 
     // TODO: Validare che payload.id_professore corrisponda a auth_prof.id
 
@@ -61,115 +86,236 @@ async fn creare_prenotazione(
     // Il frontend invia stringhe ISO (es. da new Date().toISOString())
     let data_inizio = match DateTime::parse_from_rfc3339(&payload.data_inizio) {
         Ok(dt) => dt.with_timezone(&Utc),
-        Err(_) => return Err(status::Custom(Status::BadRequest, Json(json!({"status": "fallito", "message": "Formato Data_Inizio non valido."})))),
+        Err(_) => return Err(ErroreApi::campo("Data_Inizio", "formato_data_non_valido", "Formato Data_Inizio non valido.")),
     };
     let data_fine = match DateTime::parse_from_rfc3339(&payload.data_fine) {
         Ok(dt) => dt.with_timezone(&Utc),
-        Err(_) => return Err(status::Custom(Status::BadRequest, Json(json!({"status": "fallito", "message": "Formato Data_Fine non valido."})))),
+        Err(_) => return Err(ErroreApi::campo("Data_Fine", "formato_data_non_valido", "Formato Data_Fine non valido.")),
     };
 
-    // Validazione semplice
-    if data_fine <= data_inizio {
-        return Err(status::Custom(Status::BadRequest, Json(json!({"status": "fallito", "message": "Data_Fine deve essere successiva a Data_Inizio."}))));
+    // Validazione, controllo dei conflitti e inserimento (aula e/o attrezzatura)
+    let richiesta = prenotazioni::RichiestaPrenotazione {
+        id_professore: payload.id_professore,
+        id_aula: payload.id_aula,
+        data_inizio,
+        data_fine,
+        attrezzature: payload.attrezzature.clone(),
+        numero_studenti: payload.numero_studenti,
+        id_materia: payload.id_materia,
+    };
+    let new_id = prenotazioni::crea(db_pool.inner(), &richiesta).await?;
+    eventi::pubblica(db_pool.inner(), models::TipoEvento::Creata, new_id).await;
+
+    // Avvisa se il professore deve spostarsi da un altro plesso senza averne il tempo
+    let avvisi = match payload.id_aula {
+        Some(id_aula) => plessi::avvisi_spostamento(db_pool.inner(), lingua, payload.id_professore, id_aula, data_inizio, data_fine)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!(errore = %e, "Errore DB nel calcolare gli avvisi di spostamento");
+                Vec::new()
+            }),
+        None => Vec::new(),
+    };
+
+    Ok(Json(json!({
+        "status": "successo",
+        "message": lingua.traduci("messaggi.prenotazione_creata", "Prenotazione creata con successo!"),
+        "id_prenotazione": new_id,
+        "avvisi": avvisi
+    })))
+}
+// Annulla una prenotazione: resta nel database (e nei feed iCalendar come evento annullato)
+// ma non occupa più l'aula né l'attrezzatura
+#[delete("/prenotazioni/<id_prenotazione>")]
+async fn annulla_prenotazione(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: auth_utils::UtenteAutenticato,
+    id_prenotazione: i32,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let (id_titolare, stato) = match db::prenotazioni::titolare_e_stato(db_pool.inner(), id_prenotazione).await {
+        Ok(Some(prenotazione)) => prenotazione,
+        Ok(None) => return Err(ErroreApi::non_trovato("prenotazione_non_trovata", "Prenotazione non trovata.")),
+        Err(e) => {
+            return Err(ErroreApi::interno(format!("DB nel recuperare la prenotazione {}", id_prenotazione), e));
+        }
+    };
+
+    if id_titolare != utente.id_professore && !utente.is_admin() {
+        return Err(ErroreApi::vietato("annullamento_non_consentito", "Puoi annullare solo le tue prenotazioni."));
+    }
+    if stato != "attiva" {
+        return Err(ErroreApi::conflitto("prenotazione_non_attiva", "La prenotazione è già stata annullata o rilasciata."));
     }
 
-    match sqlx::query!(
-        "INSERT INTO prenotazione (Id_Professore, Id_Aula, Data_Inizio, Data_Fine) VALUES (?, ?, ?, ?)",
-        payload.id_professore,
-        payload.id_aula,
-        data_inizio, // Passa DateTime<Utc>
-        data_fine    // Passa DateTime<Utc>
-    )
-        .execute(db_pool.inner())
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() == 1 {
-                let new_id = result.last_insert_id();
-                Ok(Json(json!({
-                    "status": "successo",
-                    "message": "Prenotazione creata con successo!",
-                    "id_prenotazione": new_id
-                })))
-            } else {
-                Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Impossibile creare la prenotazione."}))))
+    match prenotazioni::annulla(db_pool.inner(), id_prenotazione).await {
+        Ok(annullata) => {
+            if annullata {
+                eventi::pubblica(db_pool.inner(), models::TipoEvento::Annullata, id_prenotazione).await;
             }
+            Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.prenotazione_annullata", "Prenotazione annullata.")})))
         }
         Err(e) => {
-            eprintln!("Errore DB durante la creazione della prenotazione: {}", e);
-            // Controlla errori specifici, es. violazione di vincoli
-            if let Some(db_err) = e.as_database_error() {
-                if db_err.is_unique_violation() { // Esempio
-                    return Err(status::Custom(Status::Conflict, Json(json!({"status": "fallito", "message": "Conflitto di prenotazione o dato duplicato."}))));
-                }
-            }
-            Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore interno del server durante la creazione."}))))
+            Err(ErroreApi::interno(format!("DB nell'annullare la prenotazione {}", id_prenotazione), e))
         }
     }
 }
-#[get("/prenotazioni")]
-async fn get_prenotazioni(
-    db_pool: &State<MySqlPool>
-) -> Result<Json<Vec<models::CalendarEventApi>>, Json<JsonValue>> {
-
-    let query_result = sqlx::query_as!(
-        models::PrenotazioneDb, // La struct che mappa il risultato della query
-        r#"
-        SELECT
-            p.Id_Prenotazione,
-            p.Data_Inizio,      -- Verrà letto come NaiveDateTime
-            p.Data_Fine,        -- Verrà letto come NaiveDateTime
-            a.Tipo_Aula,
-            a.Numero AS Numero_Aula,
-            pr.Nome AS Nome_Professore,
-            pr.Cognome AS Cognome_Professore
-        FROM
-            prenotazione p
-        JOIN
-            aula a ON p.Id_Aula = a.Id_Aula
-        JOIN
-            professore pr ON p.Id_Professore = pr.Id_Professore
-        ORDER BY p.Data_Inizio ASC
-        "#
-    )
-        .fetch_all(db_pool.inner())
-        .await;
-
-    match query_result {
-        Ok(prenotazioni_db) => {
-            let calendar_events: Vec<models::CalendarEventApi> = prenotazioni_db
-                .into_iter()
-                .map(|p_db| {
-                    let nome_aula_completo = format!("Aula {} {:02}", p_db.Tipo_Aula, p_db.Numero_Aula);
-                    let nome_prof_completo = format!("{} {}",
-                                                     p_db.Nome_Professore.as_deref().unwrap_or("N/D"),
-                                                     p_db.Cognome_Professore
-                    );
-
-                    // **MODIFICA CRUCIALE QUI:**
-                    // Poiché Data_Inizio e Data_Fine dal DB sono NaiveDateTime ma rappresentano UTC,
-                    // li convertiamo in DateTime<Utc> specificando che sono già UTC.
-                    let data_inizio_utc: DateTime<Utc> = DateTime::from_naive_utc_and_offset(p_db.Data_Inizio, Utc);
-                    let data_fine_utc: DateTime<Utc> = DateTime::from_naive_utc_and_offset(p_db.Data_Fine, Utc);
-
-                    models::CalendarEventApi {
-                        id: p_db.Id_Prenotazione.to_string(),
-                        title: format!("{} - {}", nome_aula_completo, nome_prof_completo),
-                        start: data_inizio_utc.to_rfc3339_opts(chrono::SecondsFormat::Secs, true), // Invia UTC con 'Z'
-                        end: data_fine_utc.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),   // Invia UTC con 'Z'
-                        allDay: false,
-                    }
-                })
-                .collect();
-
-            Ok(Json(calendar_events))
+
+// Sposta una prenotazione: solo il proprietario o un amministratore
+#[put("/prenotazioni/<id_prenotazione>", format = "json", data = "<payload>")]
+async fn modifica_prenotazione(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: auth_utils::UtenteAutenticato,
+    id_prenotazione: i32,
+    payload: Json<models::ModificaPrenotazionePayload>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let proprietario = match db::prenotazioni::titolare_e_stato(db_pool.inner(), id_prenotazione).await {
+        Ok(Some((id_titolare, _))) => id_titolare,
+        Ok(None) => return Err(ErroreApi::non_trovato("prenotazione_non_trovata", "Prenotazione non trovata.")),
+        Err(e) => {
+            return Err(ErroreApi::interno(format!("DB nel recuperare la prenotazione {}", id_prenotazione), e));
+        }
+    };
+    if proprietario != utente.id_professore && !utente.is_admin() {
+        return Err(ErroreApi::vietato("modifica_non_consentita", "Puoi modificare solo le tue prenotazioni."));
+    }
+
+    let data_inizio = parse_data(&payload.data_inizio, "Data_Inizio")?;
+    let data_fine = parse_data(&payload.data_fine, "Data_Fine")?;
+    let aula_precedente = prenotazioni::modifica(db_pool.inner(), id_prenotazione, payload.id_aula, data_inizio, data_fine, payload.numero_studenti).await?;
+    eventi::pubblica_modifica(db_pool.inner(), id_prenotazione, aula_precedente).await;
+
+    Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.prenotazione_modificata", "Prenotazione modificata.")})))
+}
+
+// Conferma una prenotazione in un'aula che richiede approvazione
+#[post("/prenotazioni/<id_prenotazione>/approva")]
+async fn approva_prenotazione(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: auth_utils::UtenteAutenticato,
+    id_prenotazione: i32,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_approvazioni", "Solo un amministratore può approvare le prenotazioni.")?;
+    match prenotazioni::approva(db_pool.inner(), id_prenotazione).await {
+        Ok(true) => {
+            eventi::pubblica(db_pool.inner(), models::TipoEvento::Approvata, id_prenotazione).await;
+            Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.prenotazione_approvata", "Prenotazione approvata.")})))
         }
+        Ok(false) => Err(ErroreApi::conflitto("prenotazione_non_in_attesa", "La prenotazione non è in attesa di approvazione.")),
         Err(e) => {
-            eprintln!("Errore nel recuperare le prenotazioni dal DB: {}", e);
-            Err(Json(json!({ "status": "errore", "message": "Impossibile caricare le prenotazioni." })))
+            Err(ErroreApi::interno(format!("DB nell'approvare la prenotazione {}", id_prenotazione), e))
         }
     }
 }
+
+// Legge un parametro data in formato ISO 8601 (es. `start`/`end` inviati da FullCalendar)
+fn parse_data(testo: &str, nome: &str) -> Result<DateTime<Utc>, ErroreApi> {
+    DateTime::parse_from_rfc3339(testo)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| ErroreApi::campo(nome, "formato_data_non_valido", format!("Formato {} non valido.", nome)))
+}
+
+fn parse_data_opzionale(valore: Option<&str>, nome: &str) -> Result<Option<DateTime<Utc>>, ErroreApi> {
+    valore.map(|testo| parse_data(testo, nome)).transpose()
+}
+
+#[get("/prenotazioni?<start>&<end>&<plesso>")]
+async fn get_prenotazioni(
+    db_pool: &State<DbPool>,
+    start: Option<&str>,
+    end: Option<&str>,
+    plesso: Option<i32>,
+    lingua: Lingua,
+) -> Result<Json<Vec<models::CalendarEventApi>>, ErroreApi> {
+    let start = parse_data_opzionale(start, "start")?;
+    let end = parse_data_opzionale(end, "end")?;
+
+    let filtro = prenotazioni::FiltroPrenotazioni { start, end, plesso, ..Default::default() };
+    let mut calendar_events: Vec<models::CalendarEventApi> = match prenotazioni::elenco(db_pool.inner(), &filtro).await {
+        Ok(elenco) => elenco
+            .into_iter()
+            .map(|voce| models::CalendarEventApi {
+                id: voce.db.Id_Prenotazione.to_string(),
+                title: voce.titolo(lingua),
+                start: voce.inizio.to_rfc3339_opts(chrono::SecondsFormat::Secs, true), // Invia UTC con 'Z'
+                end: voce.fine.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),   // Invia UTC con 'Z'
+                allDay: false,
+                display: None,
+                backgroundColor: None,
+                capacitaResidua: voce.capacita_residua,
+                guastoBloccante: voce.guasto_bloccante,
+            })
+            .collect(),
+        Err(e) => {
+            return Err(ErroreApi::interno("nel recuperare le prenotazioni dal DB", e));
+        }
+    };
+
+    // I blocchi di indisponibilità e le chiusure dei plessi vengono mostrati come eventi di sfondo.
+    // Senza un intervallo esplicito le ricorrenze vengono espanse attorno a oggi.
+    let adesso = Utc::now();
+    let da = start.unwrap_or(adesso - Duration::days(blocchi::ORIZZONTE_RICORRENZE_GIORNI));
+    let a = end.unwrap_or(adesso + Duration::days(blocchi::ORIZZONTE_RICORRENZE_GIORNI));
+    let errore_sfondo = |e: sqlx::Error| {
+        ErroreApi::interno("nel recuperare blocchi e chiusure dal DB", e)
+    };
+
+    let aule_del_plesso = match plesso {
+        Some(id_plesso) => Some(
+            db::aule::id_aule(db_pool.inner(), Some(id_plesso))
+                .await
+                .map_err(errore_sfondo)?,
+        ),
+        None => None,
+    };
+    let occorrenze = blocchi::occorrenze_nel_periodo(db_pool.inner(), None, da, a)
+        .await
+        .map_err(errore_sfondo)?;
+    calendar_events.extend(
+        occorrenze
+            .into_iter()
+            .filter(|o| aule_del_plesso.as_ref().is_none_or(|aule| aule.contains(&o.id_aula)))
+            .map(|o| models::CalendarEventApi {
+                id: format!("blocco-{}-{}", o.id_blocco, o.inizio.timestamp()),
+                title: o.motivo,
+                start: o.inizio.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                end: o.fine.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                allDay: false,
+                display: Some("background".to_string()),
+                backgroundColor: Some("#dc3545".to_string()),
+                capacitaResidua: None,
+                guastoBloccante: false,
+            }),
+    );
+
+    let chiusure = db::plessi::chiusure_nel_periodo(
+        db_pool.inner(),
+        plesso,
+        da.with_timezone(&Rome).date_naive(),
+        a.with_timezone(&Rome).date_naive(),
+    )
+        .await
+        .map_err(errore_sfondo)?;
+    calendar_events.extend(chiusure.into_iter().map(|c| models::CalendarEventApi {
+        id: format!("chiusura-{}", c.Id_Chiusura),
+        title: lingua.traduci_con(
+            "prenotazioni.plesso_chiuso",
+            &format!("{} chiuso: {}", c.Nome_Plesso, c.Motivo),
+            &valori(&[("plesso", c.Nome_Plesso.clone()), ("motivo", c.Motivo.clone())]),
+        ),
+        start: c.Data_Inizio.to_string(),
+        end: (c.Data_Fine + Duration::days(1)).to_string(), // FullCalendar: fine esclusiva per gli eventi allDay
+        allDay: true,
+        display: Some("background".to_string()),
+        backgroundColor: Some("#6c757d".to_string()),
+        capacitaResidua: None,
+        guastoBloccante: false,
+    }));
+
+    Ok(Json(calendar_events))
+}
 #[derive(Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
 struct LoginSuccessResponse {
@@ -177,144 +323,105 @@ struct LoginSuccessResponse {
     token: String,
     user_id: i32, // Assumendo che Id_Professore sia i32
     user_name: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(crate = "rocket::serde")]
-struct Claims {
-    sub: String, // Subject (Id_Professore come stringa)
-    name: String,
-    exp: usize,  // Expiration timestamp (secondi da epoch)
+    lingua: Option<Lingua>,
 }
 
 // Ora la tua funzione login_professore:
 
 #[post("/auth/login", format = "json", data = "<login_attempt>")] // Rinominato data per chiarezza
 async fn login_professore(
-    db_pool: &State<MySqlPool>,
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
     login_attempt: Json<LoginCredentials<'_>>,
-) -> Result<Json<LoginSuccessResponse>, Json<JsonValue>> { // Usiamo JsonValue per errori JSON
+) -> Result<Json<LoginSuccessResponse>, ErroreApi> {
 
-    // 1. Cerca le credenziali basate sull'email nella tabella Credenziali
-    let cred_record = match sqlx::query!(
-        "SELECT Id_Professore_Cred, password_hash FROM Credenziali WHERE email = ?",
-        login_attempt.email
-    )
-        .fetch_optional(db_pool.inner()) // db_pool.inner() per accedere al pool
-        .await
-    {
-        Ok(Some(record)) => record,
+    // 1. Cerca le credenziali basate sull'email nella tabella credenziali
+    let (professor_id, password_hash) = match db::professori::credenziali(db_pool.inner(), login_attempt.email).await {
+        Ok(Some(credenziali)) => credenziali,
         Ok(None) => {
-            // Email non trovata nella tabella Credenziali
+            // Email non trovata nella tabella credenziali
             // Per sicurezza, restituisci un messaggio generico
-            return Err(Json(json!({ "status": "fallito", "message": "Email o password non corretta." })));
+            metriche::login(false);
+            return Err(ErroreApi::non_autenticato("credenziali_non_valide", "Email o password non corretta."));
         }
         Err(e) => {
-            eprintln!("Errore database durante la ricerca delle credenziali: {}", e);
-            return Err(Json(json!({ "status": "errore", "message": "Errore interno del server." })));
+            return Err(ErroreApi::interno("DB durante la ricerca delle credenziali", e));
         }
     };
 
     // 2. Verifica la password fornita contro l'hash memorizzato
-    let is_password_valid = match PasswordHash::new(&cred_record.password_hash) {
+    let is_password_valid = match PasswordHash::new(&password_hash) {
         Ok(parsed_hash) => Argon2::default()
             .verify_password(login_attempt.password.as_bytes(), &parsed_hash)
             .is_ok(),
         Err(_) => {
             // L'hash memorizzato non è valido (problema di sicurezza o corruzione dati)
-            eprintln!("Hash password corrotto o non valido per l'email: {}", login_attempt.email);
+            tracing::warn!(email = %login_attempt.email, "Hash password corrotto o non valido");
             false // Tratta come password non valida per sicurezza
         }
     };
 
     if !is_password_valid {
         // Password errata
-        return Err(Json(json!({ "status": "fallito", "message": "Email o password non corretta." })));
+        metriche::login(false);
+        return Err(ErroreApi::non_autenticato("credenziali_non_valide", "Email o password non corretta."));
     }
 
-    // 3. Password corretta. Ora recupera Nome e Cognome dalla tabella Professore
+    // 3. Password corretta. Ora recupera Nome e Cognome dalla tabella professore
     //    usando Id_Professore_Cred (che è l'Id_Professore)
-    let professor_id = cred_record.Id_Professore_Cred;
-
-    let professor_details = match sqlx::query!(
-        "SELECT Nome, Cognome FROM Professore WHERE Id_Professore = ?",
-        professor_id
-    )
-        .fetch_one(db_pool.inner()) // Ci aspettiamo che esista sempre se le credenziali esistono
-        .await
-    {
+    let professor_details = match db::professori::professore(db_pool.inner(), professor_id).await { // Ci aspettiamo che esista sempre se le credenziali esistono
         Ok(record) => record,
         Err(e) => {
             // Questo sarebbe strano se le credenziali esistono ma il professore no (violazione FK?)
             // O un errore di connessione
-            eprintln!("Errore database nel recuperare i dettagli del professore (ID: {}): {}", professor_id, e);
-            return Err(Json(json!({ "status": "errore", "message": "Errore interno del server (dati utente)." })));
+            return Err(ErroreApi::interno(format!("DB nel recuperare i dettagli del professore (ID: {})", professor_id), e));
         }
     };
     let nome_prof = professor_details.Nome.as_deref().unwrap_or_else(|| {
         // Azione di fallback se Nome è NULL nel DB.
         // Potresti restituire una stringa vuota, "N/D", o gestire l'errore.
         // Per ora, usiamo una stringa vuota se è NULL, dato che Cognome è NOT NULL.
-        eprintln!("Attenzione: Nome del professore con ID {} è NULL nel database.", professor_id);
+        tracing::warn!(id_professore = professor_id, "Nome del professore NULL nel database");
         ""
     });
     let cognome_prof = &professor_details.Cognome; // Cognome è String (NOT NULL
     let nome_completo = format!("{} {}", nome_prof, cognome_prof).trim().to_string();
-    // 4. Genera il token JWT
-    let now = Utc::now();
-    let expiration = now + Duration::days(7);
-
-    let claims = Claims {
-        sub: professor_id.to_string(),
-        name: nome_completo.clone(),
-        exp: expiration.timestamp() as usize,
-    };
-
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .expect("Not present");
-
-    let token = match encode(&Header::default(), &claims, &EncodingKey::from_secret(jwt_secret.as_ref())) {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("Errore nella generazione del token JWT: {}", e);
-            return Err(Json(json!({ "status": "errore", "message": "Errore interno del server (generazione token)." })));
-        }
-    };
+    // 4. Genera il token JWT, con la lingua preferita se il professore l'ha scelta
+    let lingua_preferita = professor_details.Lingua.as_deref().and_then(Lingua::da_codice);
+    let token = auth_utils::emetti_token(professor_id, &nome_completo, models::Ruolo::from_db(&professor_details.Ruolo), lingua_preferita)?;
+    metriche::login(true);
 
     Ok(Json(LoginSuccessResponse {
-        message: "Login effettuato con successo!".to_string(),
+        message: lingua_preferita.unwrap_or(lingua).traduci("messaggi.login_effettuato", "Login effettuato con successo!"),
         token,
         user_id: professor_id,
         user_name: nome_completo,
+        lingua: lingua_preferita,
     }))
 }
 #[post("/auth/register", format = "json", data = "<payload>")]
 async fn register_professore(
-    db_pool: &State<MySqlPool>,
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
     payload: Json<models::RegistrazioneProfessorePayload>,
-) -> Result<status::Custom<Json<JsonValue>>, status::Custom<Json<JsonValue>>> {
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
 
     // --- Validazione Input Base ---
-    if payload.nome.trim().is_empty() ||
-        payload.cognome.trim().is_empty() ||
-        payload.email.trim().is_empty() ||
-        payload.password.is_empty() {
-        return Err(status::Custom(Status::BadRequest, Json(json!({
-            "status": "fallito", 
-            "message": "Nome, cognome, email e password sono obbligatori."
-        }))));
+    // Tutti i campi non validi insieme, così il form li evidenzia in una volta sola
+    let mut campi_non_validi = Vec::new();
+    for (campo, valore) in [("nome", payload.nome.trim()), ("cognome", payload.cognome.trim()), ("email", payload.email.trim())] {
+        if valore.is_empty() {
+            campi_non_validi.push(ErroreCampo::new(campo, "campo_obbligatorio", format!("Il campo {} è obbligatorio.", campo)));
+        }
     }
     if payload.password.len() < 8 {
-        return Err(status::Custom(Status::BadRequest, Json(json!({
-            "status": "fallito", 
-            "message": "La password deve essere di almeno 8 caratteri."
-        }))));
+        campi_non_validi.push(ErroreCampo::new("password", "password_troppo_corta", "La password deve essere di almeno 8 caratteri."));
     }
     if payload.materie_ids.is_empty() {
-        return Err(status::Custom(Status::BadRequest, Json(json!({
-            "status": "fallito", 
-            "message": "Seleziona almeno una materia insegnata."
-        }))));
+        campi_non_validi.push(ErroreCampo::new("materie_ids", "materie_obbligatorie", "Seleziona almeno una materia insegnata."));
+    }
+    if !campi_non_validi.is_empty() {
+        return Err(ErroreApi::CampiNonValidi(campi_non_validi));
     }
     // TODO: Aggiungere validazione più robusta per formato email.
 
@@ -322,204 +429,262 @@ async fn register_professore(
     let mut tx = match db_pool.begin().await {
         Ok(transaction) => transaction,
         Err(e) => {
-            eprintln!("Errore nell'iniziare la transazione DB per registrazione: {}", e);
-            return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore del server (transazione)."}))));
+            return Err(ErroreApi::interno("nell'iniziare la transazione DB per registrazione", e));
         }
     };
 
-    // 1. Controlla se l'email esiste già nella tabella Credenziali
-    let email_exists: bool = match sqlx::query_scalar::<_, bool>( // Specifica il tipo di ritorno atteso
-                                                                  "SELECT EXISTS(SELECT 1 FROM Credenziali WHERE email = ?)"
-    )
-        .bind(&payload.email)
-        .fetch_one(&mut *tx) // Usa la transazione
-        .await
-    {
+    // 1. Controlla se l'email esiste già nella tabella credenziali
+    let email_exists: bool = match db::professori::email_registrata(&mut *tx, &payload.email).await { // Usa la transazione
         Ok(exists) => exists,
         Err(e) => {
-            eprintln!("Errore DB nel controllare l'email esistente: {}", e);
             let _ = tx.rollback().await; // Non dimenticare il rollback
-            return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore del server durante la verifica email."}))));
+            return Err(ErroreApi::interno("DB nel controllare l'email esistente", e));
         }
     };
 
     if email_exists {
         let _ = tx.rollback().await;
-        return Err(status::Custom(Status::Conflict, Json(json!({"status": "fallito", "message": "L'email fornita è già registrata."}))));
+        return Err(ErroreApi::conflitto("email_gia_registrata", "L'email fornita è già registrata."));
     }
 
     // 2. Hasha la password
     let password_hash = match auth_utils::hash_password(&payload.password) {
         Ok(hash) => hash,
         Err(e) => {
-            eprintln!("Errore durante l'hashing della password: {}", e);
             let _ = tx.rollback().await;
-            return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore durante la preparazione della password."}))));
+            return Err(ErroreApi::interno("durante l'hashing della password", e));
         }
     };
 
     // 3. Inserisci nella tabella Professore
     let nome_prof = payload.nome.trim();
     let cognome_prof = payload.cognome.trim();
-    let insert_prof_result = sqlx::query!(
-        "INSERT INTO professore (Nome, Cognome) VALUES (?, ?)",
-        nome_prof, cognome_prof
-    )
-        .execute(&mut *tx) // Usa la transazione
-        .await;
+    let insert_prof_result = db::professori::inserisci(&mut *tx, nome_prof, cognome_prof).await; // Usa la transazione
 
     let id_professore_inserito = match insert_prof_result {
-        Ok(result) => result.last_insert_id() as i32,
+        Ok(id_professore) => id_professore,
         Err(e) => {
-            eprintln!("Errore DB nell'inserire il professore: {}", e);
             let _ = tx.rollback().await;
-            return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore durante la registrazione del professore."}))));
+            return Err(ErroreApi::interno("DB nell'inserire il professore", e));
         }
     };
 
-    // 4. Inserisci nella tabella Credenziali
-    if let Err(e) = sqlx::query!(
-        "INSERT INTO credenziali (Id_Professore_Cred, email, password_hash) VALUES (?, ?, ?)",
-        id_professore_inserito, payload.email, password_hash
-    )
-        .execute(&mut *tx) // Usa la transazione
-        .await {
-        eprintln!("Errore DB nell'inserire le credenziali: {}", e);
+    // 4. Inserisci nella tabella credenziali
+    if let Err(e) = db::professori::inserisci_credenziali(&mut *tx, id_professore_inserito, &payload.email, &password_hash).await { // Usa la transazione
         let _ = tx.rollback().await;
-        return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore durante la registrazione delle credenziali."}))));
+        return Err(ErroreApi::interno("DB nell'inserire le credenziali", e));
     }
 
     // 5. Inserisci nella tabella Insegna
     for id_materia in &payload.materie_ids {
-        if let Err(e) = sqlx::query!(
-            "INSERT INTO insegna (Id_Professore, Id_Materia) VALUES (?, ?)",
-            id_professore_inserito, id_materia
-        )
-            .execute(&mut *tx) // Usa la transazione
-            .await {
-            eprintln!("Errore DB nell'associare la materia {} al professore {}: {}", id_materia, id_professore_inserito, e);
+        if let Err(e) = db::professori::aggiungi_materia(&mut *tx, id_professore_inserito, *id_materia).await { // Usa la transazione
             let _ = tx.rollback().await;
-            return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore durante l'associazione delle materie."}))));
+            return Err(ErroreApi::interno(format!("DB nell'associare la materia {} al professore {}", id_materia, id_professore_inserito), e));
         }
     }
 
     // --- Commit della Transazione ---
     if let Err(e) = tx.commit().await {
-        eprintln!("Errore nel fare commit della transazione DB: {}", e);
-        return Err(status::Custom(Status::InternalServerError, Json(json!({"status": "errore", "message": "Errore finale nella registrazione."}))));
+        return Err(ErroreApi::interno("nel fare commit della transazione DB", e));
     }
 
     Ok(status::Custom(Status::Created, Json(json!({ // HTTP 201 Created
         "status": "successo",
-        "message": "Registrazione professore avvenuta con successo!",
+        "message": lingua.traduci("messaggi.registrazione_effettuata", "Registrazione professore avvenuta con successo!"),
         "id_professore": id_professore_inserito
     }))))
 }
 
-#[get("/materie")]
+// Con `professore` solo le materie che insegna (quelle che può indicare in una prenotazione)
+#[get("/materie?<professore>")]
 async fn get_materie(
-    db_pool: &State<MySqlPool>
-) -> Result<Json<Vec<models::MateriaApi>>, Json<JsonValue>> { // <-- TIPO DI RITORNO ATTESO
-    match sqlx::query_as!(
-        models::MateriaApi,
-        "SELECT Id_Materia, Nome, Descrizione FROM materia ORDER BY Nome ASC"
-
-    )
-        .fetch_all(db_pool.inner())
+    db_pool: &State<DbPool>,
+    professore: Option<i32>,
+) -> Result<Json<Vec<models::MateriaApi>>, ErroreApi> {
+    db::professori::materie(db_pool.inner(), professore)
         .await
-    { // Inizio del blocco match
-        Ok(materie) => Ok(Json(materie)), // Questo braccio restituisce il tipo corretto
-        Err(e) => { // Inizio del braccio Err
-            eprintln!("Errore nel recuperare le materie dal DB: {}", e);
-            // QUI È IL PROBLEMA PROBABILE: questo blocco deve restituire un Err del tipo atteso
-            Err(Json(json!({ "status": "errore", "message": "Impossibile caricare l'elenco delle materie." }))) // Restituisce il tipo corretto
-        } // Fine del braccio Err
-    } // Fine del blocco match <-- Se questo match non copre tutti i casi o se un braccio non restituisce, il match potrebbe restituire ()
+        .map(Json)
+        .map_err(|e| ErroreApi::interno("nel recuperare le materie dal DB", e))
 }
-#[get("/<path..>", rank = 10)]
-async fn frontend_catch_all(path: PathBuf) -> Option<NamedFile> {
-    println!("Catch-all (NamedFile) per path: {:?}", path);
-    // Assicurati che questo percorso sia corretto!
-    NamedFile::open(Path::new("frontend/dist/index.html")).await.ok()
+#[get("/<_path..>", rank = 10)]
+async fn frontend_catch_all(_path: PathBuf) -> Option<NamedFile> {
+    // Le route del frontend (es. /checkin/<id>) sono gestite dall'app: si serve sempre index.html
+    let cartella = configurazione::attuale().cartella_frontend.as_ref()?;
+    NamedFile::open(cartella.join("index.html")).await.ok()
 }
-#[get("/aulas")]
+#[get("/aulas?<tipo>&<plesso>")]
 async fn get_aule(
-    db_pool: &State<MySqlPool>
-) -> Result<Json<Vec<models::AulaApi>>, Json<JsonValue>> {
-    match sqlx::query_as!(
-        models::AulaApi,
-        "SELECT Id_Aula, Tipo_Aula, Numero FROM aula ORDER BY Tipo_Aula, Numero"
-    )
-        .fetch_all(db_pool.inner())
+    db_pool: &State<DbPool>,
+    tipo: Option<&str>,
+    plesso: Option<i32>,
+) -> Result<Json<Vec<models::AulaApi>>, ErroreApi> {
+    db::aule::elenco(db_pool.inner(), tipo, plesso)
         .await
-    {
-        Ok(aule) => Ok(Json(aule)),
-        Err(e) => {
-            eprintln!("Errore nel recuperare le aule dal DB: {}", e);
-            Err(Json(json!({ "status": "errore", "message": "Impossibile caricare l'elenco delle aule." })))
-        }
-    }
+        .map(Json)
+        .map_err(|e| ErroreApi::interno("nel recuperare le aule dal DB", e))
 }
 
-#[launch]
-async fn rocket() -> _ {
-    match dotenvy::dotenv() {
-        Ok(path) => println!("Variabili d'ambiente caricate da: {:?}", path),
-        Err(e) => eprintln!(" Errore nel caricare il file .env: {:?}. Le variabili d'ambiente potrebbero non essere impostate.", e),
+// Aule libere nell'intervallo richiesto: non bloccate, non inagibili, in un plesso aperto e con posti residui
+// secondo la loro politica (per le aule condivise "per studenti" si può indicare quanti)
+#[get("/aule/disponibili?<inizio>&<fine>&<tipo>&<plesso>&<studenti>")]
+async fn get_aule_disponibili(
+    db_pool: &State<DbPool>,
+    inizio: &str,
+    fine: &str,
+    tipo: Option<&str>,
+    plesso: Option<i32>,
+    studenti: Option<i32>,
+) -> Result<Json<Vec<models::AulaDisponibileApi>>, ErroreApi> {
+    let inizio = parse_data(inizio, "inizio")?;
+    let fine = parse_data(fine, "fine")?;
+    if fine <= inizio {
+        return Err(ErroreApi::campo("fine", "data_fine_non_successiva", "fine deve essere successiva a inizio."));
     }
-    let password_da_hashare = "ciao";
-    let password_bytes = password_da_hashare.as_bytes();
-
-    // Genera un sale casuale
-    let salt = SaltString::generate(&mut OsRng);
 
-    let argon2 = Argon2::default();
+    let errore_interno = |e: sqlx::Error| {
+        ErroreApi::interno("nel calcolare le aule disponibili", e)
+    };
 
-    match argon2.hash_password(password_bytes, &salt) {
-        Ok(password_hash_object) => {
-            let password_hash_string = password_hash_object.to_string();
-            println!("Password originale: {}", password_da_hashare);
-            println!("Hash da memorizzare nel database: {}", password_hash_string);
+    let aule = db::aule::elenco(db_pool.inner(), tipo, plesso)
+        .await
+        .map_err(errore_interno)?;
+    let occorrenze = blocchi::occorrenze_nel_periodo(db_pool.inner(), None, inizio, fine)
+        .await
+        .map_err(errore_interno)?;
+    let inagibili = guasti::aule_inagibili(db_pool.inner()).await.map_err(errore_interno)?;
+    let chiusi = plessi::plessi_chiusi(db_pool.inner(), inizio, fine).await.map_err(errore_interno)?;
+
+    let mut conn = db_pool.acquire().await.map_err(errore_interno)?;
+    let mut disponibili = Vec::new();
+    for aula in aule {
+        if occorrenze.iter().any(|o| o.id_aula == aula.Id_Aula)
+            || inagibili.contains(&aula.Id_Aula)
+            || chiusi.contains(&aula.Id_Plesso)
+        {
+            continue;
         }
-        Err(e) => {
-            eprintln!("Errore durante l'hashing della password: {}", e);
+        let politica = prenotazioni::PoliticaAula::from_db(&aula.Politica_Prenotazione, aula.Capacita);
+        let residui = prenotazioni::posti_residui(&mut conn, aula.Id_Aula, &politica, inizio, fine)
+            .await
+            .map_err(errore_interno)?;
+        if residui >= politica.consumo(studenti) {
+            disponibili.push(models::AulaDisponibileApi {
+                Id_Aula: aula.Id_Aula,
+                Tipo_Aula: aula.Tipo_Aula,
+                Numero: aula.Numero,
+                Politica_Prenotazione: politica.politica,
+                Capacita: aula.Capacita,
+                Id_Plesso: aula.Id_Plesso,
+                Posti_Residui: residui,
+            });
         }
     }
-    let database_url = std::env::var("DATABASE_URL")
-        .expect(
-            "Link database incorretto"
-        );
-
-    let db_pool = match MySqlPool::connect(&database_url).await {
-        Ok(pool) => {
-            println!("✅ Connessione al database stabilita con successo!");
-            pool
-        }
+    Ok(Json(disponibili))
+}
+
+#[put("/aule/<id_aula>/politica", format = "json", data = "<payload>")]
+async fn imposta_politica_aula(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: auth_utils::UtenteAutenticato,
+    id_aula: i32,
+    payload: Json<models::PoliticaAulaPayload>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_politica_aula", "Solo un amministratore può modificare la politica di un'aula.")?;
+    let capacita = match (payload.politica, payload.capacita) {
+        (models::PoliticaPrenotazione::Esclusiva, _) => None,
+        (_, Some(n)) if n >= 1 => Some(n),
+        _ => return Err(ErroreApi::campo("Capacita", "capacita_non_valida", "Le aule condivise richiedono una Capacita di almeno 1.")),
+    };
+
+    match db::aule::imposta_politica(db_pool.inner(), id_aula, payload.politica.as_db(), capacita, payload.richiede_approvazione).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.politica_aula_aggiornata", "Politica dell'aula aggiornata.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata.")),
+        // Su PostgreSQL un'aula non diventa esclusiva finché ha prenotazioni attive sovrapposte
+        Err(e) if db::violazione_sovrapposizione(&e) => Err(ErroreApi::conflitto("aula_con_prenotazioni_sovrapposte", "L'aula ha prenotazioni future sovrapposte: risolvile prima di renderla esclusiva.")),
         Err(e) => {
-            eprintln!("❌ Impossibile connettersi al database: {:?}", e);
+            Err(ErroreApi::interno(format!("DB nell'aggiornare la politica dell'aula {}", id_aula), e))
+        }
+    }
+}
 
-            std::process::exit(1);
+#[rocket::main]
+async fn main() -> ExitCode {
+    let server = match rocket().await {
+        Ok(server) => server,
+        Err(messaggio) => {
+            tracing::error!("{}", messaggio);
+            return ExitCode::FAILURE;
         }
     };
+    if let Err(e) = server.launch().await {
+        tracing::error!(errore = %e, "Il server si è fermato con un errore");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
 
-    let cors = rocket_cors::CorsOptions::default()
-        .allowed_origins(rocket_cors::AllowedOrigins::some_regex(&[
+async fn rocket() -> Result<Rocket<Build>, String> {
+    // Il .env va letto prima della configurazione, che comprende anche i log
+    let file_env = dotenvy::dotenv();
+    let figment = configurazione::figment();
+    let caricata = configurazione::carica(&figment);
+    match &caricata {
+        Ok(caricata) => registro::inizializza(&caricata.log_livello, caricata.log_json),
+        Err(_) => registro::inizializza(registro::LIVELLO_PREDEFINITO, false),
+    }
+    match file_env {
+        Ok(path) => tracing::info!(percorso = ?path, "Variabili d'ambiente caricate dal file .env"),
+        Err(e) => tracing::debug!(errore = ?e, "Nessun file .env caricato"),
+    }
+    let configurazione = configurazione::imposta(caricata?);
+
+    // Senza CORS_ORIGINI si accetta il server di sviluppo del frontend su localhost
+    let origini = match &configurazione.cors_origini {
+        Some(origini) => rocket_cors::AllowedOrigins::some_exact(origini),
+        None => rocket_cors::AllowedOrigins::some_regex(&[
             r"^http://localhost:[\d]+$",
             r"^http://127.0.0.1:[\d]+$"
-        ]))
+        ]),
+    };
+    let cors = rocket_cors::CorsOptions::default()
+        .allowed_origins(origini)
         .allow_credentials(true)
         .to_cors()
-        .expect("Errore nella creazione della configurazione CORS.");
+        .map_err(|e| format!("Configurazione non valida:\n  - CORS_ORIGINI: {}", e))?;
 
-    println!("🚀 Avvio del server Rocket...");
-    println!("Le API saranno disponibili sotto /api");
-    println!("Il frontend (in produzione) sarà servito dalla root /");
-    println!("Assicurati di aver eseguito 'npm run build' nella cartella 'frontend'");
-    println!("E che la build del frontend sia in 'frontend/dist/' relativa alla CWD del backend");
+    let db_pool = db::connetti_con_riprova(&configurazione.database_url, configurazione.attesa_avvio_database)
+        .await
+        .map_err(|e| format!("Impossibile connettersi al database: {}", e))?;
+    tracing::info!("Connessione al database stabilita");
+
+    // Schema versionato in migrations/<backend>/
+    if configurazione.migrazioni_automatiche {
+        db::migra(&db_pool)
+            .await
+            .map_err(|e| format!("Impossibile applicare le migrazioni del database: {}", e))?;
+        tracing::info!("Schema del database aggiornato");
+    }
 
+    // Server CalDAV su una porta separata
+    if let Some(indirizzo) = configurazione.caldav_indirizzo {
+        tokio::spawn(caldav::avvia(db_pool.clone(), indirizzo));
+    }
 
-    rocket::build()
+    // Consegna in background dei webhook accodati
+    tokio::spawn(webhook::avvia_consegne(db_pool.clone()));
+    // Eliminazione periodica degli eventi già serviti agli aggiornamenti in tempo reale
+    tokio::spawn(aggiornamenti::avvia_pulizia(db_pool.clone()));
+    // Invio delle notifiche email accodate (disattivato se SMTP_HOST non è impostato)
+    tokio::spawn(notifiche::avvia_invio(db_pool.clone()));
+    // Promemoria prima delle prenotazioni e riepilogo serale
+    tokio::spawn(promemoria::avvia_pianificatore(db_pool.clone()));
+    // Rilascio delle prenotazioni senza check-in nelle aule con il codice QR
+    tokio::spawn(checkin::avvia_rilascio(db_pool.clone()));
+
+    let mut server = rocket::custom(figment)
         .manage(db_pool) 
+        .attach(registro::Tracciamento)
         .attach(cors)
         .mount("/api", routes![
             hello_api, 
@@ -527,9 +692,75 @@ async fn rocket() -> _ {
             register_professore,
             get_prenotazioni,
             creare_prenotazione,
+            annulla_prenotazione,
+            modifica_prenotazione,
+            approva_prenotazione,
             get_aule,
+            get_aule_disponibili,
+            imposta_politica_aula,
             get_materie,
+            blocchi::crea_blocco,
+            blocchi::get_blocchi_aula,
+            blocchi::elimina_blocco,
+            guasti::crea_segnalazione,
+            guasti::get_segnalazioni,
+            guasti::aggiorna_segnalazione,
+            attrezzature::get_attrezzature,
+            attrezzature::get_attrezzature_disponibili,
+            plessi::get_plessi,
+            plessi::crea_plesso,
+            plessi::aggiorna_plesso,
+            plessi::get_orario_plesso,
+            plessi::aggiorna_orario_plesso,
+            plessi::get_chiusure_plesso,
+            plessi::crea_chiusura_plesso,
+            plessi::elimina_chiusura_plesso,
+            calendari::get_token_feed,
+            calendari::rigenera_token_feed,
+            calendari::revoca_token_feed,
+            calendari::feed_professore,
+            calendari::feed_aula,
+            calendari::feed_tipo_aula,
+            importazione::importa_ics,
+            webhook::get_webhook,
+            webhook::crea_webhook,
+            webhook::modifica_webhook,
+            webhook::elimina_webhook,
+            webhook::get_consegne_webhook,
+            webhook::riprova_consegna,
+            aggiornamenti::stream_prenotazioni,
+            notifiche::get_preferenze_notifiche,
+            notifiche::aggiorna_preferenze_notifiche,
+            stampa::pdf_settimana_aula,
+            stampa::pdf_settimana_aule,
+            esportazione::esporta_csv,
+            esportazione::esporta_xlsx,
+            chiosco::stato_chiosco,
+            chiosco::pagina_chiosco,
+            chiosco::prenota_da_chiosco,
+            chiosco::imposta_pin_chiosco,
+            chiosco::elimina_pin_chiosco,
+            chiosco::get_dispositivi_chiosco,
+            chiosco::crea_dispositivo_chiosco,
+            chiosco::elimina_dispositivo_chiosco,
+            checkin::check_in,
+            checkin::qr_aula,
+            checkin::rigenera_qr_aula,
+            checkin::disattiva_check_in_aula,
+            checkin::report_assenze,
+            traduzioni::get_lingua_profilo,
+            traduzioni::imposta_lingua_profilo,
         ])
-        .mount("/", FileServer::from("frontend/dist").rank(5)) 
-        .mount("/", routes![frontend_catch_all])
+        .mount("/", routes![metriche::esponi, salute::vivo, salute::pronto])
+        .register("/api", errori::catchers());
+    match &configurazione.cartella_frontend {
+        Some(cartella) => {
+            tracing::info!(cartella = %cartella.display(), "Avvio del server: API sotto /api, frontend dalla root /");
+            server = server
+                .mount("/", FileServer::from(cartella).rank(5))
+                .mount("/", routes![frontend_catch_all]);
+        }
+        None => tracing::info!("Avvio del server: solo API sotto /api, frontend non servito"),
+    }
+    Ok(server)
 }
\ No newline at end of file
@@ -0,0 +1,179 @@
+// src/metriche.rs
+// Metriche in formato Prometheus su GET /metrics: richieste e latenza per route, connessioni del pool,
+// login, prenotazioni e conflitti, esito dei lavori in background e delle consegne di email e webhook.
+// METRICHE_RETI è l'elenco, separato da virgole, delle reti da cui /metrics risponde (predefinito solo
+// localhost, es. "10.20.0.0/16,127.0.0.1"). Conta l'indirizzo della connessione, non X-Real-IP,
+// che il client può impostare a piacere.
+
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+use ipnet::IpNet;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+
+use crate::configurazione;
+use crate::db::DbPool;
+use crate::models::TipoEvento;
+
+struct Metriche {
+    registro: Registry,
+    richieste: IntCounterVec,
+    durata_richieste: HistogramVec,
+    connessioni: IntGaugeVec,
+    connessioni_massime: IntGauge,
+    login: IntCounterVec,
+    prenotazioni: IntCounterVec,
+    conflitti: IntCounterVec,
+    lavori: IntCounterVec,
+    consegne: IntCounterVec,
+}
+
+fn contatore(registro: &Registry, nome: &str, descrizione: &str, etichette: &[&str]) -> IntCounterVec {
+    let contatore = IntCounterVec::new(Opts::new(nome, descrizione), etichette).expect("Metrica non valida");
+    registro.register(Box::new(contatore.clone())).expect("Metrica registrata due volte");
+    contatore
+}
+
+impl Metriche {
+    fn new() -> Metriche {
+        let registro = Registry::new();
+        let durata_richieste = HistogramVec::new(
+            HistogramOpts::new("planner_durata_richieste_http_secondi", "Durata delle richieste HTTP per route"),
+            &["metodo", "route"],
+        )
+            .expect("Metrica non valida");
+        registro.register(Box::new(durata_richieste.clone())).expect("Metrica registrata due volte");
+        let connessioni = IntGaugeVec::new(
+            Opts::new("planner_db_connessioni", "Connessioni al database aperte dal pool"),
+            &["stato"],
+        )
+            .expect("Metrica non valida");
+        registro.register(Box::new(connessioni.clone())).expect("Metrica registrata due volte");
+        let connessioni_massime = IntGauge::new("planner_db_connessioni_massime", "Connessioni massime del pool").expect("Metrica non valida");
+        registro.register(Box::new(connessioni_massime.clone())).expect("Metrica registrata due volte");
+
+        Metriche {
+            richieste: contatore(&registro, "planner_richieste_http_totale", "Richieste HTTP per route e stato", &["metodo", "route", "stato"]),
+            durata_richieste,
+            connessioni,
+            connessioni_massime,
+            login: contatore(&registro, "planner_login_totale", "Tentativi di login per esito", &["esito"]),
+            prenotazioni: contatore(&registro, "planner_prenotazioni_totale", "Prenotazioni create, modificate, annullate, approvate o rilasciate", &["evento"]),
+            conflitti: contatore(&registro, "planner_conflitti_totale", "Operazioni rifiutate per un conflitto, per codice d'errore", &["codice"]),
+            lavori: contatore(&registro, "planner_lavori_totale", "Giri dei lavori in background per esito", &["lavoro", "esito"]),
+            consegne: contatore(&registro, "planner_consegne_totale", "Tentativi di consegna di email e webhook per esito", &["canale", "esito"]),
+            registro,
+        }
+    }
+}
+
+fn metriche() -> &'static Metriche {
+    static METRICHE: OnceLock<Metriche> = OnceLock::new();
+    METRICHE.get_or_init(Metriche::new)
+}
+
+// `route` è il modello della route (es. "/api/prenotazioni/<id_prenotazione>"), così gli id non moltiplicano le serie
+pub fn richiesta(metodo: &str, route: &str, stato: u16, secondi: f64) {
+    let metriche = metriche();
+    metriche.richieste.with_label_values(&[metodo, route, &stato.to_string()]).inc();
+    metriche.durata_richieste.with_label_values(&[metodo, route]).observe(secondi);
+}
+
+pub fn login(riuscito: bool) {
+    metriche().login.with_label_values(&[if riuscito { "successo" } else { "fallito" }]).inc();
+}
+
+pub fn prenotazione(tipo: TipoEvento) {
+    metriche().prenotazioni.with_label_values(&[tipo.as_db()]).inc();
+}
+
+pub fn prenotazione_rilasciata() {
+    metriche().prenotazioni.with_label_values(&["rilasciata"]).inc();
+}
+
+pub fn conflitto(codice: &str) {
+    metriche().conflitti.with_label_values(&[codice]).inc();
+}
+
+pub fn lavoro<T, E>(lavoro: &str, esito: &Result<T, E>) {
+    metriche().lavori.with_label_values(&[lavoro, if esito.is_ok() { "successo" } else { "errore" }]).inc();
+}
+
+// `esito`: "riuscita", "ripianificata" (nuovo tentativo più tardi) o "fallita" (tentativi esauriti)
+pub fn consegna(canale: &str, esito: &str) {
+    metriche().consegne.with_label_values(&[canale, esito]).inc();
+}
+
+// Richiesta arrivata da una delle reti di monitoraggio
+pub struct ReteMonitoraggio;
+
+fn in_reti(reti: &[IpNet], indirizzo: IpAddr) -> bool {
+    // Con un socket IPv6 i client IPv4 arrivano come ::ffff:a.b.c.d
+    let indirizzo = indirizzo.to_canonical();
+    reti.iter().any(|rete| rete.contains(&indirizzo))
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ReteMonitoraggio {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let ammessa = req.remote().is_some_and(|indirizzo| in_reti(&configurazione::attuale().metriche_reti, indirizzo.ip()));
+        if ammessa {
+            Outcome::Success(ReteMonitoraggio)
+        } else {
+            Outcome::Error((Status::Forbidden, ()))
+        }
+    }
+}
+
+#[get("/metrics")]
+pub fn esponi(db_pool: &State<DbPool>, _rete: ReteMonitoraggio) -> Result<(ContentType, String), Status> {
+    let metriche = metriche();
+    // Lo stato del pool si legge al momento della raccolta
+    let aperte = db_pool.size() as i64;
+    let inattive = db_pool.num_idle() as i64;
+    metriche.connessioni.with_label_values(&["in_uso"]).set(aperte - inattive);
+    metriche.connessioni.with_label_values(&["inattive"]).set(inattive);
+    metriche.connessioni_massime.set(db_pool.options().get_max_connections() as i64);
+
+    let mut testo = Vec::new();
+    TextEncoder::new().encode(&metriche.registro.gather(), &mut testo).map_err(|e| {
+        tracing::error!(errore = %e, "Errore nel codificare le metriche");
+        Status::InternalServerError
+    })?;
+    let testo = String::from_utf8(testo).map_err(|_| Status::InternalServerError)?;
+    Ok((ContentType::new("text", "plain").with_params(("version", "0.0.4")), testo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reti(elenco: &[&str]) -> Vec<IpNet> {
+        elenco.iter().map(|rete| rete.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn solo_le_reti_configurate_vedono_le_metriche() {
+        let reti = reti(&["10.20.0.0/16", "127.0.0.1/32", "fd00::/8"]);
+        for ammesso in ["10.20.3.4", "127.0.0.1", "fd00::1"] {
+            assert!(in_reti(&reti, ammesso.parse().unwrap()), "{} doveva essere ammesso", ammesso);
+        }
+        for escluso in ["10.21.0.1", "127.0.0.2", "192.168.1.1", "2001:db8::1", "::1"] {
+            assert!(!in_reti(&reti, escluso.parse().unwrap()), "{} doveva essere escluso", escluso);
+        }
+    }
+
+    #[test]
+    fn un_client_ipv4_su_socket_ipv6_vale_come_ipv4() {
+        let reti = reti(&["10.20.0.0/16"]);
+        assert!(in_reti(&reti, "::ffff:10.20.3.4".parse().unwrap()));
+        assert!(!in_reti(&reti, "::ffff:192.168.1.1".parse().unwrap()));
+        // Un indirizzo IPv6 qualsiasi non diventa IPv4
+        assert!(!in_reti(&reti, "::10.20.3.4".parse().unwrap()));
+    }
+}
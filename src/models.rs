@@ -1,9 +1,13 @@
 //models.rs
+// I campi seguono i nomi delle colonne del database (Id_Prenotazione, Data_Inizio...)
+#![allow(non_snake_case)]
 
-use chrono::{ NaiveDateTime}; // Per gestire e formattare le date
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime}; // Per gestire e formattare le date
 use rocket::serde::{Serialize};
 use serde::Deserialize;
 use sqlx::FromRow;
+
+use crate::traduzioni::Lingua;
 // Serialize per la risposta
 
 // Struct per i dati letti dalla query al database
@@ -12,10 +16,21 @@ pub struct PrenotazioneDb {
     pub Id_Prenotazione: i32,
     pub Data_Inizio: NaiveDateTime,   // <-- DEVE ESSERE NaiveDateTime
     pub Data_Fine: NaiveDateTime,     // <-- DEVE ESSERE NaiveDateTime
-    pub Tipo_Aula: String,
-    pub Numero_Aula: i32,
+    pub Id_Aula: Option<i32>,
+    pub Tipo_Aula: Option<String>,   // NULL per le prenotazioni di sola attrezzatura
+    pub Numero_Aula: Option<i32>,
+    pub Politica_Prenotazione: Option<String>,
+    pub Capacita: Option<i32>,
+    pub Numero_Studenti: Option<i32>,
+    pub Nome_Plesso: Option<String>,
+    pub Id_Professore: i32,
+    pub Stato: String,                // attiva, annullata
+    pub Sequenza: i32,                // Incrementata a ogni modifica (SEQUENCE nei feed iCalendar)
+    pub Data_Modifica: NaiveDateTime,
+    pub Approvata: Option<bool>,      // NULL se l'aula non richiede approvazione
     pub Nome_Professore: Option<String>,
     pub Cognome_Professore: String,
+    pub Materia: Option<String>,
 }
 // In models.rs o dove hai le struct per le risposte API
 #[derive(Serialize, FromRow, Debug)]
@@ -49,19 +64,58 @@ pub struct CalendarEventApi {
     pub(crate) start: String,
     pub(crate) end: String,
     pub(crate) allDay: bool,
-    // Puoi aggiungere altri campi come backgroundColor, borderColor se vuoi
+    // "background" per i blocchi di indisponibilità, assente per le prenotazioni
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) backgroundColor: Option<String>,
+    // Solo per le aule condivise: prenotazioni o studenti ancora accoglibili
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) capacitaResidua: Option<i32>,
+    // Prenotazione ancora da svolgere in un'aula resa non prenotabile da un guasto
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) guastoBloccante: bool,
 }
 #[derive(Deserialize, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct NuovaPrenotazionePayload {
     #[serde(rename = "Id_Professore")] // Per matchare il case del JSON dal frontend
     pub(crate) id_professore: i32,
-    #[serde(rename = "Id_Aula")]
-    pub(crate) id_aula: i32,
+    #[serde(rename = "Id_Aula", default)]
+    pub(crate) id_aula: Option<i32>, // Assente se si prenota solo attrezzatura
     #[serde(rename = "Data_Inizio")]
     pub(crate) data_inizio: String, // Riceviamo come stringa ISO 8601 dal frontend
     #[serde(rename = "Data_Fine")]
     pub(crate) data_fine: String,   // Riceviamo come stringa ISO 8601 dal frontend
+    #[serde(rename = "Attrezzature", default)]
+    pub(crate) attrezzature: Vec<AttrezzaturaRichiesta>,
+    #[serde(rename = "Numero_Studenti", default)]
+    pub(crate) numero_studenti: Option<i32>, // Usato dalle aule condivise "per studenti"
+    #[serde(rename = "Id_Materia", default)]
+    pub(crate) id_materia: Option<i32>,
+}
+// Nuovo orario (ed eventualmente nuova aula) di una prenotazione esistente; l'attrezzatura resta la stessa
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ModificaPrenotazionePayload {
+    #[serde(rename = "Id_Aula", default)]
+    pub(crate) id_aula: Option<i32>,
+    #[serde(rename = "Data_Inizio")]
+    pub(crate) data_inizio: String,
+    #[serde(rename = "Data_Fine")]
+    pub(crate) data_fine: String,
+    #[serde(rename = "Numero_Studenti", default)]
+    pub(crate) numero_studenti: Option<i32>,
+}
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AttrezzaturaRichiesta {
+    pub Id_Attrezzatura: i32,
+    #[serde(default = "quantita_predefinita")]
+    pub Quantita: i32,
+}
+fn quantita_predefinita() -> i32 {
+    1
 }
 #[derive(Serialize, FromRow, Debug)] // FromRow per sqlx, Serialize per la risposta JSON
 #[serde(crate = "rocket::serde")]
@@ -72,6 +126,720 @@ pub struct AulaApi { // Nome diverso da Aula del DB se i campi sono diversi
     pub Tipo_Aula: String,      // Corrisponde a AulaInfo.Tipo_Aula
     #[sqlx(rename = "Numero")]
     pub Numero: i32,            // Corrisponde a AulaInfo.Numero
+    pub Politica_Prenotazione: String, // esclusiva, condivisa_prenotazioni, condivisa_studenti
+    pub Capacita: Option<i32>,
+    pub Id_Plesso: i32,
+    pub Richiede_Approvazione: bool, // Le prenotazioni restano "da approvare" finché un amministratore non le conferma
     // Aggiungi qui il campo Nome_Aula se lo hai aggiunto alla tabella Aula
     // pub Nome_Aula: Option<String>, // Esempio
-}
\ No newline at end of file
+}
+// Blocchi di indisponibilità di un'aula (manutenzione, chiusure, lavori...)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum Ricorrenza {
+    Nessuna,
+    Giornaliera,
+    Settimanale,
+}
+
+impl Ricorrenza {
+    // Valore memorizzato nella colonna ENUM `Ricorrenza` della tabella blocco_aula
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            Ricorrenza::Nessuna => "nessuna",
+            Ricorrenza::Giornaliera => "giornaliera",
+            Ricorrenza::Settimanale => "settimanale",
+        }
+    }
+
+    pub fn from_db(valore: &str) -> Ricorrenza {
+        match valore {
+            "giornaliera" => Ricorrenza::Giornaliera,
+            "settimanale" => Ricorrenza::Settimanale,
+            _ => Ricorrenza::Nessuna,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct BloccoAulaDb {
+    pub Id_Blocco: i32,
+    pub Id_Aula: i32,
+    pub Data_Inizio: NaiveDateTime, // UTC, come per prenotazione
+    pub Data_Fine: NaiveDateTime,
+    pub Motivo: String,
+    pub Ricorrenza: String,
+    pub Ricorrenza_Fine: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct NuovoBloccoPayload {
+    #[serde(rename = "Data_Inizio")]
+    pub(crate) data_inizio: String, // ISO 8601, come per le prenotazioni
+    #[serde(rename = "Data_Fine")]
+    pub(crate) data_fine: String,
+    #[serde(rename = "Motivo")]
+    pub(crate) motivo: String,
+    #[serde(rename = "Ricorrenza", default)]
+    pub(crate) ricorrenza: Option<Ricorrenza>,
+    #[serde(rename = "Ricorrenza_Fine", default)]
+    pub(crate) ricorrenza_fine: Option<String>, // Se assente la ricorrenza non ha fine
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct BloccoAulaApi {
+    pub Id_Blocco: i32,
+    pub Id_Aula: i32,
+    pub Data_Inizio: String,
+    pub Data_Fine: String,
+    pub Motivo: String,
+    pub Ricorrenza: Ricorrenza,
+    pub Ricorrenza_Fine: Option<String>,
+}
+
+// Prenotazione esistente che cade dentro un blocco appena creato
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PrenotazioneSovrappostaApi {
+    pub Id_Prenotazione: i32,
+    pub Id_Professore: i32,
+    pub Data_Inizio: String,
+    pub Data_Fine: String,
+}
+
+// Ruolo dell'utente, memorizzato nella colonna `Ruolo` della tabella professore
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum Ruolo {
+    #[default]
+    Docente,
+    Tecnico,
+    Admin,
+}
+
+impl Ruolo {
+    pub fn from_db(valore: &str) -> Ruolo {
+        match valore {
+            "tecnico" => Ruolo::Tecnico,
+            "admin" => Ruolo::Admin,
+            _ => Ruolo::Docente,
+        }
+    }
+}
+
+// Attrezzatura presente in un'aula (proiettori, LIM, microscopi...)
+#[derive(Serialize, FromRow, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct AttrezzaturaApi {
+    pub Id_Attrezzatura: i32,
+    pub Nome: String,
+    pub Id_Aula: Option<i32>, // NULL se l'attrezzatura non è installata in un'aula
+    pub Quantita: i32,        // Unità disponibili in totale (es. 3 carrelli portatili)
+    pub Prenotabile: bool,    // TRUE per le risorse mobili prenotabili
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct AttrezzaturaDisponibileApi {
+    pub Id_Attrezzatura: i32,
+    pub Nome: String,
+    pub Quantita: i32,
+    pub Disponibili: i32, // Unità libere per tutto l'intervallo richiesto
+}
+
+// Segnalazioni di guasto su aule e attrezzature
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum Gravita {
+    Bassa,
+    Media,
+    Alta,
+}
+
+impl Gravita {
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            Gravita::Bassa => "bassa",
+            Gravita::Media => "media",
+            Gravita::Alta => "alta",
+        }
+    }
+
+    pub fn from_db(valore: &str) -> Gravita {
+        match valore {
+            "alta" => Gravita::Alta,
+            "media" => Gravita::Media,
+            _ => Gravita::Bassa,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum StatoGuasto {
+    Aperta,
+    InLavorazione,
+    Risolta,
+}
+
+impl StatoGuasto {
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            StatoGuasto::Aperta => "aperta",
+            StatoGuasto::InLavorazione => "in_lavorazione",
+            StatoGuasto::Risolta => "risolta",
+        }
+    }
+
+    pub fn from_db(valore: &str) -> StatoGuasto {
+        match valore {
+            "in_lavorazione" => StatoGuasto::InLavorazione,
+            "risolta" => StatoGuasto::Risolta,
+            _ => StatoGuasto::Aperta,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct SegnalazioneGuastoDb {
+    pub Id_Segnalazione: i32,
+    pub Id_Aula: Option<i32>,
+    pub Id_Attrezzatura: Option<i32>,
+    pub Id_Segnalatore: i32,
+    pub Gravita: String,
+    pub Descrizione: String,
+    pub Stato: String,
+    pub Blocca_Aula: bool,
+    pub Note_Tecnico: Option<String>,
+    pub Data_Creazione: NaiveDateTime,
+    pub Data_Aggiornamento: NaiveDateTime,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct SegnalazioneGuastoApi {
+    pub Id_Segnalazione: i32,
+    pub Id_Aula: Option<i32>,
+    pub Id_Attrezzatura: Option<i32>,
+    pub Id_Segnalatore: i32,
+    pub Gravita: Gravita,
+    pub Descrizione: String,
+    pub Stato: StatoGuasto,
+    pub Blocca_Aula: bool,
+    pub Note_Tecnico: Option<String>,
+    pub Data_Creazione: String,
+    pub Data_Aggiornamento: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct NuovaSegnalazionePayload {
+    #[serde(rename = "Id_Aula", default)]
+    pub(crate) id_aula: Option<i32>,
+    #[serde(rename = "Id_Attrezzatura", default)]
+    pub(crate) id_attrezzatura: Option<i32>,
+    #[serde(rename = "Gravita")]
+    pub(crate) gravita: Gravita,
+    #[serde(rename = "Descrizione")]
+    pub(crate) descrizione: String,
+    #[serde(rename = "Blocca_Aula", default)]
+    pub(crate) blocca_aula: bool, // Solo per la gravità alta, e solo se la apre un tecnico o un amministratore
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct AggiornamentoSegnalazionePayload {
+    #[serde(rename = "Stato")]
+    pub(crate) stato: StatoGuasto,
+    #[serde(rename = "Note_Tecnico", default)]
+    pub(crate) note_tecnico: Option<String>,
+    #[serde(rename = "Blocca_Aula", default)]
+    pub(crate) blocca_aula: Option<bool>,
+}
+
+// Come un'aula accetta prenotazioni sovrapposte (biblioteca e palestra possono ospitare più classi)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum PoliticaPrenotazione {
+    Esclusiva,
+    CondivisaPrenotazioni, // Fino a Capacita prenotazioni contemporanee
+    CondivisaStudenti,     // Fino a Capacita studenti contemporanei
+}
+
+impl PoliticaPrenotazione {
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            PoliticaPrenotazione::Esclusiva => "esclusiva",
+            PoliticaPrenotazione::CondivisaPrenotazioni => "condivisa_prenotazioni",
+            PoliticaPrenotazione::CondivisaStudenti => "condivisa_studenti",
+        }
+    }
+
+    pub fn from_db(valore: &str) -> PoliticaPrenotazione {
+        match valore {
+            "condivisa_prenotazioni" => PoliticaPrenotazione::CondivisaPrenotazioni,
+            "condivisa_studenti" => PoliticaPrenotazione::CondivisaStudenti,
+            _ => PoliticaPrenotazione::Esclusiva,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PoliticaAulaPayload {
+    #[serde(rename = "Politica_Prenotazione")]
+    pub(crate) politica: PoliticaPrenotazione,
+    #[serde(rename = "Capacita", default)]
+    pub(crate) capacita: Option<i32>, // Obbligatoria per le politiche condivise
+    #[serde(rename = "Richiede_Approvazione", default)]
+    pub(crate) richiede_approvazione: Option<bool>, // Se assente resta invariato
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct AulaDisponibileApi {
+    pub Id_Aula: i32,
+    pub Tipo_Aula: String,
+    pub Numero: i32,
+    pub Politica_Prenotazione: PoliticaPrenotazione,
+    pub Capacita: Option<i32>,
+    pub Id_Plesso: i32,
+    pub Posti_Residui: i32, // 1 per un'aula esclusiva libera
+}
+
+// Plessi (sedi) dell'istituto: ogni aula appartiene a un plesso
+#[derive(Serialize, FromRow, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PlessoApi {
+    pub Id_Plesso: i32,
+    pub Nome: String,
+    pub Indirizzo: Option<String>,
+}
+
+// Un modulo dell'orario delle lezioni di un plesso (es. modulo 1, 8:30 - 9:20)
+#[derive(Serialize, FromRow, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ModuloOrarioApi {
+    pub Numero_Modulo: i32,
+    pub Ora_Inizio: NaiveTime,
+    pub Ora_Fine: NaiveTime,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PlessoPayload {
+    #[serde(rename = "Nome")]
+    pub(crate) nome: String,
+    #[serde(rename = "Indirizzo", default)]
+    pub(crate) indirizzo: Option<String>,
+}
+
+// Un modulo dell'orario da salvare, in ora locale (HH:MM)
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ModuloOrarioPayload {
+    #[serde(rename = "Numero_Modulo")]
+    pub(crate) numero_modulo: i32,
+    #[serde(rename = "Ora_Inizio")]
+    pub(crate) ora_inizio: NaiveTime,
+    #[serde(rename = "Ora_Fine")]
+    pub(crate) ora_fine: NaiveTime,
+}
+
+#[derive(Serialize, FromRow, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ChiusuraPlessoApi {
+    pub Id_Chiusura: i32,
+    pub Id_Plesso: i32,
+    pub Data_Inizio: NaiveDate, // Giorni di calendario locali, estremi inclusi
+    pub Data_Fine: NaiveDate,
+    pub Motivo: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct NuovaChiusuraPayload {
+    #[serde(rename = "Data_Inizio")]
+    pub(crate) data_inizio: NaiveDate, // YYYY-MM-DD
+    #[serde(rename = "Data_Fine")]
+    pub(crate) data_fine: NaiveDate,
+    #[serde(rename = "Motivo")]
+    pub(crate) motivo: String,
+}
+
+// Eventi del ciclo di vita di una prenotazione a cui ci si può iscrivere con un webhook
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde")]
+pub enum TipoEvento {
+    #[serde(rename = "prenotazione.creata")]
+    Creata,
+    #[serde(rename = "prenotazione.modificata")]
+    Modificata,
+    #[serde(rename = "prenotazione.annullata")]
+    Annullata,
+    #[serde(rename = "prenotazione.approvata")]
+    Approvata,
+}
+
+impl TipoEvento {
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            TipoEvento::Creata => "prenotazione.creata",
+            TipoEvento::Modificata => "prenotazione.modificata",
+            TipoEvento::Annullata => "prenotazione.annullata",
+            TipoEvento::Approvata => "prenotazione.approvata",
+        }
+    }
+
+    pub fn from_db(valore: &str) -> Self {
+        match valore {
+            "prenotazione.modificata" => TipoEvento::Modificata,
+            "prenotazione.annullata" => TipoEvento::Annullata,
+            "prenotazione.approvata" => TipoEvento::Approvata,
+            _ => TipoEvento::Creata,
+        }
+    }
+}
+
+// Formato del corpo inviato: JSON firmato, oppure MessageCard per i connettori di Teams
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum FormatoWebhook {
+    #[default]
+    Json,
+    Teams,
+}
+
+impl FormatoWebhook {
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            FormatoWebhook::Json => "json",
+            FormatoWebhook::Teams => "teams",
+        }
+    }
+
+    pub fn from_db(valore: &str) -> FormatoWebhook {
+        match valore {
+            "teams" => FormatoWebhook::Teams,
+            _ => FormatoWebhook::Json,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct WebhookApi {
+    pub Id_Webhook: i32,
+    pub Url: String,
+    pub Eventi: Vec<String>,
+    pub Formato: String,
+    pub Attivo: bool,
+    pub Descrizione: Option<String>,
+    pub Data_Creazione: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct NuovoWebhookPayload {
+    #[serde(rename = "Url")]
+    pub(crate) url: String,
+    #[serde(rename = "Eventi")]
+    pub(crate) eventi: Vec<TipoEvento>,
+    #[serde(rename = "Formato", default)]
+    pub(crate) formato: FormatoWebhook,
+    #[serde(rename = "Descrizione", default)]
+    pub(crate) descrizione: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ModificaWebhookPayload {
+    #[serde(rename = "Attivo", default)]
+    pub(crate) attivo: Option<bool>,
+    #[serde(rename = "Eventi", default)]
+    pub(crate) eventi: Option<Vec<TipoEvento>>,
+}
+
+#[derive(Serialize, FromRow, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ConsegnaWebhookApi {
+    pub Id_Consegna: i64,
+    pub Id_Webhook: i32,
+    pub Evento: String,
+    pub Stato: String, // in_attesa, consegnata, fallita
+    pub Tentativi: i32,
+    pub Prossimo_Tentativo: NaiveDateTime,
+    pub Ultimo_Codice: Option<i32>, // Codice HTTP dell'ultima risposta
+    pub Ultimo_Errore: Option<String>,
+    pub Data_Creazione: NaiveDateTime,
+    pub Data_Consegna: Option<NaiveDateTime>,
+    pub Payload: String,
+}
+
+// Quali email di notifica ricevere; senza una riga in preferenza_notifica sono tutte attive
+// tranne il riepilogo serale
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PreferenzeNotificheApi {
+    pub Creata: bool,
+    pub Modificata: bool,
+    pub Annullata: bool,
+    pub Approvata: bool,
+    pub Scavalcata: bool, // Un blocco dell'aula si sovrappone alla prenotazione
+    pub Promemoria: bool, // Poco prima dell'inizio di ogni prenotazione
+    pub Riepilogo: bool,  // La sera, le prenotazioni del giorno dopo
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PreferenzeNotifichePayload {
+    #[serde(rename = "Creata", default)]
+    pub(crate) creata: Option<bool>,
+    #[serde(rename = "Modificata", default)]
+    pub(crate) modificata: Option<bool>,
+    #[serde(rename = "Annullata", default)]
+    pub(crate) annullata: Option<bool>,
+    #[serde(rename = "Approvata", default)]
+    pub(crate) approvata: Option<bool>,
+    #[serde(rename = "Scavalcata", default)]
+    pub(crate) scavalcata: Option<bool>,
+    #[serde(rename = "Promemoria", default)]
+    pub(crate) promemoria: Option<bool>,
+    #[serde(rename = "Riepilogo", default)]
+    pub(crate) riepilogo: Option<bool>,
+}
+
+// Lingua preferita del professore; None (null) segue la lingua del browser
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct LinguaProfilo {
+    #[serde(default)]
+    pub Lingua: Option<Lingua>,
+}
+
+// Tablet installato fuori da un'aula; il token è mostrato solo alla creazione
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct DispositivoChioscoApi {
+    pub Id_Dispositivo: i32,
+    pub Id_Aula: i32,
+    pub Descrizione: Option<String>,
+    pub Data_Creazione: NaiveDateTime,
+    pub Ultimo_Accesso: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct NuovoDispositivoChioscoPayload {
+    #[serde(rename = "Id_Aula")]
+    pub(crate) id_aula: i32,
+    #[serde(rename = "Descrizione", default)]
+    pub(crate) descrizione: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PinChioscoPayload {
+    #[serde(rename = "Pin")]
+    pub(crate) pin: String,
+}
+
+// Al tablet il professore si identifica con il suo codice (Id_Professore) e il PIN
+#[derive(Deserialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PrenotazioneChioscoPayload {
+    #[serde(rename = "Codice")]
+    pub(crate) codice: i32,
+    #[serde(rename = "Pin")]
+    pub(crate) pin: String,
+}
+
+// Orari in ora locale (HH:MM): il tablet li mostra così come sono
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct PrenotazioneChioscoApi {
+    pub Professore: String,
+    pub Materia: Option<String>,
+    pub Ora_Inizio: String,
+    pub Ora_Fine: String,
+    pub Da_Approvare: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct ModuloChioscoApi {
+    pub Numero_Modulo: i32,
+    pub Ora_Inizio: String,
+    pub Ora_Fine: String,
+    pub Prenotabile: bool, // Aula libera per tutto il modulo
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct StatoChioscoApi {
+    pub Aula: String,
+    pub Plesso: String,
+    pub Ora_Attuale: String,
+    pub Corrente: Option<PrenotazioneChioscoApi>,
+    pub Indisponibile: Option<String>, // Motivo di un blocco o di una chiusura in corso
+    pub Prossime: Vec<PrenotazioneChioscoApi>, // Il resto della giornata
+    pub Libera: bool,
+    pub Libera_Fino: Option<String>, // None se l'aula è libera fino a fine giornata
+    pub Modulo_Corrente: Option<ModuloChioscoApi>,
+}
+
+// Mancati check-in per professore, per i report dell'amministrazione.
+// Contano solo le prenotazioni già iniziate nelle aule con il check-in attivo.
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct AssenzeProfessoreApi {
+    pub Id_Professore: i32,
+    pub Nome: Option<String>,
+    pub Cognome: String,
+    pub Prenotazioni: i64,
+    pub Check_In: i64,
+    pub Rilasciate: i64,         // Senza check-in entro la tolleranza
+    pub Percentuale_Assenze: f64, // Rilasciate sul totale, da 0 a 100
+}
+
+// Righe lette dal livello di accesso ai dati (src/db) per le query che non hanno già una struct API
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct ProfessoreDb {
+    pub Nome: Option<String>,
+    pub Cognome: String,
+    pub Ruolo: String,
+    pub Lingua: Option<String>,
+}
+
+// Aula con il nome del suo plesso (per titoli, stampe e importazioni)
+#[derive(sqlx::FromRow, Debug)]
+pub struct AulaPlessoDb {
+    pub Id_Aula: i32,
+    pub Tipo_Aula: String,
+    pub Numero: i32,
+    pub Id_Plesso: i32,
+    pub Nome_Plesso: String,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct PrenotazioneIntervalloDb {
+    pub Id_Prenotazione: i32,
+    pub Id_Professore: i32,
+    pub Data_Inizio: NaiveDateTime,
+    pub Data_Fine: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct ChiusuraConPlessoDb {
+    pub Id_Chiusura: i32,
+    pub Id_Plesso: i32,
+    pub Nome_Plesso: String,
+    pub Data_Inizio: NaiveDate,
+    pub Data_Fine: NaiveDate,
+    pub Motivo: String,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct ModuloPlessoDb {
+    pub Id_Plesso: i32,
+    pub Numero_Modulo: i32,
+    pub Ora_Inizio: NaiveTime,
+    pub Ora_Fine: NaiveTime,
+}
+
+// Prenotazione dello stesso professore in un altro plesso, con i minuti per raggiungerlo
+#[derive(sqlx::FromRow, Debug)]
+pub struct PrenotazioneVicinaDb {
+    pub Data_Inizio: NaiveDateTime,
+    pub Data_Fine: NaiveDateTime,
+    pub Tipo_Aula: String,
+    pub Numero: i32,
+    pub Nome_Plesso: String,
+    pub Minuti: i32,
+}
+
+// Una riga delle esportazioni CSV/XLSX
+#[derive(sqlx::FromRow, Debug)]
+pub struct RigaEsportazioneDb {
+    pub Data_Inizio: NaiveDateTime,
+    pub Data_Fine: NaiveDateTime,
+    pub Tipo_Aula: Option<String>,
+    pub Numero_Aula: Option<i32>,
+    pub Id_Plesso: Option<i32>,
+    pub Nome_Plesso: Option<String>,
+    pub Nome_Professore: Option<String>,
+    pub Cognome_Professore: String,
+    pub Materia: Option<String>,
+    pub Approvata: Option<bool>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct EventoPrenotazioneDb {
+    pub Id_Evento: u64,
+    pub Tipo: String,
+    pub Id_Prenotazione: i32,
+    pub Id_Aula: Option<i32>,
+    pub Id_Aula_Precedente: Option<i32>,
+    pub Data_Inizio: NaiveDateTime,
+    pub Data_Fine: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct WebhookDb {
+    pub Id_Webhook: i32,
+    pub Url: String,
+    pub Eventi: String,
+    pub Formato: String,
+    pub Attivo: bool,
+    pub Descrizione: Option<String>,
+    pub Data_Creazione: NaiveDateTime,
+}
+
+// Consegna di webhook pronta per un nuovo tentativo, con i dati del webhook
+#[derive(sqlx::FromRow, Debug)]
+pub struct ConsegnaInScadenzaDb {
+    pub Id_Consegna: i64,
+    pub Evento: String,
+    pub Payload: String,
+    pub Tentativi: i32,
+    pub Url: String,
+    pub Segreto: String,
+    pub Formato: String,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct EmailInUscitaDb {
+    pub Id_Email: i64,
+    pub Destinatario: String,
+    pub Oggetto: String,
+    pub Corpo: String,
+    pub Tentativi: i32,
+}
+
+// Indirizzo del professore e preferenze di notifica (None senza una riga in preferenza_notifica)
+#[derive(sqlx::FromRow, Debug)]
+pub struct DestinatarioDb {
+    pub email: String,
+    pub Nome: Option<String>,
+    pub Lingua: Option<String>,
+    pub Creata: Option<bool>,
+    pub Modificata: Option<bool>,
+    pub Annullata: Option<bool>,
+    pub Approvata: Option<bool>,
+    pub Scavalcata: Option<bool>,
+    pub Promemoria: Option<bool>,
+    pub Riepilogo: Option<bool>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct AssenzeProfessoreDb {
+    pub Id_Professore: i32,
+    pub Nome: Option<String>,
+    pub Cognome: String,
+    pub Prenotazioni: i64,
+    pub Check_In: i64,
+    pub Rilasciate: i64,
+}
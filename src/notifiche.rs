@@ -0,0 +1,392 @@
+// src/notifiche.rs
+// Notifiche via email al professore titolare della prenotazione: creazione, modifica, annullamento,
+// approvazione e blocco dell'aula sovrapposto (la prenotazione viene "scavalcata").
+// Promemoria e riepilogo serale passano dalla stessa coda (vedi promemoria.rs).
+// I testi sono modelli minijinja in templates/email/<lingua>/, con l'oggetto nella prima riga,
+// nella lingua scelta dal professore (italiano se non l'ha scelta).
+// Le email vengono composte subito e salvate in email_in_uscita; un task in background le invia
+// via SMTP e ritenta in caso di errore, come per i webhook.
+//
+// Configurazione (vedi configurazione.rs): SMTP_HOST (vuoto o assente = notifiche disattivate),
+// SMTP_PORTA, SMTP_SICUREZZA (nessuna, starttls, tls), SMTP_UTENTE, SMTP_PASSWORD, SMTP_MITTENTE.
+// Per le prove basta un catcher locale, ad esempio Mailpit: SMTP_HOST=127.0.0.1 SMTP_PORTA=1025.
+
+use std::sync::OnceLock;
+
+use chrono::{Duration, Utc};
+use chrono_tz::Europe::Rome;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use minijinja::{context, Environment};
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::configurazione::{self, ConfigurazioneSmtp, SicurezzaSmtp};
+use crate::db::{self, DbPool, Esecutore};
+use crate::errori::ErroreApi;
+use crate::metriche;
+use crate::models::{self, TipoEvento};
+use crate::prenotazioni::{self, FiltroPrenotazioni};
+use crate::traduzioni::Lingua;
+
+const MODELLI: &[(&str, &str)] = &[
+    ("it/creata.txt", include_str!("../templates/email/it/creata.txt")),
+    ("it/modificata.txt", include_str!("../templates/email/it/modificata.txt")),
+    ("it/annullata.txt", include_str!("../templates/email/it/annullata.txt")),
+    ("it/approvata.txt", include_str!("../templates/email/it/approvata.txt")),
+    ("it/scavalcata.txt", include_str!("../templates/email/it/scavalcata.txt")),
+    ("it/rilasciata.txt", include_str!("../templates/email/it/rilasciata.txt")),
+    ("it/promemoria.txt", include_str!("../templates/email/it/promemoria.txt")),
+    ("it/riepilogo.txt", include_str!("../templates/email/it/riepilogo.txt")),
+    ("en/creata.txt", include_str!("../templates/email/en/creata.txt")),
+    ("en/modificata.txt", include_str!("../templates/email/en/modificata.txt")),
+    ("en/annullata.txt", include_str!("../templates/email/en/annullata.txt")),
+    ("en/approvata.txt", include_str!("../templates/email/en/approvata.txt")),
+    ("en/scavalcata.txt", include_str!("../templates/email/en/scavalcata.txt")),
+    ("en/rilasciata.txt", include_str!("../templates/email/en/rilasciata.txt")),
+    ("en/promemoria.txt", include_str!("../templates/email/en/promemoria.txt")),
+    ("en/riepilogo.txt", include_str!("../templates/email/en/riepilogo.txt")),
+];
+
+const INTERVALLO_CONTROLLO_SECONDI: u64 = 10;
+const EMAIL_PER_GIRO: i64 = 20;
+const DURATA_PRESA_IN_CARICO_SECONDI: i64 = 120;
+const MASSIMO_TENTATIVI: i32 = 6;
+const ATTESA_INIZIALE_SECONDI: i64 = 60;
+const ATTESA_MASSIMA_SECONDI: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notifica {
+    Creata,
+    Modificata,
+    Annullata,
+    Approvata,
+    Scavalcata,
+    Rilasciata,
+    Promemoria,
+    Riepilogo,
+}
+
+impl Notifica {
+    pub fn modello(&self) -> &'static str {
+        match self {
+            Notifica::Creata => "creata",
+            Notifica::Modificata => "modificata",
+            Notifica::Annullata => "annullata",
+            Notifica::Approvata => "approvata",
+            Notifica::Scavalcata => "scavalcata",
+            Notifica::Rilasciata => "rilasciata",
+            Notifica::Promemoria => "promemoria",
+            Notifica::Riepilogo => "riepilogo",
+        }
+    }
+
+    // Valore usato finché il professore non salva le sue preferenze
+    fn predefinita(&self) -> bool {
+        *self != Notifica::Riepilogo
+    }
+}
+
+impl From<TipoEvento> for Notifica {
+    fn from(tipo: TipoEvento) -> Self {
+        match tipo {
+            TipoEvento::Creata => Notifica::Creata,
+            TipoEvento::Modificata => Notifica::Modificata,
+            TipoEvento::Annullata => Notifica::Annullata,
+            TipoEvento::Approvata => Notifica::Approvata,
+        }
+    }
+}
+
+pub fn notifiche_attive() -> bool {
+    configurazione::attuale().smtp.is_some()
+}
+
+fn trasporto(configurazione: &ConfigurazioneSmtp) -> Result<AsyncSmtpTransport<Tokio1Executor>, lettre::transport::smtp::Error> {
+    let mut builder = match configurazione.sicurezza {
+        SicurezzaSmtp::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&configurazione.host)?,
+        SicurezzaSmtp::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&configurazione.host)?,
+        // Senza cifratura: solo per server interni o catcher di prova
+        SicurezzaSmtp::Nessuna => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&configurazione.host),
+    };
+    if let Some(porta) = configurazione.porta {
+        builder = builder.port(porta);
+    }
+    if let Some(credenziali) = &configurazione.credenziali {
+        builder = builder.credentials(credenziali.clone());
+    }
+    Ok(builder.build())
+}
+
+fn modelli() -> &'static Environment<'static> {
+    static MODELLI_EMAIL: OnceLock<Environment<'static>> = OnceLock::new();
+    MODELLI_EMAIL.get_or_init(|| {
+        let mut ambiente = Environment::new();
+        for (nome, testo) in MODELLI {
+            ambiente.add_template(nome, testo).expect("Modello email non valido");
+        }
+        ambiente
+    })
+}
+
+// Oggetto e corpo dell'email: la prima riga del modello è "Oggetto: ..." ("Subject: ..." in inglese)
+pub fn componi(lingua: Lingua, modello: &str, contesto: minijinja::Value) -> Result<(String, String), minijinja::Error> {
+    let nome = format!("{}/{}.txt", lingua.codice(), modello);
+    let modello = match modelli().get_template(&nome) {
+        Ok(modello) => modello,
+        Err(_) => modelli().get_template(&format!("{}/{}.txt", Lingua::default().codice(), modello))?,
+    };
+    let testo = modello.render(contesto)?;
+    let (prima_riga, corpo) = testo.split_once('\n').unwrap_or((&testo, ""));
+    let oggetto = prima_riga
+        .strip_prefix("Oggetto:")
+        .or_else(|| prima_riga.strip_prefix("Subject:"))
+        .unwrap_or(prima_riga)
+        .trim();
+    Ok((oggetto.to_string(), corpo.trim_start_matches('\n').to_string()))
+}
+
+// Email, nome e lingua del professore, se vuole ricevere questo tipo di notifica (anche dentro una transazione)
+pub async fn destinatario(db: impl Esecutore<'_>, id_professore: i32, notifica: Notifica) -> Result<Option<(String, String, Lingua)>, sqlx::Error> {
+    let record = db::notifiche::destinatario(db, id_professore).await?;
+    Ok(record.and_then(|r| {
+        let attiva = match notifica {
+            Notifica::Creata => r.Creata,
+            Notifica::Modificata => r.Modificata,
+            Notifica::Annullata => r.Annullata,
+            Notifica::Approvata => r.Approvata,
+            Notifica::Scavalcata => r.Scavalcata,
+            Notifica::Rilasciata => r.Annullata, // Stessa preferenza delle prenotazioni annullate
+            Notifica::Promemoria => r.Promemoria,
+            Notifica::Riepilogo => r.Riepilogo,
+        };
+        let lingua = r.Lingua.as_deref().and_then(Lingua::da_codice).unwrap_or_default();
+        attiva.unwrap_or(notifica.predefinita()).then_some((r.email, r.Nome.unwrap_or_default(), lingua))
+    }))
+}
+
+// Salva un'email già composta nella coda di invio (anche dentro una transazione)
+pub async fn accoda_email(db: impl Esecutore<'_>, id_professore: i32, destinatario: &str, oggetto: &str, corpo: &str) -> Result<(), sqlx::Error> {
+    db::notifiche::accoda_email(db, id_professore, destinatario, oggetto, corpo).await
+}
+
+async fn accoda_notifica(db_pool: &DbPool, notifica: Notifica, id_prenotazione: i32, motivo: Option<&str>) -> Result<(), sqlx::Error> {
+    if !notifiche_attive() {
+        return Ok(());
+    }
+    let filtro = FiltroPrenotazioni { id_prenotazione: Some(id_prenotazione), includi_annullate: true, ..Default::default() };
+    let voce = match prenotazioni::elenco(db_pool, &filtro).await?.into_iter().next() {
+        Some(voce) => voce,
+        None => return Ok(()),
+    };
+    let (email, nome, lingua) = match destinatario(db_pool, voce.db.Id_Professore, notifica).await? {
+        Some(destinatario) => destinatario,
+        None => return Ok(()),
+    };
+
+    let ora = |data: chrono::DateTime<Utc>| data.with_timezone(&Rome).format("%d/%m/%Y %H:%M").to_string();
+    let contesto = context! {
+        nome => nome,
+        id_prenotazione => id_prenotazione,
+        risorsa => voce.nome_risorsa,
+        giorno => voce.inizio.with_timezone(&Rome).format("%d/%m/%Y").to_string(),
+        inizio => ora(voce.inizio),
+        fine => ora(voce.fine),
+        da_approvare => voce.db.Approvata == Some(false),
+        motivo => motivo,
+    };
+    match componi(lingua, notifica.modello(), contesto) {
+        Ok((oggetto, corpo)) => accoda_email(db_pool, voce.db.Id_Professore, &email, &oggetto, &corpo).await,
+        Err(e) => {
+            tracing::error!(modello = notifica.modello(), id_prenotazione, errore = %e, "Errore nel comporre l'email della prenotazione");
+            Ok(())
+        }
+    }
+}
+
+pub async fn accoda(db_pool: &DbPool, tipo: TipoEvento, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    accoda_notifica(db_pool, Notifica::from(tipo), id_prenotazione, None).await
+}
+
+// Un blocco appena creato si sovrappone alla prenotazione
+pub async fn accoda_scavalcamento(db_pool: &DbPool, id_prenotazione: i32, motivo: &str) -> Result<(), sqlx::Error> {
+    accoda_notifica(db_pool, Notifica::Scavalcata, id_prenotazione, Some(motivo)).await
+}
+
+// La prenotazione è stata rilasciata per mancato check-in
+pub async fn accoda_rilascio(db_pool: &DbPool, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    accoda_notifica(db_pool, Notifica::Rilasciata, id_prenotazione, None).await
+}
+
+// Attesa prima del tentativo successivo: 1m, 2m, 4m... fino a un'ora
+fn attesa_dopo(tentativi: i32) -> Duration {
+    let esponente = (tentativi - 1).clamp(0, 20) as u32;
+    Duration::seconds((ATTESA_INIZIALE_SECONDI * 2i64.pow(esponente)).min(ATTESA_MASSIMA_SECONDI))
+}
+
+async fn invia(
+    trasporto: &AsyncSmtpTransport<Tokio1Executor>,
+    mittente: &Mailbox,
+    destinatario: &str,
+    oggetto: &str,
+    corpo: &str,
+) -> Result<(), String> {
+    let destinatario: Mailbox = destinatario.parse().map_err(|e| format!("Indirizzo non valido: {}", e))?;
+    let messaggio = Message::builder()
+        .from(mittente.clone())
+        .to(destinatario)
+        .subject(oggetto)
+        .header(ContentType::TEXT_PLAIN)
+        .body(corpo.to_string())
+        .map_err(|e| e.to_string())?;
+    trasporto.send(messaggio).await.map(|_| ()).map_err(|e| e.to_string())
+}
+
+async fn invia_in_scadenza(db_pool: &DbPool, trasporto: &AsyncSmtpTransport<Tokio1Executor>, mittente: &Mailbox) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now();
+    let da_inviare = db::notifiche::email_in_scadenza(db_pool, adesso, EMAIL_PER_GIRO).await?;
+
+    for email in da_inviare {
+        // Presa in carico: se un altro processo l'ha già presa si passa oltre
+        let scadenza = adesso + Duration::seconds(DURATA_PRESA_IN_CARICO_SECONDI);
+        if !db::notifiche::prendi_in_carico(db_pool, email.Id_Email, adesso, scadenza).await? {
+            continue;
+        }
+
+        let tentativi = email.Tentativi + 1;
+        match invia(trasporto, mittente, &email.Destinatario, &email.Oggetto, &email.Corpo).await {
+            Ok(()) => {
+                metriche::consegna("email", "riuscita");
+                db::notifiche::segna_inviata(db_pool, email.Id_Email, tentativi).await?
+            }
+            Err(errore) => {
+                tracing::error!(errore = %errore, "Invio dell'email {} a {} non riuscito", email.Id_Email, email.Destinatario);
+                let stato = if tentativi >= MASSIMO_TENTATIVI { "fallita" } else { "in_attesa" };
+                metriche::consegna("email", if stato == "fallita" { "fallita" } else { "ripianificata" });
+                db::notifiche::ripianifica(db_pool, email.Id_Email, stato, tentativi, &errore, Utc::now() + attesa_dopo(tentativi)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Task in background che svuota la coda delle email
+pub async fn avvia_invio(db_pool: DbPool) {
+    let configurazione = match &configurazione::attuale().smtp {
+        Some(configurazione) => configurazione,
+        None => {
+            tracing::info!("SMTP_HOST non impostato: le notifiche email sono disattivate.");
+            return;
+        }
+    };
+    let trasporto = match trasporto(configurazione) {
+        Ok(trasporto) => trasporto,
+        Err(e) => {
+            tracing::error!(errore = %e, "Impossibile creare il trasporto SMTP per {}", configurazione.host);
+            return;
+        }
+    };
+    loop {
+        let esito = invia_in_scadenza(&db_pool, &trasporto, &configurazione.mittente).await;
+        metriche::lavoro("invio_email", &esito);
+        if let Err(e) = esito {
+            tracing::error!(errore = %e, "Errore DB nell'invio delle email");
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(INTERVALLO_CONTROLLO_SECONDI)).await;
+    }
+}
+
+async fn leggi_preferenze(db_pool: &DbPool, id_professore: i32) -> Result<models::PreferenzeNotificheApi, sqlx::Error> {
+    let record = db::notifiche::preferenze(db_pool, id_professore).await?;
+    Ok(match record {
+        Some(preferenze) => preferenze,
+        None => models::PreferenzeNotificheApi {
+            Creata: Notifica::Creata.predefinita(),
+            Modificata: Notifica::Modificata.predefinita(),
+            Annullata: Notifica::Annullata.predefinita(),
+            Approvata: Notifica::Approvata.predefinita(),
+            Scavalcata: Notifica::Scavalcata.predefinita(),
+            Promemoria: Notifica::Promemoria.predefinita(),
+            Riepilogo: Notifica::Riepilogo.predefinita(),
+        },
+    })
+}
+
+#[get("/notifiche/preferenze")]
+pub async fn get_preferenze_notifiche(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+) -> Result<Json<models::PreferenzeNotificheApi>, ErroreApi> {
+    match leggi_preferenze(db_pool.inner(), utente.id_professore).await {
+        Ok(preferenze) => Ok(Json(preferenze)),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("DB nel leggere le preferenze di notifica del professore {}", utente.id_professore), e))
+        }
+    }
+}
+
+// I campi assenti nel payload restano invariati
+#[put("/notifiche/preferenze", format = "json", data = "<payload>")]
+pub async fn aggiorna_preferenze_notifiche(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+    payload: Json<models::PreferenzeNotifichePayload>,
+) -> Result<Json<models::PreferenzeNotificheApi>, ErroreApi> {
+    let errore_interno = |e: sqlx::Error| {
+        ErroreApi::interno(format!("DB nel salvare le preferenze di notifica del professore {}", utente.id_professore), e)
+    };
+    let attuali = leggi_preferenze(db_pool.inner(), utente.id_professore).await.map_err(errore_interno)?;
+    let nuove = models::PreferenzeNotificheApi {
+        Creata: payload.creata.unwrap_or(attuali.Creata),
+        Modificata: payload.modificata.unwrap_or(attuali.Modificata),
+        Annullata: payload.annullata.unwrap_or(attuali.Annullata),
+        Approvata: payload.approvata.unwrap_or(attuali.Approvata),
+        Scavalcata: payload.scavalcata.unwrap_or(attuali.Scavalcata),
+        Promemoria: payload.promemoria.unwrap_or(attuali.Promemoria),
+        Riepilogo: payload.riepilogo.unwrap_or(attuali.Riepilogo),
+    };
+    db::notifiche::salva_preferenze(db_pool.inner(), utente.id_professore, &nuove)
+        .await
+        .map_err(errore_interno)?;
+    Ok(Json(nuove))
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::prova;
+
+    #[tokio::test]
+    async fn senza_preferenze_salvate_vale_il_predefinito() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        // Senza credenziali non c'è un indirizzo a cui scrivere
+        assert!(destinatario(&db_pool, id_professore, Notifica::Creata).await.unwrap().is_none());
+
+        prova::credenziali(&db_pool, id_professore, "rossi@scuola.it").await;
+        let (email, nome, lingua) = destinatario(&db_pool, id_professore, Notifica::Creata).await.unwrap().unwrap();
+        assert_eq!((email.as_str(), nome.as_str(), lingua), ("rossi@scuola.it", "Prova", Lingua::default()));
+        assert!(destinatario(&db_pool, id_professore, Notifica::Promemoria).await.unwrap().is_some());
+        // Il riepilogo serale va chiesto esplicitamente
+        assert!(destinatario(&db_pool, id_professore, Notifica::Riepilogo).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn le_preferenze_salvate_decidono_chi_riceve_cosa() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        prova::credenziali(&db_pool, id_professore, "rossi@scuola.it").await;
+        let preferenze = models::PreferenzeNotificheApi { Annullata: false, Riepilogo: true, ..leggi_preferenze(&db_pool, id_professore).await.unwrap() };
+        db::notifiche::salva_preferenze(&db_pool, id_professore, &preferenze).await.unwrap();
+
+        assert!(destinatario(&db_pool, id_professore, Notifica::Annullata).await.unwrap().is_none());
+        // Il rilascio per mancato check-in segue la preferenza delle prenotazioni annullate
+        assert!(destinatario(&db_pool, id_professore, Notifica::Rilasciata).await.unwrap().is_none());
+        assert!(destinatario(&db_pool, id_professore, Notifica::Riepilogo).await.unwrap().is_some());
+        assert!(destinatario(&db_pool, id_professore, Notifica::Creata).await.unwrap().is_some());
+        let salvate = leggi_preferenze(&db_pool, id_professore).await.unwrap();
+        assert_eq!((salvate.Annullata, salvate.Riepilogo, salvate.Creata), (false, true, true));
+    }
+}
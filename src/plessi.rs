@@ -0,0 +1,286 @@
+// src/plessi.rs
+// Plessi (sedi) dell'istituto: indirizzo, orario delle lezioni e giorni di chiusura.
+// Ogni aula appartiene a un plesso; un plesso chiuso rende non prenotabili tutte le sue aule.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Europe::Rome;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::db::{self, DbPool, Esecutore};
+use crate::errori::ErroreApi;
+use crate::models;
+use crate::traduzioni::{valori, Lingua};
+
+// Tempo di spostamento tra due plessi quando non è indicato in distanza_plessi
+pub const MINUTI_SPOSTAMENTO_PREDEFINITI: i32 = 15;
+
+// Giorni di calendario locali (Europe/Rome) toccati dall'intervallo [inizio, fine)
+fn giorni_locali(inizio: DateTime<Utc>, fine: DateTime<Utc>) -> (NaiveDate, NaiveDate) {
+    let primo = inizio.with_timezone(&Rome).date_naive();
+    let ultimo = (fine - Duration::seconds(1)).with_timezone(&Rome).date_naive();
+    (primo, ultimo.max(primo))
+}
+
+// Istante di un orario locale (Europe/Rome), come quelli dei moduli; None se il cambio d'ora lo salta
+pub fn in_utc(giorno: NaiveDate, ora: NaiveTime) -> Option<DateTime<Utc>> {
+    Rome.from_local_datetime(&giorno.and_time(ora)).earliest().map(|d| d.with_timezone(&Utc))
+}
+
+// Chiusura del plesso dell'aula che cade nell'intervallo, se esiste
+pub async fn chiusura_nel_periodo(
+    db: impl Esecutore<'_>,
+    id_aula: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Result<Option<(i32, String)>, sqlx::Error> {
+    let (primo, ultimo) = giorni_locali(inizio, fine);
+    db::plessi::chiusura_aula(db, id_aula, primo, ultimo).await
+}
+
+// Plessi chiusi in almeno uno dei giorni dell'intervallo
+pub async fn plessi_chiusi(db_pool: &DbPool, inizio: DateTime<Utc>, fine: DateTime<Utc>) -> Result<Vec<i32>, sqlx::Error> {
+    let (primo, ultimo) = giorni_locali(inizio, fine);
+    db::plessi::chiusi(db_pool, primo, ultimo).await
+}
+
+// Avvisi per un professore che ha prenotazioni a ridosso in un altro plesso,
+// senza il tempo di spostarsi da una sede all'altra, nella lingua indicata
+pub async fn avvisi_spostamento(
+    db_pool: &DbPool,
+    lingua: Lingua,
+    id_professore: i32,
+    id_aula: i32,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let margine = Duration::hours(3);
+    let vicine = db::plessi::prenotazioni_in_altri_plessi(
+        db_pool,
+        id_professore,
+        id_aula,
+        inizio - margine,
+        fine + margine,
+        MINUTI_SPOSTAMENTO_PREDEFINITI,
+    )
+        .await?;
+
+    let mut avvisi = Vec::new();
+    for p in vicine {
+        let p_inizio: DateTime<Utc> = DateTime::from_naive_utc_and_offset(p.Data_Inizio, Utc);
+        let p_fine: DateTime<Utc> = DateTime::from_naive_utc_and_offset(p.Data_Fine, Utc);
+        let numero = format!("{:02}", p.Numero);
+        let aula = lingua.traduci_con(
+            "spostamento.aula",
+            &format!("Aula {} {} ({})", p.Tipo_Aula, numero, p.Nome_Plesso),
+            &valori(&[("tipo", p.Tipo_Aula.clone()), ("numero", numero.clone()), ("plesso", p.Nome_Plesso.clone())]),
+        );
+        let ora = |dt: DateTime<Utc>| dt.with_timezone(&Rome).format("%H:%M").to_string();
+        let spostamento = Duration::minutes(p.Minuti as i64);
+        let minuti = p.Minuti.to_string();
+
+        if p_fine <= inizio && inizio - p_fine < spostamento {
+            avvisi.push(lingua.traduci_con(
+                "spostamento.termina",
+                &format!("Hai una prenotazione in {} che termina alle {}: servono circa {} minuti per cambiare plesso.", aula, ora(p_fine), minuti),
+                &valori(&[("aula", aula.clone()), ("ora", ora(p_fine)), ("minuti", minuti)]),
+            ));
+        } else if p_inizio >= fine && p_inizio - fine < spostamento {
+            avvisi.push(lingua.traduci_con(
+                "spostamento.inizia",
+                &format!("Hai una prenotazione in {} che inizia alle {}: servono circa {} minuti per cambiare plesso.", aula, ora(p_inizio), minuti),
+                &valori(&[("aula", aula.clone()), ("ora", ora(p_inizio)), ("minuti", minuti)]),
+            ));
+        } else if p_inizio < fine && p_fine > inizio {
+            avvisi.push(lingua.traduci_con(
+                "spostamento.sovrapposta",
+                &format!("Hai già una prenotazione sovrapposta in {} dalle {} alle {}.", aula, ora(p_inizio), ora(p_fine)),
+                &valori(&[("aula", aula.clone()), ("inizio", ora(p_inizio)), ("fine", ora(p_fine))]),
+            ));
+        }
+    }
+    Ok(avvisi)
+}
+
+#[get("/plessi")]
+pub async fn get_plessi(
+    db_pool: &State<DbPool>
+) -> Result<Json<Vec<models::PlessoApi>>, ErroreApi> {
+    match db::plessi::elenco(db_pool.inner()).await {
+        Ok(plessi) => Ok(Json(plessi)),
+        Err(e) => {
+            Err(ErroreApi::interno("nel recuperare i plessi dal DB", e))
+        }
+    }
+}
+
+// Nome obbligatorio; un indirizzo vuoto vale come assente
+fn valida_plesso(payload: &models::PlessoPayload) -> Result<(&str, Option<&str>), ErroreApi> {
+    let nome = payload.nome.trim();
+    if nome.is_empty() {
+        return Err(ErroreApi::campo("Nome", "campo_obbligatorio", "Il campo Nome è obbligatorio."));
+    }
+    let indirizzo = payload.indirizzo.as_deref().map(str::trim).filter(|i| !i.is_empty());
+    Ok((nome, indirizzo))
+}
+
+// Moduli con numeri positivi e distinti, che non si sovrappongono tra loro
+fn valida_orario(moduli: &[models::ModuloOrarioPayload]) -> Result<(), ErroreApi> {
+    let mut ordinati: Vec<&models::ModuloOrarioPayload> = moduli.iter().collect();
+    ordinati.sort_by_key(|m| m.ora_inizio);
+    let mut numeri = std::collections::HashSet::new();
+    for (i, modulo) in ordinati.iter().enumerate() {
+        if modulo.numero_modulo < 1 || !numeri.insert(modulo.numero_modulo) {
+            return Err(ErroreApi::campo("Numero_Modulo", "numero_modulo_non_valido", "I numeri dei moduli devono essere positivi e diversi tra loro."));
+        }
+        if modulo.ora_fine <= modulo.ora_inizio {
+            return Err(ErroreApi::campo("Ora_Fine", "ora_fine_non_successiva", "Ora_Fine deve essere successiva a Ora_Inizio."));
+        }
+        if i > 0 && ordinati[i - 1].ora_fine > modulo.ora_inizio {
+            return Err(ErroreApi::campo("Ora_Inizio", "moduli_sovrapposti", "I moduli dell'orario non possono sovrapporsi."));
+        }
+    }
+    Ok(())
+}
+
+#[post("/plessi", format = "json", data = "<payload>")]
+pub async fn crea_plesso(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    payload: Json<models::PlessoPayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_plessi", "Solo un amministratore può gestire i plessi.")?;
+    let (nome, indirizzo) = valida_plesso(&payload)?;
+    match db::plessi::inserisci_plesso(db_pool.inner(), nome, indirizzo).await {
+        Ok(id_plesso) => Ok(status::Custom(Status::Created, Json(json!({
+            "status": "successo",
+            "message": lingua.traduci("messaggi.plesso_creato", "Plesso creato con successo!"),
+            "id_plesso": id_plesso
+        })))),
+        Err(e) => Err(ErroreApi::interno("DB durante la creazione del plesso", e)),
+    }
+}
+
+#[put("/plessi/<id_plesso>", format = "json", data = "<payload>")]
+pub async fn aggiorna_plesso(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_plesso: i32,
+    payload: Json<models::PlessoPayload>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_plessi", "Solo un amministratore può gestire i plessi.")?;
+    let (nome, indirizzo) = valida_plesso(&payload)?;
+    match db::plessi::aggiorna_plesso(db_pool.inner(), id_plesso, nome, indirizzo).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.plesso_aggiornato", "Plesso aggiornato.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("plesso_non_trovato", "Plesso non trovato.")),
+        Err(e) => Err(ErroreApi::interno(format!("DB nell'aggiornare il plesso {}", id_plesso), e)),
+    }
+}
+
+// Sostituisce l'intero orario delle lezioni del plesso. Le prenotazioni già fatte restano come sono.
+#[put("/plessi/<id_plesso>/orario", format = "json", data = "<payload>")]
+pub async fn aggiorna_orario_plesso(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_plesso: i32,
+    payload: Json<Vec<models::ModuloOrarioPayload>>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_plessi", "Solo un amministratore può gestire i plessi.")?;
+    valida_orario(&payload)?;
+
+    let errore_interno = |e: sqlx::Error| ErroreApi::interno(format!("DB nell'aggiornare l'orario del plesso {}", id_plesso), e);
+    let mut tx = db_pool.begin().await.map_err(errore_interno)?;
+    db::plessi::elimina_orario(&mut *tx, id_plesso).await.map_err(errore_interno)?;
+    for modulo in payload.iter() {
+        if let Err(e) = db::plessi::inserisci_modulo(&mut *tx, id_plesso, modulo.numero_modulo, modulo.ora_inizio, modulo.ora_fine).await {
+            if e.as_database_error().is_some_and(|db_err| db_err.is_foreign_key_violation()) {
+                return Err(ErroreApi::non_trovato("plesso_non_trovato", "Plesso non trovato."));
+            }
+            return Err(errore_interno(e));
+        }
+    }
+    tx.commit().await.map_err(errore_interno)?;
+    Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.orario_aggiornato", "Orario del plesso aggiornato.")})))
+}
+
+#[get("/plessi/<id_plesso>/orario")]
+pub async fn get_orario_plesso(
+    db_pool: &State<DbPool>,
+    id_plesso: i32,
+) -> Result<Json<Vec<models::ModuloOrarioApi>>, ErroreApi> {
+    match db::plessi::orario(db_pool.inner(), id_plesso).await {
+        Ok(moduli) => Ok(Json(moduli)),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("nel recuperare l'orario del plesso {}", id_plesso), e))
+        }
+    }
+}
+
+#[get("/plessi/<id_plesso>/chiusure")]
+pub async fn get_chiusure_plesso(
+    db_pool: &State<DbPool>,
+    id_plesso: i32,
+) -> Result<Json<Vec<models::ChiusuraPlessoApi>>, ErroreApi> {
+    match db::plessi::chiusure(db_pool.inner(), id_plesso).await {
+        Ok(chiusure) => Ok(Json(chiusure)),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("nel recuperare le chiusure del plesso {}", id_plesso), e))
+        }
+    }
+}
+
+#[post("/plessi/<id_plesso>/chiusure", format = "json", data = "<payload>")]
+pub async fn crea_chiusura_plesso(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_plesso: i32,
+    payload: Json<models::NuovaChiusuraPayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_chiusure", "Solo un amministratore può gestire le chiusure dei plessi.")?;
+    if payload.data_fine < payload.data_inizio {
+        return Err(ErroreApi::campo("Data_Fine", "data_fine_precedente", "Data_Fine non può precedere Data_Inizio."));
+    }
+    if payload.motivo.trim().is_empty() {
+        return Err(ErroreApi::campo("Motivo", "motivo_obbligatorio", "Il motivo della chiusura è obbligatorio."));
+    }
+
+    match db::plessi::inserisci_chiusura(db_pool.inner(), id_plesso, payload.data_inizio, payload.data_fine, payload.motivo.trim()).await {
+        Ok(id_chiusura) => Ok(status::Custom(Status::Created, Json(json!({
+            "status": "successo",
+            "message": lingua.traduci("messaggi.chiusura_registrata", "Chiusura registrata con successo!"),
+            "id_chiusura": id_chiusura
+        })))),
+        Err(e) => {
+            if let Some(db_err) = e.as_database_error() {
+                if db_err.is_foreign_key_violation() {
+                    return Err(ErroreApi::non_trovato("plesso_non_trovato", "Plesso non trovato."));
+                }
+            }
+            Err(ErroreApi::interno(format!("DB durante la creazione della chiusura del plesso {}", id_plesso), e))
+        }
+    }
+}
+
+#[delete("/plessi/chiusure/<id_chiusura>")]
+pub async fn elimina_chiusura_plesso(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_chiusura: i32,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_chiusure", "Solo un amministratore può gestire le chiusure dei plessi.")?;
+    match db::plessi::elimina_chiusura(db_pool.inner(), id_chiusura).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.chiusura_eliminata", "Chiusura eliminata.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("chiusura_non_trovata", "Chiusura non trovata.")),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("DB nell'eliminare la chiusura {}", id_chiusura), e))
+        }
+    }
+}
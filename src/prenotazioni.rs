@@ -0,0 +1,742 @@
+// src/prenotazioni.rs
+// Validazione e inserimento di una prenotazione (aula, attrezzatura o entrambe).
+// Tutti i controlli di disponibilità passano da qui, così ogni canale che crea
+// prenotazioni applica le stesse regole di creare_prenotazione.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::db::{self, DbConnection, DbPool};
+use crate::errori::ErroreApi;
+use crate::models::{self, AttrezzaturaRichiesta, PoliticaPrenotazione};
+use crate::attrezzature::{self, Utilizzo};
+use crate::{blocchi, guasti, plessi};
+use crate::traduzioni::{valori, Lingua};
+
+#[derive(Debug, Clone)]
+pub struct RichiestaPrenotazione {
+    pub id_professore: i32,
+    pub id_aula: Option<i32>,
+    pub data_inizio: DateTime<Utc>,
+    pub data_fine: DateTime<Utc>,
+    pub attrezzature: Vec<AttrezzaturaRichiesta>,
+    pub numero_studenti: Option<i32>,
+    pub id_materia: Option<i32>, // Materia della lezione, tra quelle insegnate dal professore
+}
+
+// Politica di condivisione di un'aula. Un'aula esclusiva equivale a una condivisa
+// con capienza 1 in cui ogni prenotazione consuma un posto.
+#[derive(Debug, Clone, Copy)]
+pub struct PoliticaAula {
+    pub politica: PoliticaPrenotazione,
+    pub capacita: Option<i32>,
+}
+
+impl PoliticaAula {
+    pub fn from_db(politica: &str, capacita: Option<i32>) -> PoliticaAula {
+        PoliticaAula { politica: PoliticaPrenotazione::from_db(politica), capacita }
+    }
+
+    pub fn capienza(&self) -> i32 {
+        match self.politica {
+            PoliticaPrenotazione::Esclusiva => 1,
+            _ => self.capacita.unwrap_or(1).max(1),
+        }
+    }
+
+    // Posti consumati da una prenotazione; senza numero di studenti si occupa l'intera aula
+    pub fn consumo(&self, numero_studenti: Option<i32>) -> i32 {
+        match self.politica {
+            PoliticaPrenotazione::CondivisaStudenti => numero_studenti.unwrap_or_else(|| self.capienza()),
+            _ => 1,
+        }
+    }
+
+    pub fn is_condivisa(&self) -> bool {
+        self.politica != PoliticaPrenotazione::Esclusiva
+    }
+}
+
+// Posti ancora liberi nell'aula per tutto l'intervallo [inizio, fine)
+pub async fn posti_residui(
+    conn: &mut DbConnection,
+    id_aula: i32,
+    politica: &PoliticaAula,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+) -> Result<i32, sqlx::Error> {
+    posti_residui_con(conn, id_aula, politica, inizio, fine, &[]).await
+}
+
+// Inizio, fine e numero di studenti di una prenotazione non ancora salvata
+type InSospeso = (DateTime<Utc>, DateTime<Utc>, Option<i32>);
+
+// Come posti_residui, contando anche le prenotazioni non ancora salvate di un'anteprima
+async fn posti_residui_con(
+    conn: &mut DbConnection,
+    id_aula: i32,
+    politica: &PoliticaAula,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+    in_sospeso: &[InSospeso],
+) -> Result<i32, sqlx::Error> {
+    let sovrapposte = db::prenotazioni::utilizzi_aula(conn, id_aula, inizio, fine).await?;
+
+    let utilizzi: Vec<Utilizzo> = sovrapposte
+        .into_iter()
+        .map(|(da, a, numero_studenti)| (DateTime::from_naive_utc_and_offset(da, Utc), DateTime::from_naive_utc_and_offset(a, Utc), numero_studenti))
+        .chain(in_sospeso.iter().copied().filter(|&(da, a, _)| da < fine && a > inizio))
+        .map(|(da, a, numero_studenti)| (da.max(inizio), a.min(fine), politica.consumo(numero_studenti)))
+        .collect();
+    Ok((politica.capienza() - attrezzature::picco_utilizzo(&utilizzi)).max(0))
+}
+
+// Prenotazioni accettate da un'anteprima ma non salvate: per le richieste successive
+// della stessa anteprima occupano l'aula e l'attrezzatura come se fossero nel database
+#[derive(Debug, Default)]
+pub struct Anteprima {
+    aule: HashMap<i32, Vec<InSospeso>>,
+    attrezzature: HashMap<i32, Vec<Utilizzo>>,
+}
+
+// Controlli che non richiedono il database
+fn valida(richiesta: &RichiestaPrenotazione) -> Result<Vec<AttrezzaturaRichiesta>, ErroreApi> {
+    if richiesta.data_fine <= richiesta.data_inizio {
+        return Err(ErroreApi::campo("Data_Fine", "data_fine_non_successiva", "Data_Fine deve essere successiva a Data_Inizio."));
+    }
+    if richiesta.id_aula.is_none() && richiesta.attrezzature.is_empty() {
+        return Err(ErroreApi::richiesta_non_valida("risorsa_mancante", "Seleziona un'aula o almeno un'attrezzatura."));
+    }
+    if richiesta.numero_studenti.is_some_and(|n| n <= 0) {
+        return Err(ErroreApi::campo("Numero_Studenti", "numero_studenti_non_positivo", "Numero_Studenti deve essere positivo."));
+    }
+
+    // Più righe per la stessa risorsa vengono sommate
+    let mut unite: Vec<AttrezzaturaRichiesta> = Vec::new();
+    for voce in &richiesta.attrezzature {
+        if voce.Quantita <= 0 {
+            return Err(ErroreApi::campo("Attrezzature", "quantita_non_positiva", "La quantità di ogni attrezzatura deve essere positiva."));
+        }
+        match unite.iter_mut().find(|u| u.Id_Attrezzatura == voce.Id_Attrezzatura) {
+            Some(esistente) => esistente.Quantita += voce.Quantita,
+            None => unite.push(voce.clone()),
+        }
+    }
+    Ok(unite)
+}
+
+async fn verifica_aula(
+    conn: &mut DbConnection,
+    richiesta: &RichiestaPrenotazione,
+    id_aula: i32,
+    anteprima: Option<&Anteprima>,
+) -> Result<bool, ErroreApi> {
+    let (inizio, fine) = (richiesta.data_inizio, richiesta.data_fine);
+
+    // Blocca la riga dell'aula fino al commit: due richieste concorrenti sulla stessa aula vengono serializzate.
+    // Un'anteprima non scrive nulla e si limita a leggerla.
+    let aula = match anteprima {
+        None => db::aule::blocca(conn, id_aula).await,
+        Some(_) => db::aule::politica(&mut *conn, id_aula).await,
+    }
+        .map_err(|e| ErroreApi::database("nel bloccare l'aula", e))?;
+    let (politica, richiede_approvazione) = match aula {
+        Some((politica, capacita, richiede_approvazione)) => (PoliticaAula::from_db(&politica, capacita), richiede_approvazione),
+        None => return Err(ErroreApi::non_trovato("aula_non_trovata", "Aula non trovata.")),
+    };
+
+    // L'aula non deve essere bloccata (manutenzione, chiusura...) nell'intervallo richiesto
+    let occorrenze = blocchi::occorrenze_nel_periodo(&mut *conn, Some(id_aula), inizio, fine)
+        .await
+        .map_err(|e| ErroreApi::database("nel controllare i blocchi dell'aula", e))?;
+    if let Some(blocco) = occorrenze.first() {
+        return Err(ErroreApi::conflitto("aula_bloccata", format!("L'aula non è disponibile in questo orario: {}", blocco.motivo))
+            .con("motivo", &blocco.motivo)
+            .con("id_blocco", blocco.id_blocco));
+    }
+
+    // Né in un plesso chiuso (festività, elezioni...)
+    if let Some((id_chiusura, motivo)) = plessi::chiusura_nel_periodo(&mut *conn, id_aula, inizio, fine)
+        .await
+        .map_err(|e| ErroreApi::database("nel controllare le chiusure del plesso", e))?
+    {
+        return Err(ErroreApi::conflitto("plesso_chiuso", format!("Il plesso è chiuso in questa data: {}", motivo))
+            .con("motivo", &motivo)
+            .con("id_chiusura", id_chiusura));
+    }
+
+    // Né resa inagibile da un guasto grave ancora aperto
+    if let Some((id_segnalazione, descrizione)) = guasti::guasto_bloccante(&mut *conn, id_aula)
+        .await
+        .map_err(|e| ErroreApi::database("nel controllare i guasti dell'aula", e))?
+    {
+        return Err(ErroreApi::conflitto("aula_guasta", format!("L'aula non è prenotabile per un guasto: {}", descrizione))
+            .con("descrizione", &descrizione)
+            .con("id_segnalazione", id_segnalazione));
+    }
+
+    // Né già piena secondo la sua politica (esclusiva o condivisa)
+    let consumo = politica.consumo(richiesta.numero_studenti);
+    let in_sospeso = anteprima.and_then(|a| a.aule.get(&id_aula)).map(Vec::as_slice).unwrap_or_default();
+    let residui = posti_residui_con(conn, id_aula, &politica, inizio, fine, in_sospeso)
+        .await
+        .map_err(|e| ErroreApi::database("nel controllare le sovrapposizioni dell'aula", e))?;
+    if consumo > residui {
+        return Err(match politica.politica {
+            PoliticaPrenotazione::Esclusiva => ErroreApi::conflitto("aula_occupata", "L'aula è già prenotata in questo orario."),
+            PoliticaPrenotazione::CondivisaPrenotazioni => ErroreApi::conflitto(
+                "aula_al_completo",
+                format!("L'aula ha già raggiunto il massimo di {} prenotazioni contemporanee.", politica.capienza()),
+            )
+                .con("capienza", politica.capienza())
+                .con("posti_residui", residui),
+            PoliticaPrenotazione::CondivisaStudenti => ErroreApi::conflitto(
+                "posti_insufficienti",
+                format!("Posti insufficienti: richiesti {}, disponibili {}.", consumo, residui),
+            )
+                .con("richiesti", consumo)
+                .con("posti_residui", residui),
+        });
+    }
+    Ok(richiede_approvazione)
+}
+
+async fn verifica_attrezzatura(
+    conn: &mut DbConnection,
+    voce: &AttrezzaturaRichiesta,
+    inizio: DateTime<Utc>,
+    fine: DateTime<Utc>,
+    anteprima: Option<&Anteprima>,
+) -> Result<(), ErroreApi> {
+    let risorsa = match anteprima {
+        None => db::attrezzature::blocca(&mut *conn, voce.Id_Attrezzatura).await,
+        Some(_) => db::attrezzature::prenotabilita(&mut *conn, voce.Id_Attrezzatura).await,
+    }
+        .map_err(|e| ErroreApi::database("nel bloccare l'attrezzatura", e))?;
+    let (nome, quantita) = match risorsa {
+        Some((nome, quantita, true)) => (nome, quantita),
+        Some(_) => return Err(ErroreApi::campo("Attrezzature", "attrezzatura_non_prenotabile", "L'attrezzatura selezionata non è prenotabile.")),
+        None => return Err(ErroreApi::non_trovato("attrezzatura_non_trovata", "Attrezzatura non trovata.")),
+    };
+
+    let mut utilizzi = attrezzature::utilizzi_nel_periodo(conn, voce.Id_Attrezzatura, inizio, fine)
+        .await
+        .map_err(|e| ErroreApi::database("nel controllare gli utilizzi dell'attrezzatura", e))?;
+    if let Some(in_sospeso) = anteprima.and_then(|a| a.attrezzature.get(&voce.Id_Attrezzatura)) {
+        utilizzi.extend(
+            in_sospeso
+                .iter()
+                .filter(|&&(da, a, _)| da < fine && a > inizio)
+                .map(|&(da, a, quantita)| (da.max(inizio), a.min(fine), quantita)),
+        );
+    }
+    let disponibili = quantita - attrezzature::picco_utilizzo(&utilizzi);
+    if voce.Quantita > disponibili {
+        return Err(ErroreApi::conflitto(
+            "attrezzatura_insufficiente",
+            format!("{}: richieste {} unità, disponibili {}.", nome, voce.Quantita, disponibili.max(0)),
+        )
+            .con("nome", &nome)
+            .con("Id_Attrezzatura", voce.Id_Attrezzatura)
+            .con("richieste", voce.Quantita)
+            .con("disponibili", disponibili.max(0)));
+    }
+    Ok(())
+}
+
+// Tutti i controlli su una transazione aperta. Restituisce l'attrezzatura richiesta
+// (righe duplicate già sommate) e se l'aula richiede l'approvazione di un amministratore.
+// Con un'anteprima le righe vengono solo lette, senza bloccarle.
+async fn verifica(
+    conn: &mut DbConnection,
+    richiesta: &RichiestaPrenotazione,
+    anteprima: Option<&Anteprima>,
+) -> Result<(Vec<AttrezzaturaRichiesta>, bool), ErroreApi> {
+    let attrezzature_richieste = valida(richiesta)?;
+
+    if let Some(id_materia) = richiesta.id_materia {
+        let insegnata = db::professori::insegna(&mut *conn, richiesta.id_professore, id_materia)
+            .await
+            .map_err(|e| ErroreApi::database("nel controllare la materia", e))?;
+        if !insegnata {
+            return Err(ErroreApi::campo("Id_Materia", "materia_non_insegnata", "Il professore non insegna la materia indicata."));
+        }
+    }
+
+    let mut richiede_approvazione = false;
+    if let Some(id_aula) = richiesta.id_aula {
+        richiede_approvazione = verifica_aula(conn, richiesta, id_aula, anteprima).await?;
+    }
+    for voce in &attrezzature_richieste {
+        verifica_attrezzatura(conn, voce, richiesta.data_inizio, richiesta.data_fine, anteprima).await?;
+    }
+    Ok((attrezzature_richieste, richiede_approvazione))
+}
+
+// Gli stessi controlli di inserisci, senza scrivere nel database: se la richiesta passa
+// viene aggiunta all'anteprima, così le richieste successive ne tengono conto
+pub async fn prova(conn: &mut DbConnection, richiesta: &RichiestaPrenotazione, anteprima: &mut Anteprima) -> Result<(), ErroreApi> {
+    let (attrezzature_richieste, _) = verifica(conn, richiesta, Some(anteprima)).await?;
+    // inserisci lo scopre dalla chiave esterna
+    if let Err(e) = db::professori::professore(&mut *conn, richiesta.id_professore).await {
+        return Err(match e {
+            sqlx::Error::RowNotFound => ErroreApi::non_trovato("professore_non_trovato", "Professore non trovato."),
+            e => ErroreApi::database("nel controllare il professore", e),
+        });
+    }
+
+    let (inizio, fine) = (richiesta.data_inizio, richiesta.data_fine);
+    if let Some(id_aula) = richiesta.id_aula {
+        anteprima.aule.entry(id_aula).or_default().push((inizio, fine, richiesta.numero_studenti));
+    }
+    for voce in attrezzature_richieste {
+        anteprima.attrezzature.entry(voce.Id_Attrezzatura).or_default().push((inizio, fine, voce.Quantita));
+    }
+    Ok(())
+}
+
+// Verifica la disponibilità e inserisce la prenotazione in un'unica transazione.
+// Restituisce l'id della nuova prenotazione.
+pub async fn crea(db_pool: &DbPool, richiesta: &RichiestaPrenotazione) -> Result<i32, ErroreApi> {
+    let mut tx = db_pool.begin().await.map_err(|e| ErroreApi::database("nell'iniziare la transazione", e))?;
+    let id_prenotazione = inserisci(&mut tx, richiesta).await?;
+
+    tx.commit().await.map_err(|e| ErroreApi::database("nel fare commit della prenotazione", e))?;
+    Ok(id_prenotazione)
+}
+
+// Verifica e inserimento su una transazione già aperta dal chiamante, che decide
+// se fare commit (es. l'importazione conferma tutte le occorrenze insieme)
+pub async fn inserisci(conn: &mut DbConnection, richiesta: &RichiestaPrenotazione) -> Result<i32, ErroreApi> {
+    let (attrezzature_richieste, richiede_approvazione) = verifica(conn, richiesta, None).await?;
+
+    let id_prenotazione = match db::prenotazioni::inserisci(&mut *conn, richiesta, richiede_approvazione.then_some(false)).await {
+        Ok(id_prenotazione) => id_prenotazione,
+        Err(e) => {
+            // Su PostgreSQL il vincolo di esclusione ferma anche le sovrapposizioni sfuggite al controllo
+            if db::violazione_sovrapposizione(&e) {
+                return Err(ErroreApi::conflitto("aula_occupata", "L'aula è già prenotata in questo orario."));
+            }
+            if let Some(db_err) = e.as_database_error() {
+                if db_err.is_foreign_key_violation() {
+                    return Err(ErroreApi::non_trovato("professore_non_trovato", "Professore non trovato."));
+                }
+            }
+            return Err(ErroreApi::interno("DB durante la creazione della prenotazione", e));
+        }
+    };
+
+    for voce in &attrezzature_richieste {
+        if let Err(e) = db::prenotazioni::aggiungi_attrezzatura(&mut *conn, id_prenotazione, voce).await {
+            return Err(ErroreApi::interno(format!("DB nell'associare l'attrezzatura {} alla prenotazione", voce.Id_Attrezzatura), e));
+        }
+    }
+    Ok(id_prenotazione)
+}
+
+// Sposta una prenotazione attiva su un nuovo orario (ed eventualmente un'altra aula),
+// con gli stessi controlli della creazione. L'attrezzatura già prenotata viene riverificata.
+// Restituisce l'aula in cui si trovava prima della modifica.
+pub async fn modifica(
+    db_pool: &DbPool,
+    id_prenotazione: i32,
+    id_aula: Option<i32>,
+    data_inizio: DateTime<Utc>,
+    data_fine: DateTime<Utc>,
+    numero_studenti: Option<i32>,
+) -> Result<Option<i32>, ErroreApi> {
+    let mut tx = db_pool.begin().await.map_err(|e| ErroreApi::database("nell'iniziare la transazione", e))?;
+
+    let attuale = db::prenotazioni::blocca(&mut tx, id_prenotazione)
+        .await
+        .map_err(|e| ErroreApi::database("nel bloccare la prenotazione", e))?;
+    let id_professore = match attuale {
+        Some((id_professore, stato)) if stato == "attiva" => id_professore,
+        Some(_) => return Err(ErroreApi::conflitto("prenotazione_non_attiva", "La prenotazione è stata annullata o rilasciata.")),
+        None => return Err(ErroreApi::non_trovato("prenotazione_non_trovata", "Prenotazione non trovata.")),
+    };
+    let attrezzature = db::prenotazioni::attrezzature(&mut *tx, id_prenotazione)
+        .await
+        .map_err(|e| ErroreApi::database("nel leggere l'attrezzatura prenotata", e))?;
+    let aula_precedente = db::aggiornamenti::stato_prenotazione(&mut *tx, id_prenotazione)
+        .await
+        .map_err(|e| ErroreApi::database("nel leggere l'aula della prenotazione", e))?
+        .and_then(|(id_aula, _, _)| id_aula);
+
+    // Dentro la transazione la prenotazione non deve entrare in conflitto con se stessa:
+    // la si esclude dai controlli marcandola annullata, e la si riattiva con i nuovi dati.
+    // Se un controllo fallisce la transazione viene annullata e nulla cambia.
+    db::prenotazioni::sospendi(&mut *tx, id_prenotazione)
+        .await
+        .map_err(|e| ErroreApi::database("nel preparare la modifica", e))?;
+
+    // La materia non cambia e non va riverificata
+    let richiesta = RichiestaPrenotazione { id_professore, id_aula, data_inizio, data_fine, attrezzature, numero_studenti, id_materia: None };
+    let (_, richiede_approvazione) = verifica(&mut tx, &richiesta, None).await?;
+
+    // In un'aula con approvazione ogni modifica va riapprovata
+    db::prenotazioni::sposta(&mut *tx, id_prenotazione, id_aula, data_inizio, data_fine, numero_studenti, richiede_approvazione.then_some(false))
+        .await
+        .map_err(|e| ErroreApi::database("nel modificare la prenotazione", e))?;
+
+    tx.commit().await.map_err(|e| ErroreApi::database("nel fare commit della modifica", e))?;
+    Ok(aula_precedente)
+}
+
+// Conferma una prenotazione in attesa di approvazione. Restituisce false se non era in attesa.
+pub async fn approva(db_pool: &DbPool, id_prenotazione: i32) -> Result<bool, sqlx::Error> {
+    db::prenotazioni::approva(db_pool, id_prenotazione).await
+}
+
+// Annulla una prenotazione attiva. Resta nel database (e nei feed iCalendar come evento
+// annullato) ma non occupa più l'aula né l'attrezzatura. Restituisce false se non era attiva.
+pub async fn annulla(db_pool: &DbPool, id_prenotazione: i32) -> Result<bool, sqlx::Error> {
+    db::prenotazioni::annulla(db_pool, id_prenotazione).await
+}
+
+// Filtri comuni a tutti gli elenchi di prenotazioni (calendario, feed iCalendar...)
+#[derive(Debug, Default, Clone)]
+pub struct FiltroPrenotazioni {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub plesso: Option<i32>,
+    pub id_professore: Option<i32>,
+    pub id_aula: Option<i32>,
+    pub tipo_aula: Option<String>,
+    pub id_prenotazione: Option<i32>,
+    pub includi_annullate: bool,
+}
+
+#[derive(Debug)]
+pub struct VoceElenco {
+    pub db: models::PrenotazioneDb,
+    pub inizio: DateTime<Utc>,
+    pub fine: DateTime<Utc>,
+    pub nome_risorsa: String,     // Es. "Aula Lab 01 [Sede centrale] (+ Proiettore x1)"
+    pub nome_professore: String,
+    pub capacita_residua: Option<i32>, // Solo per le aule condivise
+    pub guasto_bloccante: bool,        // Prenotazione attiva e non ancora finita in un'aula resa inagibile da un guasto
+}
+
+// "Aula Lab 01", per chioschi e calendari delle singole aule
+pub fn nome_aula(lingua: Lingua, tipo_aula: &str, numero: i32) -> String {
+    let numero = format!("{:02}", numero);
+    lingua.traduci_con(
+        "prenotazioni.aula",
+        &format!("Aula {} {}", tipo_aula, numero),
+        &valori(&[("tipo", tipo_aula.to_string()), ("numero", numero.clone())]),
+    )
+}
+
+impl VoceElenco {
+    // Titolo dell'evento nei calendari, es. "Aula Lab 01 [Sede centrale] - Mario Rossi (posti liberi: 3)"
+    pub fn titolo(&self, lingua: Lingua) -> String {
+        let mut titolo = format!("{} - {}", self.nome_risorsa, self.nome_professore);
+        if let Some(residui) = self.capacita_residua {
+            titolo.push(' ');
+            titolo.push_str(&lingua.traduci_con(
+                "prenotazioni.posti_liberi",
+                &format!("(posti liberi: {})", residui),
+                &valori(&[("posti", residui.to_string())]),
+            ));
+        }
+        if self.db.Approvata == Some(false) {
+            titolo.push(' ');
+            titolo.push_str(&lingua.traduci("prenotazioni.da_approvare", "[da approvare]"));
+        }
+        titolo
+    }
+
+    // Descrizione dell'evento nei calendari
+    pub fn descrizione(&self, lingua: Lingua) -> String {
+        lingua.traduci_con(
+            "prenotazioni.prenotato_da",
+            &format!("Prenotato da {}", self.nome_professore),
+            &valori(&[("nome", self.nome_professore.clone())]),
+        )
+    }
+}
+
+// Prenotazioni che rispettano il filtro, con i nomi di risorsa e professore già composti
+pub async fn elenco(db_pool: &DbPool, filtro: &FiltroPrenotazioni) -> Result<Vec<VoceElenco>, sqlx::Error> {
+    let prenotazioni_db = db::prenotazioni::elenco(db_pool, filtro).await?;
+
+    // Attrezzature prenotate, raggruppate per prenotazione (es. "Carrello portatili x2")
+    let righe = db::prenotazioni::attrezzature_prenotate(db_pool, filtro).await?;
+    let mut attrezzature_prenotate: HashMap<i32, Vec<String>> = HashMap::new();
+    for (id_prenotazione, nome, quantita) in righe {
+        attrezzature_prenotate
+            .entry(id_prenotazione)
+            .or_default()
+            .push(format!("{} x{}", nome, quantita));
+    }
+
+    // Periodo coperto dalle prenotazioni elencate in ciascuna aula condivisa
+    let mut periodi_condivise: HashMap<i32, (PoliticaAula, DateTime<Utc>, DateTime<Utc>)> = HashMap::new();
+    for p_db in prenotazioni_db.iter().filter(|p| p.Stato == "attiva") {
+        if let (Some(id_aula), Some(politica)) = (p_db.Id_Aula, p_db.Politica_Prenotazione.as_deref()) {
+            let politica = PoliticaAula::from_db(politica, p_db.Capacita);
+            if politica.is_condivisa() {
+                let inizio = DateTime::from_naive_utc_and_offset(p_db.Data_Inizio, Utc);
+                let fine = DateTime::from_naive_utc_and_offset(p_db.Data_Fine, Utc);
+                let periodo = periodi_condivise.entry(id_aula).or_insert((politica, inizio, fine));
+                periodo.1 = periodo.1.min(inizio);
+                periodo.2 = periodo.2.max(fine);
+            }
+        }
+    }
+    let inagibili = guasti::aule_inagibili(db_pool).await?;
+    let adesso = Utc::now();
+
+    // Utilizzi di tutte le prenotazioni attive di quelle aule, anche escluse dal filtro
+    // (di altri professori o fuori dal periodo richiesto), per calcolare i posti rimasti
+    let mut utilizzi_condivise: HashMap<i32, Vec<Utilizzo>> = HashMap::new();
+    for (id_aula, (politica, inizio, fine)) in periodi_condivise {
+        let utilizzi = db::prenotazioni::utilizzi_aula(db_pool, id_aula, inizio, fine).await?;
+        utilizzi_condivise.insert(id_aula, utilizzi
+            .into_iter()
+            .map(|(da, a, numero_studenti)| (
+                DateTime::from_naive_utc_and_offset(da, Utc),
+                DateTime::from_naive_utc_and_offset(a, Utc),
+                politica.consumo(numero_studenti),
+            ))
+            .collect());
+    }
+
+    Ok(prenotazioni_db
+        .into_iter()
+        .map(|p_db| {
+            let attrezzature = attrezzature_prenotate.get(&p_db.Id_Prenotazione).map(|v| v.join(", "));
+            // Il plesso distingue aule con lo stesso nome in sedi diverse
+            let plesso = p_db.Nome_Plesso.as_deref().map(|n| format!(" [{}]", n)).unwrap_or_default();
+            let nome_risorsa = match (&p_db.Tipo_Aula, p_db.Numero_Aula, attrezzature) {
+                (Some(tipo), Some(numero), None) => format!("Aula {} {:02}{}", tipo, numero, plesso),
+                (Some(tipo), Some(numero), Some(attr)) => format!("Aula {} {:02}{} (+ {})", tipo, numero, plesso, attr),
+                (_, _, Some(attr)) => attr,
+                _ => "N/D".to_string(),
+            };
+            let nome_professore = format!("{} {}", p_db.Nome_Professore.as_deref().unwrap_or("N/D"), p_db.Cognome_Professore);
+
+            // Poiché Data_Inizio e Data_Fine dal DB sono NaiveDateTime ma rappresentano UTC,
+            // li convertiamo in DateTime<Utc> specificando che sono già UTC.
+            let inizio: DateTime<Utc> = DateTime::from_naive_utc_and_offset(p_db.Data_Inizio, Utc);
+            let fine: DateTime<Utc> = DateTime::from_naive_utc_and_offset(p_db.Data_Fine, Utc);
+
+            // Posti rimasti nell'aula condivisa durante questa prenotazione
+            let capacita_residua = match (p_db.Id_Aula, p_db.Politica_Prenotazione.as_deref()) {
+                (Some(id_aula), Some(politica)) if p_db.Stato == "attiva" => utilizzi_condivise.get(&id_aula).map(|utilizzi| {
+                    let politica = PoliticaAula::from_db(politica, p_db.Capacita);
+                    let sovrapposti: Vec<_> = utilizzi
+                        .iter()
+                        .filter(|(u_inizio, u_fine, _)| *u_inizio < fine && *u_fine > inizio)
+                        .map(|&(u_inizio, u_fine, consumo)| (u_inizio.max(inizio), u_fine.min(fine), consumo))
+                        .collect();
+                    (politica.capienza() - attrezzature::picco_utilizzo(&sovrapposti)).max(0)
+                }),
+                _ => None,
+            };
+            let guasto_bloccante = p_db.Stato == "attiva" && fine > adesso && p_db.Id_Aula.is_some_and(|id_aula| inagibili.contains(&id_aula));
+
+            VoceElenco { db: p_db, inizio, fine, nome_risorsa, nome_professore, capacita_residua, guasto_bloccante }
+        })
+        .collect())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::prova::{self, richiesta, stato, utc};
+
+    fn codice(esito: Result<i32, ErroreApi>) -> &'static str {
+        esito.expect_err("prenotazione accettata").codice()
+    }
+
+    #[tokio::test]
+    async fn crea_modifica_e_annulla() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "esclusiva", None).await;
+
+        let prima = crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T09:00:00Z", None)).await.unwrap();
+        let seconda = crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T09:00:00Z", "2030-01-07T10:00:00Z", None)).await.unwrap();
+
+        // Spostarla sopra l'altra non si può, sopra se stessa sì
+        let esito = modifica(&db_pool, seconda, Some(id_aula), utc("2030-01-07T08:30:00Z"), utc("2030-01-07T09:30:00Z"), None).await;
+        assert_eq!(esito.unwrap_err().codice(), "aula_occupata");
+        modifica(&db_pool, prima, Some(id_aula), utc("2030-01-07T07:30:00Z"), utc("2030-01-07T08:30:00Z"), None).await.unwrap();
+
+        let filtro = FiltroPrenotazioni { id_prenotazione: Some(prima), ..Default::default() };
+        let voce = elenco(&db_pool, &filtro).await.unwrap().remove(0);
+        assert_eq!((voce.inizio, voce.fine), (utc("2030-01-07T07:30:00Z"), utc("2030-01-07T08:30:00Z")));
+        assert_eq!(voce.db.Sequenza, 1);
+
+        assert!(annulla(&db_pool, prima).await.unwrap());
+        assert!(!annulla(&db_pool, prima).await.unwrap());
+        assert_eq!(stato(&db_pool, prima).await, "annullata");
+        let esito = modifica(&db_pool, prima, Some(id_aula), utc("2030-01-07T07:00:00Z"), utc("2030-01-07T08:00:00Z"), None).await;
+        assert_eq!(esito.unwrap_err().codice(), "prenotazione_non_attiva");
+
+        // L'orario annullato torna libero
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T07:30:00Z", "2030-01-07T08:30:00Z", None)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rifiuta_le_sovrapposizioni_in_un_aula_esclusiva() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_collega = prova::professore(&db_pool, "Bianchi").await;
+        let id_aula = prova::aula(&db_pool, "esclusiva", None).await;
+
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T10:00:00Z", None)).await.unwrap();
+
+        let dentro = richiesta(id_collega, id_aula, "2030-01-07T08:30:00Z", "2030-01-07T09:00:00Z", None);
+        assert_eq!(codice(crea(&db_pool, &dentro).await), "aula_occupata");
+        let a_cavallo = richiesta(id_collega, id_aula, "2030-01-07T09:59:00Z", "2030-01-07T11:00:00Z", None);
+        assert_eq!(codice(crea(&db_pool, &a_cavallo).await), "aula_occupata");
+
+        // Gli estremi si possono toccare
+        crea(&db_pool, &richiesta(id_collega, id_aula, "2030-01-07T10:00:00Z", "2030-01-07T11:00:00Z", None)).await.unwrap();
+        crea(&db_pool, &richiesta(id_collega, id_aula, "2030-01-07T07:00:00Z", "2030-01-07T08:00:00Z", None)).await.unwrap();
+
+        let senza_professore = richiesta(9999, id_aula, "2030-01-08T08:00:00Z", "2030-01-08T09:00:00Z", None);
+        assert_eq!(codice(crea(&db_pool, &senza_professore).await), "professore_non_trovato");
+        let senza_aula = richiesta(id_professore, 9999, "2030-01-08T08:00:00Z", "2030-01-08T09:00:00Z", None);
+        assert_eq!(codice(crea(&db_pool, &senza_aula).await), "aula_non_trovata");
+    }
+
+    #[tokio::test]
+    async fn rispetta_i_blocchi_ricorrenti() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "esclusiva", None).await;
+
+        // Ogni lunedì 8-10 per tre settimane
+        db::blocchi::inserisci(
+            &db_pool,
+            id_aula,
+            utc("2030-01-07T08:00:00Z"),
+            utc("2030-01-07T10:00:00Z"),
+            "Manutenzione",
+            "settimanale",
+            Some(utc("2030-01-21T23:00:00Z")),
+        )
+            .await
+            .unwrap();
+
+        let lunedi = richiesta(id_professore, id_aula, "2030-01-14T09:00:00Z", "2030-01-14T09:30:00Z", None);
+        let errore = crea(&db_pool, &lunedi).await.unwrap_err();
+        assert_eq!(errore.codice(), "aula_bloccata");
+        assert_eq!(errore.messaggio(), "L'aula non è disponibile in questo orario: Manutenzione");
+
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-14T10:00:00Z", "2030-01-14T11:00:00Z", None)).await.unwrap();
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-15T09:00:00Z", "2030-01-15T09:30:00Z", None)).await.unwrap();
+        // Dopo la fine della ricorrenza il lunedì torna libero
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-28T09:00:00Z", "2030-01-28T09:30:00Z", None)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn un_aula_condivisa_per_studenti_somma_i_posti() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "condivisa_studenti", Some(30)).await;
+
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T10:00:00Z", Some(20))).await.unwrap();
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T09:00:00Z", "2030-01-07T11:00:00Z", Some(10))).await.unwrap();
+
+        let troppi = richiesta(id_professore, id_aula, "2030-01-07T09:30:00Z", "2030-01-07T10:30:00Z", Some(1));
+        let errore = crea(&db_pool, &troppi).await.unwrap_err();
+        assert_eq!(errore.codice(), "posti_insufficienti");
+        assert_eq!(errore.messaggio(), "Posti insufficienti: richiesti 1, disponibili 0.");
+        // Senza numero di studenti si occupa l'intera aula
+        let intera = richiesta(id_professore, id_aula, "2030-01-07T10:00:00Z", "2030-01-07T10:30:00Z", None);
+        assert_eq!(codice(crea(&db_pool, &intera).await), "posti_insufficienti");
+
+        // Dalle 10 restano i 20 posti lasciati dalla prima prenotazione
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T10:00:00Z", "2030-01-07T11:00:00Z", Some(20))).await.unwrap();
+
+        let mut conn = db_pool.acquire().await.unwrap();
+        let politica = PoliticaAula::from_db("condivisa_studenti", Some(30));
+        let residui = posti_residui(&mut conn, id_aula, &politica, utc("2030-01-07T07:00:00Z"), utc("2030-01-07T08:30:00Z")).await.unwrap();
+        assert_eq!(residui, 10);
+    }
+
+    #[tokio::test]
+    async fn un_aula_condivisa_per_prenotazioni_ne_conta_il_numero() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "condivisa_prenotazioni", Some(2)).await;
+
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T09:00:00Z", Some(25))).await.unwrap();
+        let seconda = crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:30:00Z", "2030-01-07T09:30:00Z", None)).await.unwrap();
+
+        let terza = richiesta(id_professore, id_aula, "2030-01-07T08:45:00Z", "2030-01-07T09:15:00Z", Some(1));
+        assert_eq!(codice(crea(&db_pool, &terza).await), "aula_al_completo");
+
+        // Annullandone una si libera un posto
+        assert!(annulla(&db_pool, seconda).await.unwrap());
+        crea(&db_pool, &terza).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn il_titolo_e_nella_lingua_richiesta() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "condivisa_studenti", Some(30)).await;
+        let id_prenotazione = crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T09:00:00Z", Some(20))).await.unwrap();
+        sqlx::query("UPDATE prenotazione SET Approvata = 0 WHERE Id_Prenotazione = ?")
+            .bind(id_prenotazione)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        let voce = elenco(&db_pool, &FiltroPrenotazioni::default()).await.unwrap().remove(0);
+        assert_eq!(voce.titolo(Lingua::Italiano), "Aula Lab 01 [Sede centrale] - Prova Rossi (posti liberi: 10) [da approvare]");
+        assert_eq!(voce.titolo(Lingua::Inglese), "Aula Lab 01 [Sede centrale] - Prova Rossi (free seats: 10) [awaiting approval]");
+        assert_eq!(voce.descrizione(Lingua::Inglese), "Booked by Prova Rossi");
+        assert_eq!(nome_aula(Lingua::Inglese, "Lab", 1), "Room Lab 01");
+    }
+
+    #[tokio::test]
+    async fn segnala_le_prenotazioni_colpite_da_un_guasto_bloccante() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "esclusiva", None).await;
+        let futura = crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T09:00:00Z", None)).await.unwrap();
+        crea(&db_pool, &richiesta(id_professore, id_aula, "2020-01-07T08:00:00Z", "2020-01-07T09:00:00Z", None)).await.unwrap();
+        let annullata = crea(&db_pool, &richiesta(id_professore, id_aula, "2030-01-08T08:00:00Z", "2030-01-08T09:00:00Z", None)).await.unwrap();
+        assert!(annulla(&db_pool, annullata).await.unwrap());
+
+        let colpite = |elenco: Vec<VoceElenco>| -> Vec<i32> {
+            elenco.into_iter().filter(|v| v.guasto_bloccante).map(|v| v.db.Id_Prenotazione).collect()
+        };
+        let filtro = FiltroPrenotazioni { includi_annullate: true, ..Default::default() };
+        assert!(colpite(elenco(&db_pool, &filtro).await.unwrap()).is_empty());
+
+        // Un guasto grave che non blocca l'aula non conta
+        db::guasti::inserisci(&db_pool, Some(id_aula), None, id_professore, "alta", "Proiettore rotto", false).await.unwrap();
+        assert!(colpite(elenco(&db_pool, &filtro).await.unwrap()).is_empty());
+
+        db::guasti::inserisci(&db_pool, Some(id_aula), None, id_professore, "alta", "Allagamento", true).await.unwrap();
+        // Né quelle già svolte o annullate
+        assert_eq!(colpite(elenco(&db_pool, &filtro).await.unwrap()), vec![futura]);
+    }
+
+    #[tokio::test]
+    async fn l_anteprima_non_scrive_ma_ricorda_le_richieste_accettate() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        let id_aula = prova::aula(&db_pool, "esclusiva", None).await;
+
+        let mut conn = db_pool.acquire().await.unwrap();
+        let mut anteprima = Anteprima::default();
+        let prima = richiesta(id_professore, id_aula, "2030-01-07T08:00:00Z", "2030-01-07T09:00:00Z", None);
+        prova(&mut conn, &prima, &mut anteprima).await.unwrap();
+        let sovrapposta = richiesta(id_professore, id_aula, "2030-01-07T08:30:00Z", "2030-01-07T09:30:00Z", None);
+        assert_eq!(prova(&mut conn, &sovrapposta, &mut anteprima).await.unwrap_err().codice(), "aula_occupata");
+        drop(conn);
+
+        assert!(elenco(&db_pool, &FiltroPrenotazioni::default()).await.unwrap().is_empty());
+        crea(&db_pool, &sovrapposta).await.unwrap();
+    }
+}
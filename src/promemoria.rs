@@ -0,0 +1,197 @@
+// src/promemoria.rs
+// Pianificatore interno al processo: promemoria poco prima di ogni prenotazione e, per chi lo
+// ha attivato, riepilogo serale delle prenotazioni del giorno dopo.
+// Ogni attività eseguita viene registrata in attivita_pianificata con una chiave univoca, nella
+// stessa transazione che accoda l'email: dopo un riavvio, o con più istanze, la stessa attività
+// non parte due volte. Una prenotazione modificata ha una nuova Sequenza e riceve un nuovo promemoria.
+//
+// Configurazione: PROMEMORIA_MINUTI_PRIMA (predefinito 60) e RIEPILOGO_ORA (predefinito 18:00, ora di Roma).
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use chrono_tz::Europe::Rome;
+use minijinja::context;
+
+use crate::configurazione;
+use crate::db::{self, DbPool};
+use crate::metriche;
+use crate::notifiche::{self, Notifica};
+use crate::plessi;
+use crate::prenotazioni::{self, FiltroPrenotazioni};
+
+const INTERVALLO_CONTROLLO_SECONDI: u64 = 60;
+const INTERVALLO_PULIZIA_SECONDI: u64 = 60 * 60;
+const GIORNI_CONSERVAZIONE: i64 = 30;
+
+fn ora_locale(data: DateTime<Utc>, formato: &str) -> String {
+    data.with_timezone(&Rome).format(formato).to_string()
+}
+
+// Prenotazioni che iniziano entro l'anticipo configurato. Dopo un riavvio partono in ritardo
+// i promemoria ancora utili; quelli di prenotazioni già iniziate vengono saltati.
+async fn invia_promemoria(db_pool: &DbPool, anticipo: Duration) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now();
+    let filtro = FiltroPrenotazioni { start: Some(adesso), end: Some(adesso + anticipo), ..Default::default() };
+    for voce in prenotazioni::elenco(db_pool, &filtro).await? {
+        if voce.inizio <= adesso || voce.db.Approvata == Some(false) {
+            continue;
+        }
+        let chiave = format!("promemoria:{}:{}", voce.db.Id_Prenotazione, voce.db.Sequenza);
+        let mut tx = db_pool.begin().await?;
+        if !db::promemoria::segna_eseguita(&mut *tx, &chiave).await? {
+            continue;
+        }
+        if let Some((email, nome, lingua)) = notifiche::destinatario(&mut *tx, voce.db.Id_Professore, Notifica::Promemoria).await? {
+            let contesto = context! {
+                nome => nome,
+                risorsa => voce.nome_risorsa,
+                ora => ora_locale(voce.inizio, "%H:%M"),
+                inizio => ora_locale(voce.inizio, "%d/%m/%Y %H:%M"),
+                fine => ora_locale(voce.fine, "%d/%m/%Y %H:%M"),
+            };
+            match notifiche::componi(lingua, Notifica::Promemoria.modello(), contesto) {
+                Ok((oggetto, corpo)) => notifiche::accoda_email(&mut *tx, voce.db.Id_Professore, &email, &oggetto, &corpo).await?,
+                Err(e) => tracing::error!(errore = %e, "Errore nel comporre il promemoria della prenotazione {}", voce.db.Id_Prenotazione),
+            }
+        }
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+// Dopo l'ora del riepilogo, una sola email per professore con le prenotazioni di domani
+async fn invia_riepiloghi(db_pool: &DbPool, ora_riepilogo: NaiveTime) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now().with_timezone(&Rome);
+    if adesso.time() < ora_riepilogo {
+        return Ok(());
+    }
+    let domani = adesso.date_naive() + Duration::days(1);
+    let (da, a) = match (plessi::in_utc(domani, NaiveTime::MIN), plessi::in_utc(domani + Duration::days(1), NaiveTime::MIN)) {
+        (Some(da), Some(a)) => (da, a),
+        _ => return Ok(()),
+    };
+
+    let iscritti = db::promemoria::iscritti_riepilogo(db_pool).await?;
+    for id_professore in iscritti {
+        let chiave = format!("riepilogo:{}:{}", id_professore, domani);
+        let mut tx = db_pool.begin().await?;
+        if !db::promemoria::segna_eseguita(&mut *tx, &chiave).await? {
+            continue;
+        }
+        let filtro = FiltroPrenotazioni { start: Some(da), end: Some(a), id_professore: Some(id_professore), ..Default::default() };
+        let voci = prenotazioni::elenco(db_pool, &filtro).await?;
+        // Nessuna email se domani non ci sono prenotazioni
+        if !voci.is_empty() {
+            if let Some((email, nome, lingua)) = notifiche::destinatario(&mut *tx, id_professore, Notifica::Riepilogo).await? {
+                let elenco: Vec<minijinja::Value> = voci
+                    .iter()
+                    .map(|voce| context! {
+                        risorsa => voce.nome_risorsa,
+                        ora_inizio => ora_locale(voce.inizio, "%H:%M"),
+                        ora_fine => ora_locale(voce.fine, "%H:%M"),
+                        da_approvare => voce.db.Approvata == Some(false),
+                    })
+                    .collect();
+                let contesto = context! {
+                    nome => nome,
+                    giorno => domani.format("%d/%m/%Y").to_string(),
+                    prenotazioni => elenco,
+                };
+                match notifiche::componi(lingua, Notifica::Riepilogo.modello(), contesto) {
+                    Ok((oggetto, corpo)) => notifiche::accoda_email(&mut *tx, id_professore, &email, &oggetto, &corpo).await?,
+                    Err(e) => tracing::error!(errore = %e, "Errore nel comporre il riepilogo del professore {}", id_professore),
+                }
+            }
+        }
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+// Task in background del pianificatore; non parte se le notifiche email sono disattivate
+pub async fn avvia_pianificatore(db_pool: DbPool) {
+    if !notifiche::notifiche_attive() {
+        return;
+    }
+    let configurazione = configurazione::attuale();
+    let mut ultima_pulizia: Option<std::time::Instant> = None;
+    loop {
+        let esito = invia_promemoria(&db_pool, configurazione.anticipo_promemoria).await;
+        metriche::lavoro("promemoria", &esito);
+        if let Err(e) = esito {
+            tracing::error!(errore = %e, "Errore DB nell'invio dei promemoria");
+        }
+        let esito = invia_riepiloghi(&db_pool, configurazione.ora_riepilogo).await;
+        metriche::lavoro("riepiloghi", &esito);
+        if let Err(e) = esito {
+            tracing::error!(errore = %e, "Errore DB nell'invio dei riepiloghi serali");
+        }
+        if ultima_pulizia.is_none_or(|istante| istante.elapsed().as_secs() >= INTERVALLO_PULIZIA_SECONDI) {
+            let limite = Utc::now() - Duration::days(GIORNI_CONSERVAZIONE);
+            let esito = db::promemoria::elimina_precedenti(&db_pool, limite).await;
+            metriche::lavoro("pulizia_attivita", &esito);
+            if let Err(e) = esito {
+                tracing::error!(errore = %e, "Errore DB nella pulizia delle attività pianificate");
+            }
+            ultima_pulizia = Some(std::time::Instant::now());
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(INTERVALLO_CONTROLLO_SECONDI)).await;
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::prova;
+
+    async fn email_accodate(db_pool: &DbPool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM email_in_uscita").fetch_one(db_pool).await.unwrap()
+    }
+
+    // Prenotazione di un'ora che inizia tra mezz'ora: identificativo, aula e inizio
+    async fn tra_mezz_ora(db_pool: &DbPool, id_professore: i32) -> (i32, i32, DateTime<Utc>) {
+        let id_aula = prova::aula(db_pool, "esclusiva", None).await;
+        let inizio = Utc::now() + Duration::minutes(30);
+        let richiesta = prenotazioni::RichiestaPrenotazione {
+            data_inizio: inizio,
+            data_fine: inizio + Duration::hours(1),
+            ..prova::richiesta(id_professore, id_aula, "2030-01-01T08:00:00Z", "2030-01-01T09:00:00Z", None)
+        };
+        (prenotazioni::crea(db_pool, &richiesta).await.unwrap(), id_aula, inizio)
+    }
+
+    #[tokio::test]
+    async fn il_promemoria_parte_una_volta_per_ogni_versione_della_prenotazione() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        prova::credenziali(&db_pool, id_professore, "rossi@scuola.it").await;
+        let (id_prenotazione, id_aula, inizio) = tra_mezz_ora(&db_pool, id_professore).await;
+
+        // Due giri del pianificatore, come dopo un riavvio o con due istanze
+        invia_promemoria(&db_pool, Duration::hours(1)).await.unwrap();
+        invia_promemoria(&db_pool, Duration::hours(1)).await.unwrap();
+        assert_eq!(email_accodate(&db_pool).await, 1);
+
+        // Spostata di un quarto d'ora: nuova Sequenza, nuovo promemoria
+        let inizio = inizio + Duration::minutes(15);
+        prenotazioni::modifica(&db_pool, id_prenotazione, Some(id_aula), inizio, inizio + Duration::hours(1), None).await.unwrap();
+        invia_promemoria(&db_pool, Duration::hours(1)).await.unwrap();
+        invia_promemoria(&db_pool, Duration::hours(1)).await.unwrap();
+        assert_eq!(email_accodate(&db_pool).await, 2);
+    }
+
+    #[tokio::test]
+    async fn senza_il_consenso_il_promemoria_non_viene_accodato() {
+        let db_pool = prova::database().await;
+        let id_professore = prova::professore(&db_pool, "Rossi").await;
+        prova::credenziali(&db_pool, id_professore, "rossi@scuola.it").await;
+        sqlx::query("INSERT INTO preferenza_notifica (Id_Professore, Creata, Modificata, Annullata, Approvata, Scavalcata, Promemoria, Riepilogo) VALUES (?, TRUE, TRUE, TRUE, TRUE, TRUE, FALSE, FALSE)")
+            .bind(id_professore)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        tra_mezz_ora(&db_pool, id_professore).await;
+
+        invia_promemoria(&db_pool, Duration::hours(1)).await.unwrap();
+        assert_eq!(email_accodate(&db_pool).await, 0);
+    }
+}
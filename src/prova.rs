@@ -0,0 +1,94 @@
+// src/prova.rs
+// Database SQLite in memoria per i test che passano dal database, con le migrazioni applicate
+// e pochi dati di partenza. Ogni test ha il suo database: i test possono girare in parallelo.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+use rocket::figment::Figment;
+
+use crate::configurazione;
+use crate::db::{self, DbPool};
+use crate::prenotazioni::RichiestaPrenotazione;
+
+static DATABASE_CREATI: AtomicUsize = AtomicUsize::new(0);
+
+// Alcune funzioni leggono la configurazione globale (notifiche, tolleranze...): SMTP resta spento
+fn configura() {
+    let figment = Figment::new()
+        .merge(("database_url", "sqlite::memory:"))
+        .merge(("jwt_secret", "segreto-dei-test-lungo-almeno-32-caratteri"))
+        .merge(("cartella_frontend", ""));
+    configurazione::imposta(configurazione::carica(&figment).expect("Configurazione dei test non valida"));
+}
+
+pub async fn database() -> DbPool {
+    configura();
+    // Con cache condivisa tutte le connessioni del pool vedono lo stesso database in memoria
+    let numero = DATABASE_CREATI.fetch_add(1, Ordering::Relaxed);
+    let db_pool = db::connetti(&format!("sqlite:file:prova{}?mode=memory&cache=shared", numero))
+        .await
+        .expect("Connessione al database di prova");
+    db::migra(&db_pool).await.expect("Migrazioni del database di prova");
+    db_pool
+}
+
+pub async fn professore(db_pool: &DbPool, cognome: &str) -> i32 {
+    sqlx::query("INSERT INTO professore (Nome, Cognome) VALUES ('Prova', ?)")
+        .bind(cognome)
+        .execute(db_pool)
+        .await
+        .expect("Inserimento del professore")
+        .last_insert_rowid() as i32
+}
+
+// Aula in un plesso nuovo, con la politica di prenotazione indicata
+pub async fn aula(db_pool: &DbPool, politica: &str, capacita: Option<i32>) -> i32 {
+    let id_plesso = sqlx::query("INSERT INTO plesso (Nome) VALUES ('Sede centrale')")
+        .execute(db_pool)
+        .await
+        .expect("Inserimento del plesso")
+        .last_insert_rowid();
+    sqlx::query("INSERT INTO aula (Tipo_Aula, Numero, Id_Plesso, Politica_Prenotazione, Capacita) VALUES ('Lab', 1, ?, ?, ?)")
+        .bind(id_plesso)
+        .bind(politica)
+        .bind(capacita)
+        .execute(db_pool)
+        .await
+        .expect("Inserimento dell'aula")
+        .last_insert_rowid() as i32
+}
+
+pub fn richiesta(id_professore: i32, id_aula: i32, inizio: &str, fine: &str, numero_studenti: Option<i32>) -> RichiestaPrenotazione {
+    RichiestaPrenotazione {
+        id_professore,
+        id_aula: Some(id_aula),
+        data_inizio: utc(inizio),
+        data_fine: utc(fine),
+        attrezzature: Vec::new(),
+        numero_studenti,
+        id_materia: None,
+    }
+}
+
+pub fn utc(testo: &str) -> DateTime<Utc> {
+    testo.parse().expect("Data di prova non valida")
+}
+
+pub async fn stato(db_pool: &DbPool, id_prenotazione: i32) -> String {
+    sqlx::query_scalar("SELECT Stato FROM prenotazione WHERE Id_Prenotazione = ?")
+        .bind(id_prenotazione)
+        .fetch_one(db_pool)
+        .await
+        .expect("Lettura dello stato della prenotazione")
+}
+
+// Credenziali di accesso, necessarie perché il professore riceva le email
+pub async fn credenziali(db_pool: &DbPool, id_professore: i32, email: &str) {
+    sqlx::query("INSERT INTO credenziali (Id_Professore_Cred, email, password_hash) VALUES (?, ?, 'hash')")
+        .bind(id_professore)
+        .bind(email)
+        .execute(db_pool)
+        .await
+        .expect("Inserimento delle credenziali");
+}
@@ -0,0 +1,123 @@
+// src/registro.rs
+// Log strutturati e tracciamento delle richieste. Ogni richiesta riceve un id, restituito
+// nell'intestazione X-Request-Id (o ripreso da quella in arrivo, ad esempio da un proxy), che
+// compare in tutte le righe di log che la riguardano: la segnalazione di un utente si ritrova così nel log.
+// LOG_FORMATO=json scrive una riga JSON per evento, altrimenti testo leggibile.
+// LOG_LIVELLO segue la sintassi di RUST_LOG (es. "debug" o "info,planner::notifiche=debug"); vedi configurazione.rs.
+
+use std::time::Instant;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Data, Request, Response};
+use tracing::{Level, Metadata};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+use crate::metriche;
+
+pub const INTESTAZIONE_ID: &str = "X-Request-Id";
+
+// Rocket registra già ogni richiesta, con le guardie fallite e le risposte: lo fa qui il fairing, in una riga sola
+pub const LIVELLO_PREDEFINITO: &str = "info,rocket=error,rocket::launch=warn,hyper=warn,sqlx=warn";
+
+// I dettagli di Rocket su una singola richiesta hanno come target "<modulo>::_"; restano solo gli errori
+fn dettaglio_di_rocket(metadati: &Metadata<'_>) -> bool {
+    metadati.target().ends_with("::_") && *metadati.level() > Level::ERROR
+}
+
+// Il livello è già verificato dalla configurazione; se la configurazione non è valida si usa quello predefinito
+pub fn inizializza(livello: &str, json: bool) {
+    let filtro = EnvFilter::try_new(livello).unwrap_or_else(|_| EnvFilter::new(LIVELLO_PREDEFINITO));
+    let registratore = tracing_subscriber::fmt().with_env_filter(filtro);
+    let senza_dettagli = filter_fn(|metadati| !dettaglio_di_rocket(metadati));
+    // Fallisce solo se un registratore è già installato (es. un secondo avvio nello stesso processo)
+    let _ = if json {
+        registratore.json().flatten_event(true).with_current_span(false).finish().with(senza_dettagli).try_init()
+    } else {
+        registratore.finish().with(senza_dettagli).try_init()
+    };
+}
+
+struct IdRichiesta(String);
+struct InizioRichiesta(Instant);
+struct ProfessoreRichiesta(Option<i32>);
+
+// Solo id ragionevoli: finiscono nel log e nella risposta
+fn id_accettabile(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+pub fn id_richiesta(req: &Request<'_>) -> String {
+    req.local_cache(|| {
+        let id = req
+            .headers()
+            .get_one(INTESTAZIONE_ID)
+            .filter(|id| id_accettabile(id))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+        IdRichiesta(id)
+    })
+    .0
+    .clone()
+}
+
+// Chiamata dalla guardia di autenticazione: il professore compare nella riga di log della richiesta
+pub fn imposta_professore(req: &Request<'_>, id_professore: i32) {
+    req.local_cache(|| ProfessoreRichiesta(Some(id_professore)));
+}
+
+pub fn professore(req: &Request<'_>) -> Option<i32> {
+    req.local_cache(|| ProfessoreRichiesta(None)).0
+}
+
+pub struct Tracciamento;
+
+#[rocket::async_trait]
+impl Fairing for Tracciamento {
+    fn info(&self) -> Info {
+        Info { name: "Tracciamento delle richieste", kind: Kind::Request | Kind::Response }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        req.local_cache(|| InizioRichiesta(Instant::now()));
+        id_richiesta(req);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let id = id_richiesta(req);
+        let latenza_ms = req.local_cache(|| InizioRichiesta(Instant::now())).0.elapsed().as_secs_f64() * 1000.0;
+        let stato = res.status().code;
+        let metodo = req.method().as_str();
+        // Senza query string: può contenere token (feed dei calendari, codici QR)
+        let percorso = req.uri().path().as_str();
+        let id_professore = professore(req);
+        // Per le metriche il modello della route: gli id nel percorso moltiplicherebbero le serie
+        let route = req.route().map_or("nessuna", |route| route.uri.origin.path().as_str());
+        metriche::richiesta(metodo, route, stato, latenza_ms / 1000.0);
+        res.set_header(Header::new(INTESTAZIONE_ID, id.clone()));
+
+        macro_rules! registra {
+            ($livello:ident) => {
+                tracing::$livello!(
+                    id_richiesta = %id,
+                    metodo,
+                    percorso,
+                    stato,
+                    latenza_ms = (latenza_ms * 10.0).round() / 10.0,
+                    id_professore,
+                    "Richiesta servita"
+                )
+            };
+        }
+        if stato >= 500 {
+            registra!(warn);
+        } else if percorso.starts_with("/api") {
+            registra!(info);
+        } else {
+            // File statici del frontend
+            registra!(debug);
+        }
+    }
+}
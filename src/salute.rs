@@ -0,0 +1,51 @@
+// src/salute.rs
+// Sonde per il supervisore del servizio (systemd, Kubernetes, il bilanciatore):
+//   GET /salute/vivo    il processo risponde (liveness); non tocca il database
+//   GET /salute/pronto  il server può servire le richieste (readiness): database raggiungibile
+//                       e tutte le migrazioni applicate
+// Rispondono 200 oppure 503, senza autenticazione; il dettaglio degli errori resta nel log.
+
+use std::time::Duration;
+
+use rocket::http::Status;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+
+use crate::db::{self, DbPool};
+
+// Una sonda deve rispondere in fretta anche quando il pool aspetterebbe una connessione libera
+const TIMEOUT_CONTROLLO: Duration = Duration::from_secs(3);
+
+#[get("/salute/vivo")]
+pub fn vivo() -> Json<JsonValue> {
+    Json(json!({"status": "vivo"}))
+}
+
+#[get("/salute/pronto")]
+pub async fn pronto(db_pool: &State<DbPool>) -> (Status, Json<JsonValue>) {
+    let controllo = tokio::time::timeout(TIMEOUT_CONTROLLO, db::migrazioni_mancanti(db_pool.inner())).await;
+    let (database, migrazioni) = match controllo {
+        Ok(Ok(mancanti)) if mancanti.is_empty() => ("ok", json!("ok")),
+        Ok(Ok(mancanti)) => {
+            tracing::warn!(?mancanti, "Migrazioni del database non applicate");
+            ("ok", json!({"mancanti": mancanti}))
+        }
+        Ok(Err(e)) => {
+            // Database irraggiungibile, oppure schema mai creato (manca la tabella delle migrazioni)
+            tracing::warn!(errore = %e, "Controllo del database non riuscito");
+            ("errore", json!("sconosciute"))
+        }
+        Err(_) => {
+            tracing::warn!(timeout_secondi = TIMEOUT_CONTROLLO.as_secs(), "Il database non ha risposto in tempo");
+            ("timeout", json!("sconosciute"))
+        }
+    };
+    let pronto = database == "ok" && migrazioni == "ok";
+    let stato = if pronto { Status::Ok } else { Status::ServiceUnavailable };
+    let corpo = json!({
+        "status": if pronto { "pronto" } else { "non_pronto" },
+        "database": database,
+        "migrazioni": migrazioni,
+    });
+    (stato, Json(corpo))
+}
@@ -0,0 +1,397 @@
+// src/stampa.rs
+// Griglia settimanale stampabile (PDF) di un'aula, da appendere alla porta: i moduli dell'orario
+// del plesso come righe, i giorni come colonne, professore e materia in ogni cella.
+// In modalità multipla produce un unico PDF con una pagina per aula (filtrabile per plesso e tipo).
+// Le prenotazioni fuori dai moduli dell'orario non compaiono nella griglia.
+// Le etichette sono nella lingua della richiesta; nomi di aule, plessi e materie restano come sono.
+// Le griglie riportano i nomi dei professori: le stampa solo un amministratore.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Europe::Rome;
+use printpdf::path::PaintMode;
+use printpdf::{BuiltinFont, Color, Greyscale, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point, Rect};
+use rocket::http::ContentType;
+use rocket::State;
+
+use crate::auth_utils::UtenteAutenticato;
+use crate::blocchi;
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::plessi;
+use crate::prenotazioni::{self, FiltroPrenotazioni};
+use crate::traduzioni::{valori, Lingua};
+
+const LARGHEZZA_PAGINA: f32 = 297.0; // A4 orizzontale
+const ALTEZZA_PAGINA: f32 = 210.0;
+const MARGINE: f32 = 12.0;
+const LARGHEZZA_COLONNA_ORARIO: f32 = 26.0;
+const ALTEZZA_INTESTAZIONE: f32 = 8.0;
+const ALTEZZA_MASSIMA_RIGA: f32 = 28.0;
+const CORPO_CELLA: f32 = 8.0; // punti
+const INTERLINEA_CELLA: f32 = 3.6; // mm
+// Larghezza media di un carattere Helvetica rispetto al corpo: i font standard del PDF
+// non portano le metriche, quindi il testo viene accorciato a stima
+const LARGHEZZA_MEDIA_CARATTERE: f32 = 0.52;
+const MM_PER_PUNTO: f32 = 0.3528;
+
+// Contenuto di una cella: il titolo (professore o "Non disponibile") in grassetto,
+// il dettaglio (materia o motivo del blocco) sotto
+struct VoceCella {
+    titolo: String,
+    dettaglio: Option<String>,
+}
+
+struct RigaGriglia {
+    etichetta: String, // "1ª ora"
+    orario: String,    // "08:00-09:00"
+    celle: Vec<Vec<VoceCella>>, // Una per giorno
+}
+
+struct PaginaSettimana {
+    titolo: String,
+    sottotitolo: String,
+    giorni: Vec<String>,
+    righe: Vec<RigaGriglia>,
+}
+
+fn accorcia(testo: &str, larghezza: f32, corpo: f32) -> String {
+    let massimo = ((larghezza / (corpo * LARGHEZZA_MEDIA_CARATTERE * MM_PER_PUNTO)) as usize).max(1);
+    if testo.chars().count() <= massimo {
+        testo.to_string()
+    } else {
+        let mut corto: String = testo.chars().take(massimo - 1).collect();
+        corto.push('…');
+        corto
+    }
+}
+
+fn linea(livello: &PdfLayerReference, x1: f32, y1: f32, x2: f32, y2: f32) {
+    livello.add_line(Line {
+        points: vec![(Point::new(Mm(x1), Mm(y1)), false), (Point::new(Mm(x2), Mm(y2)), false)],
+        is_closed: false,
+    });
+}
+
+fn disegna_pagina(livello: &PdfLayerReference, pagina: &PaginaSettimana, normale: &IndirectFontRef, grassetto: &IndirectFontRef, piede: &str, lingua: Lingua) {
+    let alto = ALTEZZA_PAGINA - MARGINE;
+    livello.use_text(pagina.titolo.as_str(), 18.0, Mm(MARGINE), Mm(alto - 6.0), grassetto);
+    livello.use_text(pagina.sottotitolo.as_str(), 11.0, Mm(MARGINE), Mm(alto - 12.0), normale);
+    livello.use_text(piede, 7.0, Mm(MARGINE), Mm(MARGINE - 5.0), normale);
+
+    let inizio_tabella = alto - 18.0;
+    let larghezza_giorno = (LARGHEZZA_PAGINA - 2.0 * MARGINE - LARGHEZZA_COLONNA_ORARIO) / pagina.giorni.len().max(1) as f32;
+    let altezza_riga = ((inizio_tabella - ALTEZZA_INTESTAZIONE - MARGINE) / pagina.righe.len().max(1) as f32).min(ALTEZZA_MASSIMA_RIGA);
+    let fine_tabella = inizio_tabella - ALTEZZA_INTESTAZIONE - altezza_riga * pagina.righe.len() as f32;
+    let destra = LARGHEZZA_PAGINA - MARGINE;
+    let x_giorno = |indice: usize| MARGINE + LARGHEZZA_COLONNA_ORARIO + larghezza_giorno * indice as f32;
+
+    // Intestazione grigia con i giorni
+    livello.set_fill_color(Color::Greyscale(Greyscale::new(0.88, None)));
+    livello.add_rect(Rect::new(Mm(MARGINE), Mm(inizio_tabella - ALTEZZA_INTESTAZIONE), Mm(destra), Mm(inizio_tabella)).with_mode(PaintMode::Fill));
+    livello.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+    for (indice, giorno) in pagina.giorni.iter().enumerate() {
+        livello.use_text(giorno.as_str(), 10.0, Mm(x_giorno(indice) + 2.0), Mm(inizio_tabella - 5.5), grassetto);
+    }
+
+    for (indice_riga, riga) in pagina.righe.iter().enumerate() {
+        let cima = inizio_tabella - ALTEZZA_INTESTAZIONE - altezza_riga * indice_riga as f32;
+        livello.use_text(riga.etichetta.as_str(), 9.0, Mm(MARGINE + 2.0), Mm(cima - 4.5), grassetto);
+        livello.use_text(riga.orario.as_str(), 8.0, Mm(MARGINE + 2.0), Mm(cima - 8.5), normale);
+
+        let righe_disponibili = (((altezza_riga - 1.5) / INTERLINEA_CELLA) as usize).max(1);
+        for (indice_giorno, voci) in riga.celle.iter().enumerate() {
+            let x = x_giorno(indice_giorno) + 1.5;
+            let larghezza = larghezza_giorno - 3.0;
+            let mut testo: Vec<(String, bool)> = Vec::new();
+            for voce in voci {
+                testo.push((accorcia(&voce.titolo, larghezza, CORPO_CELLA), true));
+                if let Some(dettaglio) = &voce.dettaglio {
+                    testo.push((accorcia(dettaglio, larghezza, CORPO_CELLA), false));
+                }
+            }
+            // Se non c'è spazio per tutto, l'ultima riga dice quante voci mancano
+            if testo.len() > righe_disponibili {
+                let nascoste = voci.len();
+                testo.truncate(righe_disponibili.saturating_sub(1));
+                let mostrate = testo.iter().filter(|(_, grassetto)| *grassetto).count();
+                let altre = (nascoste - mostrate).to_string();
+                testo.push((lingua.traduci_con("stampa.altre", &format!("(+ altre {})", altre), &valori(&[("numero", altre.clone())])), false));
+            }
+            for (indice, (riga_testo, in_grassetto)) in testo.iter().enumerate() {
+                let font = if *in_grassetto { grassetto } else { normale };
+                livello.use_text(riga_testo.as_str(), CORPO_CELLA, Mm(x), Mm(cima - 3.8 - INTERLINEA_CELLA * indice as f32), font);
+            }
+        }
+    }
+
+    // Bordi della tabella
+    livello.set_outline_thickness(0.6);
+    for indice in 0..=pagina.righe.len() {
+        let y = inizio_tabella - ALTEZZA_INTESTAZIONE - altezza_riga * indice as f32;
+        linea(livello, MARGINE, y, destra, y);
+    }
+    linea(livello, MARGINE, inizio_tabella, destra, inizio_tabella);
+    linea(livello, MARGINE, inizio_tabella, MARGINE, fine_tabella);
+    for indice in 0..=pagina.giorni.len() {
+        linea(livello, x_giorno(indice), inizio_tabella, x_giorno(indice), fine_tabella);
+    }
+}
+
+// Un PDF con una pagina A4 orizzontale per ogni griglia
+fn genera_pdf(titolo: &str, pagine: &[PaginaSettimana], piede: &str, lingua: Lingua) -> Result<Vec<u8>, printpdf::Error> {
+    let (documento, prima_pagina, primo_livello) = PdfDocument::new(titolo, Mm(LARGHEZZA_PAGINA), Mm(ALTEZZA_PAGINA), "Griglia");
+    let normale = documento.add_builtin_font(BuiltinFont::Helvetica)?;
+    let grassetto = documento.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    for (indice, pagina) in pagine.iter().enumerate() {
+        let (indice_pagina, indice_livello) = if indice == 0 {
+            (prima_pagina, primo_livello)
+        } else {
+            documento.add_page(Mm(LARGHEZZA_PAGINA), Mm(ALTEZZA_PAGINA), "Griglia")
+        };
+        let livello = documento.get_page(indice_pagina).get_layer(indice_livello);
+        disegna_pagina(&livello, pagina, &normale, &grassetto, piede, lingua);
+    }
+    documento.save_to_bytes()
+}
+
+const GIORNI: [&str; 6] = ["Lun", "Mar", "Mer", "Gio", "Ven", "Sab"];
+// Righe orarie usate quando il plesso non ha un orario delle lezioni
+const ORA_PRIMA_RIGA: u32 = 8;
+const ORA_ULTIMA_RIGA: u32 = 18;
+
+type RispostaPdf = Result<(ContentType, Vec<u8>), ErroreApi>;
+
+struct AulaStampa {
+    id_aula: i32,
+    nome: String,
+    id_plesso: i32,
+    nome_plesso: String,
+}
+
+struct Modulo {
+    etichetta: String,
+    inizio: NaiveTime,
+    fine: NaiveTime,
+}
+
+// "Rossi M."
+fn nome_breve(nome: Option<&str>, cognome: &str) -> String {
+    match nome.and_then(|n| n.chars().next()) {
+        Some(iniziale) => format!("{} {}.", cognome, iniziale),
+        None => cognome.to_string(),
+    }
+}
+
+async fn aule_da_stampare(
+    db_pool: &DbPool,
+    id_aula: Option<i32>,
+    plesso: Option<i32>,
+    tipo: Option<&str>,
+    lingua: Lingua,
+) -> Result<Vec<AulaStampa>, sqlx::Error> {
+    let aule = db::aule::elenco_con_plesso(db_pool, id_aula, plesso, tipo).await?;
+    Ok(aule
+        .into_iter()
+        .map(|a| AulaStampa {
+            id_aula: a.Id_Aula,
+            nome: format!("{} {} {:02}", lingua.traduci("stampa.aula", "Aula"), a.Tipo_Aula, a.Numero),
+            id_plesso: a.Id_Plesso,
+            nome_plesso: a.Nome_Plesso,
+        })
+        .collect())
+}
+
+// Una pagina per aula, con prenotazioni, blocchi e chiusure della settimana che inizia il lunedì indicato
+async fn pagine_settimana(
+    db_pool: &DbPool,
+    aule: &[AulaStampa],
+    lunedi: NaiveDate,
+    filtro: FiltroPrenotazioni,
+    lingua: Lingua,
+) -> Result<Vec<PaginaSettimana>, sqlx::Error> {
+    let (da, a) = match (plessi::in_utc(lunedi, NaiveTime::MIN), plessi::in_utc(lunedi + Duration::days(7), NaiveTime::MIN)) {
+        (Some(da), Some(a)) => (da, a),
+        _ => return Ok(Vec::new()),
+    };
+    let sabato = lunedi + Duration::days(5);
+
+    let filtro = FiltroPrenotazioni { start: Some(da), end: Some(a), ..filtro };
+    let mut prenotazioni_aula: HashMap<i32, Vec<prenotazioni::VoceElenco>> = HashMap::new();
+    for voce in prenotazioni::elenco(db_pool, &filtro).await? {
+        if let Some(id_aula) = voce.db.Id_Aula {
+            prenotazioni_aula.entry(id_aula).or_default().push(voce);
+        }
+    }
+    let mut blocchi_aula: HashMap<i32, Vec<blocchi::OccorrenzaBlocco>> = HashMap::new();
+    for occorrenza in blocchi::occorrenze_nel_periodo(db_pool, filtro.id_aula, da, a).await? {
+        blocchi_aula.entry(occorrenza.id_aula).or_default().push(occorrenza);
+    }
+
+    let mut moduli_plesso: HashMap<i32, Vec<Modulo>> = HashMap::new();
+    for modulo in db::plessi::orari(db_pool).await? {
+        moduli_plesso.entry(modulo.Id_Plesso).or_default().push(Modulo {
+            etichetta: lingua.traduci_con(
+                "stampa.modulo",
+                &format!("{}ª ora", modulo.Numero_Modulo),
+                &valori(&[("numero", modulo.Numero_Modulo.to_string())]),
+            ),
+            inizio: modulo.Ora_Inizio,
+            fine: modulo.Ora_Fine,
+        });
+    }
+    let moduli_orari: Vec<Modulo> = (ORA_PRIMA_RIGA..ORA_ULTIMA_RIGA)
+        .filter_map(|ora| {
+            Some(Modulo {
+                etichetta: lingua.traduci_con("stampa.ore", &format!("Ore {}", ora), &valori(&[("ora", ora.to_string())])),
+                inizio: NaiveTime::from_hms_opt(ora, 0, 0)?,
+                fine: NaiveTime::from_hms_opt(ora + 1, 0, 0)?,
+            })
+        })
+        .collect();
+
+    let chiusure = db::plessi::chiusure_nel_periodo(db_pool, None, lunedi, sabato).await?;
+    let plesso_chiuso = lingua.traduci("stampa.plesso_chiuso", "Plesso chiuso");
+    let non_disponibile = lingua.traduci("stampa.non_disponibile", "Non disponibile");
+    let da_approvare = lingua.traduci("stampa.da_approvare", "da approvare");
+
+    let mut pagine = Vec::new();
+    for aula in aule {
+        let prenotazioni = prenotazioni_aula.get(&aula.id_aula).map(Vec::as_slice).unwrap_or_default();
+        let blocchi = blocchi_aula.get(&aula.id_aula).map(Vec::as_slice).unwrap_or_default();
+        let moduli = moduli_plesso.get(&aula.id_plesso).filter(|m| !m.is_empty()).unwrap_or(&moduli_orari);
+
+        // Il sabato compare solo se quel giorno l'aula è prenotata
+        let ha_sabato = prenotazioni.iter().any(|p| p.inizio.with_timezone(&Rome).date_naive() == sabato);
+        let giorni: Vec<NaiveDate> = (0..if ha_sabato { 6 } else { 5 }).map(|i| lunedi + Duration::days(i)).collect();
+
+        let righe = moduli
+            .iter()
+            .map(|modulo| RigaGriglia {
+                etichetta: modulo.etichetta.clone(),
+                orario: format!("{}-{}", modulo.inizio.format("%H:%M"), modulo.fine.format("%H:%M")),
+                celle: giorni
+                    .iter()
+                    .map(|&giorno| {
+                        let (inizio, fine) = match (plessi::in_utc(giorno, modulo.inizio), plessi::in_utc(giorno, modulo.fine)) {
+                            (Some(inizio), Some(fine)) => (inizio, fine),
+                            _ => return Vec::new(),
+                        };
+                        if let Some(chiusura) = chiusure
+                            .iter()
+                            .find(|c| c.Id_Plesso == aula.id_plesso && c.Data_Inizio <= giorno && c.Data_Fine >= giorno)
+                        {
+                            return vec![VoceCella { titolo: plesso_chiuso.clone(), dettaglio: Some(chiusura.Motivo.clone()) }];
+                        }
+                        let mut voci: Vec<VoceCella> = blocchi
+                            .iter()
+                            .filter(|b| b.inizio < fine && b.fine > inizio)
+                            .map(|b| VoceCella { titolo: non_disponibile.clone(), dettaglio: Some(b.motivo.clone()) })
+                            .collect();
+                        voci.extend(prenotazioni.iter().filter(|p| p.inizio < fine && p.fine > inizio).map(|p| {
+                            let mut dettaglio = p.db.Materia.clone();
+                            if p.db.Approvata == Some(false) {
+                                dettaglio = Some(format!("{} ({})", dettaglio.unwrap_or_default(), da_approvare).trim_start().to_string());
+                            }
+                            VoceCella {
+                                titolo: nome_breve(p.db.Nome_Professore.as_deref(), &p.db.Cognome_Professore),
+                                dettaglio,
+                            }
+                        }));
+                        voci
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let dal = lunedi.format("%d/%m/%Y").to_string();
+        let al = giorni.last().copied().unwrap_or(lunedi).format("%d/%m/%Y").to_string();
+        let sottotitolo = lingua.traduci_con(
+            "stampa.sottotitolo",
+            &format!("{} - settimana dal {} al {}", aula.nome_plesso, dal, al),
+            &valori(&[("plesso", aula.nome_plesso.clone()), ("dal", dal), ("al", al)]),
+        );
+        pagine.push(PaginaSettimana {
+            titolo: aula.nome.clone(),
+            sottotitolo,
+            giorni: giorni
+                .iter()
+                .map(|giorno| {
+                    let indice = giorno.weekday().num_days_from_monday() as usize;
+                    let nome = lingua.traduci(&format!("stampa.giorni.{}", indice), GIORNI[indice]);
+                    format!("{} {}", nome, giorno.format("%d/%m"))
+                })
+                .collect(),
+            righe,
+        });
+    }
+    Ok(pagine)
+}
+
+// Lunedì della settimana che contiene `settimana` (AAAA-MM-GG), o di quella corrente
+fn lunedi_della_settimana(settimana: Option<&str>) -> Result<NaiveDate, ErroreApi> {
+    let giorno = match settimana {
+        Some(testo) => NaiveDate::parse_from_str(testo, "%Y-%m-%d")
+            .map_err(|_| ErroreApi::campo("settimana", "formato_settimana_non_valido", "Formato settimana non valido (AAAA-MM-GG)."))?,
+        None => Utc::now().with_timezone(&Rome).date_naive(),
+    };
+    Ok(giorno - Duration::days(giorno.weekday().num_days_from_monday() as i64))
+}
+
+async fn stampa(db_pool: &DbPool, aule: Vec<AulaStampa>, lunedi: NaiveDate, filtro: FiltroPrenotazioni, lingua: Lingua) -> RispostaPdf {
+    if aule.is_empty() {
+        return Err(ErroreApi::non_trovato("nessuna_aula", "Nessuna aula corrisponde ai filtri."));
+    }
+    let pagine = pagine_settimana(db_pool, &aule, lunedi, filtro, lingua)
+        .await
+        .map_err(|e| ErroreApi::interno("DB nel preparare la griglia settimanale", e))?;
+
+    let adesso = Utc::now().with_timezone(&Rome);
+    let (data, ora) = (adesso.format("%d/%m/%Y").to_string(), adesso.format("%H:%M").to_string());
+    let piede = lingua.traduci_con("stampa.piede", &format!("Generato il {} alle {}", data, ora), &valori(&[("data", data.clone()), ("ora", ora.clone())]));
+    let settimana = lunedi.format("%d/%m/%Y").to_string();
+    let titolo = lingua.traduci_con(
+        "stampa.titolo",
+        &format!("Prenotazioni della settimana del {}", settimana),
+        &valori(&[("settimana", settimana.clone())]),
+    );
+    let pdf = genera_pdf(&titolo, &pagine, &piede, lingua).map_err(|e| ErroreApi::interno("nel generare il PDF", e))?;
+    Ok((ContentType::PDF, pdf))
+}
+
+#[get("/aule/<id_aula>/settimana.pdf?<settimana>")]
+pub async fn pdf_settimana_aula(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_aula: i32,
+    settimana: Option<&str>,
+) -> RispostaPdf {
+    utente.richiedi_admin("solo_admin_stampa", "Solo un amministratore può stampare le griglie delle aule.")?;
+    let lunedi = lunedi_della_settimana(settimana)?;
+    let aule = aule_da_stampare(db_pool.inner(), Some(id_aula), None, None, lingua).await.map_err(|e| {
+        ErroreApi::interno(format!("DB nel recuperare l'aula {} da stampare", id_aula), e)
+    })?;
+    let filtro = FiltroPrenotazioni { id_aula: Some(id_aula), ..Default::default() };
+    stampa(db_pool.inner(), aule, lunedi, filtro, lingua).await
+}
+
+// Modalità multipla: una pagina per ogni aula del plesso e/o del tipo indicati
+#[get("/aule/settimana.pdf?<settimana>&<plesso>&<tipo>")]
+pub async fn pdf_settimana_aule(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    settimana: Option<&str>,
+    plesso: Option<i32>,
+    tipo: Option<&str>,
+) -> RispostaPdf {
+    utente.richiedi_admin("solo_admin_stampa", "Solo un amministratore può stampare le griglie delle aule.")?;
+    let lunedi = lunedi_della_settimana(settimana)?;
+    let aule = aule_da_stampare(db_pool.inner(), None, plesso, tipo, lingua).await.map_err(|e| {
+        ErroreApi::interno("DB nel recuperare le aule da stampare", e)
+    })?;
+    let filtro = FiltroPrenotazioni { plesso, tipo_aula: tipo.map(str::to_string), ..Default::default() };
+    stampa(db_pool.inner(), aule, lunedi, filtro, lingua).await
+}
@@ -0,0 +1,163 @@
+// src/traduzioni.rs
+// Lingua di messaggi delle API, email e documenti esportati. L'italiano è la lingua del codice:
+// ogni testo resta scritto in italiano dove viene prodotto, e i cataloghi in traduzioni/<lingua>.json
+// lo traducono per chiave (per gli errori delle API la chiave è il codice dell'errore).
+// Nei testi tradotti {nome} viene sostituito con il valore omonimo (es. {posti_residui}).
+// Si usa la lingua scelta dal professore (PUT /profilo/lingua), altrimenti quella di Accept-Language.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::serde_json::{self, Map};
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::State;
+
+use crate::auth_utils::{self, UtenteAutenticato};
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::models::{LinguaProfilo, Ruolo};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(crate = "rocket::serde")]
+pub enum Lingua {
+    #[default]
+    #[serde(rename = "it")]
+    Italiano,
+    #[serde(rename = "en")]
+    Inglese,
+}
+
+const CATALOGHI: &[(Lingua, &str)] = &[(Lingua::Inglese, include_str!("../traduzioni/en.json"))];
+
+fn cataloghi() -> &'static HashMap<Lingua, JsonValue> {
+    static CATALOGHI_CARICATI: OnceLock<HashMap<Lingua, JsonValue>> = OnceLock::new();
+    CATALOGHI_CARICATI.get_or_init(|| {
+        CATALOGHI
+            .iter()
+            .map(|(lingua, testo)| (*lingua, serde_json::from_str(testo).expect("Catalogo delle traduzioni non valido")))
+            .collect()
+    })
+}
+
+// Sostituisce i segnaposto {nome} con i valori corrispondenti; quelli sconosciuti restano com'erano
+fn riempi(modello: &str, valori: &Map<String, JsonValue>) -> String {
+    let mut testo = modello.to_string();
+    for (nome, valore) in valori {
+        let valore = match valore {
+            JsonValue::String(stringa) => stringa.clone(),
+            altro => altro.to_string(),
+        };
+        testo = testo.replace(&format!("{{{}}}", nome), &valore);
+    }
+    testo
+}
+
+impl Lingua {
+    pub fn codice(&self) -> &'static str {
+        match self {
+            Lingua::Italiano => "it",
+            Lingua::Inglese => "en",
+        }
+    }
+
+    // Accetta anche le varianti regionali ("en-GB", "it_IT")
+    pub fn da_codice(codice: &str) -> Option<Lingua> {
+        let primaria = codice.trim().split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        match primaria.as_str() {
+            "it" => Some(Lingua::Italiano),
+            "en" => Some(Lingua::Inglese),
+            _ => None,
+        }
+    }
+
+    // La lingua supportata con il peso q più alto, es. "de-DE,en;q=0.8,it;q=0.5" -> inglese
+    pub fn da_accept_language(intestazione: &str) -> Option<Lingua> {
+        intestazione
+            .split(',')
+            .filter_map(|voce| {
+                let mut parti = voce.split(';');
+                let lingua = Lingua::da_codice(parti.next()?)?;
+                let peso = parti
+                    .find_map(|parametro| parametro.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (peso > 0.0).then_some((lingua, peso))
+            })
+            // A parità di peso vince la prima indicata
+            .fold(None, |migliore: Option<(Lingua, f32)>, (lingua, peso)| match migliore {
+                Some((_, peso_migliore)) if peso_migliore >= peso => migliore,
+                _ => Some((lingua, peso)),
+            })
+            .map(|(lingua, _)| lingua)
+    }
+
+    // Preferenza salvata nel token del professore, poi Accept-Language, poi italiano.
+    // Senza accesso al database: la usano anche le risposte d'errore e i catcher.
+    pub fn della_richiesta(req: &Request<'_>) -> Lingua {
+        let preferita = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .and_then(|token| auth_utils::decodifica_token(token).ok())
+            .and_then(|utente| utente.lingua);
+        preferita
+            .or_else(|| req.headers().get_one("Accept-Language").and_then(Lingua::da_accept_language))
+            .unwrap_or_default()
+    }
+
+    pub fn traduci(&self, chiave: &str, italiano: &str) -> String {
+        self.traduci_con(chiave, italiano, &Map::new())
+    }
+
+    // `italiano` è il testo già composto, usato così com'è in italiano o se manca la traduzione
+    pub fn traduci_con(&self, chiave: &str, italiano: &str, valori: &Map<String, JsonValue>) -> String {
+        let percorso = format!("/{}", chiave.replace('.', "/"));
+        match cataloghi().get(self).and_then(|catalogo| catalogo.pointer(&percorso)).and_then(JsonValue::as_str) {
+            Some(modello) => riempi(modello, valori),
+            None => italiano.to_string(),
+        }
+    }
+}
+
+// Valori per i segnaposto dei testi tradotti
+pub fn valori(coppie: &[(&str, String)]) -> Map<String, JsonValue> {
+    coppie.iter().map(|(nome, valore)| (nome.to_string(), JsonValue::String(valore.clone()))).collect()
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Lingua {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Lingua::della_richiesta(req))
+    }
+}
+
+#[get("/profilo/lingua")]
+pub async fn get_lingua_profilo(db_pool: &State<DbPool>, utente: UtenteAutenticato) -> Result<Json<LinguaProfilo>, ErroreApi> {
+    let professore = db::professori::professore(db_pool.inner(), utente.id_professore)
+        .await
+        .map_err(|e| ErroreApi::interno("DB nel leggere la lingua del professore", e))?;
+    Ok(Json(LinguaProfilo { Lingua: professore.Lingua.as_deref().and_then(Lingua::da_codice) }))
+}
+
+// La lingua viaggia nel token, quindi la risposta ne contiene uno nuovo da usare al posto del precedente
+#[put("/profilo/lingua", format = "json", data = "<payload>")]
+pub async fn imposta_lingua_profilo(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+    payload: Json<LinguaProfilo>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    let errore_interno = |e: sqlx::Error| ErroreApi::interno("DB nel salvare la lingua del professore", e);
+    let lingua = payload.Lingua;
+    db::professori::imposta_lingua(db_pool.inner(), utente.id_professore, lingua.as_ref().map(Lingua::codice))
+        .await
+        .map_err(errore_interno)?;
+    // Nome e ruolo riletti dal database, come al login
+    let professore = db::professori::professore(db_pool.inner(), utente.id_professore).await.map_err(errore_interno)?;
+    let nome = format!("{} {}", professore.Nome.as_deref().unwrap_or(""), professore.Cognome).trim().to_string();
+    let token = auth_utils::emetti_token(utente.id_professore, &nome, Ruolo::from_db(&professore.Ruolo), lingua)?;
+    let messaggio = lingua.unwrap_or_default().traduci("messaggi.lingua_aggiornata", "Lingua aggiornata.");
+    Ok(Json(json!({"status": "successo", "message": messaggio, "Lingua": lingua, "token": token})))
+}
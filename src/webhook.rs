@@ -0,0 +1,470 @@
+// src/webhook.rs
+// Webhook in uscita per gli eventi delle prenotazioni (gestionale dell'edificio, canali Teams...).
+// Ogni evento diventa una riga di consegna_webhook per ciascuna iscrizione interessata; un task
+// in background invia le consegne in scadenza e, in caso di errore, le ripianifica con attesa
+// esponenziale. La coda sta nel database, quindi sopravvive ai riavvii.
+//
+// Il corpo JSON è firmato con HMAC-SHA256 sul testo "<timestamp>.<corpo>" usando il segreto
+// dell'iscrizione; il destinatario verifica l'intestazione X-Prenotaula-Firma e scarta i
+// timestamp troppo vecchi per evitare che una richiesta intercettata venga ripetuta.
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Europe::Rome;
+use hmac::{Hmac, Mac};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::{json, Json, Value as JsonValue};
+use rocket::State;
+use sha2::Sha256;
+
+use crate::auth_utils::{self, UtenteAutenticato};
+use crate::db::{self, DbPool};
+use crate::errori::ErroreApi;
+use crate::metriche;
+use crate::models::{self, FormatoWebhook, TipoEvento};
+use crate::prenotazioni::{self, FiltroPrenotazioni};
+use crate::traduzioni::Lingua;
+
+const INTERVALLO_CONTROLLO_SECONDI: u64 = 5;
+const CONSEGNE_PER_GIRO: i64 = 20;
+const TIMEOUT_RICHIESTA_SECONDI: u64 = 10;
+// Una consegna presa in carico non viene ripresa da altri processi per questo tempo
+const DURATA_PRESA_IN_CARICO_SECONDI: i64 = 60;
+const MASSIMO_TENTATIVI: i32 = 8;
+const ATTESA_INIZIALE_SECONDI: i64 = 30;
+const ATTESA_MASSIMA_SECONDI: i64 = 6 * 60 * 60;
+
+// Attesa prima del tentativo successivo: 30s, 1m, 2m, 4m... fino a 6 ore
+fn attesa_dopo(tentativi: i32) -> Duration {
+    let esponente = (tentativi - 1).clamp(0, 20) as u32;
+    Duration::seconds((ATTESA_INIZIALE_SECONDI * 2i64.pow(esponente)).min(ATTESA_MASSIMA_SECONDI))
+}
+
+pub fn firma(segreto: &str, timestamp: i64, corpo: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(segreto.as_bytes()).expect("HMAC accetta chiavi di qualsiasi lunghezza");
+    mac.update(format!("{}.{}", timestamp, corpo).as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn eventi_iscrizione(eventi: &str) -> Vec<String> {
+    eventi.split(',').map(str::trim).filter(|e| !e.is_empty()).map(str::to_string).collect()
+}
+
+// Fotografia della prenotazione al momento dell'evento
+async fn payload_evento(db_pool: &DbPool, tipo: TipoEvento, id_prenotazione: i32) -> Result<Option<JsonValue>, sqlx::Error> {
+    let filtro = FiltroPrenotazioni { id_prenotazione: Some(id_prenotazione), includi_annullate: true, ..Default::default() };
+    let voce = match prenotazioni::elenco(db_pool, &filtro).await?.into_iter().next() {
+        Some(voce) => voce,
+        None => return Ok(None),
+    };
+    Ok(Some(json!({
+        "evento": tipo.as_db(),
+        "data": Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        "prenotazione": {
+            "Id_Prenotazione": voce.db.Id_Prenotazione,
+            "Id_Aula": voce.db.Id_Aula,
+            "Risorsa": voce.nome_risorsa,
+            "Id_Professore": voce.db.Id_Professore,
+            "Professore": voce.nome_professore,
+            "Data_Inizio": voce.inizio.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "Data_Fine": voce.fine.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "Numero_Studenti": voce.db.Numero_Studenti,
+            "Stato": voce.db.Stato,
+            "Approvata": voce.db.Approvata,
+            "Sequenza": voce.db.Sequenza,
+        }
+    })))
+}
+
+// Accoda l'evento per tutte le iscrizioni attive che lo hanno richiesto
+pub async fn accoda(db_pool: &DbPool, tipo: TipoEvento, id_prenotazione: i32) -> Result<(), sqlx::Error> {
+    let iscrizioni: Vec<i32> = db::webhook::iscrizioni_attive(db_pool)
+        .await?
+        .into_iter()
+        .filter(|(_, eventi)| eventi_iscrizione(eventi).iter().any(|e| e == tipo.as_db()))
+        .map(|(id_webhook, _)| id_webhook)
+        .collect();
+    if iscrizioni.is_empty() {
+        return Ok(());
+    }
+
+    let payload = match payload_evento(db_pool, tipo, id_prenotazione).await? {
+        Some(payload) => payload.to_string(),
+        None => return Ok(()),
+    };
+    for id_webhook in iscrizioni {
+        db::webhook::accoda_consegna(db_pool, id_webhook, tipo.as_db(), &payload).await?;
+    }
+    Ok(())
+}
+
+// MessageCard leggibile in un canale Teams, costruita dal payload JSON
+fn corpo_teams(payload: &str) -> String {
+    let valore: JsonValue = rocket::serde::json::from_str(payload).unwrap_or_default();
+    let p = &valore["prenotazione"];
+    let ora = |campo: &str| {
+        p[campo]
+            .as_str()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Rome).format("%d/%m/%Y %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let azione = match valore["evento"].as_str().unwrap_or_default() {
+        "prenotazione.creata" => "Nuova prenotazione",
+        "prenotazione.modificata" => "Prenotazione modificata",
+        "prenotazione.annullata" => "Prenotazione annullata",
+        "prenotazione.approvata" => "Prenotazione approvata",
+        altro => altro,
+    };
+    let testo = format!(
+        "**{}**: {} - {}, dal {} al {}",
+        azione,
+        p["Risorsa"].as_str().unwrap_or_default(),
+        p["Professore"].as_str().unwrap_or_default(),
+        ora("Data_Inizio"),
+        ora("Data_Fine")
+    );
+    json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": azione,
+        "text": testo,
+    })
+    .to_string()
+}
+
+// Esito di un invio: codice HTTP (se c'è stata una risposta) ed eventuale errore
+async fn invia(
+    client: &reqwest::Client,
+    id_consegna: i64,
+    evento: &str,
+    url: &str,
+    segreto: &str,
+    formato: FormatoWebhook,
+    payload: &str,
+) -> (Option<u16>, Option<String>) {
+    let corpo = match formato {
+        FormatoWebhook::Json => payload.to_string(),
+        FormatoWebhook::Teams => corpo_teams(payload),
+    };
+    let timestamp = Utc::now().timestamp();
+    let risposta = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Prenotaula-Evento", evento)
+        .header("X-Prenotaula-Consegna", id_consegna.to_string())
+        .header("X-Prenotaula-Timestamp", timestamp.to_string())
+        .header("X-Prenotaula-Firma", format!("sha256={}", firma(segreto, timestamp, &corpo)))
+        .body(corpo)
+        .send()
+        .await;
+    match risposta {
+        Ok(r) if r.status().is_success() => (Some(r.status().as_u16()), None),
+        Ok(r) => {
+            let codice = r.status().as_u16();
+            let testo: String = r.text().await.unwrap_or_default().chars().take(500).collect();
+            (Some(codice), Some(format!("Risposta HTTP {}: {}", codice, testo)))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+async fn consegna_in_scadenza(db_pool: &DbPool, client: &reqwest::Client) -> Result<(), sqlx::Error> {
+    let adesso = Utc::now();
+    let da_consegnare = db::webhook::consegne_in_scadenza(db_pool, adesso, CONSEGNE_PER_GIRO).await?;
+
+    for consegna in da_consegnare {
+        // Presa in carico: se un altro processo l'ha già presa si passa oltre
+        let scadenza = adesso + Duration::seconds(DURATA_PRESA_IN_CARICO_SECONDI);
+        if !db::webhook::prendi_in_carico(db_pool, consegna.Id_Consegna, adesso, scadenza).await? {
+            continue;
+        }
+
+        let (codice, errore) = invia(
+            client,
+            consegna.Id_Consegna,
+            &consegna.Evento,
+            &consegna.Url,
+            &consegna.Segreto,
+            FormatoWebhook::from_db(&consegna.Formato),
+            &consegna.Payload,
+        )
+            .await;
+        let tentativi = consegna.Tentativi + 1;
+        let codice = codice.map(i32::from);
+        match errore {
+            None => {
+                metriche::consegna("webhook", "riuscita");
+                db::webhook::segna_consegnata(db_pool, consegna.Id_Consegna, tentativi, codice).await?
+            }
+            Some(errore) => {
+                let stato = if tentativi >= MASSIMO_TENTATIVI { "fallita" } else { "in_attesa" };
+                metriche::consegna("webhook", if stato == "fallita" { "fallita" } else { "ripianificata" });
+                db::webhook::ripianifica(db_pool, consegna.Id_Consegna, stato, tentativi, codice, &errore, Utc::now() + attesa_dopo(tentativi)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Task in background che svuota la coda delle consegne
+pub async fn avvia_consegne(db_pool: DbPool) {
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(TIMEOUT_RICHIESTA_SECONDI))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!(errore = %e, "Impossibile creare il client HTTP dei webhook");
+            return;
+        }
+    };
+    loop {
+        let esito = consegna_in_scadenza(&db_pool, &client).await;
+        metriche::lavoro("consegna_webhook", &esito);
+        if let Err(e) = esito {
+            tracing::error!(errore = %e, "Errore DB nella consegna dei webhook");
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(INTERVALLO_CONTROLLO_SECONDI)).await;
+    }
+}
+
+fn elenco_eventi(eventi: &[TipoEvento]) -> Result<String, ErroreApi> {
+    if eventi.is_empty() {
+        return Err(ErroreApi::campo("Eventi", "eventi_obbligatori", "Indica almeno un evento."));
+    }
+    let mut nomi: Vec<&str> = eventi.iter().map(|e| e.as_db()).collect();
+    nomi.sort();
+    nomi.dedup();
+    Ok(nomi.join(","))
+}
+
+#[get("/webhook")]
+pub async fn get_webhook(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+) -> Result<Json<Vec<models::WebhookApi>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_webhook", "Solo un amministratore può gestire i webhook.")?;
+    match db::webhook::elenco(db_pool.inner()).await {
+        Ok(webhook) => Ok(Json(
+            webhook
+                .into_iter()
+                .map(|w| models::WebhookApi {
+                    Id_Webhook: w.Id_Webhook,
+                    Url: w.Url,
+                    Eventi: eventi_iscrizione(&w.Eventi),
+                    Formato: w.Formato,
+                    Attivo: w.Attivo,
+                    Descrizione: w.Descrizione,
+                    Data_Creazione: w.Data_Creazione,
+                })
+                .collect(),
+        )),
+        Err(e) => {
+            Err(ErroreApi::interno("nel recuperare i webhook dal DB", e))
+        }
+    }
+}
+
+// Il segreto per verificare le firme viene mostrato solo qui, alla creazione
+#[post("/webhook", format = "json", data = "<payload>")]
+pub async fn crea_webhook(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    payload: Json<models::NuovoWebhookPayload>,
+) -> Result<status::Custom<Json<JsonValue>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_webhook", "Solo un amministratore può gestire i webhook.")?;
+    let url = payload.url.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(ErroreApi::campo("Url", "url_non_valido", "L'URL del webhook deve iniziare con http:// o https://."));
+    }
+    let eventi = elenco_eventi(&payload.eventi)?;
+    let segreto = auth_utils::token_casuale();
+
+    match db::webhook::inserisci(db_pool.inner(), url, &segreto, &eventi, payload.formato.as_db(), payload.descrizione.as_deref()).await {
+        Ok(id_webhook) => Ok(status::Custom(Status::Created, Json(json!({
+            "status": "successo",
+            "message": lingua.traduci("messaggi.webhook_creato", "Webhook creato. Conserva il segreto: non verrà più mostrato."),
+            "id_webhook": id_webhook,
+            "segreto": segreto
+        })))),
+        Err(e) => {
+            Err(ErroreApi::interno("DB durante la creazione del webhook", e))
+        }
+    }
+}
+
+#[patch("/webhook/<id_webhook>", format = "json", data = "<payload>")]
+pub async fn modifica_webhook(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_webhook: i32,
+    payload: Json<models::ModificaWebhookPayload>,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_webhook", "Solo un amministratore può gestire i webhook.")?;
+    let eventi = payload.eventi.as_deref().map(elenco_eventi).transpose()?;
+    match db::webhook::modifica(db_pool.inner(), id_webhook, payload.attivo, eventi.as_deref()).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.webhook_aggiornato", "Webhook aggiornato.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("webhook_non_trovato", "Webhook non trovato.")),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("DB nell'aggiornare il webhook {}", id_webhook), e))
+        }
+    }
+}
+
+#[delete("/webhook/<id_webhook>")]
+pub async fn elimina_webhook(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_webhook: i32,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_webhook", "Solo un amministratore può gestire i webhook.")?;
+    match db::webhook::elimina(db_pool.inner(), id_webhook).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.webhook_eliminato", "Webhook eliminato.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("webhook_non_trovato", "Webhook non trovato.")),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("DB nell'eliminare il webhook {}", id_webhook), e))
+        }
+    }
+}
+
+// Registro delle consegne, dalla più recente
+#[get("/webhook/<id_webhook>/consegne?<stato>&<limite>")]
+pub async fn get_consegne_webhook(
+    db_pool: &State<DbPool>,
+    utente: UtenteAutenticato,
+    id_webhook: i32,
+    stato: Option<&str>,
+    limite: Option<i64>,
+) -> Result<Json<Vec<models::ConsegnaWebhookApi>>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_webhook", "Solo un amministratore può gestire i webhook.")?;
+    let limite = limite.unwrap_or(50).clamp(1, 500);
+    match db::webhook::consegne(db_pool.inner(), id_webhook, stato, limite).await {
+        Ok(consegne) => Ok(Json(consegne)),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("nel recuperare le consegne del webhook {}", id_webhook), e))
+        }
+    }
+}
+
+// Rimette in coda una consegna (anche già fallita o consegnata) per un nuovo invio immediato
+#[post("/webhook/consegne/<id_consegna>/riprova")]
+pub async fn riprova_consegna(
+    db_pool: &State<DbPool>,
+    lingua: Lingua,
+    utente: UtenteAutenticato,
+    id_consegna: i64,
+) -> Result<Json<JsonValue>, ErroreApi> {
+    utente.richiedi_admin("solo_admin_webhook", "Solo un amministratore può gestire i webhook.")?;
+    match db::webhook::riprova(db_pool.inner(), id_consegna).await {
+        Ok(true) => Ok(Json(json!({"status": "successo", "message": lingua.traduci("messaggi.consegna_in_coda", "Consegna rimessa in coda.")}))),
+        Ok(false) => Err(ErroreApi::non_trovato("consegna_non_trovata", "Consegna non trovata.")),
+        Err(e) => {
+            Err(ErroreApi::interno(format!("DB nel rimettere in coda la consegna {}", id_consegna), e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn la_firma_copre_timestamp_e_corpo() {
+        let corpo = r#"{"evento":"prenotazione.creata"}"#;
+        // HMAC-SHA256 di "1700000000.<corpo>" con chiave "segreto"
+        assert_eq!(firma("segreto", 1_700_000_000, corpo), "af9c60308312021f6437ecbb30cf6a9640ba1086dda7a8159169d7549227b7a2");
+        // Cambiando il timestamp cambia la firma: una richiesta intercettata non si può ridatare
+        assert_ne!(firma("segreto", 1_700_000_001, corpo), firma("segreto", 1_700_000_000, corpo));
+        assert_ne!(firma("altro segreto", 1_700_000_000, corpo), firma("segreto", 1_700_000_000, corpo));
+    }
+
+    #[test]
+    fn l_attesa_raddoppia_fino_a_sei_ore() {
+        let attese: Vec<i64> = (1..=MASSIMO_TENTATIVI).map(|tentativi| attesa_dopo(tentativi).num_seconds()).collect();
+        assert_eq!(attese, vec![30, 60, 120, 240, 480, 960, 1920, 3840]);
+        assert_eq!(attesa_dopo(20), Duration::hours(6));
+        assert_eq!(attesa_dopo(0), Duration::seconds(30));
+    }
+
+    // Un destinatario che accetta una sola richiesta e la restituisce com'è arrivata
+    fn destinatario() -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+        let ascolto = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ricevi", ascolto.local_addr().unwrap());
+        let ricevuta = std::thread::spawn(move || {
+            let (mut connessione, _) = ascolto.accept().unwrap();
+            let mut richiesta = Vec::new();
+            let mut pezzo = [0u8; 4096];
+            loop {
+                let letti = connessione.read(&mut pezzo).unwrap();
+                richiesta.extend_from_slice(&pezzo[..letti]);
+                let testo = String::from_utf8_lossy(&richiesta).to_string();
+                if let Some((intestazioni, corpo)) = testo.split_once("\r\n\r\n") {
+                    let lunghezza = intestazioni
+                        .lines()
+                        .find_map(|riga| riga.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if corpo.len() >= lunghezza {
+                        connessione.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+                        return testo;
+                    }
+                }
+            }
+        });
+        (url, ricevuta)
+    }
+
+    #[tokio::test]
+    async fn la_consegna_porta_la_firma_del_corpo_inviato() {
+        let (url, ricevuta) = destinatario();
+        let payload = r#"{"evento":"prenotazione.creata","prenotazione":{"Id_Prenotazione":1}}"#;
+        let esito = invia(&reqwest::Client::new(), 7, "prenotazione.creata", &url, "segreto", FormatoWebhook::Json, payload).await;
+        assert_eq!(esito, (Some(200), None));
+
+        let richiesta = ricevuta.join().unwrap();
+        let (intestazioni, corpo) = richiesta.split_once("\r\n\r\n").unwrap();
+        let intestazione = |nome: &str| {
+            intestazioni
+                .lines()
+                .find_map(|riga| riga.split_once(": ").filter(|(chiave, _)| chiave.eq_ignore_ascii_case(nome)).map(|(_, valore)| valore.to_string()))
+                .unwrap()
+        };
+        assert_eq!(corpo, payload);
+        assert_eq!(intestazione("X-Prenotaula-Consegna"), "7");
+        let timestamp: i64 = intestazione("X-Prenotaula-Timestamp").parse().unwrap();
+        assert_eq!(intestazione("X-Prenotaula-Firma"), format!("sha256={}", firma("segreto", timestamp, corpo)));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn dopo_l_ultimo_tentativo_la_consegna_fallisce() {
+        let db_pool = crate::prova::database().await;
+        // Nessuno in ascolto sulla porta 1: la connessione viene rifiutata subito
+        let id_webhook = db::webhook::inserisci(&db_pool, "http://127.0.0.1:1/", "segreto", "prenotazione.creata", "json", None).await.unwrap();
+        db::webhook::accoda_consegna(&db_pool, id_webhook, "prenotazione.creata", "{}").await.unwrap();
+        db::webhook::accoda_consegna(&db_pool, id_webhook, "prenotazione.creata", "{}").await.unwrap();
+        let ultima: i64 = sqlx::query_scalar("SELECT MAX(Id_Consegna) FROM consegna_webhook").fetch_one(&db_pool).await.unwrap();
+        sqlx::query("UPDATE consegna_webhook SET Tentativi = ? WHERE Id_Consegna = ?")
+            .bind(MASSIMO_TENTATIVI - 1)
+            .bind(ultima)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        consegna_in_scadenza(&db_pool, &reqwest::Client::new()).await.unwrap();
+
+        let consegne: Vec<(String, i32, Option<String>)> = sqlx::query_as("SELECT Stato, Tentativi, Ultimo_Errore FROM consegna_webhook ORDER BY Id_Consegna")
+            .fetch_all(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!((consegne[0].0.as_str(), consegne[0].1), ("in_attesa", 1));
+        assert_eq!((consegne[1].0.as_str(), consegne[1].1), ("fallita", MASSIMO_TENTATIVI));
+        assert!(consegne.iter().all(|(_, _, errore)| errore.is_some()));
+        // La prima ritorna in coda solo dopo l'attesa
+        assert!(db::webhook::consegne_in_scadenza(&db_pool, Utc::now(), 10).await.unwrap().is_empty());
+    }
+}